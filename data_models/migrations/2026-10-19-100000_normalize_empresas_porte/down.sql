@@ -0,0 +1,5 @@
+UPDATE empresas
+    INNER JOIN empresas_porte_invalido ON empresas_porte_invalido.cnpj_basico = empresas.cnpj_basico
+    SET empresas.porte = empresas_porte_invalido.porte;
+
+DROP TABLE empresas_porte_invalido;
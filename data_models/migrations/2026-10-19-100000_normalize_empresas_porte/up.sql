@@ -0,0 +1,11 @@
+-- Os códigos de porte inválidos (ex: 'ER') são gravados como nulos. Os valores originais são guardados em
+-- empresas_porte_invalido para que possam ser restaurados pela migração reversa.
+CREATE TABLE empresas_porte_invalido (
+    cnpj_basico CHAR(8) NOT NULL PRIMARY KEY,
+    porte CHAR(2) NOT NULL
+);
+
+INSERT INTO empresas_porte_invalido (cnpj_basico, porte)
+    SELECT cnpj_basico, porte FROM empresas WHERE porte NOT IN ('00', '01', '03', '05');
+
+UPDATE empresas SET porte = NULL WHERE porte NOT IN ('00', '01', '03', '05');
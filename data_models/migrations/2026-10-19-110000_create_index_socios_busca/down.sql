@@ -0,0 +1,3 @@
+ALTER TABLE socios DROP INDEX socios_nome_ou_razao_social_do_socio;
+ALTER TABLE socios DROP INDEX socios_cnpj_ou_cpf_do_socio;
+ALTER TABLE socios DROP INDEX socios_cpf_do_representante_legal;
\ No newline at end of file
@@ -0,0 +1,3 @@
+CREATE INDEX socios_nome_ou_razao_social_do_socio ON socios (nome_ou_razao_social_do_socio);
+CREATE INDEX socios_cnpj_ou_cpf_do_socio ON socios (cnpj_ou_cpf_do_socio);
+CREATE INDEX socios_cpf_do_representante_legal ON socios (cpf_do_representante_legal);
\ No newline at end of file
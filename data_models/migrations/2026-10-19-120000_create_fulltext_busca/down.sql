@@ -0,0 +1,2 @@
+ALTER TABLE empresas DROP INDEX empresas_busca_razao_social;
+ALTER TABLE estabelecimentos DROP INDEX estabelecimentos_busca_nome_fantasia;
\ No newline at end of file
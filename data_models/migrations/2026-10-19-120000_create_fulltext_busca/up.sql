@@ -0,0 +1,5 @@
+-- A collation utf8mb4_unicode_ci faz com que a busca textual não diferencie acentos nem maiúsculas/minúsculas
+ALTER TABLE empresas MODIFY razao_social VARCHAR(250) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci NOT NULL;
+ALTER TABLE estabelecimentos MODIFY nome_fantasia VARCHAR(200) CHARACTER SET utf8mb4 COLLATE utf8mb4_unicode_ci;
+CREATE FULLTEXT INDEX empresas_busca_razao_social ON empresas (razao_social);
+CREATE FULLTEXT INDEX estabelecimentos_busca_nome_fantasia ON estabelecimentos (nome_fantasia);
\ No newline at end of file
@@ -0,0 +1,2 @@
+DROP TABLE uso_das_chaves_de_api;
+DROP TABLE chaves_de_api;
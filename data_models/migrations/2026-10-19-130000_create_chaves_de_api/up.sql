@@ -0,0 +1,21 @@
+-- Chaves de acesso às APIs (somente o hash SHA-256 da chave é armazenado) e as suas cotas de requisições
+CREATE TABLE chaves_de_api (
+    id INT UNSIGNED NOT NULL AUTO_INCREMENT PRIMARY KEY,
+    nome VARCHAR(100) NOT NULL,
+    hash_da_chave CHAR(64) NOT NULL,
+    requisicoes_por_minuto INT UNSIGNED,
+    requisicoes_por_dia INT UNSIGNED,
+    ativa BOOLEAN NOT NULL DEFAULT TRUE,
+    data_hora_de_criacao DATETIME NOT NULL,
+    UNIQUE KEY chaves_de_api_hash_da_chave (hash_da_chave)
+);
+
+-- Quantidade diária de requisições (atendidas e recusadas por excesso da cota) de cada chave
+CREATE TABLE uso_das_chaves_de_api (
+    chave_de_api INT UNSIGNED NOT NULL,
+    data DATE NOT NULL,
+    requisicoes INT UNSIGNED NOT NULL DEFAULT 0,
+    requisicoes_recusadas INT UNSIGNED NOT NULL DEFAULT 0,
+    PRIMARY KEY (chave_de_api, data),
+    CONSTRAINT uso_das_chaves_de_api_chave_de_api FOREIGN KEY (chave_de_api) REFERENCES chaves_de_api (id)
+);
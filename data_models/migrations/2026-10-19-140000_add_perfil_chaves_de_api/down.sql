@@ -0,0 +1 @@
+ALTER TABLE chaves_de_api DROP COLUMN perfil;
@@ -0,0 +1,2 @@
+-- Perfil de exposição dos dados pessoais (LGPD) nas respostas às requisições feitas com a chave
+ALTER TABLE chaves_de_api ADD perfil VARCHAR(30) NOT NULL DEFAULT 'publico' AFTER nome;
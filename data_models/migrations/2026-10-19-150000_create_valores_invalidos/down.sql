@@ -0,0 +1 @@
+DROP TABLE valores_invalidos;
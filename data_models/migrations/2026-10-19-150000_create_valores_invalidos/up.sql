@@ -0,0 +1,11 @@
+-- Valores que não puderam ser convertidos na importação (ex: datas inválidas e códigos de porte inexistentes)
+-- e que, por isso, foram gravados como nulos. Utilizada pelo relatório de qualidade dos dados (importer quality).
+CREATE TABLE valores_invalidos (
+    nome_do_arquivo VARCHAR(255) NOT NULL,
+    coluna VARCHAR(64) NOT NULL,
+    tabela VARCHAR(100) NOT NULL,
+    quantidade INT UNSIGNED NOT NULL,
+    exemplo VARCHAR(255) NOT NULL,
+    PRIMARY KEY (nome_do_arquivo, coluna),
+    KEY valores_invalidos_tabela (tabela)
+);
@@ -0,0 +1,3 @@
+ALTER TABLE estabelecimentos
+    DROP COLUMN endereco_normalizado,
+    DROP COLUMN cep_valido;
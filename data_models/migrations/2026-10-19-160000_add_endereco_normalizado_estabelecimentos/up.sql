@@ -0,0 +1,5 @@
+-- Endereço normalizado em uma única linha e indicação se o CEP pertence às faixas de CEP da UF, gravados
+-- pelo importer somente com --normalize-addresses (nulos nas demais importações)
+ALTER TABLE estabelecimentos
+    ADD endereco_normalizado VARCHAR(1024),
+    ADD cep_valido BOOLEAN;
@@ -0,0 +1,6 @@
+ALTER TABLE estabelecimentos
+    DROP COLUMN telefone1_e164,
+    DROP COLUMN telefone2_e164,
+    DROP COLUMN telefone_fax_e164,
+    DROP COLUMN email_normalizado,
+    DROP COLUMN email_valido;
@@ -0,0 +1,8 @@
+-- Telefones no formato E.164 (nulos quando inválidos), e-mail normalizado e indicação se ele é válido,
+-- gravados pelo importer somente com --normalize-contacts (nulos nas demais importações)
+ALTER TABLE estabelecimentos
+    ADD telefone1_e164 VARCHAR(14),
+    ADD telefone2_e164 VARCHAR(14),
+    ADD telefone_fax_e164 VARCHAR(14),
+    ADD email_normalizado VARCHAR(200),
+    ADD email_valido BOOLEAN;
@@ -0,0 +1,6 @@
+ALTER TABLE municipios
+    DROP KEY municipios_codigo_ibge,
+    DROP COLUMN codigo_ibge,
+    DROP COLUMN uf;
+
+DROP TABLE municipios_ibge;
@@ -0,0 +1,13 @@
+-- Correspondência entre os códigos de município da RF (TOM/SIAFI) e os códigos de 7 dígitos do IBGE, carregada
+-- por importer ibge. As colunas codigo_ibge e uf dos municípios são preenchidas a partir dela.
+CREATE TABLE municipios_ibge (
+    municipio INT NOT NULL PRIMARY KEY,
+    codigo_ibge INT UNSIGNED NOT NULL,
+    uf CHAR(2) NOT NULL,
+    UNIQUE KEY municipios_ibge_codigo_ibge (codigo_ibge)
+);
+
+ALTER TABLE municipios
+    ADD codigo_ibge INT UNSIGNED,
+    ADD uf CHAR(2),
+    ADD UNIQUE KEY municipios_codigo_ibge (codigo_ibge);
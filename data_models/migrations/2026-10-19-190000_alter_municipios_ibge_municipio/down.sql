@@ -0,0 +1,3 @@
+ALTER TABLE municipios_ibge
+    DROP FOREIGN KEY FK_MunicIbgeMunic,
+    MODIFY municipio INT NOT NULL;
@@ -0,0 +1,8 @@
+-- O código do município da RF tem o mesmo tipo da chave da tabela municipios, que passa a ser referenciada.
+-- As correspondências de municípios que não constam da tabela municipios são removidas (serão carregadas
+-- novamente por importer ibge após a importação dos municípios da RF).
+DELETE c FROM municipios_ibge c LEFT JOIN municipios m ON m.id = c.municipio WHERE m.id IS NULL;
+
+ALTER TABLE municipios_ibge
+    MODIFY municipio SMALLINT UNSIGNED NOT NULL,
+    ADD CONSTRAINT FK_MunicIbgeMunic FOREIGN KEY (municipio) REFERENCES municipios(id);
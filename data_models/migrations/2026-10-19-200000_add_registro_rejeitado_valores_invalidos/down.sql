@@ -0,0 +1,3 @@
+DROP TABLE leituras_das_tabelas;
+
+ALTER TABLE valores_invalidos DROP COLUMN registro_rejeitado;
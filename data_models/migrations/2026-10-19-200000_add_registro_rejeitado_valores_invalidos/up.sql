@@ -0,0 +1,10 @@
+-- Indica se o valor inválido foi gravado como nulo (ex: datas e códigos de porte) ou se o registro foi
+-- rejeitado (ex: códigos de situação cadastral inexistentes e datas de entrada do sócio inválidas).
+ALTER TABLE valores_invalidos ADD COLUMN registro_rejeitado BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Quantidade de registros lidos dos arquivos da importação atual de cada tabela grande (zerada ao importar a
+-- primeira parte), utilizada pelo relatório de qualidade para calcular o percentual de registros rejeitados.
+CREATE TABLE leituras_das_tabelas (
+    tabela VARCHAR(100) NOT NULL PRIMARY KEY,
+    registros_lidos BIGINT UNSIGNED NOT NULL
+);
@@ -0,0 +1,374 @@
+// Autenticação por chave de API e controle das cotas de requisições de cada chave.
+//
+// As chaves são geradas pelo importer (comando `importer chaves-de-api`) e somente o hash SHA-256 delas é
+// gravado na tabela chaves_de_api. Cada chave pode ter uma cota de requisições por minuto e/ou por dia.
+//
+// O ControleDeAcesso mantém em memória as chaves ativas e os contadores de uso, que os servidores carregam na
+// inicialização e sincronizam com o banco de dados a cada INTERVALO_DE_SINCRONIZACAO: as requisições
+// contabilizadas desde a última sincronização são somadas na tabela uso_das_chaves_de_api e as chaves são
+// lidas novamente (assim, as chaves criadas ou revogadas passam a valer sem reiniciar os servidores). A cota diária considera as requisições de todas as
+// instâncias dos servidores (gravadas no banco de dados), enquanto a cota por minuto é controlada
+// separadamente por cada instância.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::mysql::MysqlConnection;
+use diesel::prelude::*;
+use diesel::sql_types::{Date, Unsigned, Integer};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::models::{ChaveDeApi, NewChaveDeApi, UsoDaChaveDeApi};
+use crate::schema::{chaves_de_api, uso_das_chaves_de_api};
+
+// prefixo das chaves geradas, para facilitar a sua identificação (ex: em varreduras de segredos)
+const PREFIXO_DA_CHAVE: &str = "cnpj_";
+
+// intervalo entre as sincronizações das chaves e dos contadores de uso com o banco de dados
+pub const INTERVALO_DE_SINCRONIZACAO: Duration = Duration::from_secs(60);
+
+// Hash (SHA-256, em hexadecimal) de uma chave de API, que é o que fica gravado no banco de dados
+pub fn hash_da_chave(chave: &str) -> String {
+    Sha256::digest(chave.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Gera uma nova chave aleatória (256 bits), grava o seu hash e retorna a chave, que não poderá ser recuperada depois.
+// O perfil define quais dados pessoais são expostos nas respostas às requisições feitas com a chave (vide lgpd).
+pub fn criar_chave(conn: &MysqlConnection, nome: &str, perfil: &str, requisicoes_por_minuto: Option<u32>, requisicoes_por_dia: Option<u32>) -> QueryResult<String> {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let chave = format!("{}{}", PREFIXO_DA_CHAVE, bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>());
+
+    diesel::insert_into(chaves_de_api::table)
+        .values(&NewChaveDeApi {
+            nome,
+            perfil,
+            hash_da_chave: &hash_da_chave(&chave),
+            requisicoes_por_minuto,
+            requisicoes_por_dia,
+            ativa: true,
+            data_hora_de_criacao: Utc::now().naive_utc(),
+        })
+        .execute(conn)?;
+
+    Ok(chave)
+}
+
+// Desativa a chave; retorna false quando a chave não existe
+pub fn revogar_chave(conn: &MysqlConnection, id: u32) -> QueryResult<bool> {
+    let alteradas = diesel::update(chaves_de_api::table.filter(chaves_de_api::id.eq(id)))
+        .set(chaves_de_api::ativa.eq(false))
+        .execute(conn)?;
+
+    Ok(alteradas > 0)
+}
+
+// Chave informada na requisição, no cabeçalho X-API-Key ou no Authorization (Bearer)
+pub fn chave_da_requisicao<'a>(x_api_key: Option<&'a str>, authorization: Option<&'a str>) -> Option<&'a str> {
+    x_api_key.or_else(|| {
+        authorization
+            .and_then(|v| v.trim().split_once(' '))
+            .filter(|(esquema, _)| esquema.eq_ignore_ascii_case("bearer"))
+            .map(|(_, chave)| chave)
+    })
+}
+
+// Todas as chaves (ativas ou não) e o uso de cada uma na data informada
+pub fn chaves_e_uso(conn: &MysqlConnection, data: NaiveDate) -> QueryResult<Vec<(ChaveDeApi, Option<UsoDaChaveDeApi>)>> {
+    let chaves = chaves_de_api::table.order(chaves_de_api::id).load::<ChaveDeApi>(conn)?;
+    let uso = uso_das_chaves_de_api::table
+        .filter(uso_das_chaves_de_api::data.eq(data))
+        .load::<UsoDaChaveDeApi>(conn)?;
+
+    Ok(chaves
+        .into_iter()
+        .map(|chave| {
+            let uso_da_chave = uso.iter().find(|u| u.chave_de_api == chave.id).cloned();
+            (chave, uso_da_chave)
+        })
+        .collect())
+}
+
+// Motivo pelo qual a requisição foi recusada
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecusaDeAcesso {
+    ChaveAusente,
+    ChaveInvalida,
+    // cota excedida ("minuto" ou "dia") e a quantidade de segundos até que ela seja renovada (Retry-After)
+    CotaExcedida { periodo: &'static str, segundos_para_renovacao: u64 },
+}
+
+impl RecusaDeAcesso {
+    pub fn mensagem(&self) -> String {
+        match self {
+            RecusaDeAcesso::ChaveAusente => String::from("Informe a chave de API no cabeçalho X-API-Key"),
+            RecusaDeAcesso::ChaveInvalida => String::from("Chave de API inválida ou revogada"),
+            RecusaDeAcesso::CotaExcedida { periodo, segundos_para_renovacao } => format!(
+                "Cota de requisições por {} excedida. Tente novamente em {} segundos",
+                periodo, segundos_para_renovacao
+            ),
+        }
+    }
+}
+
+// Contadores de uso de uma chave
+#[derive(Default)]
+struct UsoDaChave {
+    // minuto (desde 01/01/1970) e a quantidade de requisições atendidas nele
+    minuto: i64,
+    requisicoes_no_minuto: u32,
+    // dia e a quantidade de requisições atendidas nele, conforme gravado no banco de dados na última sincronização
+    dia: Option<NaiveDate>,
+    requisicoes_no_dia: u32,
+}
+
+struct EstadoDoControle {
+    // chaves ativas pelo hash
+    chaves: HashMap<String, ChaveDeApi>,
+    uso: HashMap<u32, UsoDaChave>,
+    // requisições atendidas e recusadas ainda não gravadas no banco de dados, por chave e dia
+    pendentes: HashMap<(u32, NaiveDate), (u32, u32)>,
+}
+
+pub struct ControleDeAcesso {
+    estado: Mutex<EstadoDoControle>,
+}
+
+impl Default for ControleDeAcesso {
+    fn default() -> ControleDeAcesso {
+        ControleDeAcesso {
+            estado: Mutex::new(EstadoDoControle {
+                chaves: HashMap::new(),
+                uso: HashMap::new(),
+                pendentes: HashMap::new(),
+            }),
+        }
+    }
+}
+
+impl ControleDeAcesso {
+    // Grava os contadores de uso pendentes e carrega novamente as chaves ativas e o uso de cada uma no dia. Em
+    // caso de erro, as chaves carregadas na sincronização anterior continuam valendo.
+    pub fn sincronizar(&self, conn: &MysqlConnection) -> QueryResult<()> {
+        let pendentes: Vec<((u32, NaiveDate), (u32, u32))> = self.estado.lock().unwrap().pendentes.drain().collect();
+
+        let gravados = conn.transaction::<_, diesel::result::Error, _>(|| {
+            for ((chave, data), (requisicoes, recusadas)) in &pendentes {
+                diesel::sql_query(
+                    "INSERT INTO uso_das_chaves_de_api (chave_de_api, data, requisicoes, requisicoes_recusadas) VALUES (?, ?, ?, ?) \
+                     ON DUPLICATE KEY UPDATE requisicoes = requisicoes + VALUES(requisicoes), requisicoes_recusadas = requisicoes_recusadas + VALUES(requisicoes_recusadas)",
+                )
+                .bind::<Unsigned<Integer>, _>(chave)
+                .bind::<Date, _>(data)
+                .bind::<Unsigned<Integer>, _>(requisicoes)
+                .bind::<Unsigned<Integer>, _>(recusadas)
+                .execute(conn)?;
+            }
+            Ok(())
+        });
+
+        if let Err(error) = gravados {
+            // os contadores voltam a ficar pendentes, para serem gravados na próxima sincronização
+            let mut estado = self.estado.lock().unwrap();
+            for (chave_e_data, (requisicoes, recusadas)) in pendentes {
+                let contadores = estado.pendentes.entry(chave_e_data).or_default();
+                contadores.0 += requisicoes;
+                contadores.1 += recusadas;
+            }
+            return Err(error);
+        }
+
+        let hoje = Utc::now().date_naive();
+        let chaves = chaves_de_api::table
+            .filter(chaves_de_api::ativa.eq(true))
+            .load::<ChaveDeApi>(conn)?;
+        let uso_de_hoje = uso_das_chaves_de_api::table
+            .filter(uso_das_chaves_de_api::data.eq(hoje))
+            .load::<UsoDaChaveDeApi>(conn)?;
+
+        self.carregar(chaves, uso_de_hoje, hoje);
+        Ok(())
+    }
+
+    // Substitui as chaves ativas e atualiza o uso de cada uma no dia com o gravado no banco de dados
+    fn carregar(&self, chaves: Vec<ChaveDeApi>, uso_de_hoje: Vec<UsoDaChaveDeApi>, hoje: NaiveDate) {
+        let mut estado = self.estado.lock().unwrap();
+        estado.chaves = chaves.into_iter().map(|chave| (chave.hash_da_chave.clone(), chave)).collect();
+        for uso in uso_de_hoje {
+            let contadores = estado.uso.entry(uso.chave_de_api).or_default();
+            contadores.dia = Some(hoje);
+            contadores.requisicoes_no_dia = uso.requisicoes;
+        }
+    }
+
+    // Verifica a chave informada na requisição e contabiliza a requisição nas cotas da chave. Retorna o
+    // registro da chave quando a requisição pode ser atendida.
+    pub fn verificar(&self, chave: Option<&str>) -> Result<ChaveDeApi, RecusaDeAcesso> {
+        self.verificar_em(chave, Utc::now())
+    }
+
+    fn verificar_em(&self, chave: Option<&str>, agora: DateTime<Utc>) -> Result<ChaveDeApi, RecusaDeAcesso> {
+        let chave = chave.map(str::trim).filter(|v| !v.is_empty()).ok_or(RecusaDeAcesso::ChaveAusente)?;
+        let hash = hash_da_chave(chave);
+
+        let hoje = agora.date_naive();
+        let minuto = agora.timestamp().div_euclid(60);
+
+        let mut estado = self.estado.lock().unwrap();
+        let chave = estado.chaves.get(&hash).cloned().ok_or(RecusaDeAcesso::ChaveInvalida)?;
+
+        let pendentes_de_hoje = estado.pendentes.get(&(chave.id, hoje)).map(|p| p.0).unwrap_or(0);
+        let uso = estado.uso.entry(chave.id).or_default();
+        if uso.minuto != minuto {
+            uso.minuto = minuto;
+            uso.requisicoes_no_minuto = 0;
+        }
+        if uso.dia != Some(hoje) {
+            uso.dia = Some(hoje);
+            uso.requisicoes_no_dia = 0;
+        }
+
+        let recusa = if chave.requisicoes_por_minuto.is_some_and(|limite| uso.requisicoes_no_minuto >= limite) {
+            Some(RecusaDeAcesso::CotaExcedida {
+                periodo: "minuto",
+                segundos_para_renovacao: 60 - agora.timestamp().rem_euclid(60) as u64,
+            })
+        } else if chave.requisicoes_por_dia.is_some_and(|limite| uso.requisicoes_no_dia + pendentes_de_hoje >= limite) {
+            let amanha = hoje.succ_opt().unwrap_or(hoje).and_hms_opt(0, 0, 0).unwrap_or_default();
+            Some(RecusaDeAcesso::CotaExcedida {
+                periodo: "dia",
+                segundos_para_renovacao: (amanha - agora.naive_utc()).num_seconds().max(1) as u64,
+            })
+        } else {
+            uso.requisicoes_no_minuto += 1;
+            None
+        };
+
+        let contadores = estado.pendentes.entry((chave.id, hoje)).or_default();
+        match recusa {
+            Some(recusa) => {
+                contadores.1 += 1;
+                Err(recusa)
+            }
+            None => {
+                contadores.0 += 1;
+                Ok(chave)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const CHAVE: &str = "cnpj_chave_de_teste";
+
+    fn chave_de_api(requisicoes_por_minuto: Option<u32>, requisicoes_por_dia: Option<u32>) -> ChaveDeApi {
+        ChaveDeApi {
+            id: 7,
+            nome: String::from("Teste"),
+            perfil: String::from("publico"),
+            hash_da_chave: hash_da_chave(CHAVE),
+            requisicoes_por_minuto,
+            requisicoes_por_dia,
+            ativa: true,
+            data_hora_de_criacao: dia(1).and_hms_opt(0, 0, 0).unwrap(),
+        }
+    }
+
+    fn dia(dia: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, dia).unwrap()
+    }
+
+    fn instante(dia_do_mes: u32, hora: u32, minuto: u32, segundo: u32) -> DateTime<Utc> {
+        Utc.from_utc_datetime(&dia(dia_do_mes).and_hms_opt(hora, minuto, segundo).unwrap())
+    }
+
+    fn controle(chave: ChaveDeApi, requisicoes_no_dia: u32) -> ControleDeAcesso {
+        let controle = ControleDeAcesso::default();
+        let uso = UsoDaChaveDeApi { chave_de_api: chave.id, data: dia(19), requisicoes: requisicoes_no_dia, requisicoes_recusadas: 0 };
+        controle.carregar(vec![chave], vec![uso], dia(19));
+        controle
+    }
+
+    fn pendentes(controle: &ControleDeAcesso, data: NaiveDate) -> (u32, u32) {
+        controle.estado.lock().unwrap().pendentes.get(&(7, data)).copied().unwrap_or_default()
+    }
+
+    #[test]
+    fn cota_por_minuto() {
+        let controle = controle(chave_de_api(Some(2), None), 0);
+
+        assert!(controle.verificar_em(Some(CHAVE), instante(19, 10, 0, 5)).is_ok());
+        assert!(controle.verificar_em(Some(CHAVE), instante(19, 10, 0, 10)).is_ok());
+        assert_eq!(
+            controle.verificar_em(Some(CHAVE), instante(19, 10, 0, 15)).err(),
+            Some(RecusaDeAcesso::CotaExcedida { periodo: "minuto", segundos_para_renovacao: 45 })
+        );
+        assert_eq!(pendentes(&controle, dia(19)), (2, 1));
+
+        // o contador é zerado no minuto seguinte
+        assert!(controle.verificar_em(Some(CHAVE), instante(19, 10, 1, 0)).is_ok());
+        assert!(controle.verificar_em(Some(CHAVE), instante(19, 10, 1, 59)).is_ok());
+        assert!(controle.verificar_em(Some(CHAVE), instante(19, 10, 1, 59)).is_err());
+        assert_eq!(pendentes(&controle, dia(19)), (4, 2));
+    }
+
+    #[test]
+    fn cota_diaria() {
+        // 2 requisições já gravadas no banco de dados (inclusive por outras instâncias dos servidores)
+        let controle = controle(chave_de_api(None, Some(4)), 2);
+
+        assert!(controle.verificar_em(Some(CHAVE), instante(19, 23, 0, 0)).is_ok());
+        assert!(controle.verificar_em(Some(CHAVE), instante(19, 23, 10, 0)).is_ok());
+        assert_eq!(
+            controle.verificar_em(Some(CHAVE), instante(19, 23, 59, 30)).err(),
+            Some(RecusaDeAcesso::CotaExcedida { periodo: "dia", segundos_para_renovacao: 30 })
+        );
+
+        // a cota é renovada no dia seguinte
+        assert!(controle.verificar_em(Some(CHAVE), instante(20, 0, 0, 0)).is_ok());
+        assert_eq!(pendentes(&controle, dia(19)), (2, 1));
+        assert_eq!(pendentes(&controle, dia(20)), (1, 0));
+    }
+
+    #[test]
+    fn chave_sem_cota() {
+        let controle = controle(chave_de_api(None, None), 0);
+
+        for segundo in 0..100 {
+            assert!(controle.verificar_em(Some(CHAVE), instante(19, 10, 0, segundo % 60)).is_ok());
+        }
+    }
+
+    #[test]
+    fn chave_ausente_desconhecida_ou_revogada() {
+        let controle = controle(chave_de_api(Some(10), None), 0);
+        let agora = instante(19, 10, 0, 0);
+
+        assert_eq!(controle.verificar_em(None, agora).err(), Some(RecusaDeAcesso::ChaveAusente));
+        assert_eq!(controle.verificar_em(Some("  "), agora).err(), Some(RecusaDeAcesso::ChaveAusente));
+        assert_eq!(controle.verificar_em(Some("cnpj_desconhecida"), agora).err(), Some(RecusaDeAcesso::ChaveInvalida));
+        assert_eq!(controle.verificar_em(Some(&format!(" {} ", CHAVE)), agora).map(|chave| chave.id), Ok(7));
+
+        // as chaves revogadas deixam de ser carregadas na sincronização
+        controle.carregar(vec![], vec![], dia(19));
+        assert_eq!(controle.verificar_em(Some(CHAVE), agora).err(), Some(RecusaDeAcesso::ChaveInvalida));
+
+        // as requisições sem chave válida não são contabilizadas
+        assert_eq!(pendentes(&controle, dia(19)), (1, 0));
+    }
+
+    #[test]
+    fn chave_da_requisicao_nos_cabecalhos() {
+        assert_eq!(chave_da_requisicao(Some("abc"), Some("Bearer def")), Some("abc"));
+        assert_eq!(chave_da_requisicao(None, Some("bearer def")), Some("def"));
+        assert_eq!(chave_da_requisicao(None, Some("Basic def")), None);
+        assert_eq!(chave_da_requisicao(None, None), None);
+    }
+}
@@ -0,0 +1,185 @@
+// Busca textual (full-text) pelo nome das empresas (razão social) e dos estabelecimentos (nome fantasia).
+//
+// A busca utiliza os índices FULLTEXT das colunas empresas.razao_social e estabelecimentos.nome_fantasia
+// no modo booleano do MySQL, onde cada termo informado é obrigatório e pode ser apenas o início de
+// uma palavra (ex: "padaria jo" encontra "PADARIA DO JOAO"). As colunas utilizam a collation
+// utf8mb4_unicode_ci, portanto a busca não diferencia maiúsculas/minúsculas nem acentos.
+
+use diesel::mysql::MysqlConnection;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Double, Integer, Nullable, Text, TinyInt, Unsigned};
+use serde::Serialize;
+
+use crate::dominios::SituacaoCadastral;
+
+// quantidade de resultados retornados quando o limite não é informado
+pub const LIMITE_PADRAO_DA_BUSCA: i64 = 20;
+// quantidade máxima de resultados retornados em uma única busca
+pub const LIMITE_MAXIMO_DA_BUSCA: i64 = 100;
+// tamanho mínimo de um termo para que seja considerado na busca (innodb_ft_min_token_size)
+const TAMANHO_MINIMO_DO_TERMO: usize = 3;
+
+#[derive(Debug, Clone, Serialize, QueryableByName)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(description = "Estabelecimento encontrado na busca pelo nome da empresa"))]
+pub struct ResultadoDaBusca {
+    #[sql_type = "Text"]
+    pub cnpj_basico: String,
+    #[sql_type = "Text"]
+    pub cnpj_ordem: String,
+    #[sql_type = "Text"]
+    pub cnpj_dv: String,
+    #[sql_type = "Text"]
+    pub razao_social: String,
+    #[sql_type = "Nullable<Text>"]
+    pub nome_fantasia: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    pub uf: Option<String>,
+    #[sql_type = "Unsigned<TinyInt>"]
+    pub situacao_cadastral: SituacaoCadastral,
+    #[sql_type = "Double"]
+    pub relevancia: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct FiltroDaBusca {
+    pub uf: Option<String>,
+    pub situacao_cadastral: Option<SituacaoCadastral>,
+    // código de 7 dígitos do IBGE do município do estabelecimento (vide municipios.codigo_ibge)
+    pub municipio_ibge: Option<u32>,
+}
+
+// Indica se o código do município no IBGE tem 7 dígitos
+pub fn codigo_ibge_valido(codigo: u32) -> bool {
+    (1_000_000..=9_999_999).contains(&codigo)
+}
+
+// Converte o texto informado pelo usuário em uma expressão de busca do modo booleano do MySQL,
+// removendo os operadores do modo booleano e os termos muito curtos. Retorna None quando não
+// sobra nenhum termo a ser buscado.
+pub fn expressao_de_busca(texto: &str) -> Option<String> {
+    let termos: Vec<String> = texto
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|termo| termo.chars().count() >= TAMANHO_MINIMO_DO_TERMO)
+        .map(|termo| format!("+{}*", termo.to_uppercase()))
+        .collect();
+
+    if termos.is_empty() {
+        return None;
+    }
+
+    Some(termos.join(" "))
+}
+
+// Cláusulas FROM e WHERE da busca. A condição de continuação (os estabelecimentos com CNPJ maior que o
+// informado) é utilizada na leitura em blocos; com o CNPJ vazio ela não restringe os resultados.
+const CONSULTA_DA_BUSCA: &str = "\
+    FROM ( \
+        SELECT u.cnpj_basico, MAX(u.relevancia) AS relevancia FROM ( \
+            SELECT cnpj_basico, MATCH (razao_social) AGAINST (? IN BOOLEAN MODE) * 2 AS relevancia \
+            FROM empresas WHERE MATCH (razao_social) AGAINST (? IN BOOLEAN MODE) \
+            UNION ALL \
+            SELECT cnpj_basico, MATCH (nome_fantasia) AGAINST (? IN BOOLEAN MODE) AS relevancia \
+            FROM estabelecimentos WHERE MATCH (nome_fantasia) AGAINST (? IN BOOLEAN MODE) \
+        ) u GROUP BY u.cnpj_basico \
+    ) b \
+    INNER JOIN empresas e ON e.cnpj_basico = b.cnpj_basico \
+    INNER JOIN estabelecimentos es ON es.cnpj_basico = b.cnpj_basico \
+    WHERE (? IS NULL OR es.uf = ?) \
+      AND (? IS NULL OR es.situacao_cadastral = ?) \
+      AND (? IS NULL OR es.municipio = (SELECT m.id FROM municipios m WHERE m.codigo_ibge = ?)) \
+      AND (es.cnpj_basico, es.cnpj_ordem, es.cnpj_dv) > (?, ?, ?)";
+
+// CNPJ (básico, ordem e DV) do último estabelecimento lido na busca em blocos
+pub type ContinuacaoDaBusca = (String, String, String);
+
+fn consultar(conn: &MysqlConnection, expressao: &str, filtro: &FiltroDaBusca, apos: Option<&ContinuacaoDaBusca>, ordem: &str, limite: i64, deslocamento: i64) -> QueryResult<Vec<ResultadoDaBusca>> {
+    let uf = filtro.uf.as_ref().map(|v| v.to_uppercase());
+    let (cnpj_basico, cnpj_ordem, cnpj_dv) = apos.map(|(b, o, d)| (b.as_str(), o.as_str(), d.as_str())).unwrap_or(("", "", ""));
+
+    sql_query(format!(
+        "SELECT es.cnpj_basico, es.cnpj_ordem, es.cnpj_dv, e.razao_social, es.nome_fantasia, es.uf, \
+                es.situacao_cadastral, b.relevancia \
+         {} ORDER BY {} LIMIT ? OFFSET ?",
+        CONSULTA_DA_BUSCA, ordem
+    ))
+    .bind::<Text, _>(expressao)
+    .bind::<Text, _>(expressao)
+    .bind::<Text, _>(expressao)
+    .bind::<Text, _>(expressao)
+    .bind::<Nullable<Text>, _>(&uf)
+    .bind::<Nullable<Text>, _>(&uf)
+    .bind::<Nullable<Unsigned<TinyInt>>, _>(filtro.situacao_cadastral)
+    .bind::<Nullable<Unsigned<TinyInt>>, _>(filtro.situacao_cadastral)
+    .bind::<Nullable<Unsigned<Integer>>, _>(filtro.municipio_ibge)
+    .bind::<Nullable<Unsigned<Integer>>, _>(filtro.municipio_ibge)
+    .bind::<Text, _>(cnpj_basico)
+    .bind::<Text, _>(cnpj_ordem)
+    .bind::<Text, _>(cnpj_dv)
+    .bind::<BigInt, _>(limite)
+    .bind::<BigInt, _>(deslocamento)
+    .load::<ResultadoDaBusca>(conn)
+}
+
+// Busca os estabelecimentos cuja razão social da empresa ou cujo nome fantasia correspondam à
+// expressão de busca, ordenados pela relevância. A razão social tem peso maior que o nome fantasia.
+pub fn buscar_empresas(conn: &MysqlConnection, expressao: &str, filtro: &FiltroDaBusca, limite: i64, deslocamento: i64) -> QueryResult<Vec<ResultadoDaBusca>> {
+    consultar(conn, expressao, filtro, None, "b.relevancia DESC, es.cnpj_basico, es.cnpj_ordem", limite, deslocamento)
+}
+
+// Busca um bloco de estabelecimentos, ordenados pelo CNPJ, após o último estabelecimento do bloco anterior.
+// É utilizada nas respostas em streaming, que leem todos os resultados: a continuação pelo CNPJ não relê os
+// blocos anteriores, como aconteceria com o OFFSET. O deslocamento é aplicado somente ao primeiro bloco.
+pub fn buscar_empresas_em_blocos(conn: &MysqlConnection, expressao: &str, filtro: &FiltroDaBusca, apos: Option<&ContinuacaoDaBusca>, limite: i64, deslocamento: i64) -> QueryResult<Vec<ResultadoDaBusca>> {
+    let deslocamento = if apos.is_some() { 0 } else { deslocamento };
+    consultar(conn, expressao, filtro, apos, "es.cnpj_basico, es.cnpj_ordem, es.cnpj_dv", limite, deslocamento)
+}
+
+impl ResultadoDaBusca {
+    pub fn continuacao(&self) -> ContinuacaoDaBusca {
+        (self.cnpj_basico.clone(), self.cnpj_ordem.clone(), self.cnpj_dv.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn termos_com_curinga_de_prefixo() {
+        assert_eq!(expressao_de_busca("padaria"), Some(String::from("+PADARIA*")));
+        assert_eq!(expressao_de_busca("  Padaria   pão  quente "), Some(String::from("+PADARIA* +PÃO* +QUENTE*")));
+        // os termos muito curtos são descartados
+        assert_eq!(expressao_de_busca("casa do pão de ló"), Some(String::from("+CASA* +PÃO*")));
+        assert_eq!(expressao_de_busca("3M do Brasil"), Some(String::from("+BRASIL*")));
+        assert_eq!(expressao_de_busca("posto 123"), Some(String::from("+POSTO* +123*")));
+    }
+
+    #[test]
+    fn operadores_do_modo_booleano_removidos() {
+        assert_eq!(
+            expressao_de_busca("+padaria -pão *quente \"bom gosto\" (massa) <forno> ~lenha @8 sabor*"),
+            Some(String::from("+PADARIA* +PÃO* +QUENTE* +BOM* +GOSTO* +MASSA* +FORNO* +LENHA* +SABOR*"))
+        );
+        // o operador no meio do termo o divide
+        assert_eq!(expressao_de_busca("auto-peças"), Some(String::from("+AUTO* +PEÇAS*")));
+        assert_eq!(expressao_de_busca("padaria*pão"), Some(String::from("+PADARIA* +PÃO*")));
+    }
+
+    #[test]
+    fn termos_acentuados() {
+        assert_eq!(expressao_de_busca("são joão"), Some(String::from("+SÃO* +JOÃO*")));
+        assert_eq!(expressao_de_busca("AÇÚCAR e CAFÉ"), Some(String::from("+AÇÚCAR* +CAFÉ*")));
+        assert_eq!(expressao_de_busca("ônibus"), Some(String::from("+ÔNIBUS*")));
+    }
+
+    #[test]
+    fn sem_termos_a_buscar() {
+        assert_eq!(expressao_de_busca(""), None);
+        assert_eq!(expressao_de_busca("   "), None);
+        assert_eq!(expressao_de_busca("+-*\"()<>~@"), None);
+        assert_eq!(expressao_de_busca("+ - * \" ( ) < > ~ @"), None);
+        assert_eq!(expressao_de_busca("de da do ó"), None);
+    }
+}
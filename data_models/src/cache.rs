@@ -0,0 +1,309 @@
+// Cache em memória das consultas mais frequentes dos servidores.
+//
+// O CacheLru mantém até `capacidade` registros, descartando os menos utilizados recentemente quando está
+// cheio e os que foram incluídos há mais de `ttl`. As tabelas auxiliares (pequenas) são carregadas
+// integralmente em memória (TabelasAuxiliares). Como os dados só mudam a cada importação, os caches devem
+// ser invalidados quando a data/hora de importação de alguma tabela mudar (vide VersaoDoCache).
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDateTime;
+use diesel::mysql::MysqlConnection;
+use diesel::prelude::*;
+use serde::Serialize;
+
+use crate::models::{CNAE, FaixaEtaria, MetadadosDasTabelas, MotivoDeSituacaoCadastral, Municipio, NaturezaJuridica, NomeDaSituacaoCadastral, Pais, QualificacaoDeSocio};
+use crate::schema::{cnaes, faixas_etarias, motivos_de_situacoes_cadastrais, municipios, naturezas_juridicas, paises, qualificacoes_de_socios, situacoes_cadastrais};
+
+struct Entrada<V> {
+    valor: V,
+    incluida_em: Instant,
+    // ordem do último uso, chave de EstadoDoCache::ordem_de_uso
+    uso: u64,
+}
+
+struct EstadoDoCache<V> {
+    entradas: HashMap<String, Entrada<V>>,
+    // chaves das entradas pela ordem do último uso (a primeira é a menos utilizada recentemente)
+    ordem_de_uso: BTreeMap<u64, String>,
+    proximo_uso: u64,
+}
+
+impl<V> EstadoDoCache<V> {
+    fn remover(&mut self, chave: &str) {
+        if let Some(entrada) = self.entradas.remove(chave) {
+            self.ordem_de_uso.remove(&entrada.uso);
+        }
+    }
+}
+
+pub struct CacheLru<V> {
+    nome: &'static str,
+    capacidade: usize,
+    ttl: Duration,
+    estado: Mutex<EstadoDoCache<V>>,
+    acertos: AtomicU64,
+    falhas: AtomicU64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EstatisticasDoCache {
+    pub nome: &'static str,
+    pub capacidade: usize,
+    pub entradas: usize,
+    pub acertos: u64,
+    pub falhas: u64,
+}
+
+impl<V: Clone> CacheLru<V> {
+    // Um cache com capacidade 0 fica desabilitado: nada é armazenado e as consultas não são contabilizadas
+    pub fn new(nome: &'static str, capacidade: usize, ttl: Duration) -> CacheLru<V> {
+        CacheLru {
+            nome,
+            capacidade,
+            ttl,
+            estado: Mutex::new(EstadoDoCache {
+                entradas: HashMap::new(),
+                ordem_de_uso: BTreeMap::new(),
+                proximo_uso: 0,
+            }),
+            acertos: AtomicU64::new(0),
+            falhas: AtomicU64::new(0),
+        }
+    }
+
+    pub fn habilitado(&self) -> bool {
+        self.capacidade > 0
+    }
+
+    pub fn obter(&self, chave: &str) -> Option<V> {
+        if !self.habilitado() {
+            return None;
+        }
+
+        let mut estado = self.estado.lock().unwrap();
+        let uso = estado.proximo_uso;
+
+        let valor = match estado.entradas.get_mut(chave) {
+            Some(entrada) if entrada.incluida_em.elapsed() < self.ttl => {
+                let uso_anterior = entrada.uso;
+                entrada.uso = uso;
+                Some((uso_anterior, entrada.valor.clone()))
+            }
+            _ => None,
+        };
+
+        match valor {
+            Some((uso_anterior, valor)) => {
+                estado.ordem_de_uso.remove(&uso_anterior);
+                estado.ordem_de_uso.insert(uso, chave.to_string());
+                estado.proximo_uso += 1;
+                self.acertos.fetch_add(1, Ordering::Relaxed);
+                Some(valor)
+            }
+            None => {
+                // a entrada pode existir, mas estar expirada
+                estado.remover(chave);
+                self.falhas.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    pub fn incluir(&self, chave: String, valor: V) {
+        if !self.habilitado() {
+            return;
+        }
+
+        let mut estado = self.estado.lock().unwrap();
+        estado.remover(&chave);
+
+        while estado.entradas.len() >= self.capacidade {
+            let menos_utilizada = match estado.ordem_de_uso.values().next() {
+                Some(chave) => chave.clone(),
+                None => break,
+            };
+            estado.remover(&menos_utilizada);
+        }
+
+        let uso = estado.proximo_uso;
+        estado.proximo_uso += 1;
+        estado.ordem_de_uso.insert(uso, chave.clone());
+        estado.entradas.insert(chave, Entrada { valor, incluida_em: Instant::now(), uso });
+    }
+
+    pub fn limpar(&self) {
+        let mut estado = self.estado.lock().unwrap();
+        estado.entradas.clear();
+        estado.ordem_de_uso.clear();
+    }
+
+    pub fn estatisticas(&self) -> EstatisticasDoCache {
+        EstatisticasDoCache {
+            nome: self.nome,
+            capacidade: self.capacidade,
+            entradas: self.estado.lock().unwrap().entradas.len(),
+            acertos: self.acertos.load(Ordering::Relaxed),
+            falhas: self.falhas.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// Tabelas auxiliares carregadas integralmente em memória, cada uma ordenada pelo id
+#[derive(Clone)]
+pub struct TabelasAuxiliares {
+    pub cnaes: Vec<CNAE>,
+    pub naturezas_juridicas: Vec<NaturezaJuridica>,
+    pub qualificacoes_de_socios: Vec<QualificacaoDeSocio>,
+    pub paises: Vec<Pais>,
+    pub municipios: Vec<Municipio>,
+    pub motivos_de_situacoes_cadastrais: Vec<MotivoDeSituacaoCadastral>,
+    pub faixas_etarias: Vec<FaixaEtaria>,
+    pub situacoes_cadastrais: Vec<NomeDaSituacaoCadastral>,
+}
+
+impl TabelasAuxiliares {
+    pub fn carregar(conn: &MysqlConnection) -> QueryResult<TabelasAuxiliares> {
+        Ok(TabelasAuxiliares {
+            cnaes: cnaes::table.order(cnaes::id).load(conn)?,
+            naturezas_juridicas: naturezas_juridicas::table.order(naturezas_juridicas::id).load(conn)?,
+            qualificacoes_de_socios: qualificacoes_de_socios::table.order(qualificacoes_de_socios::id).load(conn)?,
+            paises: paises::table.order(paises::id).load(conn)?,
+            municipios: municipios::table.order(municipios::id).load(conn)?,
+            motivos_de_situacoes_cadastrais: motivos_de_situacoes_cadastrais::table.order(motivos_de_situacoes_cadastrais::id).load(conn)?,
+            faixas_etarias: faixas_etarias::table.order(faixas_etarias::id).load(conn)?,
+            situacoes_cadastrais: situacoes_cadastrais::table.order(situacoes_cadastrais::id).load(conn)?,
+        })
+    }
+}
+
+// Versão dos dados em cache: a maior data/hora de importação entre as tabelas
+#[derive(Default)]
+pub struct VersaoDoCache(Mutex<Option<NaiveDateTime>>);
+
+impl VersaoDoCache {
+    // Registra a versão atual dos dados, retornando true quando ela mudou (ou seja, o cache deve ser invalidado)
+    pub fn mudou(&self, metadados: &[MetadadosDasTabelas]) -> bool {
+        let atual = metadados.iter().map(|m| m.data_hora_de_importacao).max();
+        let mut versao = self.0.lock().unwrap();
+        if *versao == atual && atual.is_some() {
+            return false;
+        }
+        *versao = atual;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const UMA_HORA: Duration = Duration::from_secs(3600);
+
+    fn chaves(cache: &CacheLru<u32>) -> Vec<String> {
+        let estado = cache.estado.lock().unwrap();
+        let mut chaves: Vec<String> = estado.entradas.keys().cloned().collect();
+        chaves.sort();
+        chaves
+    }
+
+    fn metadados(importacoes: &[u32]) -> Vec<MetadadosDasTabelas> {
+        importacoes
+            .iter()
+            .map(|dia| MetadadosDasTabelas {
+                tabela: format!("tabela_{}", dia),
+                data_hora_de_atualizacao: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+                data_hora_de_importacao: NaiveDate::from_ymd_opt(2026, 10, *dia).unwrap().and_hms_opt(3, 0, 0).unwrap(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn descarta_a_entrada_menos_utilizada_recentemente() {
+        let cache = CacheLru::new("teste", 3, UMA_HORA);
+        cache.incluir(String::from("a"), 1);
+        cache.incluir(String::from("b"), 2);
+        cache.incluir(String::from("c"), 3);
+
+        // "a" passa a ser a mais utilizada recentemente, então "b" é descartada
+        assert_eq!(cache.obter("a"), Some(1));
+        cache.incluir(String::from("d"), 4);
+        assert_eq!(chaves(&cache), vec!["a", "c", "d"]);
+
+        // a inclusão de uma chave existente substitui o valor e também conta como uso
+        cache.incluir(String::from("c"), 30);
+        cache.incluir(String::from("e"), 5);
+        assert_eq!(chaves(&cache), vec!["c", "d", "e"]);
+        assert_eq!(cache.obter("c"), Some(30));
+        assert_eq!(cache.obter("a"), None);
+        assert_eq!(cache.obter("b"), None);
+
+        let estatisticas = cache.estatisticas();
+        assert_eq!((estatisticas.entradas, estatisticas.acertos, estatisticas.falhas), (3, 2, 2));
+    }
+
+    #[test]
+    fn entradas_expiradas() {
+        let cache = CacheLru::new("teste", 10, Duration::from_millis(20));
+        cache.incluir(String::from("a"), 1);
+        std::thread::sleep(Duration::from_millis(40));
+
+        assert_eq!(cache.obter("a"), None);
+        // a entrada expirada é removida na consulta
+        assert_eq!(cache.estatisticas().entradas, 0);
+
+        let cache = CacheLru::new("teste", 10, Duration::ZERO);
+        cache.incluir(String::from("a"), 1);
+        assert_eq!(cache.obter("a"), None);
+
+        let cache = CacheLru::new("teste", 10, UMA_HORA);
+        cache.incluir(String::from("a"), 1);
+        assert_eq!(cache.obter("a"), Some(1));
+    }
+
+    #[test]
+    fn cache_desabilitado() {
+        let cache = CacheLru::new("teste", 0, UMA_HORA);
+        cache.incluir(String::from("a"), 1);
+
+        assert!(!cache.habilitado());
+        assert_eq!(cache.obter("a"), None);
+        let estatisticas = cache.estatisticas();
+        assert_eq!((estatisticas.entradas, estatisticas.acertos, estatisticas.falhas), (0, 0, 0));
+    }
+
+    #[test]
+    fn invalidacao_pela_versao_dos_dados() {
+        let cache = CacheLru::new("teste", 10, UMA_HORA);
+        let versao = VersaoDoCache::default();
+        // como os servidores, o cache é limpo quando a versão muda
+        let atualizar = |metadados: &[MetadadosDasTabelas]| {
+            let mudou = versao.mudou(metadados);
+            if mudou {
+                cache.limpar();
+            }
+            mudou
+        };
+
+        assert!(atualizar(&metadados(&[10, 12])));
+        cache.incluir(String::from("a"), 1);
+
+        // a versão é a maior data/hora de importação, independentemente da ordem das tabelas
+        assert!(!atualizar(&metadados(&[12, 10])));
+        assert_eq!(cache.obter("a"), Some(1));
+
+        // a importação de uma tabela muda a versão
+        assert!(atualizar(&metadados(&[13, 10])));
+        assert_eq!(cache.obter("a"), None);
+
+        // sem os metadados (ex: tabelas ainda não importadas), o cache é sempre invalidado
+        cache.incluir(String::from("a"), 1);
+        assert!(atualizar(&[]));
+        assert!(atualizar(&[]));
+        assert_eq!(cache.obter("a"), None);
+    }
+}
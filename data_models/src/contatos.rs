@@ -0,0 +1,253 @@
+// Normalização dos telefones e do correio eletrônico dos estabelecimentos, que nos arquivos da Receita Federal
+// são copiados como foram informados (DDD com zeros à esquerda, números com pontuação, celulares antigos sem
+// o nono dígito, e-mails em maiúsculas etc.). Utilizada pelo importer (--normalize-contacts), que grava os
+// telefones no formato E.164 (+55DDXXXXXXXX) e o e-mail normalizado, e pelos servidores, que retornam os
+// telefones e o e-mail com a indicação de validade.
+
+use serde::Serialize;
+
+const CODIGO_DO_BRASIL: &str = "+55";
+
+/// Tipo do telefone, pelo número no formato E.164
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLEnum))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TipoDeTelefone {
+    /// Telefone fixo (8 dígitos)
+    Fixo,
+    /// Celular (9 dígitos, iniciado por 9)
+    Celular,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(description = "Telefone ou fax do estabelecimento"))]
+pub struct Telefone {
+    // campo de origem: telefone1, telefone2 ou fax
+    pub origem: String,
+    pub ddd: Option<String>,
+    pub numero: Option<String>,
+    // número no formato E.164 (ex: +5511987654321), ou None quando o telefone é inválido
+    pub e164: Option<String>,
+    pub tipo: Option<TipoDeTelefone>,
+    pub valido: bool,
+}
+
+impl Telefone {
+    // Telefone a partir do DDD e do número como constam nos arquivos e do número no formato E.164 gravado na
+    // importação (calculado quando não gravado). Retorna None quando o DDD e o número estão vazios.
+    pub fn new(origem: &str, ddd: Option<&str>, numero: Option<&str>, e164_gravado: Option<&str>) -> Option<Telefone> {
+        let ddd = ddd.map(str::trim).filter(|ddd| !ddd.is_empty());
+        let numero = numero.map(str::trim).filter(|numero| !numero.is_empty());
+        if ddd.is_none() && numero.is_none() {
+            return None;
+        }
+
+        let e164 = match e164_gravado {
+            Some(e164) => Some(e164.to_string()),
+            None => telefone_e164(ddd, numero),
+        };
+        Some(Telefone {
+            origem: origem.to_string(),
+            ddd: ddd.map(String::from),
+            numero: numero.map(String::from),
+            tipo: e164.as_deref().map(tipo_do_telefone),
+            valido: e164.is_some(),
+            e164,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(description = "Correio eletrônico (e-mail) do estabelecimento"))]
+pub struct Email {
+    // e-mail normalizado (sem espaços e em minúsculas)
+    pub endereco: String,
+    pub valido: bool,
+}
+
+impl Email {
+    // E-mail a partir do correio eletrônico como consta nos arquivos e do e-mail normalizado e da validade
+    // gravados na importação (calculados quando não gravados). Retorna None quando o correio eletrônico está vazio.
+    pub fn new(correio_eletronico: Option<&str>, normalizado: Option<&str>, valido: Option<bool>) -> Option<Email> {
+        let endereco = match normalizado {
+            Some(endereco) => endereco.to_string(),
+            None => normalizar_email(correio_eletronico?)?,
+        };
+        Some(Email {
+            valido: valido.unwrap_or_else(|| email_valido(&endereco)),
+            endereco,
+        })
+    }
+}
+
+// Telefone no formato E.164 (+55DDXXXXXXXX ou +55DD9XXXXXXXX), a partir do DDD e do número. Os celulares
+// antigos, com 8 dígitos iniciados por 6, 7, 8 ou 9, recebem o nono dígito. Retorna None quando o telefone
+// é inválido.
+pub fn telefone_e164(ddd: Option<&str>, numero: Option<&str>) -> Option<String> {
+    let ddd: String = ddd?.chars().filter(char::is_ascii_digit).collect();
+    let ddd = ddd.trim_start_matches('0');
+    let numero: String = numero?.chars().filter(char::is_ascii_digit).collect();
+
+    // os DDDs vão de 11 a 99, sem o dígito 0
+    if ddd.len() != 2 || ddd.contains('0') {
+        return None;
+    }
+
+    let numero = match (numero.len(), numero.chars().next()?) {
+        (8, '2'..='5') => numero,
+        (8, '6'..='9') => format!("9{}", numero),
+        (9, '9') => numero,
+        _ => return None,
+    };
+    Some(format!("{}{}{}", CODIGO_DO_BRASIL, ddd, numero))
+}
+
+// Tipo do telefone pela quantidade de dígitos do número no formato E.164 (+55, DDD e número)
+pub fn tipo_do_telefone(e164: &str) -> TipoDeTelefone {
+    if e164.len() == CODIGO_DO_BRASIL.len() + 2 + 9 {
+        TipoDeTelefone::Celular
+    } else {
+        TipoDeTelefone::Fixo
+    }
+}
+
+// Remove os espaços e converte o e-mail para minúsculas (None se ficar vazio)
+pub fn normalizar_email(email: &str) -> Option<String> {
+    let email: String = email.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_lowercase();
+    if email.is_empty() {
+        return None;
+    }
+    Some(email)
+}
+
+// Validação sintática do e-mail (já normalizado): uma parte local e um domínio com pelo menos dois rótulos,
+// formados por letras, dígitos e hífens, e terminado por um rótulo com pelo menos duas letras
+pub fn email_valido(email: &str) -> bool {
+    let (local, dominio) = match email.split_once('@') {
+        Some(partes) => partes,
+        None => return false,
+    };
+
+    let local_valido = !local.is_empty()
+        && local.len() <= 64
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local.chars().all(|c| c.is_ascii_alphanumeric() || "._%+-'".contains(c));
+
+    let rotulos: Vec<&str> = dominio.split('.').collect();
+    let tld = rotulos.last().copied().unwrap_or_default();
+    let dominio_valido = rotulos.len() >= 2
+        && rotulos.iter().all(|rotulo| {
+            !rotulo.is_empty()
+                && !rotulo.starts_with('-')
+                && !rotulo.ends_with('-')
+                && rotulo.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+        && tld.len() >= 2
+        && tld.chars().all(|c| c.is_ascii_alphabetic());
+
+    local_valido && dominio_valido
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn telefones_no_formato_e164() {
+        let casos = [
+            // DDD com zeros à esquerda
+            (Some("011"), Some("32345678"), Some("+551132345678")),
+            (Some("0011"), Some("32345678"), Some("+551132345678")),
+            (Some(" 21 "), Some("2345-6789"), Some("+552123456789")),
+            // celulares com 9 dígitos
+            (Some("11"), Some("987654321"), Some("+5511987654321")),
+            (Some("11"), Some("98765-4321"), Some("+5511987654321")),
+            // celulares antigos, com 8 dígitos, recebem o nono dígito
+            (Some("11"), Some("87654321"), Some("+5511987654321")),
+            (Some("85"), Some("6123-4567"), Some("+5585961234567")),
+            (Some("061"), Some("71234567"), Some("+5561971234567")),
+            // DDD inválido
+            (Some("10"), Some("32345678"), None),
+            (Some("1"), Some("32345678"), None),
+            (Some("123"), Some("32345678"), None),
+            (Some("000"), Some("32345678"), None),
+            (None, Some("32345678"), None),
+            // número inválido
+            (Some("11"), Some("12345678"), None),
+            (Some("11"), Some("0800123456"), None),
+            (Some("11"), Some("887654321"), None),
+            (Some("11"), Some("1234567"), None),
+            (Some("11"), Some(""), None),
+            (Some("11"), None, None),
+        ];
+        for (ddd, numero, e164) in casos {
+            assert_eq!(telefone_e164(ddd, numero).as_deref(), e164, "telefone_e164({:?}, {:?})", ddd, numero);
+        }
+    }
+
+    #[test]
+    fn tipo_do_telefone_pelo_numero_e164() {
+        assert_eq!(tipo_do_telefone("+551132345678"), TipoDeTelefone::Fixo);
+        assert_eq!(tipo_do_telefone("+5511987654321"), TipoDeTelefone::Celular);
+
+        let celular_antigo = Telefone::new("telefone1", Some("011"), Some("8765-4321"), None).unwrap();
+        assert_eq!(celular_antigo.e164.as_deref(), Some("+5511987654321"));
+        assert_eq!(celular_antigo.tipo, Some(TipoDeTelefone::Celular));
+        assert!(celular_antigo.valido);
+
+        let invalido = Telefone::new("fax", Some("11"), Some("123"), None).unwrap();
+        assert_eq!(invalido.e164, None);
+        assert_eq!(invalido.tipo, None);
+        assert!(!invalido.valido);
+
+        assert_eq!(Telefone::new("telefone2", Some(" "), None, None), None);
+    }
+
+    #[test]
+    fn emails_validos_e_invalidos() {
+        let casos = [
+            ("contato@empresa.com.br", true),
+            ("nome.sobrenome+tag@sub-dominio.exemplo.com", true),
+            ("o'neil@exemplo.org", true),
+            ("a@b.co", true),
+            ("", false),
+            ("sem-arroba.com.br", false),
+            ("@empresa.com.br", false),
+            ("contato@", false),
+            ("contato@empresa", false),
+            ("contato@empresa.", false),
+            ("contato@.empresa.com", false),
+            ("contato@empresa..com", false),
+            ("contato@-empresa.com", false),
+            ("contato@empresa-.com", false),
+            ("contato@empresa.c", false),
+            ("contato@empresa.com1", false),
+            ("contato@empresa_x.com", false),
+            (".contato@empresa.com", false),
+            ("contato.@empresa.com", false),
+            ("con..tato@empresa.com", false),
+            ("con tato@empresa.com", false),
+            ("contato@@empresa.com", false),
+            ("contato@empresa@com.br", false),
+        ];
+        for (email, valido) in casos {
+            assert_eq!(email_valido(email), valido, "email_valido({:?})", email);
+        }
+    }
+
+    #[test]
+    fn normalizacao_do_email() {
+        assert_eq!(normalizar_email(" Contato @Empresa.COM.BR ").as_deref(), Some("contato@empresa.com.br"));
+        assert_eq!(normalizar_email("   "), None);
+
+        let email = Email::new(Some("CONTATO@EMPRESA.COM.BR"), None, None).unwrap();
+        assert_eq!(email.endereco, "contato@empresa.com.br");
+        assert!(email.valido);
+        assert!(!Email::new(Some("contato(at)empresa"), None, None).unwrap().valido);
+        assert_eq!(Email::new(None, None, None), None);
+    }
+}
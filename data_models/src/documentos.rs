@@ -0,0 +1,59 @@
+// Funções auxiliares para tratar os documentos (CPF e CNPJ) informados pelos usuários das APIs.
+
+// Remove a formatação (pontos, barras, traços e espaços) de um CPF ou CNPJ
+pub fn somente_digitos(documento: &str) -> String {
+    documento.chars().filter(|c| c.is_ascii_digit()).collect()
+}
+
+// Nos arquivos da Receita Federal o CPF dos sócios e dos representantes legais é mascarado,
+// sendo exibidos apenas os 6 dígitos centrais (ex: ***123456**). Esta função aceita tanto o CPF
+// completo (formatado ou não) quanto o CPF já mascarado e devolve o CPF no formato da RF.
+pub fn mascarar_cpf(cpf: &str) -> Option<String> {
+    let cpf = cpf.trim();
+
+    if cpf.len() == 11 && cpf.starts_with("***") && cpf.ends_with("**") {
+        let digitos_centrais = &cpf[3..9];
+        if digitos_centrais.chars().all(|c| c.is_ascii_digit()) {
+            return Some(cpf.to_string());
+        }
+        return None;
+    }
+
+    let digitos = somente_digitos(cpf);
+    if digitos.len() != 11 {
+        return None;
+    }
+
+    Some(format!("***{}**", &digitos[3..9]))
+}
+
+// Calcula um dígito verificador do CNPJ a partir dos dígitos anteriores (módulo 11)
+fn digito_verificador_do_cnpj(digitos: &[u32]) -> u32 {
+    let pesos = [6, 5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2];
+    let inicio = pesos.len() - digitos.len();
+    let soma: u32 = digitos.iter().zip(&pesos[inicio..]).map(|(d, p)| d * p).sum();
+    match soma % 11 {
+        0 | 1 => 0,
+        resto => 11 - resto,
+    }
+}
+
+// Verifica se o CNPJ (somente os 14 dígitos) possui os dígitos verificadores corretos
+pub fn cnpj_valido(cnpj: &str) -> bool {
+    if cnpj.len() != 14 || !cnpj.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    let digitos: Vec<u32> = cnpj.chars().filter_map(|c| c.to_digit(10)).collect();
+
+    digito_verificador_do_cnpj(&digitos[..12]) == digitos[12]
+        && digito_verificador_do_cnpj(&digitos[..13]) == digitos[13]
+}
+
+// Remove a formatação do CNPJ completo (ex: 00.000.000/0001-91) e retorna os 14 dígitos, caso o CNPJ seja válido
+pub fn normalizar_cnpj(cnpj: &str) -> Option<String> {
+    let cnpj = somente_digitos(cnpj);
+    if cnpj_valido(&cnpj) {
+        return Some(cnpj);
+    }
+    None
+}
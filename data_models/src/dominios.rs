@@ -0,0 +1,535 @@
+// Domínios (códigos) utilizados nos arquivos de dados abertos do CNPJ da Receita Federal.
+//
+// Cada domínio é gravado no banco de dados com o mesmo código utilizado nos arquivos da RF
+// (ex: "01" para Micro Empresa, 2 para situação cadastral Ativa), mas é exposto nas APIs
+// (REST e GraphQL) pelo nome da variante (ex: "MICRO_EMPRESA", "ATIVA"). Quando o nome da
+// variante contém números, o nome é informado explicitamente para que o serde e o juniper
+// gerem exatamente o mesmo nome.
+//
+// Os códigos que não pertencem ao domínio são recusados na importação (vide importer::import), mas os
+// registros já gravados com um código desconhecido (ex: gravados por versões anteriores do importador ou
+// diretamente no banco de dados) são lidos como a variante Desconhecido/Desconhecida, que não tem código
+// e não pode ser gravada.
+
+use std::io::Write;
+use std::str::FromStr;
+
+use diesel::deserialize::{self, FromSql};
+use diesel::mysql::Mysql;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::{Text, TinyInt, Unsigned};
+use serde::{Deserialize, Serialize};
+
+// Implementa a conversão de/para as colunas do tipo CHAR/VARCHAR
+macro_rules! impl_sql_texto {
+    ($tipo:ident, $desconhecido:ident) => {
+        impl ToSql<Text, Mysql> for $tipo {
+            fn to_sql<W: Write>(&self, out: &mut Output<W, Mysql>) -> serialize::Result {
+                let codigo = self.codigo().ok_or_else(|| format!("O código desconhecido de {} não pode ser gravado", stringify!($tipo)))?;
+                ToSql::<Text, Mysql>::to_sql(codigo, out)
+            }
+        }
+
+        impl FromSql<Text, Mysql> for $tipo {
+            fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+                let codigo: String = FromSql::<Text, Mysql>::from_sql(bytes)?;
+                Ok($tipo::from_str(&codigo).unwrap_or($tipo::$desconhecido))
+            }
+        }
+    };
+}
+
+// Implementa a conversão de/para as colunas do tipo TINYINT UNSIGNED
+macro_rules! impl_sql_tinyint {
+    ($tipo:ident, $desconhecido:ident) => {
+        impl ToSql<Unsigned<TinyInt>, Mysql> for $tipo {
+            fn to_sql<W: Write>(&self, out: &mut Output<W, Mysql>) -> serialize::Result {
+                let codigo = self.codigo().ok_or_else(|| format!("O código desconhecido de {} não pode ser gravado", stringify!($tipo)))?;
+                ToSql::<Unsigned<TinyInt>, Mysql>::to_sql(&codigo, out)
+            }
+        }
+
+        impl FromSql<Unsigned<TinyInt>, Mysql> for $tipo {
+            fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+                let codigo: u8 = FromSql::<Unsigned<TinyInt>, Mysql>::from_sql(bytes)?;
+                Ok($tipo::from_codigo(codigo).unwrap_or($tipo::$desconhecido))
+            }
+        }
+
+        impl FromStr for $tipo {
+            type Err = ();
+
+            fn from_str(codigo: &str) -> Result<Self, Self::Err> {
+                codigo
+                    .trim()
+                    .parse()
+                    .ok()
+                    .and_then($tipo::from_codigo)
+                    .ok_or(())
+            }
+        }
+    };
+}
+
+/// Porte da Empresa
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsExpression, FromSqlRow, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLEnum))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sql_type = "Text"]
+pub enum Porte {
+    /// Não informado
+    NaoInformado,
+    /// Micro Empresa
+    MicroEmpresa,
+    /// Empresa de Pequeno Porte
+    EmpresaDePequenoPorte,
+    /// Demais
+    Demais,
+    /// Desconhecido (código que não pertence ao domínio)
+    Desconhecido,
+}
+
+impl Porte {
+    pub fn codigo(&self) -> Option<&'static str> {
+        match self {
+            Porte::NaoInformado => Some("00"),
+            Porte::MicroEmpresa => Some("01"),
+            Porte::EmpresaDePequenoPorte => Some("03"),
+            Porte::Demais => Some("05"),
+            Porte::Desconhecido => None,
+        }
+    }
+
+    pub fn descricao(&self) -> &'static str {
+        match self {
+            Porte::NaoInformado => "Não informado",
+            Porte::MicroEmpresa => "Micro Empresa",
+            Porte::EmpresaDePequenoPorte => "Empresa de Pequeno Porte",
+            Porte::Demais => "Demais",
+            Porte::Desconhecido => "Desconhecido",
+        }
+    }
+}
+
+impl FromStr for Porte {
+    type Err = ();
+
+    fn from_str(codigo: &str) -> Result<Self, Self::Err> {
+        match codigo.trim() {
+            "00" => Ok(Porte::NaoInformado),
+            "01" => Ok(Porte::MicroEmpresa),
+            "03" => Ok(Porte::EmpresaDePequenoPorte),
+            "05" => Ok(Porte::Demais),
+            _ => Err(()),
+        }
+    }
+}
+
+impl_sql_texto!(Porte, Desconhecido);
+
+/// Identificador de Matriz ou Filial do Estabelecimento
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsExpression, FromSqlRow, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLEnum))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sql_type = "Text"]
+pub enum MatrizFilial {
+    /// Matriz
+    Matriz,
+    /// Filial
+    Filial,
+    /// Desconhecido (código que não pertence ao domínio)
+    Desconhecido,
+}
+
+impl MatrizFilial {
+    pub fn codigo(&self) -> Option<&'static str> {
+        match self {
+            MatrizFilial::Matriz => Some("1"),
+            MatrizFilial::Filial => Some("2"),
+            MatrizFilial::Desconhecido => None,
+        }
+    }
+
+    pub fn descricao(&self) -> &'static str {
+        match self {
+            MatrizFilial::Matriz => "Matriz",
+            MatrizFilial::Filial => "Filial",
+            MatrizFilial::Desconhecido => "Desconhecido",
+        }
+    }
+}
+
+impl FromStr for MatrizFilial {
+    type Err = ();
+
+    fn from_str(codigo: &str) -> Result<Self, Self::Err> {
+        match codigo.trim() {
+            "1" => Ok(MatrizFilial::Matriz),
+            "2" => Ok(MatrizFilial::Filial),
+            _ => Err(()),
+        }
+    }
+}
+
+impl_sql_texto!(MatrizFilial, Desconhecido);
+
+/// Situação Cadastral do Estabelecimento
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsExpression, FromSqlRow, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLEnum))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sql_type = "Unsigned<TinyInt>"]
+pub enum SituacaoCadastral {
+    /// Nula
+    Nula,
+    /// Ativa
+    Ativa,
+    /// Suspensa
+    Suspensa,
+    /// Inapta
+    Inapta,
+    /// Baixada
+    Baixada,
+    /// Desconhecida (código que não pertence ao domínio)
+    Desconhecida,
+}
+
+impl SituacaoCadastral {
+    pub fn codigo(&self) -> Option<u8> {
+        match self {
+            SituacaoCadastral::Nula => Some(1),
+            SituacaoCadastral::Ativa => Some(2),
+            SituacaoCadastral::Suspensa => Some(3),
+            SituacaoCadastral::Inapta => Some(4),
+            SituacaoCadastral::Baixada => Some(8),
+            SituacaoCadastral::Desconhecida => None,
+        }
+    }
+
+    pub fn from_codigo(codigo: u8) -> Option<Self> {
+        match codigo {
+            1 => Some(SituacaoCadastral::Nula),
+            2 => Some(SituacaoCadastral::Ativa),
+            3 => Some(SituacaoCadastral::Suspensa),
+            4 => Some(SituacaoCadastral::Inapta),
+            8 => Some(SituacaoCadastral::Baixada),
+            _ => None,
+        }
+    }
+
+    pub fn descricao(&self) -> &'static str {
+        match self {
+            SituacaoCadastral::Nula => "Nula",
+            SituacaoCadastral::Ativa => "Ativa",
+            SituacaoCadastral::Suspensa => "Suspensa",
+            SituacaoCadastral::Inapta => "Inapta",
+            SituacaoCadastral::Baixada => "Baixada",
+            SituacaoCadastral::Desconhecida => "Desconhecida",
+        }
+    }
+}
+
+impl_sql_tinyint!(SituacaoCadastral, Desconhecida);
+
+/// Identificador de Sócio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsExpression, FromSqlRow, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLEnum))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sql_type = "Unsigned<TinyInt>"]
+pub enum IdentificadorDeSocio {
+    /// Pessoa Jurídica
+    PessoaJuridica,
+    /// Pessoa Física
+    PessoaFisica,
+    /// Estrangeiro
+    Estrangeiro,
+    /// Desconhecido (código que não pertence ao domínio)
+    Desconhecido,
+}
+
+impl IdentificadorDeSocio {
+    pub fn codigo(&self) -> Option<u8> {
+        match self {
+            IdentificadorDeSocio::PessoaJuridica => Some(1),
+            IdentificadorDeSocio::PessoaFisica => Some(2),
+            IdentificadorDeSocio::Estrangeiro => Some(3),
+            IdentificadorDeSocio::Desconhecido => None,
+        }
+    }
+
+    pub fn from_codigo(codigo: u8) -> Option<Self> {
+        match codigo {
+            1 => Some(IdentificadorDeSocio::PessoaJuridica),
+            2 => Some(IdentificadorDeSocio::PessoaFisica),
+            3 => Some(IdentificadorDeSocio::Estrangeiro),
+            _ => None,
+        }
+    }
+
+    pub fn descricao(&self) -> &'static str {
+        match self {
+            IdentificadorDeSocio::PessoaJuridica => "Pessoa Jurídica",
+            IdentificadorDeSocio::PessoaFisica => "Pessoa Física",
+            IdentificadorDeSocio::Estrangeiro => "Estrangeiro",
+            IdentificadorDeSocio::Desconhecido => "Desconhecido",
+        }
+    }
+}
+
+impl_sql_tinyint!(IdentificadorDeSocio, Desconhecido);
+
+/// Opção pelo Simples Nacional ou pelo MEI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsExpression, FromSqlRow, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLEnum))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sql_type = "Text"]
+pub enum OpcaoSimples {
+    /// Sim
+    Sim,
+    /// Não
+    Nao,
+    /// Outros (campo em branco no arquivo da Receita Federal)
+    Outros,
+    /// Desconhecida (código que não pertence ao domínio)
+    Desconhecida,
+}
+
+impl OpcaoSimples {
+    pub fn codigo(&self) -> Option<&'static str> {
+        match self {
+            OpcaoSimples::Sim => Some("S"),
+            OpcaoSimples::Nao => Some("N"),
+            OpcaoSimples::Outros => Some(""),
+            OpcaoSimples::Desconhecida => None,
+        }
+    }
+
+    pub fn descricao(&self) -> &'static str {
+        match self {
+            OpcaoSimples::Sim => "Sim",
+            OpcaoSimples::Nao => "Não",
+            OpcaoSimples::Outros => "Outros",
+            OpcaoSimples::Desconhecida => "Desconhecida",
+        }
+    }
+}
+
+impl FromStr for OpcaoSimples {
+    type Err = ();
+
+    fn from_str(codigo: &str) -> Result<Self, Self::Err> {
+        match codigo.trim() {
+            "S" => Ok(OpcaoSimples::Sim),
+            "N" => Ok(OpcaoSimples::Nao),
+            "" => Ok(OpcaoSimples::Outros),
+            _ => Err(()),
+        }
+    }
+}
+
+impl_sql_texto!(OpcaoSimples, Desconhecida);
+
+/// Faixa Etária do Sócio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsExpression, FromSqlRow, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLEnum))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sql_type = "Unsigned<TinyInt>"]
+pub enum FaixaEtaria {
+    /// Não se aplica
+    NaoSeAplica,
+    /// Entre 0 e 12 anos
+    #[serde(rename = "DE_0_A_12_ANOS")]
+    #[cfg_attr(feature = "graphql", graphql(name = "DE_0_A_12_ANOS"))]
+    De0A12Anos,
+    /// Entre 13 e 20 anos
+    #[serde(rename = "DE_13_A_20_ANOS")]
+    #[cfg_attr(feature = "graphql", graphql(name = "DE_13_A_20_ANOS"))]
+    De13A20Anos,
+    /// Entre 21 e 30 anos
+    #[serde(rename = "DE_21_A_30_ANOS")]
+    #[cfg_attr(feature = "graphql", graphql(name = "DE_21_A_30_ANOS"))]
+    De21A30Anos,
+    /// Entre 31 e 40 anos
+    #[serde(rename = "DE_31_A_40_ANOS")]
+    #[cfg_attr(feature = "graphql", graphql(name = "DE_31_A_40_ANOS"))]
+    De31A40Anos,
+    /// Entre 41 e 50 anos
+    #[serde(rename = "DE_41_A_50_ANOS")]
+    #[cfg_attr(feature = "graphql", graphql(name = "DE_41_A_50_ANOS"))]
+    De41A50Anos,
+    /// Entre 51 e 60 anos
+    #[serde(rename = "DE_51_A_60_ANOS")]
+    #[cfg_attr(feature = "graphql", graphql(name = "DE_51_A_60_ANOS"))]
+    De51A60Anos,
+    /// Entre 61 e 70 anos
+    #[serde(rename = "DE_61_A_70_ANOS")]
+    #[cfg_attr(feature = "graphql", graphql(name = "DE_61_A_70_ANOS"))]
+    De61A70Anos,
+    /// Entre 71 e 80 anos
+    #[serde(rename = "DE_71_A_80_ANOS")]
+    #[cfg_attr(feature = "graphql", graphql(name = "DE_71_A_80_ANOS"))]
+    De71A80Anos,
+    /// Maiores de 80 anos
+    #[serde(rename = "MAIOR_DE_80_ANOS")]
+    #[cfg_attr(feature = "graphql", graphql(name = "MAIOR_DE_80_ANOS"))]
+    MaiorDe80Anos,
+    /// Desconhecida (código que não pertence ao domínio)
+    Desconhecida,
+}
+
+impl FaixaEtaria {
+    pub fn codigo(&self) -> Option<u8> {
+        match self {
+            FaixaEtaria::NaoSeAplica => Some(0),
+            FaixaEtaria::De0A12Anos => Some(1),
+            FaixaEtaria::De13A20Anos => Some(2),
+            FaixaEtaria::De21A30Anos => Some(3),
+            FaixaEtaria::De31A40Anos => Some(4),
+            FaixaEtaria::De41A50Anos => Some(5),
+            FaixaEtaria::De51A60Anos => Some(6),
+            FaixaEtaria::De61A70Anos => Some(7),
+            FaixaEtaria::De71A80Anos => Some(8),
+            FaixaEtaria::MaiorDe80Anos => Some(9),
+            FaixaEtaria::Desconhecida => None,
+        }
+    }
+
+    pub fn from_codigo(codigo: u8) -> Option<Self> {
+        match codigo {
+            0 => Some(FaixaEtaria::NaoSeAplica),
+            1 => Some(FaixaEtaria::De0A12Anos),
+            2 => Some(FaixaEtaria::De13A20Anos),
+            3 => Some(FaixaEtaria::De21A30Anos),
+            4 => Some(FaixaEtaria::De31A40Anos),
+            5 => Some(FaixaEtaria::De41A50Anos),
+            6 => Some(FaixaEtaria::De51A60Anos),
+            7 => Some(FaixaEtaria::De61A70Anos),
+            8 => Some(FaixaEtaria::De71A80Anos),
+            9 => Some(FaixaEtaria::MaiorDe80Anos),
+            _ => None,
+        }
+    }
+
+    pub fn descricao(&self) -> &'static str {
+        match self {
+            FaixaEtaria::NaoSeAplica => "Não se aplica",
+            FaixaEtaria::De0A12Anos => "Entre 0 e 12 anos",
+            FaixaEtaria::De13A20Anos => "Entre 13 e 20 anos",
+            FaixaEtaria::De21A30Anos => "Entre 21 e 30 anos",
+            FaixaEtaria::De31A40Anos => "Entre 31 e 40 anos",
+            FaixaEtaria::De41A50Anos => "Entre 41 e 50 anos",
+            FaixaEtaria::De51A60Anos => "Entre 51 e 60 anos",
+            FaixaEtaria::De61A70Anos => "Entre 61 e 70 anos",
+            FaixaEtaria::De71A80Anos => "Entre 71 e 80 anos",
+            FaixaEtaria::MaiorDe80Anos => "Maiores de 80 anos",
+            FaixaEtaria::Desconhecida => "Desconhecida",
+        }
+    }
+}
+
+impl_sql_tinyint!(FaixaEtaria, Desconhecida);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codigos_de_texto() {
+        let casos: [(&str, Porte); 4] = [
+            ("00", Porte::NaoInformado),
+            ("01", Porte::MicroEmpresa),
+            ("03", Porte::EmpresaDePequenoPorte),
+            ("05", Porte::Demais),
+        ];
+        for (codigo, porte) in casos.iter() {
+            assert_eq!(Porte::from_str(codigo), Ok(*porte));
+            assert_eq!(porte.codigo(), Some(*codigo));
+        }
+
+        for (codigo, matriz_filial) in [("1", MatrizFilial::Matriz), ("2", MatrizFilial::Filial)].iter() {
+            assert_eq!(MatrizFilial::from_str(codigo), Ok(*matriz_filial));
+            assert_eq!(matriz_filial.codigo(), Some(*codigo));
+        }
+
+        for (codigo, opcao) in [("S", OpcaoSimples::Sim), ("N", OpcaoSimples::Nao), ("", OpcaoSimples::Outros)].iter() {
+            assert_eq!(OpcaoSimples::from_str(codigo), Ok(*opcao));
+            assert_eq!(opcao.codigo(), Some(*codigo));
+        }
+
+        // os espaços ao redor do código são ignorados
+        assert_eq!(Porte::from_str(" 01 "), Ok(Porte::MicroEmpresa));
+        assert_eq!(OpcaoSimples::from_str("  "), Ok(OpcaoSimples::Outros));
+    }
+
+    #[test]
+    fn codigos_numericos() {
+        let situacoes = [
+            (1, SituacaoCadastral::Nula),
+            (2, SituacaoCadastral::Ativa),
+            (3, SituacaoCadastral::Suspensa),
+            (4, SituacaoCadastral::Inapta),
+            (8, SituacaoCadastral::Baixada),
+        ];
+        for (codigo, situacao) in situacoes.iter() {
+            assert_eq!(SituacaoCadastral::from_codigo(*codigo), Some(*situacao));
+            assert_eq!(situacao.codigo(), Some(*codigo));
+        }
+
+        let identificadores = [
+            (1, IdentificadorDeSocio::PessoaJuridica),
+            (2, IdentificadorDeSocio::PessoaFisica),
+            (3, IdentificadorDeSocio::Estrangeiro),
+        ];
+        for (codigo, identificador) in identificadores.iter() {
+            assert_eq!(IdentificadorDeSocio::from_codigo(*codigo), Some(*identificador));
+            assert_eq!(identificador.codigo(), Some(*codigo));
+        }
+
+        for codigo in 0..=9 {
+            let faixa_etaria = FaixaEtaria::from_codigo(codigo).unwrap();
+            assert_eq!(faixa_etaria.codigo(), Some(codigo));
+        }
+        assert_eq!(FaixaEtaria::from_codigo(0), Some(FaixaEtaria::NaoSeAplica));
+        assert_eq!(FaixaEtaria::from_codigo(9), Some(FaixaEtaria::MaiorDe80Anos));
+
+        assert_eq!(SituacaoCadastral::from_str(" 2"), Ok(SituacaoCadastral::Ativa));
+    }
+
+    #[test]
+    fn codigos_fora_do_dominio() {
+        assert_eq!(Porte::from_str("02"), Err(()));
+        assert_eq!(Porte::from_str("ER"), Err(()));
+        assert_eq!(Porte::from_str(""), Err(()));
+        assert_eq!(MatrizFilial::from_str("3"), Err(()));
+        assert_eq!(OpcaoSimples::from_str("X"), Err(()));
+        assert_eq!(SituacaoCadastral::from_codigo(0), None);
+        assert_eq!(SituacaoCadastral::from_codigo(5), None);
+        assert_eq!(SituacaoCadastral::from_str("A"), Err(()));
+        assert_eq!(IdentificadorDeSocio::from_codigo(4), None);
+        assert_eq!(FaixaEtaria::from_codigo(10), None);
+
+        // a variante desconhecida não tem código
+        assert_eq!(Porte::Desconhecido.codigo(), None);
+        assert_eq!(MatrizFilial::Desconhecido.codigo(), None);
+        assert_eq!(SituacaoCadastral::Desconhecida.codigo(), None);
+        assert_eq!(IdentificadorDeSocio::Desconhecido.codigo(), None);
+        assert_eq!(OpcaoSimples::Desconhecida.codigo(), None);
+        assert_eq!(FaixaEtaria::Desconhecida.codigo(), None);
+    }
+
+    #[test]
+    fn codigos_desconhecidos_lidos_do_banco_de_dados() {
+        let porte: Porte = FromSql::<Text, Mysql>::from_sql(Some(b"03")).unwrap();
+        assert_eq!(porte, Porte::EmpresaDePequenoPorte);
+        let porte: Porte = FromSql::<Text, Mysql>::from_sql(Some(b"ER")).unwrap();
+        assert_eq!(porte, Porte::Desconhecido);
+        let opcao: OpcaoSimples = FromSql::<Text, Mysql>::from_sql(Some(b"X")).unwrap();
+        assert_eq!(opcao, OpcaoSimples::Desconhecida);
+
+        let situacao: SituacaoCadastral = FromSql::<Unsigned<TinyInt>, Mysql>::from_sql(Some(&[8])).unwrap();
+        assert_eq!(situacao, SituacaoCadastral::Baixada);
+        let situacao: SituacaoCadastral = FromSql::<Unsigned<TinyInt>, Mysql>::from_sql(Some(&[7])).unwrap();
+        assert_eq!(situacao, SituacaoCadastral::Desconhecida);
+        let faixa_etaria: FaixaEtaria = FromSql::<Unsigned<TinyInt>, Mysql>::from_sql(Some(&[42])).unwrap();
+        assert_eq!(faixa_etaria, FaixaEtaria::Desconhecida);
+    }
+}
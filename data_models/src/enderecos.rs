@@ -0,0 +1,280 @@
+// Normalização dos endereços dos estabelecimentos, que nos arquivos da Receita Federal têm espaços
+// repetidos, variações de "sem número" (SN, S/N, S/Nº, SEM NUMERO...) e CEPs sem formatação. Utilizada pelo
+// importer (--normalize-addresses), que grava o endereço normalizado em uma única linha, e pelos servidores,
+// que podem retornar os campos do endereço na forma original ou normalizada.
+
+// Unidades federativas e EX, utilizada pela RF nos estabelecimentos situados no exterior
+pub const UNIDADES_FEDERATIVAS: [&str; 28] = [
+    "AC", "AL", "AM", "AP", "BA", "CE", "DF", "ES", "GO", "MA", "MG", "MS", "MT", "PA",
+    "PB", "PE", "PI", "PR", "RJ", "RN", "RO", "RR", "RS", "SC", "SE", "SP", "TO", "EX",
+];
+
+// Faixas de CEP de cada UF (pelos 5 primeiros dígitos), conforme os Correios
+const FAIXAS_DE_CEP: [(&str, u32, u32); 30] = [
+    ("SP", 1_000, 19_999),
+    ("RJ", 20_000, 28_999),
+    ("ES", 29_000, 29_999),
+    ("MG", 30_000, 39_999),
+    ("BA", 40_000, 48_999),
+    ("SE", 49_000, 49_999),
+    ("PE", 50_000, 56_999),
+    ("AL", 57_000, 57_999),
+    ("PB", 58_000, 58_999),
+    ("RN", 59_000, 59_999),
+    ("CE", 60_000, 63_999),
+    ("PI", 64_000, 64_999),
+    ("MA", 65_000, 65_999),
+    ("PA", 66_000, 68_899),
+    ("AP", 68_900, 68_999),
+    ("AM", 69_000, 69_299),
+    ("RR", 69_300, 69_399),
+    ("AM", 69_400, 69_899),
+    ("AC", 69_900, 69_999),
+    ("DF", 70_000, 72_799),
+    ("GO", 72_800, 72_999),
+    ("DF", 73_000, 73_699),
+    ("GO", 73_700, 76_799),
+    ("RO", 76_800, 76_999),
+    ("TO", 77_000, 77_999),
+    ("MT", 78_000, 78_899),
+    ("MS", 79_000, 79_999),
+    ("PR", 80_000, 87_999),
+    ("SC", 88_000, 89_999),
+    ("RS", 90_000, 99_999),
+];
+
+// variações de "sem número" (somente as letras e os dígitos, em maiúsculas)
+const SEM_NUMERO: [&str; 10] = ["SN", "SNº", "SNO", "SNR", "SNRO", "SNUMERO", "SNÚMERO", "SEMNUMERO", "SEMNÚMERO", "SEMNRO"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnderecoNormalizado {
+    pub tipo_logradouro: Option<String>,
+    pub logradouro: Option<String>,
+    pub numero: Option<String>,
+    pub complemento: Option<String>,
+    pub bairro: Option<String>,
+    pub cep: Option<String>,
+}
+
+impl EnderecoNormalizado {
+    // Normaliza os campos do endereço: remove os espaços no início e no fim, substitui os espaços repetidos
+    // por um só, padroniza o "sem número" como S/N e formata o CEP (99999-999)
+    pub fn new(
+        tipo_logradouro: Option<&str>,
+        logradouro: Option<&str>,
+        numero: Option<&str>,
+        complemento: Option<&str>,
+        bairro: Option<&str>,
+        cep: Option<&str>,
+    ) -> EnderecoNormalizado {
+        EnderecoNormalizado {
+            tipo_logradouro: tipo_logradouro.and_then(normalizar_espacos),
+            logradouro: logradouro.and_then(normalizar_espacos),
+            numero: numero.and_then(normalizar_numero),
+            complemento: complemento.and_then(normalizar_espacos),
+            bairro: bairro.and_then(normalizar_espacos),
+            cep: cep.and_then(|cep| match normalizar_cep(cep) {
+                Some(cep) => Some(format!("{}-{}", &cep[..5], &cep[5..])),
+                None => normalizar_espacos(cep),
+            }),
+        }
+    }
+
+    // Endereço em uma única linha (ex: RUA DAS FLORES, 123, SALA 2 - CENTRO - CEP 01234-567 - SP)
+    pub fn linha(&self, uf: Option<&str>) -> Option<String> {
+        let logradouro = [&self.tipo_logradouro, &self.logradouro]
+            .iter()
+            .filter_map(|campo| campo.as_deref())
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        let mut partes: Vec<String> = Vec::new();
+        let primeira_parte = [Some(logradouro.as_str()), self.numero.as_deref(), self.complemento.as_deref()]
+            .iter()
+            .flatten()
+            .filter(|campo| !campo.is_empty())
+            .copied()
+            .collect::<Vec<&str>>()
+            .join(", ");
+        if !primeira_parte.is_empty() {
+            partes.push(primeira_parte);
+        }
+        if let Some(bairro) = &self.bairro {
+            partes.push(bairro.clone());
+        }
+        if let Some(cep) = &self.cep {
+            partes.push(format!("CEP {}", cep));
+        }
+        if let Some(uf) = uf.and_then(normalizar_espacos) {
+            partes.push(uf);
+        }
+
+        if partes.is_empty() {
+            return None;
+        }
+        Some(partes.join(" - "))
+    }
+}
+
+// Remove os espaços no início e no fim e substitui os espaços repetidos por um só (None se ficar vazio)
+pub fn normalizar_espacos(texto: &str) -> Option<String> {
+    let texto = texto.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if texto.is_empty() {
+        return None;
+    }
+    Some(texto)
+}
+
+// Normaliza o número do endereço, padronizando as variações de "sem número" como S/N
+pub fn normalizar_numero(numero: &str) -> Option<String> {
+    let numero = normalizar_espacos(numero)?;
+    let letras_e_digitos: String = numero.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_uppercase();
+    if SEM_NUMERO.contains(&letras_e_digitos.as_str()) {
+        return Some(String::from("S/N"));
+    }
+    Some(numero)
+}
+
+// Retorna os 8 dígitos do CEP (sem a formatação), caso ele seja válido
+pub fn normalizar_cep(cep: &str) -> Option<String> {
+    let digitos: String = cep.chars().filter(|c| !c.is_whitespace() && *c != '-' && *c != '.').collect();
+    if digitos.len() == 8 && digitos.chars().all(|c| c.is_ascii_digit()) {
+        return Some(digitos);
+    }
+    None
+}
+
+// Verifica se o CEP pertence a uma das faixas de CEP da UF
+pub fn cep_valido(cep: &str, uf: &str) -> bool {
+    let cep = match normalizar_cep(cep) {
+        Some(cep) => cep,
+        None => return false,
+    };
+    let prefixo: u32 = match cep[..5].parse() {
+        Ok(prefixo) => prefixo,
+        Err(_) => return false,
+    };
+    let uf = uf.trim();
+
+    FAIXAS_DE_CEP
+        .iter()
+        .any(|(uf_da_faixa, inicio, fim)| *uf_da_faixa == uf && (*inicio..=*fim).contains(&prefixo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ceps_validos_e_invalidos() {
+        let casos = [
+            ("01310-100", "SP", true),
+            ("01310100", "SP", true),
+            (" 20.040-020 ", "RJ", true),
+            ("69301-000", "RR", true),
+            ("69400-000", "AM", true),
+            ("70040-010", "DF", true),
+            ("73700-000", "GO", true),
+            ("99999-999", "RS", true),
+            // CEP de outra UF
+            ("01310-100", "RJ", false),
+            ("69301-000", "AM", false),
+            // fora de todas as faixas
+            ("00000-000", "SP", false),
+            ("00999-999", "SP", false),
+            // quantidade de dígitos ou caracteres inválidos
+            ("0131010", "SP", false),
+            ("013101000", "SP", false),
+            ("01310-10A", "SP", false),
+            ("", "SP", false),
+        ];
+        for (cep, uf, valido) in casos {
+            assert_eq!(cep_valido(cep, uf), valido, "cep_valido({:?}, {:?})", cep, uf);
+        }
+    }
+
+    #[test]
+    fn cep_de_uf_desconhecida_ou_do_exterior_e_invalido() {
+        for uf in ["XX", "", "sp", "EX"] {
+            assert!(!cep_valido("01310-100", uf), "cep_valido(\"01310-100\", {:?})", uf);
+        }
+        assert!(!UNIDADES_FEDERATIVAS.contains(&"XX"));
+        assert!(UNIDADES_FEDERATIVAS.contains(&"EX"));
+    }
+
+    #[test]
+    fn normalizacao_do_cep() {
+        let casos = [
+            ("01310-100", Some("01310100")),
+            (" 01.310-100 ", Some("01310100")),
+            ("1310100", None),
+            ("ABCDEFGH", None),
+        ];
+        for (cep, normalizado) in casos {
+            assert_eq!(normalizar_cep(cep).as_deref(), normalizado, "normalizar_cep({:?})", cep);
+        }
+
+        let endereco = |cep| EnderecoNormalizado::new(None, None, None, None, None, Some(cep));
+        assert_eq!(endereco("01310100").cep.as_deref(), Some("01310-100"));
+        // o CEP inválido é mantido, somente com os espaços normalizados
+        assert_eq!(endereco(" 1310  100 ").cep.as_deref(), Some("1310 100"));
+        assert_eq!(endereco("   ").cep, None);
+    }
+
+    #[test]
+    fn normalizacao_do_logradouro() {
+        let casos = [
+            ("AVENIDA  PAULISTA", Some("AVENIDA PAULISTA")),
+            ("  DAS   FLORES ", Some("DAS FLORES")),
+            ("\tSETE\tDE SETEMBRO", Some("SETE DE SETEMBRO")),
+            ("SEM ALTERACAO", Some("SEM ALTERACAO")),
+            ("   ", None),
+            ("", None),
+        ];
+        for (logradouro, normalizado) in casos {
+            let endereco = EnderecoNormalizado::new(Some(" RUA "), Some(logradouro), None, None, None, None);
+            assert_eq!(endereco.tipo_logradouro.as_deref(), Some("RUA"));
+            assert_eq!(endereco.logradouro.as_deref(), normalizado, "logradouro {:?}", logradouro);
+        }
+    }
+
+    #[test]
+    fn normalizacao_do_numero() {
+        let casos = [
+            ("SN", Some("S/N")),
+            ("S/N", Some("S/N")),
+            ("s/nº", Some("S/N")),
+            ("S.N.", Some("S/N")),
+            ("SEM NUMERO", Some("S/N")),
+            ("Sem Número", Some("S/N")),
+            ("S/NRO", Some("S/N")),
+            (" 123 ", Some("123")),
+            ("123  A", Some("123 A")),
+            ("SN 10", Some("SN 10")),
+            (" ", None),
+        ];
+        for (numero, normalizado) in casos {
+            assert_eq!(normalizar_numero(numero).as_deref(), normalizado, "normalizar_numero({:?})", numero);
+        }
+    }
+
+    #[test]
+    fn endereco_em_uma_linha() {
+        let endereco = EnderecoNormalizado::new(
+            Some("RUA"),
+            Some(" DAS  FLORES"),
+            Some("SN"),
+            Some("SALA 2 "),
+            Some("CENTRO"),
+            Some("01234567"),
+        );
+        assert_eq!(
+            endereco.linha(Some("SP")).as_deref(),
+            Some("RUA DAS FLORES, S/N, SALA 2 - CENTRO - CEP 01234-567 - SP")
+        );
+
+        let somente_bairro = EnderecoNormalizado::new(None, None, None, None, Some("CENTRO"), None);
+        assert_eq!(somente_bairro.linha(None).as_deref(), Some("CENTRO"));
+        assert_eq!(EnderecoNormalizado::default().linha(Some(" ")), None);
+    }
+}
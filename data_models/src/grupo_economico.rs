@@ -0,0 +1,366 @@
+// Grafo de participações societárias entre empresas (grupo econômico).
+//
+// Os registros da tabela socios onde o identificador de sócio é Pessoa Jurídica possuem, no campo
+// cnpj_ou_cpf_do_socio, o CNPJ completo da empresa sócia. Com isto é possível montar um grafo onde
+// cada nó é uma empresa (CNPJ básico) e cada aresta indica que uma empresa é sócia de outra.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+use diesel::dsl::sql;
+use diesel::expression::BoxableExpression;
+use diesel::mysql::{Mysql, MysqlConnection};
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use diesel::QueryResult;
+use serde::Serialize;
+
+use crate::dominios::IdentificadorDeSocio;
+use crate::models::{QualificacaoDeSocio, Socio};
+use crate::schema::{empresas, qualificacoes_de_socios, socios};
+
+// profundidade máxima permitida na navegação do grafo
+pub const PROFUNDIDADE_MAXIMA: u8 = 5;
+// quantidade máxima de empresas (nós) retornadas, para evitar que grupos muito grandes derrubem o servidor
+pub const LIMITE_DE_NOS: usize = 500;
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "NoDoGrupoEconomico", description = "Empresa pertencente ao grupo econômico"))]
+pub struct No {
+    pub cnpj_basico: String,
+    pub razao_social: Option<String>,
+    // distância (em quantidade de arestas) até a empresa consultada
+    pub profundidade: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLObject))]
+#[cfg_attr(feature = "graphql", graphql(name = "ArestaDoGrupoEconomico", description = "Participação de uma empresa (sócia) no quadro societário de outra empresa"))]
+pub struct Aresta {
+    // CNPJ básico da empresa sócia
+    pub socio: String,
+    // CNPJ básico da empresa da qual a empresa sócia participa
+    pub empresa: String,
+    pub cnpj_do_socio: String,
+    pub qualificacao_do_socio: i32,
+    pub nome_da_qualificacao_do_socio: Option<String>,
+    pub data_de_entrada_na_sociedade: NaiveDate,
+    // indica que a aresta fecha um ciclo de participações (ex: A é sócia de B que é sócia de A)
+    pub ciclo: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GrupoEconomico {
+    pub cnpj_basico: String,
+    pub profundidade: u8,
+    pub nos: Vec<No>,
+    pub arestas: Vec<Aresta>,
+    // indica que a navegação foi interrompida ao atingir o LIMITE_DE_NOS
+    pub truncado: bool,
+}
+
+// Navega pelo grafo de participações a partir da empresa informada, tanto para cima (quem são as
+// empresas sócias) quanto para baixo (de quais empresas ela é sócia), até a profundidade informada.
+pub fn grupo_economico(conn: &MysqlConnection, cnpj_basico: &str, profundidade: u8) -> QueryResult<GrupoEconomico> {
+    let profundidade = profundidade.min(PROFUNDIDADE_MAXIMA);
+
+    let qualificacoes: HashMap<u8, String> = qualificacoes_de_socios::table
+        .load::<QualificacaoDeSocio>(conn)?
+        .into_iter()
+        .map(|q| (q.id, q.nome))
+        .collect();
+
+    let mut profundidades: HashMap<String, i32> = HashMap::new();
+    let mut nomes_dos_socios: HashMap<String, String> = HashMap::new();
+    let mut arestas: Vec<Aresta> = Vec::new();
+    let mut ids_das_arestas: HashSet<u32> = HashSet::new();
+    let mut truncado = false;
+
+    profundidades.insert(cnpj_basico.to_string(), 0);
+    let mut fronteira = vec![cnpj_basico.to_string()];
+
+    for nivel in 1..=i32::from(profundidade) {
+        let mut proxima_fronteira = Vec::new();
+
+        // para cima: empresas que são sócias das empresas da fronteira
+        let socios_pj = socios::table
+            .filter(socios::cnpj_basico.eq_any(&fronteira))
+            .filter(socios::identificador_de_socio.eq(IdentificadorDeSocio::PessoaJuridica))
+            .load::<Socio>(conn)?;
+
+        // para baixo: empresas das quais as empresas da fronteira são sócias, em uma única query por nível
+        let participacoes = socios::table
+            .filter(socios_com_o_cnpj_basico(&fronteira))
+            .filter(socios::identificador_de_socio.eq(IdentificadorDeSocio::PessoaJuridica))
+            .load::<Socio>(conn)?;
+
+        for socio in socios_pj.into_iter().chain(participacoes) {
+            let cnpj_do_socio = match &socio.cnpj_ou_cpf_do_socio {
+                Some(v) => v.clone(),
+                None => continue,
+            };
+            let cnpj_basico_do_socio = match cnpj_basico_do_socio(&cnpj_do_socio) {
+                Some(v) => v.to_string(),
+                None => continue,
+            };
+
+            if !ids_das_arestas.insert(socio.id) {
+                continue;
+            }
+
+            nomes_dos_socios
+                .entry(cnpj_basico_do_socio.clone())
+                .or_insert_with(|| socio.nome_ou_razao_social_do_socio.clone());
+
+            for vizinho in [&cnpj_basico_do_socio, &socio.cnpj_basico].iter() {
+                if profundidades.contains_key(*vizinho) {
+                    continue;
+                }
+                if profundidades.len() >= LIMITE_DE_NOS {
+                    truncado = true;
+                    continue;
+                }
+                profundidades.insert((*vizinho).clone(), nivel);
+                proxima_fronteira.push((*vizinho).clone());
+            }
+
+            // só inclui a aresta se as duas pontas fazem parte do grafo
+            if !profundidades.contains_key(&cnpj_basico_do_socio) || !profundidades.contains_key(&socio.cnpj_basico) {
+                continue;
+            }
+
+            arestas.push(Aresta {
+                socio: cnpj_basico_do_socio,
+                empresa: socio.cnpj_basico,
+                cnpj_do_socio,
+                qualificacao_do_socio: i32::from(socio.qualificacao_do_socio),
+                nome_da_qualificacao_do_socio: qualificacoes.get(&socio.qualificacao_do_socio).cloned(),
+                data_de_entrada_na_sociedade: socio.data_de_entrada_na_sociedade,
+                ciclo: false,
+            });
+        }
+
+        if proxima_fronteira.is_empty() {
+            break;
+        }
+        fronteira = proxima_fronteira;
+    }
+
+    marcar_ciclos(&mut arestas);
+
+    let cnpjs: Vec<&String> = profundidades.keys().collect();
+    let razoes_sociais: HashMap<String, String> = empresas::table
+        .filter(empresas::cnpj_basico.eq_any(cnpjs))
+        .select((empresas::cnpj_basico, empresas::razao_social))
+        .load::<(String, String)>(conn)?
+        .into_iter()
+        .collect();
+
+    let mut nos: Vec<No> = profundidades
+        .into_iter()
+        .map(|(cnpj, profundidade)| No {
+            razao_social: razoes_sociais
+                .get(&cnpj)
+                .or_else(|| nomes_dos_socios.get(&cnpj))
+                .cloned(),
+            cnpj_basico: cnpj,
+            profundidade,
+        })
+        .collect();
+    nos.sort_by(|a, b| a.profundidade.cmp(&b.profundidade).then(a.cnpj_basico.cmp(&b.cnpj_basico)));
+
+    Ok(GrupoEconomico {
+        cnpj_basico: cnpj_basico.to_string(),
+        profundidade,
+        nos,
+        arestas,
+        truncado,
+    })
+}
+
+// CNPJ básico (8 primeiros dígitos) do CNPJ do sócio, que nos arquivos pode estar incompleto ou mal formado
+fn cnpj_basico_do_socio(cnpj_do_socio: &str) -> Option<&str> {
+    cnpj_do_socio
+        .get(..8)
+        .filter(|cnpj_basico| cnpj_basico.bytes().all(|c| c.is_ascii_digit()))
+}
+
+// Condição que seleciona os sócios cujo CNPJ começa com um dos CNPJs básicos informados (que por conterem
+// somente dígitos não precisam ser escapados no LIKE). O CNPJ do sócio é gravado completo, então a
+// comparação é feita pelo prefixo, o que ainda permite o uso do índice da coluna.
+fn socios_com_o_cnpj_basico(cnpjs_basicos: &[String]) -> Box<dyn BoxableExpression<socios::table, Mysql, SqlType = Bool>> {
+    let mut condicao: Option<Box<dyn BoxableExpression<socios::table, Mysql, SqlType = Bool>>> = None;
+
+    for cnpj in cnpjs_basicos.iter().filter(|cnpj| cnpj.len() == 8 && cnpj.bytes().all(|c| c.is_ascii_digit())) {
+        let condicao_do_cnpj = socios::cnpj_ou_cpf_do_socio.like(format!("{}%", cnpj));
+        condicao = Some(match condicao {
+            Some(anterior) => Box::new(anterior.or(condicao_do_cnpj)),
+            None => Box::new(condicao_do_cnpj),
+        });
+    }
+
+    condicao.unwrap_or_else(|| Box::new(sql::<Bool>("1 = 0")))
+}
+
+// Marca as arestas que fecham um ciclo (arestas de retorno encontradas em uma busca em profundidade)
+fn marcar_ciclos(arestas: &mut [Aresta]) {
+    let mut adjacencias: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, aresta) in arestas.iter().enumerate() {
+        adjacencias.entry(aresta.socio.as_str()).or_default().push(i);
+    }
+
+    // 1 = em visita (na pilha), 2 = visitado
+    let mut estados: HashMap<&str, u8> = HashMap::new();
+    let mut arestas_de_ciclo: Vec<usize> = Vec::new();
+
+    let mut inicios: Vec<&str> = adjacencias.keys().copied().collect();
+    inicios.sort_unstable();
+
+    for inicio in inicios {
+        if estados.contains_key(inicio) {
+            continue;
+        }
+        // pilha com o nó e o índice da próxima aresta a ser visitada
+        let mut pilha: Vec<(&str, usize)> = vec![(inicio, 0)];
+        estados.insert(inicio, 1);
+
+        while let Some((no, proxima)) = pilha.pop() {
+            let saidas = adjacencias.get(no).map(|v| v.as_slice()).unwrap_or(&[]);
+            if proxima >= saidas.len() {
+                estados.insert(no, 2);
+                continue;
+            }
+            pilha.push((no, proxima + 1));
+
+            let indice = saidas[proxima];
+            let destino = arestas[indice].empresa.as_str();
+            match estados.get(destino) {
+                Some(1) => arestas_de_ciclo.push(indice),
+                Some(_) => {}
+                None => {
+                    estados.insert(destino, 1);
+                    pilha.push((destino, 0));
+                }
+            }
+        }
+    }
+
+    for indice in arestas_de_ciclo {
+        arestas[indice].ciclo = true;
+    }
+}
+
+impl GrupoEconomico {
+    // Exporta o grafo no formato GraphML (http://graphml.graphdrawing.org)
+    pub fn to_graphml(&self) -> String {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        xml.push_str("  <key id=\"razao_social\" for=\"node\" attr.name=\"razao_social\" attr.type=\"string\"/>\n");
+        xml.push_str("  <key id=\"profundidade\" for=\"node\" attr.name=\"profundidade\" attr.type=\"int\"/>\n");
+        xml.push_str("  <key id=\"qualificacao\" for=\"edge\" attr.name=\"qualificacao\" attr.type=\"string\"/>\n");
+        xml.push_str("  <key id=\"data_de_entrada\" for=\"edge\" attr.name=\"data_de_entrada\" attr.type=\"string\"/>\n");
+        xml.push_str("  <key id=\"ciclo\" for=\"edge\" attr.name=\"ciclo\" attr.type=\"boolean\"/>\n");
+        xml.push_str(&format!("  <graph id=\"{}\" edgedefault=\"directed\">\n", escapar_xml(&self.cnpj_basico)));
+
+        for no in &self.nos {
+            xml.push_str(&format!("    <node id=\"{}\">\n", escapar_xml(&no.cnpj_basico)));
+            if let Some(razao_social) = &no.razao_social {
+                xml.push_str(&format!("      <data key=\"razao_social\">{}</data>\n", escapar_xml(razao_social)));
+            }
+            xml.push_str(&format!("      <data key=\"profundidade\">{}</data>\n", no.profundidade));
+            xml.push_str("    </node>\n");
+        }
+
+        for (i, aresta) in self.arestas.iter().enumerate() {
+            xml.push_str(&format!(
+                "    <edge id=\"e{}\" source=\"{}\" target=\"{}\">\n",
+                i,
+                escapar_xml(&aresta.socio),
+                escapar_xml(&aresta.empresa)
+            ));
+            if let Some(qualificacao) = &aresta.nome_da_qualificacao_do_socio {
+                xml.push_str(&format!("      <data key=\"qualificacao\">{}</data>\n", escapar_xml(qualificacao)));
+            }
+            xml.push_str(&format!("      <data key=\"data_de_entrada\">{}</data>\n", aresta.data_de_entrada_na_sociedade));
+            xml.push_str(&format!("      <data key=\"ciclo\">{}</data>\n", aresta.ciclo));
+            xml.push_str("    </edge>\n");
+        }
+
+        xml.push_str("  </graph>\n");
+        xml.push_str("</graphml>\n");
+        xml
+    }
+
+    // Exporta o grafo no formato DOT (Graphviz)
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        dot.push_str(&format!("digraph \"{}\" {{\n", escapar_dot(&self.cnpj_basico)));
+
+        for no in &self.nos {
+            let rotulo = match &no.razao_social {
+                Some(razao_social) => format!("{}\\n{}", escapar_dot(&no.cnpj_basico), escapar_dot(razao_social)),
+                None => escapar_dot(&no.cnpj_basico),
+            };
+            dot.push_str(&format!("  \"{}\" [label=\"{}\"];\n", escapar_dot(&no.cnpj_basico), rotulo));
+        }
+
+        for aresta in &self.arestas {
+            let rotulo = aresta.nome_da_qualificacao_do_socio.as_deref().unwrap_or("");
+            let estilo = if aresta.ciclo { ", color=red" } else { "" };
+            dot.push_str(&format!(
+                "  \"{}\" -> \"{}\" [label=\"{}\"{}];\n",
+                escapar_dot(&aresta.socio),
+                escapar_dot(&aresta.empresa),
+                escapar_dot(rotulo),
+                estilo
+            ));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escapar_xml(texto: &str) -> String {
+    texto
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn escapar_dot(texto: &str) -> String {
+    texto.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::debug_query;
+
+    #[test]
+    fn cnpj_basico_dos_socios() {
+        assert_eq!(cnpj_basico_do_socio("11222333000181"), Some("11222333"));
+        assert_eq!(cnpj_basico_do_socio("11222333"), Some("11222333"));
+        assert_eq!(cnpj_basico_do_socio("1122233"), None);
+        assert_eq!(cnpj_basico_do_socio("***222333**"), None);
+        assert_eq!(cnpj_basico_do_socio("1122233É000181"), None);
+        assert_eq!(cnpj_basico_do_socio(""), None);
+    }
+
+    #[test]
+    fn participacoes_de_um_nivel_em_uma_unica_query() {
+        let fronteira = vec![String::from("11222333"), String::from("11444777"), String::from("1' OR 1=1")];
+        let query = socios::table.select(socios::id).filter(socios_com_o_cnpj_basico(&fronteira));
+        let sql = debug_query::<Mysql, _>(&query).to_string();
+
+        assert_eq!(sql.matches("`socios`.`cnpj_ou_cpf_do_socio` LIKE ?").count(), 2, "{}", sql);
+        assert!(sql.ends_with(r#"-- binds: ["11222333%", "11444777%"]"#), "{}", sql);
+
+        let vazia = socios::table.select(socios::id).filter(socios_com_o_cnpj_basico(&[]));
+        assert!(debug_query::<Mysql, _>(&vazia).to_string().contains("WHERE 1 = 0"));
+    }
+}
@@ -0,0 +1,408 @@
+// Índice de busca textual (tantivy) gravado em disco, alternativo à busca pelos índices FULLTEXT do MySQL.
+//
+// O índice é gerado pelo comando `importer index` a partir das tabelas empresas e estabelecimentos e
+// pode ser aberto somente para leitura pelo rest-server para responder às buscas e ao autocompletar
+// sem acessar o banco de dados. Cada documento do índice corresponde a um estabelecimento e é
+// identificado pelo CNPJ completo, de modo que uma nova geração do índice apenas substitui os
+// documentos dos estabelecimentos já existentes. Cada geração grava nos documentos o seu número (campo
+// geracao), de modo que ao final são removidos os documentos das gerações anteriores que não foram
+// regravados, isto é, os dos estabelecimentos excluídos do banco de dados.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use diesel::sql_types::{Nullable, Text, TinyInt, Unsigned};
+use serde::Serialize;
+use tantivy::collector::{Count, TopDocs};
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{QueryParser, TermQuery};
+use tantivy::schema::{
+    Document, Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, INDEXED, STORED, STRING,
+};
+use tantivy::tokenizer::{
+    AsciiFoldingFilter, LowerCaser, NgramTokenizer, RemoveLongFilter, SimpleTokenizer, TextAnalyzer,
+};
+use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, TantivyError, Term};
+
+use crate::dominios::SituacaoCadastral;
+
+// tokenizador utilizado nos campos de texto (não diferencia maiúsculas/minúsculas nem acentos)
+const TOKENIZADOR_DE_TEXTO: &str = "cnpj_texto";
+// tokenizador que gera os prefixos do nome (razão social e nome fantasia) para o autocompletar
+const TOKENIZADOR_DE_PREFIXOS: &str = "cnpj_prefixos";
+// tamanho máximo do prefixo indexado para o autocompletar
+const TAMANHO_MAXIMO_DO_PREFIXO: usize = 30;
+// memória utilizada pelo escritor do índice
+const MEMORIA_DO_ESCRITOR: usize = 256_000_000;
+
+// Estabelecimento a ser gravado no índice
+#[derive(Debug, QueryableByName)]
+pub struct DocumentoDoIndice {
+    #[sql_type = "Text"]
+    pub cnpj_basico: String,
+    #[sql_type = "Text"]
+    pub cnpj_ordem: String,
+    #[sql_type = "Text"]
+    pub cnpj_dv: String,
+    #[sql_type = "Text"]
+    pub razao_social: String,
+    #[sql_type = "Nullable<Text>"]
+    pub nome_fantasia: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    pub tipo_logradouro: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    pub logradouro: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    pub bairro: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    pub municipio: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    pub cnae: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    pub uf: Option<String>,
+    #[sql_type = "Unsigned<TinyInt>"]
+    pub situacao_cadastral: SituacaoCadastral,
+}
+
+// Estabelecimento encontrado no índice
+#[derive(Debug, Clone, Serialize)]
+pub struct ResultadoDoIndice {
+    pub cnpj: String,
+    pub razao_social: Option<String>,
+    pub nome_fantasia: Option<String>,
+    pub municipio: Option<String>,
+    pub uf: Option<String>,
+    pub situacao_cadastral: Option<SituacaoCadastral>,
+    pub relevancia: f32,
+}
+
+struct Campos {
+    cnpj: Field,
+    razao_social: Field,
+    nome_fantasia: Field,
+    logradouro: Field,
+    bairro: Field,
+    municipio: Field,
+    cnae: Field,
+    uf: Field,
+    situacao_cadastral: Field,
+    prefixos: Field,
+    geracao: Field,
+}
+
+fn criar_schema() -> Schema {
+    let texto = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(TOKENIZADOR_DE_TEXTO)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    );
+    let prefixos = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(TOKENIZADOR_DE_PREFIXOS)
+            .set_index_option(IndexRecordOption::Basic),
+    );
+
+    let mut schema = Schema::builder();
+    schema.add_text_field("cnpj", STRING | STORED);
+    schema.add_text_field("razao_social", texto.clone().set_stored());
+    schema.add_text_field("nome_fantasia", texto.clone().set_stored());
+    schema.add_text_field("logradouro", texto.clone());
+    schema.add_text_field("bairro", texto.clone());
+    schema.add_text_field("municipio", texto.clone().set_stored());
+    schema.add_text_field("cnae", texto);
+    schema.add_text_field("uf", STRING | STORED);
+    schema.add_u64_field("situacao_cadastral", INDEXED | STORED);
+    schema.add_text_field("prefixos", prefixos);
+    schema.add_u64_field("geracao", INDEXED);
+    schema.build()
+}
+
+fn campo(schema: &Schema, nome: &str) -> tantivy::Result<Field> {
+    schema
+        .get_field(nome)
+        .ok_or_else(|| TantivyError::SchemaError(format!("O campo {} não existe no índice", nome)))
+}
+
+fn campos(schema: &Schema) -> tantivy::Result<Campos> {
+    Ok(Campos {
+        cnpj: campo(schema, "cnpj")?,
+        razao_social: campo(schema, "razao_social")?,
+        nome_fantasia: campo(schema, "nome_fantasia")?,
+        logradouro: campo(schema, "logradouro")?,
+        bairro: campo(schema, "bairro")?,
+        municipio: campo(schema, "municipio")?,
+        cnae: campo(schema, "cnae")?,
+        uf: campo(schema, "uf")?,
+        situacao_cadastral: campo(schema, "situacao_cadastral")?,
+        prefixos: campo(schema, "prefixos")?,
+        geracao: campo(schema, "geracao")?,
+    })
+}
+
+fn registrar_tokenizadores(index: &Index) {
+    index.tokenizers().register(
+        TOKENIZADOR_DE_TEXTO,
+        TextAnalyzer::from(SimpleTokenizer)
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser)
+            .filter(AsciiFoldingFilter),
+    );
+    index.tokenizers().register(
+        TOKENIZADOR_DE_PREFIXOS,
+        TextAnalyzer::from(NgramTokenizer::prefix_only(1, TAMANHO_MAXIMO_DO_PREFIXO))
+            .filter(LowerCaser)
+            .filter(AsciiFoldingFilter),
+    );
+}
+
+fn texto(documento: &Document, campo: Field) -> Option<String> {
+    match documento.get_first(campo) {
+        Some(Value::Str(v)) => Some(v.clone()),
+        _ => None,
+    }
+}
+
+pub struct IndiceDeBusca {
+    index: Index,
+    campos: Campos,
+    reader: Option<IndexReader>,
+}
+
+impl IndiceDeBusca {
+    // Abre o índice para gravação, criando-o caso ainda não exista
+    pub fn criar_ou_abrir(diretorio: &Path) -> tantivy::Result<IndiceDeBusca> {
+        fs::create_dir_all(diretorio)?;
+        let index = Index::open_or_create(MmapDirectory::open(diretorio)?, criar_schema())?;
+        registrar_tokenizadores(&index);
+        let campos = campos(&index.schema())?;
+
+        Ok(IndiceDeBusca { index, campos, reader: None })
+    }
+
+    // Abre o índice somente para leitura. O índice é recarregado automaticamente quando uma nova
+    // geração é gravada pelo importer.
+    pub fn abrir_somente_leitura(diretorio: &Path) -> tantivy::Result<IndiceDeBusca> {
+        let index = Index::open_in_dir(diretorio)?;
+        registrar_tokenizadores(&index);
+        let campos = campos(&index.schema())?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::OnCommit)
+            .try_into()?;
+
+        Ok(IndiceDeBusca { index, campos, reader: Some(reader) })
+    }
+
+    // Identificação da importação (data/hora de atualização dos dados) a partir da qual o índice foi gerado
+    pub fn versao(&self) -> tantivy::Result<Option<String>> {
+        Ok(self.index.load_metas()?.payload)
+    }
+
+    // Abre o índice para gravação de uma nova geração, identificada pela data/hora (em segundos) de início
+    pub fn escritor(&self) -> tantivy::Result<EscritorDoIndice<'_>> {
+        let geracao = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duracao| duracao.as_secs())
+            .unwrap_or_default();
+
+        Ok(EscritorDoIndice {
+            writer: self.index.writer(MEMORIA_DO_ESCRITOR)?,
+            index: &self.index,
+            campos: &self.campos,
+            geracao,
+        })
+    }
+
+    fn reader(&self) -> tantivy::Result<&IndexReader> {
+        self.reader
+            .as_ref()
+            .ok_or_else(|| TantivyError::InvalidArgument(String::from("O índice não foi aberto para leitura")))
+    }
+
+    // Busca os estabelecimentos por todos os termos informados na razão social, nome fantasia,
+    // endereço, município e descrição do CNAE principal.
+    pub fn buscar(&self, texto_da_busca: &str, limite: usize) -> tantivy::Result<Vec<ResultadoDoIndice>> {
+        let termos: Vec<&str> = texto_da_busca
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|termo| !termo.is_empty())
+            .collect();
+        if termos.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut query_parser = QueryParser::for_index(
+            &self.index,
+            vec![
+                self.campos.razao_social,
+                self.campos.nome_fantasia,
+                self.campos.logradouro,
+                self.campos.bairro,
+                self.campos.municipio,
+                self.campos.cnae,
+            ],
+        );
+        query_parser.set_conjunction_by_default();
+        query_parser.set_field_boost(self.campos.razao_social, 3.0);
+        query_parser.set_field_boost(self.campos.nome_fantasia, 2.0);
+
+        let query = query_parser
+            .parse_query(&termos.join(" "))
+            .map_err(|error| TantivyError::InvalidArgument(format!("{:?}", error)))?;
+
+        self.resultados(&query, limite)
+    }
+
+    // Retorna os estabelecimentos cuja razão social ou nome fantasia começam com o texto informado
+    pub fn autocompletar(&self, prefixo: &str, limite: usize) -> tantivy::Result<Vec<ResultadoDoIndice>> {
+        let analisador = self
+            .index
+            .tokenizers()
+            .get(TOKENIZADOR_DE_PREFIXOS)
+            .ok_or_else(|| TantivyError::SchemaError(format!("O tokenizador {} não foi registrado", TOKENIZADOR_DE_PREFIXOS)))?;
+
+        // o último token gerado é o maior prefixo do texto informado
+        let mut tokens = analisador.token_stream(prefixo.trim());
+        let mut maior_prefixo = None;
+        while tokens.advance() {
+            maior_prefixo = Some(tokens.token().text.clone());
+        }
+
+        match maior_prefixo {
+            Some(maior_prefixo) => {
+                let query = TermQuery::new(
+                    Term::from_field_text(self.campos.prefixos, &maior_prefixo),
+                    IndexRecordOption::Basic,
+                );
+                self.resultados(&query, limite)
+            }
+            None => Ok(vec![]),
+        }
+    }
+
+    fn resultados(&self, query: &dyn tantivy::query::Query, limite: usize) -> tantivy::Result<Vec<ResultadoDoIndice>> {
+        let searcher = self.reader()?.searcher();
+
+        let mut resultados = Vec::new();
+        for (relevancia, endereco) in searcher.search(query, &TopDocs::with_limit(limite))? {
+            let documento = searcher.doc(endereco)?;
+            let situacao_cadastral = match documento.get_first(self.campos.situacao_cadastral) {
+                Some(Value::U64(codigo)) => SituacaoCadastral::from_codigo(*codigo as u8),
+                _ => None,
+            };
+
+            resultados.push(ResultadoDoIndice {
+                cnpj: texto(&documento, self.campos.cnpj).unwrap_or_default(),
+                razao_social: texto(&documento, self.campos.razao_social),
+                nome_fantasia: texto(&documento, self.campos.nome_fantasia),
+                municipio: texto(&documento, self.campos.municipio),
+                uf: texto(&documento, self.campos.uf),
+                situacao_cadastral,
+                relevancia,
+            });
+        }
+
+        Ok(resultados)
+    }
+}
+
+pub struct EscritorDoIndice<'a> {
+    writer: IndexWriter,
+    index: &'a Index,
+    campos: &'a Campos,
+    geracao: u64,
+}
+
+impl<'a> EscritorDoIndice<'a> {
+    // Grava o estabelecimento no índice, substituindo o documento anterior do mesmo CNPJ (se houver)
+    pub fn gravar(&mut self, estabelecimento: &DocumentoDoIndice) {
+        let cnpj = format!("{}{}{}", estabelecimento.cnpj_basico, estabelecimento.cnpj_ordem, estabelecimento.cnpj_dv);
+
+        let mut documento = Document::default();
+        documento.add_text(self.campos.cnpj, &cnpj);
+        documento.add_text(self.campos.razao_social, &estabelecimento.razao_social);
+        documento.add_text(self.campos.prefixos, &estabelecimento.razao_social);
+        if let Some(nome_fantasia) = &estabelecimento.nome_fantasia {
+            documento.add_text(self.campos.nome_fantasia, nome_fantasia);
+            documento.add_text(self.campos.prefixos, nome_fantasia);
+        }
+        let logradouro = match (&estabelecimento.tipo_logradouro, &estabelecimento.logradouro) {
+            (Some(tipo), Some(logradouro)) => Some(format!("{} {}", tipo, logradouro)),
+            (None, Some(logradouro)) => Some(logradouro.clone()),
+            _ => None,
+        };
+        if let Some(logradouro) = &logradouro {
+            documento.add_text(self.campos.logradouro, logradouro);
+        }
+        if let Some(bairro) = &estabelecimento.bairro {
+            documento.add_text(self.campos.bairro, bairro);
+        }
+        if let Some(municipio) = &estabelecimento.municipio {
+            documento.add_text(self.campos.municipio, municipio);
+        }
+        if let Some(cnae) = &estabelecimento.cnae {
+            documento.add_text(self.campos.cnae, cnae);
+        }
+        if let Some(uf) = &estabelecimento.uf {
+            documento.add_text(self.campos.uf, uf);
+        }
+        if let Some(situacao_cadastral) = estabelecimento.situacao_cadastral.codigo() {
+            documento.add_u64(self.campos.situacao_cadastral, u64::from(situacao_cadastral));
+        }
+        documento.add_u64(self.campos.geracao, self.geracao);
+
+        self.writer.delete_term(Term::from_field_text(self.campos.cnpj, &cnpj));
+        self.writer.add_document(documento);
+    }
+
+    // Efetiva a gravação dos documentos, registrando a identificação da importação a partir da qual
+    // o índice foi gerado
+    pub fn confirmar(&mut self, versao: &str) -> tantivy::Result<()> {
+        let mut commit = self.writer.prepare_commit()?;
+        commit.set_payload(versao);
+        commit.commit()?;
+        Ok(())
+    }
+
+    // Remove os documentos das gerações anteriores que não foram regravados nesta geração, ou seja, os dos
+    // estabelecimentos que não existem mais no banco de dados. Deve ser chamado após gravar e confirmar todos
+    // os estabelecimentos, pois o leitor vê somente a última confirmação. Retorna a quantidade de documentos
+    // removidos.
+    pub fn remover_ausentes(&mut self) -> tantivy::Result<usize> {
+        let reader = self
+            .index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let searcher = reader.searcher();
+
+        // as gerações presentes no índice são os termos do campo geracao
+        let mut geracoes = BTreeSet::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(self.campos.geracao)?;
+            let mut termos = inverted_index.terms().stream()?;
+            while termos.advance() {
+                let mut bytes = [0u8; 8];
+                if termos.key().len() == bytes.len() {
+                    bytes.copy_from_slice(termos.key());
+                    geracoes.insert(u64::from_be_bytes(bytes));
+                }
+            }
+        }
+
+        let mut removidos = 0;
+        for geracao in geracoes.into_iter().filter(|geracao| *geracao != self.geracao) {
+            let termo = Term::from_field_u64(self.campos.geracao, geracao);
+            removidos += searcher.search(&TermQuery::new(termo.clone(), IndexRecordOption::Basic), &Count)?;
+            self.writer.delete_term(termo);
+        }
+        Ok(removidos)
+    }
+
+    // Remove todos os documentos do índice (utilizado para gerar o índice novamente desde o início)
+    pub fn remover_todos(&mut self) -> tantivy::Result<()> {
+        self.writer.delete_all_documents()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,430 @@
+// Perfis de exposição dos dados pessoais (LGPD) nas respostas das APIs.
+//
+// Os nomes e CPFs (mascarados) dos sócios pessoas físicas e dos representantes legais, os telefones e o
+// correio eletrônico dos estabelecimentos e o CPF que consta na razão social das empresas individuais (MEI)
+// são dados pessoais. Cada chave de API tem um perfil de exposição (coluna perfil da tabela chaves_de_api)
+// que define quais desses dados são ocultados nas respostas às requisições feitas com ela. Os perfis padrão
+// são:
+//
+//   publico  - oculta todos os dados pessoais
+//   parceiro - oculta somente os CPFs
+//   interno  - não oculta nenhum dado
+//
+// Os dados ocultados em cada perfil podem ser alterados (ou novos perfis criados) pelas variáveis de ambiente
+// PERFIL_<NOME>_OCULTA, com os dados separados por vírgula (ex: PERFIL_PARCEIRO_OCULTA=cpf_do_socio,telefones)
+// ou "nenhum". As requisições sem chave (quando ela não é exigida) utilizam o perfil informado em
+// PERFIL_DE_EXPOSICAO_PADRAO (interno, se não informado) e as chaves com um perfil desconhecido utilizam
+// o perfil mais restritivo, que oculta todos os dados.
+//
+// Os dados efetivamente ocultados em cada requisição são registrados no log (auditoria) ao final dela.
+
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::sync::Mutex;
+
+use crate::contatos::{Email, Telefone};
+use crate::dominios::IdentificadorDeSocio;
+use crate::models::{Empresa, Estabelecimento, Socio};
+
+// valor exibido no lugar dos campos obrigatórios ocultados (os campos opcionais são omitidos)
+pub const VALOR_OCULTO: &str = "***";
+
+const PREFIXO_DA_VARIAVEL: &str = "PERFIL_";
+const SUFIXO_DA_VARIAVEL: &str = "_OCULTA";
+const PERFIL_PADRAO: &str = "interno";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DadoPessoal {
+    NomeDoSocio,
+    CpfDoSocio,
+    NomeDoRepresentanteLegal,
+    CpfDoRepresentanteLegal,
+    CorreioEletronico,
+    Telefones,
+    CpfNaRazaoSocial,
+}
+
+impl DadoPessoal {
+    pub const TODOS: [DadoPessoal; 7] = [
+        DadoPessoal::NomeDoSocio,
+        DadoPessoal::CpfDoSocio,
+        DadoPessoal::NomeDoRepresentanteLegal,
+        DadoPessoal::CpfDoRepresentanteLegal,
+        DadoPessoal::CorreioEletronico,
+        DadoPessoal::Telefones,
+        DadoPessoal::CpfNaRazaoSocial,
+    ];
+
+    pub fn nome(&self) -> &'static str {
+        match self {
+            DadoPessoal::NomeDoSocio => "nome_do_socio",
+            DadoPessoal::CpfDoSocio => "cpf_do_socio",
+            DadoPessoal::NomeDoRepresentanteLegal => "nome_do_representante_legal",
+            DadoPessoal::CpfDoRepresentanteLegal => "cpf_do_representante_legal",
+            DadoPessoal::CorreioEletronico => "correio_eletronico",
+            DadoPessoal::Telefones => "telefones",
+            DadoPessoal::CpfNaRazaoSocial => "cpf_na_razao_social",
+        }
+    }
+
+    pub fn from_nome(nome: &str) -> Option<Self> {
+        DadoPessoal::TODOS.iter().copied().find(|dado| dado.nome() == nome)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PerfilDeExposicao {
+    pub nome: String,
+    ocultos: BTreeSet<DadoPessoal>,
+}
+
+impl PerfilDeExposicao {
+    pub fn new(nome: &str, ocultos: &[DadoPessoal]) -> PerfilDeExposicao {
+        PerfilDeExposicao {
+            nome: nome.to_string(),
+            ocultos: ocultos.iter().copied().collect(),
+        }
+    }
+
+    pub fn oculta(&self, dado: DadoPessoal) -> bool {
+        self.ocultos.contains(&dado)
+    }
+}
+
+pub struct PerfisDeExposicao {
+    perfis: HashMap<String, PerfilDeExposicao>,
+    padrao: String,
+}
+
+// Perfis de exposição padrão (publico, parceiro e interno)
+fn perfis_padrao() -> HashMap<String, PerfilDeExposicao> {
+    vec![
+        PerfilDeExposicao::new("publico", &DadoPessoal::TODOS),
+        PerfilDeExposicao::new("parceiro", &[DadoPessoal::CpfDoSocio, DadoPessoal::CpfDoRepresentanteLegal, DadoPessoal::CpfNaRazaoSocial]),
+        PerfilDeExposicao::new("interno", &[]),
+    ]
+    .into_iter()
+    .map(|perfil| (perfil.nome.clone(), perfil))
+    .collect()
+}
+
+impl PerfisDeExposicao {
+    // Perfis padrão, alterados pelas variáveis de ambiente PERFIL_<NOME>_OCULTA
+    pub fn do_ambiente() -> PerfisDeExposicao {
+        let mut perfis = perfis_padrao();
+
+        for (variavel, valor) in env::vars() {
+            let nome = match variavel.strip_prefix(PREFIXO_DA_VARIAVEL).and_then(|v| v.strip_suffix(SUFIXO_DA_VARIAVEL)) {
+                Some(nome) if !nome.is_empty() => nome.to_lowercase(),
+                _ => continue,
+            };
+            let ocultos: Vec<DadoPessoal> = valor
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty() && *v != "nenhum")
+                .map(|v| DadoPessoal::from_nome(v).unwrap_or_else(|| panic!("Dado pessoal desconhecido em {}: {}", variavel, v)))
+                .collect();
+            perfis.insert(nome.clone(), PerfilDeExposicao::new(&nome, &ocultos));
+        }
+
+        PerfisDeExposicao {
+            perfis,
+            padrao: env::var("PERFIL_DE_EXPOSICAO_PADRAO").unwrap_or_else(|_| String::from(PERFIL_PADRAO)),
+        }
+    }
+
+    // Perfil com o nome informado (o perfil da chave de API) ou o perfil padrão, quando não informado
+    pub fn perfil(&self, nome: Option<&str>) -> PerfilDeExposicao {
+        let nome = nome.unwrap_or(&self.padrao);
+        match self.perfis.get(nome) {
+            Some(perfil) => perfil.clone(),
+            None => PerfilDeExposicao::new(nome, &DadoPessoal::TODOS),
+        }
+    }
+}
+
+// Razão social sem o CPF do titular, que nas empresas individuais (MEI) consta ao final dela
+// (ex: "FULANO DE TAL 12345678901"). Retorna None quando a razão social não termina com um CPF.
+pub fn razao_social_sem_cpf(razao_social: &str) -> Option<String> {
+    let razao_social = razao_social.trim_end();
+    let (inicio, ultimo_termo) = razao_social.rsplit_once(' ')?;
+    if ultimo_termo.len() == 11 && ultimo_termo.chars().all(|c| c.is_ascii_digit()) {
+        Some(inicio.trim_end().to_string())
+    } else {
+        None
+    }
+}
+
+// Oculta os dados pessoais das respostas de uma requisição conforme o perfil de exposição e registra os
+// dados ocultados, que são exibidos no log quando a requisição termina (quando o Mascaramento é descartado)
+pub struct Mascaramento {
+    perfil: PerfilDeExposicao,
+    // identificação da requisição no log (ex: chave de API e rota)
+    origem: String,
+    ocultados: Mutex<BTreeSet<DadoPessoal>>,
+}
+
+impl Mascaramento {
+    pub fn new(perfil: PerfilDeExposicao, origem: String) -> Mascaramento {
+        Mascaramento {
+            perfil,
+            origem,
+            ocultados: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    pub fn perfil(&self) -> &PerfilDeExposicao {
+        &self.perfil
+    }
+
+    // Indica se o dado deve ser ocultado, registrando a ocultação para a auditoria
+    pub fn ocultar(&self, dado: DadoPessoal) -> bool {
+        if !self.perfil.oculta(dado) {
+            return false;
+        }
+        self.ocultados.lock().unwrap().insert(dado);
+        true
+    }
+
+    // O nome do sócio somente é um dado pessoal quando ele é pessoa física ou estrangeiro
+    pub fn nome_do_socio(&self, identificador: IdentificadorDeSocio, nome: &mut String) {
+        if identificador != IdentificadorDeSocio::PessoaJuridica && self.ocultar(DadoPessoal::NomeDoSocio) {
+            *nome = String::from(VALOR_OCULTO);
+        }
+    }
+
+    // O documento do sócio pessoa jurídica é o CNPJ, que não é ocultado
+    pub fn documento_do_socio(&self, identificador: IdentificadorDeSocio, documento: &mut Option<String>) {
+        if identificador != IdentificadorDeSocio::PessoaJuridica && documento.is_some() && self.ocultar(DadoPessoal::CpfDoSocio) {
+            *documento = None;
+        }
+    }
+
+    // Nos arquivos da RF o sócio sem representante legal tem o nome vazio e o CPF ***000000**
+    pub fn representante_legal(&self, cpf: &mut String, nome: &mut String) {
+        if nome.trim().is_empty() {
+            return;
+        }
+        if self.ocultar(DadoPessoal::NomeDoRepresentanteLegal) {
+            *nome = String::from(VALOR_OCULTO);
+        }
+        if self.ocultar(DadoPessoal::CpfDoRepresentanteLegal) {
+            *cpf = String::from(VALOR_OCULTO);
+        }
+    }
+
+    // DDD ou número de um dos telefones ou do fax
+    pub fn telefone(&self, campo: &mut Option<String>) {
+        if campo.is_some() && self.ocultar(DadoPessoal::Telefones) {
+            *campo = None;
+        }
+    }
+
+    // Telefones normalizados (vide data_models::contatos)
+    pub fn telefones(&self, telefones: &mut Vec<Telefone>) {
+        if !telefones.is_empty() && self.ocultar(DadoPessoal::Telefones) {
+            telefones.clear();
+        }
+    }
+
+    pub fn correio_eletronico(&self, correio_eletronico: &mut Option<String>) {
+        if correio_eletronico.is_some() && self.ocultar(DadoPessoal::CorreioEletronico) {
+            *correio_eletronico = None;
+        }
+    }
+
+    // E-mail normalizado (vide data_models::contatos)
+    pub fn email(&self, email: &mut Option<Email>) {
+        if email.is_some() && self.ocultar(DadoPessoal::CorreioEletronico) {
+            *email = None;
+        }
+    }
+
+    pub fn razao_social(&self, razao_social: &mut String) {
+        if let Some(sem_cpf) = razao_social_sem_cpf(razao_social) {
+            if self.ocultar(DadoPessoal::CpfNaRazaoSocial) {
+                *razao_social = sem_cpf;
+            }
+        }
+    }
+
+    pub fn socio(&self, socio: &mut Socio) {
+        self.nome_do_socio(socio.identificador_de_socio, &mut socio.nome_ou_razao_social_do_socio);
+        self.documento_do_socio(socio.identificador_de_socio, &mut socio.cnpj_ou_cpf_do_socio);
+        self.representante_legal(&mut socio.cpf_do_representante_legal, &mut socio.nome_do_representante_legal);
+    }
+
+    pub fn estabelecimento(&self, estabelecimento: &mut Estabelecimento) {
+        self.telefone(&mut estabelecimento.ddd1);
+        self.telefone(&mut estabelecimento.telefone1);
+        self.telefone(&mut estabelecimento.ddd2);
+        self.telefone(&mut estabelecimento.telefone2);
+        self.telefone(&mut estabelecimento.ddd_fax);
+        self.telefone(&mut estabelecimento.telefone_fax);
+        self.telefone(&mut estabelecimento.telefone1_e164);
+        self.telefone(&mut estabelecimento.telefone2_e164);
+        self.telefone(&mut estabelecimento.telefone_fax_e164);
+        self.correio_eletronico(&mut estabelecimento.correio_eletronico);
+        self.correio_eletronico(&mut estabelecimento.email_normalizado);
+        if estabelecimento.email_normalizado.is_none() {
+            estabelecimento.email_valido = None;
+        }
+    }
+
+    pub fn empresa(&self, empresa: &mut Empresa) {
+        self.razao_social(&mut empresa.razao_social);
+    }
+}
+
+impl Drop for Mascaramento {
+    fn drop(&mut self) {
+        let ocultados = match self.ocultados.get_mut() {
+            Ok(ocultados) if !ocultados.is_empty() => ocultados,
+            _ => return,
+        };
+        let dados: Vec<&str> = ocultados.iter().map(DadoPessoal::nome).collect();
+        tracing::info!(
+            target: "auditoria_lgpd",
+            origem = %self.origem,
+            perfil = %self.perfil.nome,
+            dados_ocultados = %dados.join(","),
+            "Dados pessoais ocultados"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Aplica o mascaramento correspondente ao dado pessoal em um valor de exemplo e indica se ele foi ocultado
+    fn ocultou(mascaramento: &Mascaramento, dado: DadoPessoal) -> bool {
+        match dado {
+            DadoPessoal::NomeDoSocio => {
+                let mut nome = String::from("FULANO DE TAL");
+                mascaramento.nome_do_socio(IdentificadorDeSocio::PessoaFisica, &mut nome);
+                nome == VALOR_OCULTO
+            }
+            DadoPessoal::CpfDoSocio => {
+                let mut documento = Some(String::from("***456789**"));
+                mascaramento.documento_do_socio(IdentificadorDeSocio::PessoaFisica, &mut documento);
+                documento.is_none()
+            }
+            DadoPessoal::NomeDoRepresentanteLegal => {
+                let (mut cpf, mut nome) = (String::from("***456789**"), String::from("BELTRANO"));
+                mascaramento.representante_legal(&mut cpf, &mut nome);
+                nome == VALOR_OCULTO
+            }
+            DadoPessoal::CpfDoRepresentanteLegal => {
+                let (mut cpf, mut nome) = (String::from("***456789**"), String::from("BELTRANO"));
+                mascaramento.representante_legal(&mut cpf, &mut nome);
+                cpf == VALOR_OCULTO
+            }
+            DadoPessoal::CorreioEletronico => {
+                let mut correio_eletronico = Some(String::from("FULANO@EXEMPLO.COM.BR"));
+                let mut email = Email::new(Some("FULANO@EXEMPLO.COM.BR"), None, None);
+                mascaramento.correio_eletronico(&mut correio_eletronico);
+                mascaramento.email(&mut email);
+                assert_eq!(correio_eletronico.is_none(), email.is_none());
+                email.is_none()
+            }
+            DadoPessoal::Telefones => {
+                let mut telefone = Some(String::from("33334444"));
+                let mut telefones: Vec<Telefone> = Telefone::new("telefone1", Some("11"), Some("33334444"), None).into_iter().collect();
+                mascaramento.telefone(&mut telefone);
+                mascaramento.telefones(&mut telefones);
+                assert_eq!(telefone.is_none(), telefones.is_empty());
+                telefone.is_none()
+            }
+            DadoPessoal::CpfNaRazaoSocial => {
+                let mut razao_social = String::from("FULANO DE TAL 12345678901");
+                mascaramento.razao_social(&mut razao_social);
+                razao_social == "FULANO DE TAL"
+            }
+        }
+    }
+
+    #[test]
+    fn dados_ocultados_em_cada_perfil_padrao() {
+        let perfis = PerfisDeExposicao { perfis: perfis_padrao(), padrao: String::from(PERFIL_PADRAO) };
+        let cpfs = [DadoPessoal::CpfDoSocio, DadoPessoal::CpfDoRepresentanteLegal, DadoPessoal::CpfNaRazaoSocial];
+
+        for dado in DadoPessoal::TODOS.iter().copied() {
+            for (perfil, oculta) in [("publico", true), ("parceiro", cpfs.contains(&dado)), ("interno", false)].iter() {
+                let mascaramento = Mascaramento::new(perfis.perfil(Some(perfil)), String::from("teste"));
+                assert_eq!(ocultou(&mascaramento, dado), *oculta, "{} no perfil {}", dado.nome(), perfil);
+                // os dados ocultados são registrados para a auditoria
+                assert_eq!(mascaramento.ocultados.lock().unwrap().contains(&dado), *oculta);
+            }
+        }
+    }
+
+    #[test]
+    fn perfil_padrao_e_perfil_desconhecido() {
+        let perfis = PerfisDeExposicao { perfis: perfis_padrao(), padrao: String::from(PERFIL_PADRAO) };
+        assert_eq!(perfis.perfil(None).nome, PERFIL_PADRAO);
+
+        // o perfil desconhecido oculta todos os dados
+        let desconhecido = Mascaramento::new(perfis.perfil(Some("inexistente")), String::from("teste"));
+        for dado in DadoPessoal::TODOS.iter().copied() {
+            assert!(ocultou(&desconhecido, dado), "{}", dado.nome());
+        }
+    }
+
+    #[test]
+    fn dados_que_nao_sao_pessoais_nao_sao_ocultados() {
+        let publico = Mascaramento::new(PerfilDeExposicao::new("publico", &DadoPessoal::TODOS), String::from("teste"));
+
+        // o nome e o CNPJ do sócio pessoa jurídica
+        let mut nome = String::from("EMPRESA S.A.");
+        let mut documento = Some(String::from("11222333000181"));
+        publico.nome_do_socio(IdentificadorDeSocio::PessoaJuridica, &mut nome);
+        publico.documento_do_socio(IdentificadorDeSocio::PessoaJuridica, &mut documento);
+        assert_eq!(nome, "EMPRESA S.A.");
+        assert_eq!(documento.as_deref(), Some("11222333000181"));
+
+        // o sócio sem representante legal
+        let (mut cpf, mut nome) = (String::from("***000000**"), String::new());
+        publico.representante_legal(&mut cpf, &mut nome);
+        assert_eq!((cpf.as_str(), nome.as_str()), ("***000000**", ""));
+
+        // a razão social sem CPF
+        let mut razao_social = String::from("EMPRESA S.A.");
+        publico.razao_social(&mut razao_social);
+        assert_eq!(razao_social, "EMPRESA S.A.");
+
+        assert!(publico.ocultados.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn razao_social_sem_o_cpf_do_titular() {
+        let casos = [
+            ("FULANO DE TAL 12345678901", Some("FULANO DE TAL")),
+            // espaços ao final da razão social e entre o nome e o CPF
+            ("FULANO DE TAL 12345678901   ", Some("FULANO DE TAL")),
+            ("FULANO DE TAL   12345678901", Some("FULANO DE TAL")),
+            // o último termo não tem 11 dígitos
+            ("FULANO DE TAL 1234567890", None),
+            ("FULANO DE TAL 123456789012", None),
+            ("FULANO DE TAL 1234567890A", None),
+            ("FULANO DE TAL 123.456.789-01", None),
+            // sem CPF
+            ("FULANO DE TAL", None),
+            ("EMPRESA 2000 LTDA", None),
+            // somente o CPF, sem o nome
+            ("12345678901", None),
+            ("", None),
+        ];
+        for (razao_social, esperado) in casos.iter() {
+            assert_eq!(razao_social_sem_cpf(razao_social).as_deref(), *esperado, "{:?}", razao_social);
+        }
+    }
+
+    #[test]
+    fn nomes_dos_dados_pessoais() {
+        for dado in DadoPessoal::TODOS.iter().copied() {
+            assert_eq!(DadoPessoal::from_nome(dado.nome()), Some(dado));
+        }
+        assert_eq!(DadoPessoal::from_nome("nenhum"), None);
+    }
+}
@@ -6,4 +6,21 @@ extern crate chrono;
 
 pub mod schema;
 pub mod models;
+pub mod dominios;
+pub mod documentos;
+pub mod enderecos;
+pub mod contatos;
+pub mod grupo_economico;
+pub mod socios;
+pub mod busca;
+pub mod lote;
+pub mod paginacao;
+pub mod versao;
+pub mod cache;
+pub mod acesso;
+pub mod lgpd;
+pub mod monitoramento;
+pub mod logs;
+#[cfg(feature = "indice")]
+pub mod indice;
 
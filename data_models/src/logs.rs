@@ -0,0 +1,28 @@
+// Logs estruturados (tracing) do importer e dos servidores, gravados na saída de erros (stderr).
+//
+// Variáveis de ambiente:
+//
+//   RUST_LOG          filtro dos logs por nível e módulo (ex: warn, info,importer=debug, rocket=warn)
+//   FORMATO_DOS_LOGS  texto (padrão) ou json (um objeto por linha, com os campos do evento e dos spans)
+//
+// Os logs das bibliotecas que utilizam a crate log (ex: o Rocket) também são encaminhados.
+
+use std::env;
+
+use tracing_subscriber::EnvFilter;
+
+// Inicia os logs com o filtro informado em RUST_LOG ou, se não informado, com o filtro padrão
+pub fn iniciar_logs(filtro_padrao: &str) {
+    let filtro = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(filtro_padrao));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filtro).with_writer(std::io::stderr);
+
+    let resultado = match env::var("FORMATO_DOS_LOGS").as_deref() {
+        Ok("json") => subscriber.json().try_init(),
+        Ok("texto") | Err(_) => subscriber.try_init(),
+        Ok(formato) => panic!("Formato dos logs inválido em FORMATO_DOS_LOGS: {} (utilize texto ou json)", formato),
+    };
+
+    if let Err(error) = resultado {
+        panic!("Não foi possível iniciar os logs: {}", error);
+    }
+}
@@ -0,0 +1,112 @@
+// Consulta de estabelecimentos em lote (vários CNPJs em uma única requisição).
+//
+// Os CNPJs são consultados em blocos, com uma única query por bloco que seleciona todos os CNPJs do bloco,
+// ao invés de uma query para cada CNPJ.
+
+use std::collections::HashMap;
+
+use diesel::dsl::sql;
+use diesel::expression::BoxableExpression;
+use diesel::mysql::Mysql;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use serde::Serialize;
+
+use crate::documentos::normalizar_cnpj;
+use crate::schema::estabelecimentos;
+
+// quantidade máxima de CNPJs aceitos em um único lote
+pub const LIMITE_DO_LOTE: usize = 1000;
+// quantidade de CNPJs consultados em cada query
+pub const TAMANHO_DO_BLOCO: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "graphql", derive(juniper::GraphQLEnum))]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StatusDoItemDoLote {
+    /// Estabelecimento encontrado
+    Encontrado,
+    /// CNPJ válido, mas o estabelecimento não foi encontrado
+    NaoEncontrado,
+    /// CNPJ inválido (tamanho ou dígitos verificadores incorretos)
+    Invalido,
+}
+
+// Normaliza os CNPJs informados, retornando para cada um o CNPJ com 14 dígitos (ou None caso seja
+// inválido), e a lista dos CNPJs válidos sem repetições a serem consultados
+pub fn normalizar_lote(cnpjs: &[String]) -> (Vec<Option<String>>, Vec<String>) {
+    let normalizados: Vec<Option<String>> = cnpjs.iter().map(|cnpj| normalizar_cnpj(cnpj)).collect();
+
+    let mut validos: Vec<String> = normalizados.iter().flatten().cloned().collect();
+    validos.sort_unstable();
+    validos.dedup();
+
+    (normalizados, validos)
+}
+
+// Condição que seleciona os estabelecimentos dos CNPJs informados (normalizados, com 14 dígitos), montada
+// com as expressões do diesel, de modo que os CNPJs são enviados como parâmetros da query. Pode ser utilizada
+// em qualquer consulta em que a tabela estabelecimentos aparece (inclusive com junções).
+pub fn condicao_dos_cnpjs<QS: 'static>(cnpjs: &[String]) -> Box<dyn BoxableExpression<QS, Mysql, SqlType = Bool>>
+where
+    estabelecimentos::cnpj_basico: SelectableExpression<QS>,
+    estabelecimentos::cnpj_ordem: SelectableExpression<QS>,
+    estabelecimentos::cnpj_dv: SelectableExpression<QS>,
+{
+    let mut condicao: Option<Box<dyn BoxableExpression<QS, Mysql, SqlType = Bool>>> = None;
+
+    for cnpj in cnpjs.iter().filter(|cnpj| cnpj.len() == 14 && cnpj.chars().all(|c| c.is_ascii_digit())) {
+        let condicao_do_cnpj = estabelecimentos::cnpj_basico
+            .eq(cnpj[..8].to_string())
+            .and(estabelecimentos::cnpj_ordem.eq(cnpj[8..12].to_string()))
+            .and(estabelecimentos::cnpj_dv.eq(cnpj[12..].to_string()));
+        condicao = Some(match condicao {
+            Some(anterior) => Box::new(anterior.or(condicao_do_cnpj)),
+            None => Box::new(condicao_do_cnpj),
+        });
+    }
+
+    condicao.unwrap_or_else(|| Box::new(sql::<Bool>("1 = 0")))
+}
+
+// Indexa os registros encontrados pelo CNPJ completo (14 dígitos), para associá-los aos itens do lote
+pub fn por_cnpj<T>(encontrados: Vec<T>, cnpj: impl Fn(&T) -> String) -> HashMap<String, T> {
+    encontrados.into_iter().map(|registro| (cnpj(&registro), registro)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::debug_query;
+
+    #[test]
+    fn cnpjs_sao_enviados_como_parametros() {
+        let cnpjs = vec![String::from("11222333000181"), String::from("1' OR '1'='1"), String::from("11444777000161")];
+        let query = estabelecimentos::table.select(estabelecimentos::cnpj_basico).filter(condicao_dos_cnpjs::<estabelecimentos::table>(&cnpjs));
+        let sql = debug_query::<Mysql, _>(&query).to_string();
+
+        assert_eq!(sql.matches("`estabelecimentos`.`cnpj_basico` = ?").count(), 2, "{}", sql);
+        assert!(!sql.contains("OR '1'='1"), "{}", sql);
+        assert!(sql.ends_with(r#"-- binds: ["11222333", "0001", "81", "11444777", "0001", "61"]"#), "{}", sql);
+    }
+
+    #[test]
+    fn lote_sem_cnpjs_validos_nao_seleciona_nada() {
+        let query = estabelecimentos::table.select(estabelecimentos::cnpj_basico).filter(condicao_dos_cnpjs::<estabelecimentos::table>(&[]));
+        assert!(debug_query::<Mysql, _>(&query).to_string().contains("WHERE 1 = 0"));
+    }
+
+    #[test]
+    fn itens_do_lote_por_cnpj() {
+        let (normalizados, validos) = normalizar_lote(&[
+            String::from("11.222.333/0001-81"),
+            String::from("11222333000181"),
+            String::from("11222333000182"),
+        ]);
+        assert_eq!(normalizados, vec![Some(String::from("11222333000181")), Some(String::from("11222333000181")), None]);
+        assert_eq!(validos, vec![String::from("11222333000181")]);
+
+        let encontrados = por_cnpj(vec![("11222333", "0001", "81")], |(basico, ordem, dv)| format!("{}{}{}", basico, ordem, dv));
+        assert!(encontrados.contains_key("11222333000181"));
+    }
+}
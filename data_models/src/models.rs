@@ -2,9 +2,11 @@ use bigdecimal::BigDecimal;
 use chrono::{NaiveDate, NaiveDateTime};
 use serde::Serialize;
 
-use super::schema::{empresas,estabelecimentos,cnaes,naturezas_juridicas,municipios,paises,qualificacoes_de_socios,motivos_de_situacoes_cadastrais,arquivos_importados,socios,simples,metadados_das_tabelas};
+use super::dominios::{FaixaEtaria as CodigoFaixaEtaria, IdentificadorDeSocio, MatrizFilial, OpcaoSimples, Porte, SituacaoCadastral};
 
-#[derive(Identifiable, Queryable, Serialize)]
+use super::schema::{empresas,estabelecimentos,cnaes,naturezas_juridicas,municipios,municipios_ibge,paises,qualificacoes_de_socios,motivos_de_situacoes_cadastrais,arquivos_importados,socios,simples,metadados_das_tabelas,chaves_de_api,valores_invalidos};
+
+#[derive(Identifiable, Queryable, Serialize, Clone)]
 #[primary_key(cnpj_basico)]
 pub struct Empresa {
     pub cnpj_basico: String,
@@ -12,7 +14,7 @@ pub struct Empresa {
     pub natureza_juridica: Option<u16>,
     pub qualificacao_do_responsavel: Option<u8>,
     pub capital_social: Option<BigDecimal>,
-    pub porte: Option<String>,
+    pub porte: Option<Porte>,
     pub ente_federativo_responsavel: Option<String>
 }
 
@@ -24,11 +26,11 @@ pub struct NewEmpresa {
     pub natureza_juridica: Option<u16>,
     pub qualificacao_do_responsavel: Option<u8>,
     pub capital_social: Option<BigDecimal>,
-    pub porte: Option<String>,
+    pub porte: Option<Porte>,
     pub ente_federativo_responsavel: Option<String>
 }
 
-#[derive(Identifiable,Queryable, Serialize)]
+#[derive(Identifiable, Queryable, Serialize, Clone)]
 #[table_name="naturezas_juridicas"]
 pub struct NaturezaJuridica {
     pub id: u16,
@@ -42,7 +44,7 @@ pub struct NewNaturezaJuridica {
     pub nome: String,
 }
 
-#[derive(Identifiable,Queryable, Serialize)]
+#[derive(Identifiable, Queryable, Serialize, Clone)]
 #[table_name="qualificacoes_de_socios"]
 pub struct QualificacaoDeSocio {
     pub id: u8,
@@ -56,7 +58,7 @@ pub struct NewQualificacaoDeSocio {
     pub nome: String,
 }
 
-#[derive(Identifiable,Queryable, Serialize)]
+#[derive(Identifiable, Queryable, Serialize, Clone)]
 #[table_name="cnaes"]
 pub struct CNAE {
     pub id: u32,
@@ -70,7 +72,7 @@ pub struct NewCNAE {
     pub nome: String,
 }
 
-#[derive(Identifiable,Queryable, Serialize)]
+#[derive(Identifiable, Queryable, Serialize, Clone)]
 #[table_name="paises"]
 pub struct Pais {
     pub id: u16,
@@ -84,7 +86,7 @@ pub struct NewPais {
     pub nome: String,
 }
 
-#[derive(Identifiable,Queryable, Serialize)]
+#[derive(Identifiable, Queryable, Serialize, Clone)]
 #[table_name="motivos_de_situacoes_cadastrais"]
 pub struct MotivoDeSituacaoCadastral {
     pub id: u8,
@@ -99,10 +101,13 @@ pub struct NewMotivoDeSituacaoCadastral {
 }
 
 
-#[derive(Identifiable,Queryable, Serialize)]
+#[derive(Identifiable, Queryable, Serialize, Clone)]
 pub struct Municipio {
     pub id: u16,
     pub nome: String,
+    // código de 7 dígitos do IBGE e UF, da correspondência carregada por importer ibge
+    pub codigo_ibge: Option<u32>,
+    pub uf: Option<String>,
 }
 
 #[derive(Debug,Insertable)]
@@ -111,16 +116,24 @@ pub struct NewMunicipio {
     pub id: u16,
     pub nome: String,
 }
-#[derive(Identifiable, Queryable, Associations, Serialize)]
+
+#[derive(Debug,Insertable)]
+#[table_name="municipios_ibge"]
+pub struct NewMunicipioIbge {
+    pub municipio: u16,
+    pub codigo_ibge: u32,
+    pub uf: String,
+}
+#[derive(Identifiable, Queryable, Associations, Serialize, Clone)]
 #[primary_key(cnpj_basico,cnpj_ordem,cnpj_dv)]
 #[belongs_to(Empresa, foreign_key="cnpj_basico")]
 pub struct Estabelecimento {
     pub cnpj_basico: String,
     pub cnpj_ordem: String,
     pub cnpj_dv: String,
-    pub identificador_matriz_filial: String,
+    pub identificador_matriz_filial: MatrizFilial,
     pub nome_fantasia: Option<String>,
-    pub situacao_cadastral: u8,
+    pub situacao_cadastral: SituacaoCadastral,
     pub data_situacao_cadastral: Option<NaiveDate>,
     pub motivo_situacao_cadastral: Option<u8>,
     pub nome_da_cidade_no_exterior: Option<String>,
@@ -145,6 +158,13 @@ pub struct Estabelecimento {
     pub correio_eletronico: Option<String>,
     pub situacao_especial: Option<String>,
     pub data_situacao_especial: Option<NaiveDate>,
+    pub endereco_normalizado: Option<String>,
+    pub cep_valido: Option<bool>,
+    pub telefone1_e164: Option<String>,
+    pub telefone2_e164: Option<String>,
+    pub telefone_fax_e164: Option<String>,
+    pub email_normalizado: Option<String>,
+    pub email_valido: Option<bool>,
 }
 
 #[derive(Debug,Insertable)]
@@ -153,9 +173,9 @@ pub struct NewEstabelecimento {
     pub cnpj_basico: String,
     pub cnpj_ordem: String,
     pub cnpj_dv: String,
-    pub identificador_matriz_filial: String,
+    pub identificador_matriz_filial: MatrizFilial,
     pub nome_fantasia: Option<String>,
-    pub situacao_cadastral: u8,
+    pub situacao_cadastral: SituacaoCadastral,
     pub data_situacao_cadastral: Option<NaiveDate>,
     pub motivo_situacao_cadastral: Option<u8>,
     pub nome_da_cidade_no_exterior: Option<String>,
@@ -179,7 +199,14 @@ pub struct NewEstabelecimento {
     pub telefone_fax: Option<String>,
     pub correio_eletronico: Option<String>,
     pub situacao_especial: Option<String>,
-    pub data_situacao_especial: Option<NaiveDate>,    
+    pub data_situacao_especial: Option<NaiveDate>,
+    pub endereco_normalizado: Option<String>,
+    pub cep_valido: Option<bool>,
+    pub telefone1_e164: Option<String>,
+    pub telefone2_e164: Option<String>,
+    pub telefone_fax_e164: Option<String>,
+    pub email_normalizado: Option<String>,
+    pub email_valido: Option<bool>,
 }
 
 #[derive(Queryable)]
@@ -201,11 +228,11 @@ pub struct NewArquivoImportado<'a> {
     pub tempo_decorrido_em_segundos: Option<u64>
 }
 
-#[derive(Queryable)]
+#[derive(Queryable, Serialize, Clone)]
 pub struct Socio {
     pub id: u32,
     pub cnpj_basico: String,
-    pub identificador_de_socio: u8,
+    pub identificador_de_socio: IdentificadorDeSocio,
     pub nome_ou_razao_social_do_socio: String,
     pub cnpj_ou_cpf_do_socio: Option<String>,
     pub qualificacao_do_socio: u8,
@@ -214,14 +241,14 @@ pub struct Socio {
     pub cpf_do_representante_legal: String,
     pub nome_do_representante_legal: String,
     pub qualificacao_do_representante_legal: u8,
-    pub faixa_etaria_do_socio: u8
+    pub faixa_etaria_do_socio: CodigoFaixaEtaria
 }
 
 #[derive(Debug,Insertable)]
 #[table_name="socios"]
 pub struct NewSocio {
     pub cnpj_basico: String,
-    pub identificador_de_socio: u8,
+    pub identificador_de_socio: IdentificadorDeSocio,
     pub nome_ou_razao_social_do_socio: String,
     pub cnpj_ou_cpf_do_socio: Option<String>,
     pub qualificacao_do_socio: u8,
@@ -230,22 +257,28 @@ pub struct NewSocio {
     pub cpf_do_representante_legal: String,
     pub nome_do_representante_legal: String,
     pub qualificacao_do_representante_legal: u8,
-    pub faixa_etaria_do_socio: u8,    
+    pub faixa_etaria_do_socio: CodigoFaixaEtaria,    
 }
 
-#[derive(Queryable)]
+#[derive(Queryable, Serialize, Clone)]
 pub struct FaixaEtaria {
     pub id: u8,
     pub nome: Option<String>,
 }
 
-#[derive(Queryable)]
+#[derive(Queryable, Serialize, Clone)]
+pub struct NomeDaSituacaoCadastral {
+    pub id: u8,
+    pub nome: String,
+}
+
+#[derive(Queryable, Serialize, Clone)]
 pub struct Simples {
     pub cnpj_basico: String,
-    pub opcao_pelo_simples: String,
+    pub opcao_pelo_simples: OpcaoSimples,
     pub data_de_opcao_pelo_simples: Option<NaiveDate>,
     pub data_de_exclusao_do_simples: Option<NaiveDate>,
-    pub opcao_pelo_mei: String,
+    pub opcao_pelo_mei: OpcaoSimples,
     pub data_de_opcao_pelo_mei: Option<NaiveDate>,
     pub data_de_exclusao_do_mei: Option<NaiveDate>,    
 }
@@ -254,15 +287,15 @@ pub struct Simples {
 #[table_name="simples"]
 pub struct NewSimples {
     pub cnpj_basico: String,
-    pub opcao_pelo_simples: String,
+    pub opcao_pelo_simples: OpcaoSimples,
     pub data_de_opcao_pelo_simples: Option<NaiveDate>,
     pub data_de_exclusao_do_simples: Option<NaiveDate>,
-    pub opcao_pelo_mei: String,
+    pub opcao_pelo_mei: OpcaoSimples,
     pub data_de_opcao_pelo_mei: Option<NaiveDate>,
     pub data_de_exclusao_do_mei: Option<NaiveDate>,
 }
 
-#[derive(Queryable)]
+#[derive(Queryable, Serialize, Clone)]
 pub struct MetadadosDasTabelas {
     pub tabela: String,
     pub data_hora_de_atualizacao: NaiveDateTime,
@@ -276,4 +309,47 @@ pub struct NewMetadadosDasTabelas<'a> {
     pub tabela: &'a str,
     pub data_hora_de_atualizacao: NaiveDateTime,
     pub data_hora_de_importacao: NaiveDateTime,
-}
\ No newline at end of file
+}
+
+#[derive(Queryable, Serialize, Clone)]
+pub struct ChaveDeApi {
+    pub id: u32,
+    pub nome: String,
+    pub perfil: String,
+    pub hash_da_chave: String,
+    pub requisicoes_por_minuto: Option<u32>,
+    pub requisicoes_por_dia: Option<u32>,
+    pub ativa: bool,
+    pub data_hora_de_criacao: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[table_name="chaves_de_api"]
+pub struct NewChaveDeApi<'a> {
+    pub nome: &'a str,
+    pub perfil: &'a str,
+    pub hash_da_chave: &'a str,
+    pub requisicoes_por_minuto: Option<u32>,
+    pub requisicoes_por_dia: Option<u32>,
+    pub ativa: bool,
+    pub data_hora_de_criacao: NaiveDateTime,
+}
+
+#[derive(Queryable, Serialize, Clone)]
+pub struct UsoDaChaveDeApi {
+    pub chave_de_api: u32,
+    pub data: NaiveDate,
+    pub requisicoes: u32,
+    pub requisicoes_recusadas: u32,
+}
+
+#[derive(Insertable)]
+#[table_name="valores_invalidos"]
+pub struct NewValorInvalido<'a> {
+    pub nome_do_arquivo: &'a str,
+    pub coluna: &'a str,
+    pub tabela: &'a str,
+    pub quantidade: u32,
+    pub exemplo: &'a str,
+    pub registro_rejeitado: bool,
+}
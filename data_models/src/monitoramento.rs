@@ -0,0 +1,233 @@
+// Monitoramento dos servidores: verificação de prontidão (readiness) e métricas no formato de texto do
+// Prometheus (https://prometheus.io/docs/instrumenting/exposition_formats/).
+//
+// As métricas das requisições e das consultas ao banco de dados são acumuladas em METRICAS, compartilhada
+// por todo o processo, e exportadas junto com as métricas calculadas no momento da coleta (utilização do
+// pool de conexões, caches e idade dos dados de cada tabela).
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use diesel::mysql::MysqlConnection;
+
+use crate::cache::EstatisticasDoCache;
+use crate::models::MetadadosDasTabelas;
+use crate::versao::metadados_das_tabelas;
+
+// limites (em segundos) dos intervalos dos histogramas de duração
+const LIMITES_DOS_HISTOGRAMAS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+// Verifica se o servidor pode atender às requisições: o banco de dados responde e os dados já foram
+// importados (a tabela metadados_das_tabelas não está vazia). Retorna a quantidade de tabelas importadas.
+pub fn verificar_prontidao(conn: &MysqlConnection) -> Result<usize, String> {
+    let metadados = metadados_das_tabelas(conn).map_err(|error| format!("Erro ao consultar o banco de dados: {}", error))?;
+    if metadados.is_empty() {
+        return Err(String::from("Nenhuma tabela foi importada (metadados_das_tabelas está vazia)"));
+    }
+    Ok(metadados.len())
+}
+
+struct Histograma {
+    // quantidade de observações em cada intervalo (não acumulada)
+    contagens: [u64; LIMITES_DOS_HISTOGRAMAS.len()],
+    soma: f64,
+    total: u64,
+}
+
+impl Histograma {
+    const fn new() -> Histograma {
+        Histograma {
+            contagens: [0; LIMITES_DOS_HISTOGRAMAS.len()],
+            soma: 0.0,
+            total: 0,
+        }
+    }
+
+    fn observar(&mut self, duracao: Duration) {
+        let segundos = duracao.as_secs_f64();
+        if let Some(posicao) = LIMITES_DOS_HISTOGRAMAS.iter().position(|limite| segundos <= *limite) {
+            self.contagens[posicao] += 1;
+        }
+        self.soma += segundos;
+        self.total += 1;
+    }
+}
+
+// Texto das métricas no formato do Prometheus
+#[derive(Default)]
+pub struct TextoDoPrometheus(String);
+
+impl TextoDoPrometheus {
+    pub fn new() -> TextoDoPrometheus {
+        TextoDoPrometheus::default()
+    }
+
+    // Inicia uma métrica (tipo: counter, gauge ou histogram)
+    pub fn metrica(&mut self, nome: &str, tipo: &str, ajuda: &str) {
+        let _ = writeln!(self.0, "# HELP {} {}", nome, ajuda);
+        let _ = writeln!(self.0, "# TYPE {} {}", nome, tipo);
+    }
+
+    pub fn valor(&mut self, nome: &str, rotulos: &[(&str, &str)], valor: f64) {
+        let _ = writeln!(self.0, "{}{} {}", nome, formatar_rotulos(rotulos), valor);
+    }
+
+    fn histograma(&mut self, nome: &str, rotulos: &[(&str, &str)], histograma: &Histograma) {
+        let mut acumulado = 0;
+        for (limite, contagem) in LIMITES_DOS_HISTOGRAMAS.iter().zip(histograma.contagens.iter()) {
+            acumulado += contagem;
+            let limite = limite.to_string();
+            let mut rotulos_do_intervalo = rotulos.to_vec();
+            rotulos_do_intervalo.push(("le", &limite));
+            self.valor(&format!("{}_bucket", nome), &rotulos_do_intervalo, acumulado as f64);
+        }
+        let mut rotulos_do_total = rotulos.to_vec();
+        rotulos_do_total.push(("le", "+Inf"));
+        self.valor(&format!("{}_bucket", nome), &rotulos_do_total, histograma.total as f64);
+        self.valor(&format!("{}_sum", nome), rotulos, histograma.soma);
+        self.valor(&format!("{}_count", nome), rotulos, histograma.total as f64);
+    }
+
+    // Utilização do pool de conexões com o banco de dados
+    pub fn pool_de_conexoes(&mut self, em_uso: u64, maximo: u64) {
+        self.metrica("cnpj_conexoes_do_banco_em_uso", "gauge", "Conexões do pool com o banco de dados em uso");
+        self.valor("cnpj_conexoes_do_banco_em_uso", &[], em_uso as f64);
+        self.metrica("cnpj_conexoes_do_banco_maximo", "gauge", "Quantidade máxima de conexões do pool com o banco de dados");
+        self.valor("cnpj_conexoes_do_banco_maximo", &[], maximo as f64);
+    }
+
+    // Idade dos dados de cada tabela, em segundos desde a última importação
+    pub fn idade_dos_dados(&mut self, metadados: &[MetadadosDasTabelas]) {
+        let agora = Utc::now().naive_utc();
+        self.metrica("cnpj_segundos_desde_a_importacao", "gauge", "Segundos desde a última importação da tabela");
+        for tabela in metadados {
+            let segundos = (agora - tabela.data_hora_de_importacao).num_seconds();
+            self.valor("cnpj_segundos_desde_a_importacao", &[("tabela", &tabela.tabela)], segundos as f64);
+        }
+    }
+
+    // Acertos (hits), falhas (misses) e registros dos caches de consultas
+    pub fn caches(&mut self, estatisticas: &[EstatisticasDoCache]) {
+        self.metrica("cnpj_cache_acertos_total", "counter", "Consultas atendidas pelo cache de consultas");
+        for cache in estatisticas {
+            self.valor("cnpj_cache_acertos_total", &[("cache", cache.nome)], cache.acertos as f64);
+        }
+        self.metrica("cnpj_cache_falhas_total", "counter", "Consultas não encontradas no cache de consultas");
+        for cache in estatisticas {
+            self.valor("cnpj_cache_falhas_total", &[("cache", cache.nome)], cache.falhas as f64);
+        }
+        self.metrica("cnpj_cache_registros", "gauge", "Registros no cache de consultas");
+        for cache in estatisticas {
+            self.valor("cnpj_cache_registros", &[("cache", cache.nome)], cache.entradas as f64);
+        }
+    }
+
+    pub fn texto(self) -> String {
+        self.0
+    }
+}
+
+fn formatar_rotulos(rotulos: &[(&str, &str)]) -> String {
+    if rotulos.is_empty() {
+        return String::new();
+    }
+    let rotulos: Vec<String> = rotulos
+        .iter()
+        .map(|(nome, valor)| format!("{}=\"{}\"", nome, valor.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")))
+        .collect();
+    format!("{{{}}}", rotulos.join(","))
+}
+
+pub struct Metricas {
+    // quantidade de requisições por rota, método e status
+    requisicoes: Mutex<BTreeMap<(String, String, u16), u64>>,
+    // duração das requisições por rota
+    duracao_das_requisicoes: Mutex<BTreeMap<String, Histograma>>,
+    // quantidade e duração das operações do GraphQL, pelo nome da operação
+    operacoes_graphql: Mutex<BTreeMap<String, Histograma>>,
+    // tempo de uso das conexões com o banco de dados e conexões em uso
+    uso_das_conexoes: Mutex<Histograma>,
+    conexoes_em_uso: AtomicI64,
+}
+
+pub static METRICAS: Metricas = Metricas::new();
+
+impl Metricas {
+    const fn new() -> Metricas {
+        Metricas {
+            requisicoes: Mutex::new(BTreeMap::new()),
+            duracao_das_requisicoes: Mutex::new(BTreeMap::new()),
+            operacoes_graphql: Mutex::new(BTreeMap::new()),
+            uso_das_conexoes: Mutex::new(Histograma::new()),
+            conexoes_em_uso: AtomicI64::new(0),
+        }
+    }
+
+    pub fn registrar_requisicao(&self, rota: &str, metodo: &str, status: u16, duracao: Duration) {
+        *self.requisicoes.lock().unwrap().entry((rota.to_string(), metodo.to_string(), status)).or_insert(0) += 1;
+        self.duracao_das_requisicoes
+            .lock()
+            .unwrap()
+            .entry(rota.to_string())
+            .or_insert_with(Histograma::new)
+            .observar(duracao);
+    }
+
+    pub fn registrar_operacao_graphql(&self, operacao: &str, duracao: Duration) {
+        self.operacoes_graphql
+            .lock()
+            .unwrap()
+            .entry(operacao.to_string())
+            .or_insert_with(Histograma::new)
+            .observar(duracao);
+    }
+
+    // Inicia a medição do uso de uma conexão com o banco de dados, que termina quando o retorno é descartado
+    pub fn iniciar_uso_da_conexao(&self) -> UsoDaConexao {
+        self.conexoes_em_uso.fetch_add(1, Ordering::Relaxed);
+        UsoDaConexao(Instant::now())
+    }
+
+    // Conexões com o banco de dados em uso
+    pub fn conexoes_em_uso(&self) -> u64 {
+        self.conexoes_em_uso.load(Ordering::Relaxed).max(0) as u64
+    }
+
+    pub fn exportar(&self, texto: &mut TextoDoPrometheus) {
+        texto.metrica("cnpj_requisicoes_total", "counter", "Requisições atendidas por rota, método e status");
+        for ((rota, metodo, status), quantidade) in self.requisicoes.lock().unwrap().iter() {
+            texto.valor("cnpj_requisicoes_total", &[("rota", rota), ("metodo", metodo), ("status", &status.to_string())], *quantidade as f64);
+        }
+
+        texto.metrica("cnpj_duracao_das_requisicoes_segundos", "histogram", "Duração das requisições por rota");
+        for (rota, histograma) in self.duracao_das_requisicoes.lock().unwrap().iter() {
+            texto.histograma("cnpj_duracao_das_requisicoes_segundos", &[("rota", rota)], histograma);
+        }
+
+        let operacoes = self.operacoes_graphql.lock().unwrap();
+        if !operacoes.is_empty() {
+            texto.metrica("cnpj_duracao_das_operacoes_graphql_segundos", "histogram", "Duração das operações do GraphQL pelo nome da operação");
+            for (operacao, histograma) in operacoes.iter() {
+                texto.histograma("cnpj_duracao_das_operacoes_graphql_segundos", &[("operacao", operacao)], histograma);
+            }
+        }
+
+        texto.metrica("cnpj_duracao_do_uso_das_conexoes_segundos", "histogram", "Tempo de uso das conexões com o banco de dados, da obtenção no pool à devolução");
+        texto.histograma("cnpj_duracao_do_uso_das_conexoes_segundos", &[], &self.uso_das_conexoes.lock().unwrap());
+    }
+}
+
+pub struct UsoDaConexao(Instant);
+
+impl Drop for UsoDaConexao {
+    fn drop(&mut self) {
+        METRICAS.conexoes_em_uso.fetch_sub(1, Ordering::Relaxed);
+        if let Ok(mut histograma) = METRICAS.uso_das_conexoes.lock() {
+            histograma.observar(self.0.elapsed());
+        }
+    }
+}
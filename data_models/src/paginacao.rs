@@ -0,0 +1,29 @@
+// Paginação das listagens e das buscas das APIs, que recebem a página (a partir de 1) e o limite de registros
+// por página informados pelos usuários.
+
+// Retorna a página (as páginas menores que 1 são tratadas como a primeira) e o deslocamento (OFFSET) do seu
+// primeiro registro, ou um erro quando o deslocamento não cabe em um i64
+pub fn deslocamento_da_pagina(pagina: Option<i64>, limite: i64) -> Result<(i64, i64), String> {
+    let pagina = pagina.unwrap_or(1).max(1);
+    let deslocamento = (pagina - 1)
+        .checked_mul(limite.max(0))
+        .ok_or_else(|| format!("Página inválida (muito grande para o limite de {} registros): {}", limite, pagina))?;
+
+    Ok((pagina, deslocamento))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deslocamento_das_paginas() {
+        assert_eq!(deslocamento_da_pagina(None, 100), Ok((1, 0)));
+        assert_eq!(deslocamento_da_pagina(Some(0), 100), Ok((1, 0)));
+        assert_eq!(deslocamento_da_pagina(Some(-5), 100), Ok((1, 0)));
+        assert_eq!(deslocamento_da_pagina(Some(3), 100), Ok((3, 200)));
+        assert_eq!(deslocamento_da_pagina(Some(i64::MAX / 1000 + 1), 1000), Ok((i64::MAX / 1000 + 1, i64::MAX / 1000 * 1000)));
+        assert!(deslocamento_da_pagina(Some(i64::MAX), 1000).is_err());
+        assert!(deslocamento_da_pagina(Some(i64::MAX / 1000 + 2), 1000).is_err());
+    }
+}
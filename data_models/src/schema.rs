@@ -8,6 +8,19 @@ table! {
     }
 }
 
+table! {
+    chaves_de_api (id) {
+        id -> Unsigned<Integer>,
+        nome -> Varchar,
+        perfil -> Varchar,
+        hash_da_chave -> Char,
+        requisicoes_por_minuto -> Nullable<Unsigned<Integer>>,
+        requisicoes_por_dia -> Nullable<Unsigned<Integer>>,
+        ativa -> Bool,
+        data_hora_de_criacao -> Datetime,
+    }
+}
+
 table! {
     cnaes (id) {
         id -> Unsigned<Integer>,
@@ -59,6 +72,13 @@ table! {
         correio_eletronico -> Nullable<Varchar>,
         situacao_especial -> Nullable<Varchar>,
         data_situacao_especial -> Nullable<Date>,
+        endereco_normalizado -> Nullable<Varchar>,
+        cep_valido -> Nullable<Bool>,
+        telefone1_e164 -> Nullable<Varchar>,
+        telefone2_e164 -> Nullable<Varchar>,
+        telefone_fax_e164 -> Nullable<Varchar>,
+        email_normalizado -> Nullable<Varchar>,
+        email_valido -> Nullable<Bool>,
     }
 }
 
@@ -69,6 +89,13 @@ table! {
     }
 }
 
+table! {
+    leituras_das_tabelas (tabela) {
+        tabela -> Varchar,
+        registros_lidos -> Unsigned<Bigint>,
+    }
+}
+
 table! {
     metadados_das_tabelas (tabela) {
         tabela -> Varchar,
@@ -88,6 +115,16 @@ table! {
     municipios (id) {
         id -> Unsigned<Smallint>,
         nome -> Varchar,
+        codigo_ibge -> Nullable<Unsigned<Integer>>,
+        uf -> Nullable<Char>,
+    }
+}
+
+table! {
+    municipios_ibge (municipio) {
+        municipio -> Unsigned<Smallint>,
+        codigo_ibge -> Unsigned<Integer>,
+        uf -> Char,
     }
 }
 
@@ -148,6 +185,26 @@ table! {
     }
 }
 
+table! {
+    uso_das_chaves_de_api (chave_de_api, data) {
+        chave_de_api -> Unsigned<Integer>,
+        data -> Date,
+        requisicoes -> Unsigned<Integer>,
+        requisicoes_recusadas -> Unsigned<Integer>,
+    }
+}
+
+table! {
+    valores_invalidos (nome_do_arquivo, coluna) {
+        nome_do_arquivo -> Varchar,
+        coluna -> Varchar,
+        tabela -> Varchar,
+        quantidade -> Unsigned<Integer>,
+        exemplo -> Varchar,
+        registro_rejeitado -> Bool,
+    }
+}
+
 joinable!(empresas -> naturezas_juridicas (natureza_juridica));
 joinable!(empresas -> qualificacoes_de_socios (qualificacao_do_responsavel));
 joinable!(estabelecimentos -> cnaes (cnae_fiscal_principal));
@@ -156,20 +213,27 @@ joinable!(estabelecimentos -> motivos_de_situacoes_cadastrais (motivo_situacao_c
 joinable!(estabelecimentos -> municipios (municipio));
 joinable!(estabelecimentos -> paises (pais));
 joinable!(estabelecimentos -> situacoes_cadastrais (situacao_cadastral));
+joinable!(socios -> empresas (cnpj_basico));
+joinable!(uso_das_chaves_de_api -> chaves_de_api (chave_de_api));
 
 allow_tables_to_appear_in_same_query!(
     arquivos_importados,
+    chaves_de_api,
     cnaes,
     empresas,
     estabelecimentos,
     faixas_etarias,
+    leituras_das_tabelas,
     metadados_das_tabelas,
     motivos_de_situacoes_cadastrais,
     municipios,
+    municipios_ibge,
     naturezas_juridicas,
     paises,
     qualificacoes_de_socios,
     simples,
     situacoes_cadastrais,
     socios,
+    uso_das_chaves_de_api,
+    valores_invalidos,
 );
@@ -0,0 +1,245 @@
+// Busca de sócios pelo CPF, pelo CNPJ do sócio pessoa jurídica e/ou pelo início do nome, utilizada pelo
+// rest-server (rotas /socios) e pelo graphql-server (consulta socios).
+//
+// O sócio é buscado pelo documento e pelo nome do sócio e, quando o CPF é informado, também pelo CPF e pelo
+// nome do representante legal, de modo que são retornadas todas as empresas onde a pessoa é sócia ou
+// representante legal.
+
+use diesel::dsl::sql;
+use diesel::expression::BoxableExpression;
+use diesel::mysql::{Mysql, MysqlConnection};
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+
+use crate::documentos::{mascarar_cpf, somente_digitos};
+use crate::lgpd::{DadoPessoal, PerfilDeExposicao};
+use crate::schema::socios;
+
+// quantidade mínima de caracteres do início do nome, para que a busca utilize o índice de forma seletiva
+pub const TAMANHO_MINIMO_DO_NOME: usize = 3;
+
+// caractere de escape dos padrões do LIKE
+const ESCAPE_DO_LIKE: char = '\\';
+
+// Parâmetros da busca de sócios já validados
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuscaDeSocios {
+    // CPF mascarado como nos arquivos da RF (ex: ***123456**)
+    pub cpf: Option<String>,
+    // documento do sócio: o CPF mascarado ou o CNPJ (14 dígitos)
+    pub documento: Option<String>,
+    // padrão do LIKE com o início do nome (em maiúsculas e com os caracteres especiais do LIKE escapados)
+    pub nome: Option<String>,
+}
+
+// Parâmetro inválido da busca de sócios (None quando o erro não se refere a um único parâmetro)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErroDaBuscaDeSocios {
+    pub parametro: Option<&'static str>,
+    pub mensagem: String,
+}
+
+impl ErroDaBuscaDeSocios {
+    fn new(parametro: Option<&'static str>, mensagem: String) -> ErroDaBuscaDeSocios {
+        ErroDaBuscaDeSocios { parametro, mensagem }
+    }
+}
+
+impl BuscaDeSocios {
+    // Valida os parâmetros informados: ao menos um deles, o CPF ou o CNPJ (não ambos, pois são o mesmo campo
+    // do sócio) e o início do nome com pelo menos TAMANHO_MINIMO_DO_NOME caracteres
+    pub fn new(cpf: Option<&str>, cnpj: Option<&str>, nome: Option<&str>) -> Result<BuscaDeSocios, ErroDaBuscaDeSocios> {
+        if cpf.is_none() && cnpj.is_none() && nome.is_none() {
+            return Err(ErroDaBuscaDeSocios::new(None, String::from("Informe ao menos um dos parâmetros: cpf, cnpj ou nome")));
+        }
+        if cpf.is_some() && cnpj.is_some() {
+            return Err(ErroDaBuscaDeSocios::new(Some("cnpj"), String::from("Informe o cpf ou o cnpj do sócio, não ambos")));
+        }
+
+        let cpf = match cpf {
+            Some(v) => Some(mascarar_cpf(v).ok_or_else(|| ErroDaBuscaDeSocios::new(Some("cpf"), format!("CPF inválido: {}", v)))?),
+            None => None,
+        };
+        let cnpj = match cnpj {
+            Some(v) => match somente_digitos(v) {
+                cnpj if cnpj.len() == 14 => Some(cnpj),
+                _ => return Err(ErroDaBuscaDeSocios::new(Some("cnpj"), format!("CNPJ inválido: {}", v))),
+            },
+            None => None,
+        };
+        let nome = match nome.map(str::trim) {
+            Some(v) if v.chars().count() < TAMANHO_MINIMO_DO_NOME => {
+                return Err(ErroDaBuscaDeSocios::new(
+                    Some("nome"),
+                    format!("Informe ao menos {} caracteres do início do nome", TAMANHO_MINIMO_DO_NOME),
+                ))
+            }
+            Some(v) => Some(format!("{}%", escapar_like(&v.to_uppercase()))),
+            None => None,
+        };
+
+        Ok(BuscaDeSocios {
+            documento: cpf.clone().or(cnpj),
+            cpf,
+            nome,
+        })
+    }
+
+    // Parâmetro da busca que corresponde a um dado pessoal ocultado pelo perfil de exposição. A busca por ele
+    // não é permitida, pois permitiria identificar as pessoas cujos dados são ocultados nas respostas.
+    pub fn parametro_oculto(&self, perfil: &PerfilDeExposicao) -> Option<&'static str> {
+        // o CPF é buscado tanto no sócio quanto no representante legal e, com ele, também o nome
+        let cpf_oculto = perfil.oculta(DadoPessoal::CpfDoSocio) || perfil.oculta(DadoPessoal::CpfDoRepresentanteLegal);
+        let nome_oculto = perfil.oculta(DadoPessoal::NomeDoSocio)
+            || (self.cpf.is_some() && perfil.oculta(DadoPessoal::NomeDoRepresentanteLegal));
+
+        if self.cpf.is_some() && cpf_oculto {
+            Some("cpf")
+        } else if self.nome.is_some() && nome_oculto {
+            Some("nome")
+        } else {
+            None
+        }
+    }
+}
+
+// Escapa os caracteres especiais do LIKE (%, _ e o próprio caractere de escape)
+pub fn escapar_like(texto: &str) -> String {
+    let mut escapado = String::with_capacity(texto.len());
+    for c in texto.chars() {
+        if c == '%' || c == '_' || c == ESCAPE_DO_LIKE {
+            escapado.push(ESCAPE_DO_LIKE);
+        }
+        escapado.push(c);
+    }
+    escapado
+}
+
+type Condicao = Box<dyn BoxableExpression<socios::table, Mysql, SqlType = Bool>>;
+
+// Condição que seleciona os sócios onde a pessoa informada é sócia ou representante legal
+fn condicao_da_busca(busca: &BuscaDeSocios) -> Condicao {
+    // busca pelo sócio
+    let mut condicao_do_socio: Condicao = Box::new(sql::<Bool>("1 = 1"));
+    if let Some(documento) = &busca.documento {
+        condicao_do_socio = Box::new(condicao_do_socio.and(socios::cnpj_ou_cpf_do_socio.eq(documento.clone())));
+    }
+    if let Some(nome) = &busca.nome {
+        condicao_do_socio = Box::new(
+            condicao_do_socio.and(socios::nome_ou_razao_social_do_socio.like(nome.clone()).escape(ESCAPE_DO_LIKE)),
+        );
+    }
+
+    // busca pelo representante legal (somente quando o CPF foi informado)
+    match &busca.cpf {
+        Some(cpf) => {
+            let mut condicao_do_representante: Condicao = Box::new(socios::cpf_do_representante_legal.eq(cpf.clone()));
+            if let Some(nome) = &busca.nome {
+                condicao_do_representante = Box::new(
+                    condicao_do_representante.and(socios::nome_do_representante_legal.like(nome.clone()).escape(ESCAPE_DO_LIKE)),
+                );
+            }
+            Box::new(condicao_do_socio.or(condicao_do_representante))
+        }
+        None => condicao_do_socio,
+    }
+}
+
+// Consulta um bloco de sócios onde a pessoa informada é sócia ou representante legal, ordenados pelo id, a
+// partir do sócio seguinte ao último do bloco anterior (apos). A continuação pelo id não relê os blocos
+// anteriores, como aconteceria com o OFFSET. O registro é genérico pois o graphql-server carrega os sócios na
+// sua própria estrutura.
+pub fn buscar_socios<S>(conn: &MysqlConnection, busca: &BuscaDeSocios, apos: Option<u32>, quantidade: i64) -> QueryResult<Vec<S>>
+where
+    S: Queryable<socios::SqlType, Mysql>,
+{
+    socios::table
+        .filter(condicao_da_busca(busca))
+        .filter(socios::id.gt(apos.unwrap_or(0)))
+        .order(socios::id)
+        .limit(quantidade)
+        .load::<S>(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::debug_query;
+
+    #[test]
+    fn parametros_da_busca() {
+        let busca = BuscaDeSocios::new(Some("123.456.789-09"), None, Some(" joão ")).unwrap();
+        assert_eq!(busca.cpf.as_deref(), Some("***456789**"));
+        assert_eq!(busca.documento.as_deref(), Some("***456789**"));
+        assert_eq!(busca.nome.as_deref(), Some("JOÃO%"));
+
+        let busca = BuscaDeSocios::new(None, Some("11.222.333/0001-81"), None).unwrap();
+        assert_eq!(busca.cpf, None);
+        assert_eq!(busca.documento.as_deref(), Some("11222333000181"));
+    }
+
+    #[test]
+    fn parametros_invalidos() {
+        let parametro = |cpf, cnpj, nome| BuscaDeSocios::new(cpf, cnpj, nome).unwrap_err().parametro;
+
+        assert_eq!(parametro(None, None, None), None);
+        assert_eq!(parametro(Some("12345678909"), Some("11222333000181"), None), Some("cnpj"));
+        assert_eq!(parametro(Some("1234"), None, None), Some("cpf"));
+        assert_eq!(parametro(None, Some("1122233300018"), None), Some("cnpj"));
+        assert_eq!(parametro(None, None, Some("JO")), Some("nome"));
+        assert_eq!(parametro(None, None, Some(" JO  ")), Some("nome"));
+        assert_eq!(parametro(None, None, Some("")), Some("nome"));
+    }
+
+    #[test]
+    fn parametros_ocultos_pelo_perfil() {
+        let publico = PerfilDeExposicao::new("publico", &DadoPessoal::TODOS);
+        let parceiro = PerfilDeExposicao::new("parceiro", &[DadoPessoal::CpfDoSocio, DadoPessoal::CpfDoRepresentanteLegal]);
+        let sem_nome_do_representante = PerfilDeExposicao::new("teste", &[DadoPessoal::NomeDoRepresentanteLegal]);
+        let interno = PerfilDeExposicao::new("interno", &[]);
+
+        let por_cpf = BuscaDeSocios::new(Some("12345678909"), None, None).unwrap();
+        let por_cnpj = BuscaDeSocios::new(None, Some("11222333000181"), None).unwrap();
+        let por_nome = BuscaDeSocios::new(None, None, Some("FULANO")).unwrap();
+        let por_cpf_e_nome = BuscaDeSocios::new(Some("12345678909"), None, Some("FULANO")).unwrap();
+
+        assert_eq!(por_cpf.parametro_oculto(&publico), Some("cpf"));
+        assert_eq!(por_nome.parametro_oculto(&publico), Some("nome"));
+        assert_eq!(por_cnpj.parametro_oculto(&publico), None);
+
+        assert_eq!(por_cpf.parametro_oculto(&parceiro), Some("cpf"));
+        assert_eq!(por_nome.parametro_oculto(&parceiro), None);
+
+        // com o CPF o nome também é buscado no representante legal
+        assert_eq!(por_nome.parametro_oculto(&sem_nome_do_representante), None);
+        assert_eq!(por_cpf_e_nome.parametro_oculto(&sem_nome_do_representante), Some("nome"));
+
+        for busca in [&por_cpf, &por_cnpj, &por_nome, &por_cpf_e_nome].iter() {
+            assert_eq!(busca.parametro_oculto(&interno), None);
+        }
+    }
+
+    #[test]
+    fn caracteres_especiais_do_like_sao_escapados() {
+        assert_eq!(escapar_like("100% S_A \\ CIA"), "100\\% S\\_A \\\\ CIA");
+
+        let busca = BuscaDeSocios::new(None, None, Some("%%%")).unwrap();
+        assert_eq!(busca.nome.as_deref(), Some("\\%\\%\\%%"));
+
+        let query = socios::table.select(socios::id).filter(condicao_da_busca(&busca));
+        let sql = debug_query::<Mysql, _>(&query).to_string();
+        assert!(sql.contains("`socios`.`nome_ou_razao_social_do_socio` LIKE ? ESCAPE ?"), "{}", sql);
+    }
+
+    #[test]
+    fn cpf_busca_tambem_o_representante_legal() {
+        let busca = BuscaDeSocios::new(Some("***456789**"), None, None).unwrap();
+        let query = socios::table.select(socios::id).filter(condicao_da_busca(&busca));
+        let sql = debug_query::<Mysql, _>(&query).to_string();
+        assert!(sql.contains("`socios`.`cpf_do_representante_legal` = ?"), "{}", sql);
+
+        let busca = BuscaDeSocios::new(None, Some("11222333000181"), None).unwrap();
+        let query = socios::table.select(socios::id).filter(condicao_da_busca(&busca));
+        assert!(!debug_query::<Mysql, _>(&query).to_string().contains("cpf_do_representante_legal"));
+    }
+}
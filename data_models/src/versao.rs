@@ -0,0 +1,202 @@
+// Versão dos dados importados, obtida dos metadados das tabelas (data/hora de atualização na RF e data/hora
+// da importação de cada tabela).
+//
+// Os dados mudam somente quando uma nova importação é feita, por isso a versão é utilizada pelos servidores
+// como validador do cache HTTP: o ETag e o Last-Modified das respostas são derivados da versão das tabelas
+// consultadas, permitindo responder 304 (Not Modified) às requisições condicionais.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{NaiveDateTime, Timelike};
+use diesel::mysql::MysqlConnection;
+use diesel::prelude::*;
+
+use crate::models::MetadadosDasTabelas;
+use crate::schema::metadados_das_tabelas;
+
+// formato das datas nos cabeçalhos HTTP (RFC 7231), sempre em GMT
+const FORMATO_DA_DATA_HTTP: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+// intervalo entre as consultas aos metadados das tabelas feitas pelos servidores
+pub const INTERVALO_DE_ATUALIZACAO_DOS_METADADOS: Duration = Duration::from_secs(60);
+
+pub fn metadados_das_tabelas(conn: &MysqlConnection) -> QueryResult<Vec<MetadadosDasTabelas>> {
+    metadados_das_tabelas::table
+        .order(metadados_das_tabelas::tabela)
+        .load::<MetadadosDasTabelas>(conn)
+}
+
+// Metadados das tabelas lidos recentemente, para que os servidores não consultem o banco de dados a cada
+// requisição. Os metadados ficam válidos por INTERVALO_DE_ATUALIZACAO_DOS_METADADOS.
+#[derive(Default)]
+pub struct MetadadosRecentes(Mutex<Option<(Instant, Vec<MetadadosDasTabelas>)>>);
+
+impl MetadadosRecentes {
+    // Retorna os metadados, caso tenham sido lidos há menos de INTERVALO_DE_ATUALIZACAO_DOS_METADADOS
+    pub fn obter(&self) -> Option<Vec<MetadadosDasTabelas>> {
+        match &*self.0.lock().unwrap() {
+            Some((lidos_em, metadados)) if lidos_em.elapsed() < INTERVALO_DE_ATUALIZACAO_DOS_METADADOS => Some(metadados.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn atualizar(&self, metadados: Vec<MetadadosDasTabelas>) {
+        *self.0.lock().unwrap() = Some((Instant::now(), metadados));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersaoDosDados {
+    // maior data/hora de atualização (na RF) entre as tabelas
+    pub data_hora_de_atualizacao: NaiveDateTime,
+    // maior data/hora de importação entre as tabelas
+    pub data_hora_de_importacao: NaiveDateTime,
+}
+
+impl VersaoDosDados {
+    // Versão das tabelas informadas (ou de todas as tabelas, se nenhuma for informada). Retorna None
+    // quando nenhuma das tabelas foi importada.
+    pub fn de(metadados: &[MetadadosDasTabelas], tabelas: &[&str]) -> Option<VersaoDosDados> {
+        let metadados: Vec<&MetadadosDasTabelas> = metadados
+            .iter()
+            .filter(|m| tabelas.is_empty() || tabelas.contains(&m.tabela.as_str()))
+            .collect();
+
+        Some(VersaoDosDados {
+            data_hora_de_atualizacao: metadados.iter().map(|m| m.data_hora_de_atualizacao).max()?,
+            data_hora_de_importacao: metadados.iter().map(|m| m.data_hora_de_importacao).max()?,
+        })
+    }
+
+    // ETag (fraco) da representação identificada por variante (ex: URI e formato da resposta) nesta versão
+    pub fn etag(&self, variante: &str) -> String {
+        format!(
+            "W/\"{}-{}-{:x}\"",
+            self.data_hora_de_atualizacao.format("%Y%m%d%H%M%S"),
+            self.data_hora_de_importacao.format("%Y%m%d%H%M%S"),
+            fnv1a(variante)
+        )
+    }
+
+    // Data/hora da última modificação dos dados servidos: a importação (ou a atualização na RF, se posterior),
+    // pois uma nova importação da mesma versão da RF também altera os dados (ex: --normalize-addresses)
+    pub fn modificado_em(&self) -> NaiveDateTime {
+        self.data_hora_de_atualizacao.max(self.data_hora_de_importacao)
+    }
+
+    pub fn last_modified(&self) -> String {
+        self.modificado_em().format(FORMATO_DA_DATA_HTTP).to_string()
+    }
+
+    // Indica se a representação em cache do cliente ainda é válida, conforme os cabeçalhos If-None-Match
+    // e If-Modified-Since da requisição (este só é considerado quando aquele não é informado)
+    pub fn nao_modificado(&self, etag: &str, if_none_match: Option<&str>, if_modified_since: Option<&str>) -> bool {
+        if let Some(if_none_match) = if_none_match {
+            // a comparação dos ETags é sempre fraca (RFC 7232, seção 3.2)
+            let etag = etag.trim_start_matches("W/");
+            return if_none_match
+                .split(',')
+                .map(|v| v.trim())
+                .any(|v| v == "*" || v.trim_start_matches("W/") == etag);
+        }
+
+        if_modified_since
+            .and_then(|v| NaiveDateTime::parse_from_str(v.trim(), FORMATO_DA_DATA_HTTP).ok())
+            // a data do cabeçalho não tem frações de segundo
+            .is_some_and(|data| self.modificado_em().with_nanosecond(0).unwrap_or(data) <= data)
+    }
+}
+
+// Hash FNV-1a (64 bits), estável entre execuções e versões do compilador, para que todas as instâncias
+// dos servidores gerem o mesmo ETag
+fn fnv1a(texto: &str) -> u64 {
+    texto.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn data_hora(dia: u32, hora: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, dia).unwrap().and_hms_milli_opt(hora, 30, 15, 250).unwrap()
+    }
+
+    fn versao(atualizacao: NaiveDateTime, importacao: NaiveDateTime) -> VersaoDosDados {
+        VersaoDosDados {
+            data_hora_de_atualizacao: atualizacao,
+            data_hora_de_importacao: importacao,
+        }
+    }
+
+    #[test]
+    fn versao_das_tabelas() {
+        let metadados = vec![
+            MetadadosDasTabelas { tabela: "empresas".into(), data_hora_de_atualizacao: data_hora(10, 8), data_hora_de_importacao: data_hora(12, 3) },
+            MetadadosDasTabelas { tabela: "socios".into(), data_hora_de_atualizacao: data_hora(11, 8), data_hora_de_importacao: data_hora(11, 23) },
+        ];
+
+        assert_eq!(VersaoDosDados::de(&metadados, &["socios"]), Some(versao(data_hora(11, 8), data_hora(11, 23))));
+        assert_eq!(VersaoDosDados::de(&metadados, &[]), Some(versao(data_hora(11, 8), data_hora(12, 3))));
+        assert_eq!(VersaoDosDados::de(&metadados, &["simples"]), None);
+    }
+
+    #[test]
+    fn etag_depende_da_versao_e_da_variante() {
+        let v = versao(data_hora(10, 8), data_hora(12, 3));
+
+        assert_eq!(v.etag("/api/empresas/12345678 application/json"), v.etag("/api/empresas/12345678 application/json"));
+        assert!(v.etag("a").starts_with("W/\"20261010083015-20261012033015-"));
+        assert_ne!(v.etag("/api/empresas/12345678 application/json"), v.etag("/api/empresas/12345678 text/csv"));
+
+        // uma nova importação da mesma versão da RF gera outro ETag
+        assert_ne!(v.etag("a"), versao(data_hora(10, 8), data_hora(13, 3)).etag("a"));
+    }
+
+    #[test]
+    fn last_modified_considera_a_importacao() {
+        assert_eq!(versao(data_hora(10, 8), data_hora(12, 3)).last_modified(), "Mon, 12 Oct 2026 03:30:15 GMT");
+        // a data posterior é utilizada mesmo que a importação seja anterior à atualização informada
+        assert_eq!(versao(data_hora(12, 3), data_hora(10, 8)).last_modified(), "Mon, 12 Oct 2026 03:30:15 GMT");
+    }
+
+    #[test]
+    fn nao_modificado_pelo_if_none_match() {
+        let v = versao(data_hora(10, 8), data_hora(12, 3));
+        let etag = v.etag("a");
+
+        assert!(v.nao_modificado(&etag, Some(&etag), None));
+        // comparação fraca, em uma lista de ETags, e o curinga
+        assert!(v.nao_modificado(&etag, Some(&format!("\"x\", {}", etag.trim_start_matches("W/"))), None));
+        assert!(v.nao_modificado(&etag, Some("*"), None));
+        assert!(!v.nao_modificado(&etag, Some("W/\"outro\""), None));
+    }
+
+    #[test]
+    fn nao_modificado_pelo_if_modified_since() {
+        let v = versao(data_hora(10, 8), data_hora(12, 3));
+        let etag = v.etag("a");
+
+        assert!(v.nao_modificado(&etag, None, Some("Mon, 12 Oct 2026 03:30:15 GMT")));
+        assert!(v.nao_modificado(&etag, None, Some("Tue, 13 Oct 2026 00:00:00 GMT")));
+        assert!(!v.nao_modificado(&etag, None, Some("Mon, 12 Oct 2026 03:30:14 GMT")));
+        assert!(!v.nao_modificado(&etag, None, Some("data inválida")));
+        assert!(!v.nao_modificado(&etag, None, None));
+
+        // uma nova importação da mesma versão da RF invalida a data informada pelo cliente
+        let reimportada = versao(data_hora(10, 8), data_hora(14, 3));
+        assert!(!reimportada.nao_modificado(&reimportada.etag("a"), None, Some(&v.last_modified())));
+    }
+
+    #[test]
+    fn if_none_match_tem_precedencia_sobre_if_modified_since() {
+        let v = versao(data_hora(10, 8), data_hora(12, 3));
+        let etag = v.etag("a");
+
+        // ETag diferente: modificado, mesmo com a data atual
+        assert!(!v.nao_modificado(&etag, Some("W/\"outro\""), Some("Tue, 13 Oct 2026 00:00:00 GMT")));
+        // mesmo ETag: não modificado, mesmo com uma data anterior
+        assert!(v.nao_modificado(&etag, Some(&etag), Some("Sat, 10 Oct 2026 00:00:00 GMT")));
+    }
+}
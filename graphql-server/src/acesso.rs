@@ -0,0 +1,132 @@
+// Exige uma chave de API (cabeçalho X-API-Key ou Authorization: Bearer) nas requisições ao GraphQL e à
+// página do GraphiQL (as rotas de monitoramento não exigem a chave) e controla as cotas de requisições de cada
+// chave (vide data_models::acesso). É habilitado pela variável de ambiente EXIGIR_CHAVE_DE_API.
+//
+// As chaves são carregadas na inicialização (o servidor não é iniciado se não for possível carregá-las) e
+// sincronizadas com o banco de dados por uma tarefa em segundo plano; se uma sincronização falhar, as chaves
+// carregadas anteriormente continuam valendo.
+//
+// O Rocket não permite que um fairing responda à requisição, então as requisições recusadas são desviadas
+// para uma rota inexistente e o catcher responde com 401 (chave ausente ou inválida) ou 429 (cota excedida,
+// com o cabeçalho Retry-After), conforme o motivo guardado no cache local da requisição.
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use data_models::acesso::{chave_da_requisicao, ControleDeAcesso, RecusaDeAcesso, INTERVALO_DE_SINCRONIZACAO};
+use diesel::mysql::MysqlConnection;
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{self, Responder, Response};
+use rocket::{Build, Catcher, Data, Request, Rocket};
+
+use crate::graphql_schema::EstadoDoServidor;
+use crate::monitoramento::ROTAS_DE_MONITORAMENTO;
+
+// caminho (sem rota) para o qual as requisições recusadas são desviadas
+const ROTA_DA_RECUSA: &str = "/acesso_recusado";
+
+#[derive(Default)]
+pub struct ControleDeAcessoDoGraphql {
+    controle: Arc<ControleDeAcesso>,
+}
+
+#[rocket::async_trait]
+impl Fairing for ControleDeAcessoDoGraphql {
+    fn info(&self) -> Info {
+        Info {
+            name: "Controle de acesso por chave de API",
+            kind: Kind::Ignite | Kind::Request,
+        }
+    }
+
+    // Carrega as chaves de API antes de iniciar o servidor e inicia a sincronização periódica das chaves e
+    // dos contadores de uso com o banco de dados
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let pool = match rocket.state::<EstadoDoServidor>() {
+            Some(estado) => estado.pool.clone(),
+            None => return Err(rocket),
+        };
+
+        if let Err(error) = sincronizar(pool.clone(), self.controle.clone()).await {
+            tracing::error!(erro = %error, "Erro ao carregar as chaves de API");
+            return Err(rocket);
+        }
+
+        let controle = self.controle.clone();
+        rocket::tokio::spawn(async move {
+            loop {
+                rocket::tokio::time::sleep(INTERVALO_DE_SINCRONIZACAO).await;
+                if let Err(error) = sincronizar(pool.clone(), controle.clone()).await {
+                    tracing::error!(erro = %error, "Erro ao sincronizar as chaves de API");
+                }
+            }
+        });
+
+        Ok(rocket)
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        if ROTAS_DE_MONITORAMENTO.contains(&request.uri().path().as_str()) {
+            return;
+        }
+
+        let headers = request.headers();
+        match self.controle.verificar(chave_da_requisicao(headers.get_one("X-API-Key"), headers.get_one("Authorization"))) {
+            Ok(chave) => {
+                request.local_cache(|| Some(chave));
+            }
+            Err(recusa) => {
+                request.local_cache(|| Some(recusa));
+                request.set_uri(Origin::parse(ROTA_DA_RECUSA).unwrap());
+            }
+        }
+    }
+}
+
+// Sincroniza as chaves e os contadores de uso com o banco de dados em uma thread separada
+async fn sincronizar(
+    pool: r2d2::Pool<r2d2_diesel::ConnectionManager<MysqlConnection>>,
+    controle: Arc<ControleDeAcesso>,
+) -> Result<(), String> {
+    rocket::tokio::task::spawn_blocking(move || {
+        let connection = pool.get().map_err(|error| format!("{:?}", error))?;
+        controle.sincronizar(&connection).map_err(|error| format!("{:?}", error))
+    })
+    .await
+    .map_err(|error| format!("{:?}", error))?
+}
+
+// Resposta de erro no formato das respostas do GraphQL ({"errors": [{"message": ...}]})
+struct RespostaDeErro(Status, String, Option<u64>);
+
+impl<'r> Responder<'r, 'static> for RespostaDeErro {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+        let corpo = serde_json::json!({ "errors": [{ "message": self.1 }] }).to_string();
+        let mut response = Response::build()
+            .status(self.0)
+            .header(ContentType::JSON)
+            .sized_body(corpo.len(), Cursor::new(corpo))
+            .finalize();
+        if let Some(segundos) = self.2 {
+            response.set_header(Header::new("Retry-After", segundos.to_string()));
+        }
+        Ok(response)
+    }
+}
+
+#[rocket::catch(404)]
+fn nao_encontrado(request: &Request<'_>) -> RespostaDeErro {
+    match request.local_cache(|| None::<RecusaDeAcesso>) {
+        Some(recusa @ RecusaDeAcesso::CotaExcedida { segundos_para_renovacao, .. }) => {
+            RespostaDeErro(Status::TooManyRequests, recusa.mensagem(), Some(*segundos_para_renovacao))
+        }
+        Some(recusa) => RespostaDeErro(Status::Unauthorized, recusa.mensagem(), None),
+        None => RespostaDeErro(Status::NotFound, String::from("Recurso não encontrado"), None),
+    }
+}
+
+pub fn catchers() -> Vec<Catcher> {
+    rocket::catchers![nao_encontrado]
+}
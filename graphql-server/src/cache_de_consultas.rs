@@ -0,0 +1,85 @@
+// Cache em memória (opcional) das consultas de empresas e estabelecimentos pelo CNPJ e das tabelas
+// auxiliares, carregadas integralmente na inicialização. É habilitado pela variável de ambiente
+// CACHE_DE_CONSULTAS (quantidade máxima de registros em cada cache) e os registros expiram após
+// CACHE_DE_CONSULTAS_TTL segundos. Todo o cache é invalidado quando uma nova importação é feita.
+
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use data_models::cache::{CacheLru, EstatisticasDoCache, TabelasAuxiliares, VersaoDoCache};
+use data_models::versao::{metadados_das_tabelas, MetadadosRecentes};
+use diesel::mysql::MysqlConnection;
+
+use crate::graphql_schema::{Empresa, Estabelecimento};
+
+// tempo (em segundos) que os registros permanecem no cache, quando não informado em CACHE_DE_CONSULTAS_TTL
+const TTL_PADRAO: u64 = 3600;
+
+pub struct CacheDeConsultas {
+    pub(crate) empresas: CacheLru<Empresa>,
+    pub(crate) estabelecimentos: CacheLru<Estabelecimento>,
+    tabelas_auxiliares: RwLock<Option<Arc<TabelasAuxiliares>>>,
+    metadados: MetadadosRecentes,
+    versao: VersaoDoCache,
+}
+
+impl CacheDeConsultas {
+    // Sem capacidade (ou com capacidade 0) o cache fica desabilitado
+    pub fn new(capacidade: Option<usize>, ttl: Option<u64>) -> CacheDeConsultas {
+        let capacidade = capacidade.unwrap_or(0);
+        let ttl = Duration::from_secs(ttl.unwrap_or(TTL_PADRAO));
+
+        CacheDeConsultas {
+            empresas: CacheLru::new("empresas", capacidade, ttl),
+            estabelecimentos: CacheLru::new("estabelecimentos", capacidade, ttl),
+            tabelas_auxiliares: RwLock::new(None),
+            metadados: MetadadosRecentes::default(),
+            versao: VersaoDoCache::default(),
+        }
+    }
+
+    pub fn habilitado(&self) -> bool {
+        self.empresas.habilitado()
+    }
+
+    // Tabelas auxiliares em memória (somente quando o cache está habilitado)
+    pub fn tabelas_auxiliares(&self) -> Option<Arc<TabelasAuxiliares>> {
+        self.tabelas_auxiliares.read().unwrap().clone()
+    }
+
+    // Verifica (periodicamente) se houve uma nova importação, caso em que todo o cache é invalidado e
+    // as tabelas auxiliares são carregadas novamente
+    pub fn atualizar(&self, connection: &MysqlConnection) {
+        if !self.habilitado() || self.metadados.obter().is_some() {
+            return;
+        }
+
+        let metadados = match metadados_das_tabelas(connection) {
+            Ok(metadados) => metadados,
+            Err(error) => {
+                tracing::error!(erro = ?error, "Erro ao ler os metadados das tabelas");
+                return;
+            }
+        };
+        self.metadados.atualizar(metadados.clone());
+
+        if !self.versao.mudou(&metadados) {
+            return;
+        }
+
+        self.empresas.limpar();
+        self.estabelecimentos.limpar();
+
+        match TabelasAuxiliares::carregar(connection) {
+            Ok(tabelas) => *self.tabelas_auxiliares.write().unwrap() = Some(Arc::new(tabelas)),
+            Err(error) => {
+                tracing::error!(erro = ?error, "Erro ao carregar as tabelas auxiliares");
+                *self.tabelas_auxiliares.write().unwrap() = None;
+            }
+        }
+    }
+
+    pub fn estatisticas(&self) -> Vec<EstatisticasDoCache> {
+        vec![self.empresas.estatisticas(), self.estabelecimentos.estatisticas()]
+    }
+}
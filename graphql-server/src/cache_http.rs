@@ -0,0 +1,181 @@
+// Cache HTTP das consultas GraphQL feitas por GET (as requisições POST não são armazenadas em cache
+// pelos clientes e CDNs). As respostas recebem os cabeçalhos ETag e Last-Modified, derivados da versão
+// dos dados importados (vide data_models::versao), e Cache-Control, e as requisições condicionais cuja
+// representação não mudou são respondidas com 304 (Not Modified).
+//
+// A validação é feita antes da rota, para que as consultas não modificadas não sejam executadas. Como o
+// Rocket não permite que um fairing responda à requisição, elas são desviadas para uma rota interna que
+// responde 304.
+//
+// A resposta depende do perfil de exposição dos dados pessoais (vide lgpd), então o nome do perfil faz parte
+// do ETag. Com a chave de API, o perfil é o da chave e as respostas não podem ser compartilhadas pelos caches
+// intermediários (Cache-Control: private); sem ela, todos recebem o perfil padrão.
+
+use data_models::acesso::RecusaDeAcesso;
+use data_models::models::{ChaveDeApi, MetadadosDasTabelas};
+use data_models::versao::{metadados_das_tabelas, MetadadosRecentes, VersaoDosDados};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::{Header, Method, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Data, Request, Response, Route};
+
+use crate::graphql_schema::EstadoDoServidor;
+
+// tempo (em segundos) que as respostas podem ser mantidas em cache pelos clientes e CDNs, quando não
+// informado na variável de ambiente CACHE_MAX_AGE
+const MAX_AGE_PADRAO: u64 = 3600;
+
+// rota interna para a qual as requisições não modificadas são desviadas
+const ROTA_DO_NAO_MODIFICADO: &str = "/nao_modificado";
+
+// Validadores da resposta solicitada, guardados no cache local da requisição
+struct Validadores {
+    etag: String,
+    last_modified: String,
+    // a requisição é feita com a chave de API
+    com_chave: bool,
+    nao_modificado: bool,
+}
+
+pub struct CacheHttp {
+    max_age: u64,
+    metadados: MetadadosRecentes,
+}
+
+impl CacheHttp {
+    pub fn new(max_age: Option<u64>) -> CacheHttp {
+        CacheHttp {
+            max_age: max_age.unwrap_or(MAX_AGE_PADRAO),
+            metadados: MetadadosRecentes::default(),
+        }
+    }
+
+    async fn metadados(&self, request: &Request<'_>) -> Option<Vec<MetadadosDasTabelas>> {
+        if let Some(metadados) = self.metadados.obter() {
+            return Some(metadados);
+        }
+
+        let pool = request.rocket().state::<EstadoDoServidor>()?.pool.clone();
+        let resultado = rocket::tokio::task::spawn_blocking(move || {
+            let connection = pool.get().map_err(|error| format!("{:?}", error))?;
+            metadados_das_tabelas(&connection).map_err(|error| format!("{:?}", error))
+        })
+        .await;
+
+        match resultado {
+            Ok(Ok(metadados)) => {
+                self.metadados.atualizar(metadados.clone());
+                Some(metadados)
+            }
+            Ok(Err(error)) => {
+                tracing::error!(erro = %error, "Erro ao ler os metadados das tabelas");
+                None
+            }
+            Err(error) => {
+                tracing::error!(erro = ?error, "Erro ao ler os metadados das tabelas");
+                None
+            }
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for CacheHttp {
+    fn info(&self) -> Info {
+        Info {
+            name: "Cache HTTP (ETag/Last-Modified)",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    // Calcula os validadores da resposta e desvia as requisições não modificadas (o fairing deve ser anexado
+    // após o do controle de acesso, que identifica a chave de API)
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        if !matches!(request.method(), Method::Get | Method::Head)
+            || request.uri().path() != "/graphql"
+            || request.local_cache(|| None::<RecusaDeAcesso>).is_some()
+        {
+            return;
+        }
+
+        // uma consulta GraphQL pode envolver qualquer tabela, então a versão considera todas elas
+        let versao = match self.metadados(request).await.and_then(|m| VersaoDosDados::de(&m, &[])) {
+            Some(versao) => versao,
+            None => return,
+        };
+
+        // a chave é guardada no cache local da requisição pelo controle de acesso (vide acesso)
+        let chave = request.local_cache(|| None::<ChaveDeApi>);
+        let perfil = match request.rocket().state::<EstadoDoServidor>() {
+            Some(estado) => estado.perfis.perfil(chave.as_ref().map(|chave| chave.perfil.as_str())).nome,
+            None => return,
+        };
+
+        let etag = versao.etag(&format!("{} {}", request.uri(), perfil));
+
+        let nao_modificado = versao.nao_modificado(
+            &etag,
+            request.headers().get_one("If-None-Match"),
+            request.headers().get_one("If-Modified-Since"),
+        );
+
+        let com_chave = chave.is_some();
+        request.local_cache(|| {
+            Some(Validadores {
+                etag,
+                last_modified: versao.last_modified(),
+                com_chave,
+                nao_modificado,
+            })
+        });
+        if nao_modificado {
+            request.set_uri(Origin::parse(ROTA_DO_NAO_MODIFICADO).unwrap());
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let validadores = match request.local_cache(|| None::<Validadores>) {
+            Some(validadores) => validadores,
+            None => return,
+        };
+
+        let status = if validadores.nao_modificado { Status::NotModified } else { Status::Ok };
+        if response.status() != status {
+            return;
+        }
+
+        response.set_header(Header::new("ETag", validadores.etag.clone()));
+        response.set_header(Header::new("Last-Modified", validadores.last_modified.clone()));
+        if validadores.com_chave {
+            response.set_header(Header::new("Cache-Control", format!("private, max-age={}", self.max_age)));
+            response.set_header(Header::new("Vary", "Authorization, X-API-Key"));
+        } else {
+            response.set_header(Header::new("Cache-Control", format!("public, max-age={}", self.max_age)));
+        }
+    }
+}
+
+// Guarda de requisição que só permite a rota interna às requisições desviadas pelo fairing
+struct NaoModificado;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for NaoModificado {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        match request.local_cache(|| None::<Validadores>) {
+            Some(validadores) if validadores.nao_modificado => Outcome::Success(NaoModificado),
+            _ => Outcome::Forward(()),
+        }
+    }
+}
+
+#[rocket::get("/nao_modificado")]
+fn get_nao_modificado(_nao_modificado: NaoModificado) -> (Status, ()) {
+    (Status::NotModified, ())
+}
+
+pub fn rotas() -> Vec<Route> {
+    rocket::routes![get_nao_modificado]
+}
@@ -1,5 +1,8 @@
 extern crate dotenv;
 
+use std::ops::Deref;
+use std::sync::Arc;
+
 use chrono::{DateTime, FixedOffset, Local, NaiveDate};
 use diesel::mysql::MysqlConnection;
 use diesel::prelude::*;
@@ -8,9 +11,30 @@ use juniper::{EmptyMutation, EmptySubscription, FieldError, RootNode};
 
 use bigdecimal::{BigDecimal, ToPrimitive};
 
+use data_models::cache::TabelasAuxiliares;
+use data_models::lgpd::{Mascaramento, PerfisDeExposicao};
+use data_models::monitoramento::{UsoDaConexao, METRICAS};
+use data_models::documentos::somente_digitos;
+use data_models::grupo_economico::{self, Aresta, No, PROFUNDIDADE_MAXIMA};
+use data_models::socios::{buscar_socios, BuscaDeSocios};
+use data_models::paginacao::deslocamento_da_pagina;
+use data_models::lote::{condicao_dos_cnpjs, normalizar_lote, por_cnpj, StatusDoItemDoLote, LIMITE_DO_LOTE, TAMANHO_DO_BLOCO};
+use data_models::busca::{buscar_empresas, codigo_ibge_valido, expressao_de_busca, FiltroDaBusca, ResultadoDaBusca, LIMITE_MAXIMO_DA_BUSCA, LIMITE_PADRAO_DA_BUSCA};
+use data_models::contatos::{Email, Telefone};
+use data_models::enderecos::{cep_valido, EnderecoNormalizado};
+use data_models::dominios::{FaixaEtaria, IdentificadorDeSocio, MatrizFilial, OpcaoSimples, Porte, SituacaoCadastral};
+
+use crate::cache_de_consultas::CacheDeConsultas;
+
+// quantidade máxima de sócios retornados em uma única consulta
+const LIMITE_PADRAO_DE_SOCIOS: i64 = 100;
+const LIMITE_MAXIMO_DE_SOCIOS: i64 = 1000;
+// profundidade padrão na navegação do grafo de participações societárias
+const PROFUNDIDADE_PADRAO_DO_GRAFO: i32 = 3;
+
 pub fn data_hora_de_atualizacao(context: &Context, table_name: &str) -> Result<String, FieldError> {
     use data_models::schema::metadados_das_tabelas;
-    let connection = context.pool.clone().get()?;
+    let connection = context.conexao()?;
 
     let metadados_das_tabelas = metadados_das_tabelas::table
         .filter(metadados_das_tabelas::tabela.eq(&table_name))
@@ -58,10 +82,13 @@ impl NaturezaJuridica {
 struct Municipio {
     id: u16,
     nome: String,
+    codigo_ibge: Option<u32>,
+    uf: Option<String>,
 }
 
 #[graphql_object(description = "Município")]
 impl Municipio {
+    #[graphql(description = "Código do município na Receita Federal (TOM/SIAFI)")]
     pub fn id(&self) -> i32 {
         self.id.into()
     }
@@ -69,6 +96,26 @@ impl Municipio {
     pub fn nome(&self) -> &String {
         &self.nome
     }
+
+    #[graphql(description = "Código de 7 dígitos do município no IBGE")]
+    pub fn codigo_ibge(&self) -> Option<i32> {
+        self.codigo_ibge.map(|codigo| codigo as i32)
+    }
+
+    pub fn uf(&self) -> &Option<String> {
+        &self.uf
+    }
+}
+
+impl From<&data_models::models::Municipio> for Municipio {
+    fn from(registro: &data_models::models::Municipio) -> Municipio {
+        Municipio {
+            id: registro.id,
+            nome: registro.nome.clone(),
+            codigo_ibge: registro.codigo_ibge,
+            uf: registro.uf.clone(),
+        }
+    }
 }
 
 #[derive(Queryable)]
@@ -122,20 +169,30 @@ impl MotivoDeSituacaoCadastral {
     }
 }
 
-#[derive(Queryable)]
-struct FaixaEtaria {
-    id: u8,
-    nome: Option<String>,
+// Conversão dos registros das tabelas auxiliares carregadas em memória (vide cache_de_consultas)
+macro_rules! de_tabela_auxiliar {
+    ($($modelo:ident),*) => {
+        $(
+            impl From<&data_models::models::$modelo> for $modelo {
+                fn from(registro: &data_models::models::$modelo) -> $modelo {
+                    $modelo { id: registro.id, nome: registro.nome.clone() }
+                }
+            }
+        )*
+    };
 }
 
-#[graphql_object(description = "Faixa Etária")]
-impl FaixaEtaria {
-    pub fn id(&self) -> i32 {
-        self.id.into()
-    }
+de_tabela_auxiliar!(CNAE, NaturezaJuridica, Pais, QualificacaoDeSocio, MotivoDeSituacaoCadastral);
 
-    pub fn nome(&self) -> &Option<String> {
-        &self.nome
+// Registro de uma tabela auxiliar em memória pelo id (as tabelas estão ordenadas pelo id)
+fn registro_da_tabela<M, T, K>(registros: &[M], id: K, chave: impl Fn(&M) -> K) -> Result<T, FieldError>
+where
+    T: for<'a> From<&'a M>,
+    K: Ord,
+{
+    match registros.binary_search_by_key(&id, chave) {
+        Ok(posicao) => Ok(T::from(&registros[posicao])),
+        Err(_) => Err(FieldError::from(diesel::result::Error::NotFound)),
     }
 }
 
@@ -143,7 +200,7 @@ impl FaixaEtaria {
 struct Socio {
     id: u32,
     cnpj_basico: String,
-    identificador_de_socio: u8,
+    identificador_de_socio: IdentificadorDeSocio,
     nome_ou_razao_social_do_socio: String,
     cnpj_ou_cpf_do_socio: Option<String>,
     qualificacao_do_socio: u8,
@@ -152,7 +209,17 @@ struct Socio {
     cpf_do_representante_legal: String,
     nome_do_representante_legal: String,
     qualificacao_do_representante_legal: u8,
-    faixa_etaria_do_socio: u8
+    faixa_etaria_do_socio: FaixaEtaria
+}
+
+impl Socio {
+    // CPF e nome do representante legal, ocultados conforme o perfil de exposição
+    fn representante_legal(&self, mascaramento: &Mascaramento) -> (String, String) {
+        let mut cpf = self.cpf_do_representante_legal.clone();
+        let mut nome = self.nome_do_representante_legal.clone();
+        mascaramento.representante_legal(&mut cpf, &mut nome);
+        (cpf, nome)
+    }
 }
 
 #[juniper::graphql_object(context = Context, description="Sócio de uma empresa do CNPJ")]
@@ -165,26 +232,25 @@ impl Socio {
         &self.cnpj_basico
     }
 
-    pub fn identificador_de_socio(&self) -> &str {
-        match self.identificador_de_socio {
-            1 => "Pessoa Jurídica",
-            2 => "Pessoa Física",
-            3 => "Estrangeiro",
-            _ => "",
-        }        
+    pub fn identificador_de_socio(&self) -> IdentificadorDeSocio {
+        self.identificador_de_socio
     }
 
-    pub fn nome_ou_razao_social_do_socio(&self) -> &String {
-        &self.nome_ou_razao_social_do_socio
+    pub fn nome_ou_razao_social_do_socio(&self, context: &Context) -> String {
+        let mut nome = self.nome_ou_razao_social_do_socio.clone();
+        context.mascaramento.nome_do_socio(self.identificador_de_socio, &mut nome);
+        nome
     }
 
-    pub fn cnpj_ou_cpf_do_socio(&self) -> &Option<String> {
-        &self.cnpj_ou_cpf_do_socio
+    pub fn cnpj_ou_cpf_do_socio(&self, context: &Context) -> Option<String> {
+        let mut documento = self.cnpj_ou_cpf_do_socio.clone();
+        context.mascaramento.documento_do_socio(self.identificador_de_socio, &mut documento);
+        documento
     }
 
     pub fn qualificacao_de_socio(&self, context: &Context) -> Result<Option<QualificacaoDeSocio>, FieldError> {
         use data_models::schema::qualificacoes_de_socios;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
 
         Ok(Some(
             qualificacoes_de_socios::table
@@ -201,7 +267,7 @@ impl Socio {
         match self.pais_do_socio {
             Some(v) => {
                 use data_models::schema::paises;
-                let connection = context.pool.clone().get()?;
+                let connection = context.conexao()?;
 
                 Ok(Some(
                     paises::table
@@ -213,17 +279,17 @@ impl Socio {
         }
     }
 
-    pub fn cpf_do_representante_legal(&self) -> &String {
-        &self.cpf_do_representante_legal
+    pub fn cpf_do_representante_legal(&self, context: &Context) -> String {
+        self.representante_legal(&context.mascaramento).0
     }
 
-    pub fn nome_do_representante_legal(&self) -> &String {
-        &self.nome_do_representante_legal
+    pub fn nome_do_representante_legal(&self, context: &Context) -> String {
+        self.representante_legal(&context.mascaramento).1
     }
 
     pub fn qualificacao_do_representante_legal(&self, context: &Context) -> Result<Option<QualificacaoDeSocio>, FieldError> {
         use data_models::schema::qualificacoes_de_socios;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
 
         Ok(Some(
             qualificacoes_de_socios::table
@@ -233,15 +299,17 @@ impl Socio {
     }
     
 
-    pub fn faixa_etaria_do_socio(&self, context: &Context) -> Result<Option<FaixaEtaria>, FieldError> {
-        use data_models::schema::faixas_etarias;
-        let connection = context.pool.clone().get()?;
+    pub fn faixa_etaria_do_socio(&self) -> FaixaEtaria {
+        self.faixa_etaria_do_socio
+    }
 
-        Ok(Some(
-            faixas_etarias::table
-                .filter(faixas_etarias::id.eq(&self.faixa_etaria_do_socio))
-                .first::<FaixaEtaria>(&*connection)?,
-        ))
+    pub fn empresa(&self, context: &Context) -> Result<Empresa, FieldError> {
+        use data_models::schema::empresas;
+        let connection = context.conexao()?;
+
+        Ok(empresas::table
+            .filter(empresas::cnpj_basico.eq(&self.cnpj_basico))
+            .first::<Empresa>(&*connection)?)
     }
 
     pub fn data_hora_de_atualizacao(&self, context: &Context) -> Result<String, FieldError> {
@@ -249,15 +317,23 @@ impl Socio {
     }
 }
 
+#[derive(GraphQLInputObject)]
+#[graphql(description = "Filtro para a busca de sócios. O CPF pode ser informado completo ou mascarado como nos arquivos da RF (ex: ***123456**) e o nome é buscado pelo seu início.")]
+struct FiltroDeSocios {
+    cpf: Option<String>,
+    cnpj: Option<String>,
+    nome: Option<String>,
+}
+
 
 
 #[derive(Queryable)]
 struct Simples {
     cnpj_basico: String,
-    opcao_pelo_simples: String,
+    opcao_pelo_simples: OpcaoSimples,
     data_de_opcao_pelo_simples: Option<NaiveDate>,
     data_de_exclusao_do_simples: Option<NaiveDate>,
-    opcao_pelo_mei: String,
+    opcao_pelo_mei: OpcaoSimples,
     data_de_opcao_pelo_mei: Option<NaiveDate>,
     data_de_exclusao_do_mei: Option<NaiveDate>,    
 }
@@ -268,12 +344,8 @@ impl Simples {
         &self.cnpj_basico
     }
 
-    pub fn opcao_pelo_simples(&self) -> &str {
-        match &self.opcao_pelo_simples[..] {
-            "S" => "Sim",
-            "N" => "Não",
-            _ => "Outros"
-        }
+    pub fn opcao_pelo_simples(&self) -> OpcaoSimples {
+        self.opcao_pelo_simples
     }
 
     pub fn data_de_opcao_pelo_simples(&self) -> &Option<NaiveDate> {
@@ -284,12 +356,8 @@ impl Simples {
         &self.data_de_exclusao_do_simples
     }
 
-    pub fn opcao_pelo_mei(&self) -> &str {
-        match &self.opcao_pelo_mei[..] {
-            "S" => "Sim",
-            "N" => "Não",
-            _ => "Outros"
-        }
+    pub fn opcao_pelo_mei(&self) -> OpcaoSimples {
+        self.opcao_pelo_mei
     }
 
     pub fn data_de_opcao_pelo_mei(&self) -> &Option<NaiveDate> {
@@ -305,15 +373,15 @@ impl Simples {
     }
 
 }
-#[derive(Queryable)]
+#[derive(Clone, Queryable)]
 // #[primary_key(cnpj_basico)]
-struct Empresa {
+pub(crate) struct Empresa {
     cnpj_basico: String,
     razao_social: String,
     natureza_juridica: Option<u16>,
     qualificacao_do_responsavel: Option<u8>,
     capital_social: Option<BigDecimal>,
-    porte: Option<String>,
+    porte: Option<Porte>,
     ente_federativo_responsavel: Option<String>,
 }
 
@@ -323,8 +391,10 @@ impl Empresa {
         &self.cnpj_basico
     }
 
-    pub fn razao_social(&self) -> &String {
-        &self.razao_social
+    pub fn razao_social(&self, context: &Context) -> String {
+        let mut razao_social = self.razao_social.clone();
+        context.mascaramento.razao_social(&mut razao_social);
+        razao_social
     }
 
     pub fn natureza_juridica(
@@ -334,7 +404,7 @@ impl Empresa {
         match self.natureza_juridica {
             Some(v) => {
                 use data_models::schema::naturezas_juridicas;
-                let connection = context.pool.clone().get()?;
+                let connection = context.conexao()?;
 
                 Ok(Some(
                     naturezas_juridicas::table
@@ -352,7 +422,7 @@ impl Empresa {
     ) -> Result<Option<QualificacaoDeSocio>, FieldError> {
         if let Some(v) = self.qualificacao_do_responsavel {
             use data_models::schema::qualificacoes_de_socios;
-            let connection = context.pool.clone().get()?;
+            let connection = context.conexao()?;
             Ok(Some(
                 qualificacoes_de_socios::table
                     .filter(qualificacoes_de_socios::id.eq(v))
@@ -367,8 +437,8 @@ impl Empresa {
         self.capital_social.as_ref()?.to_f64()
     }
 
-    pub fn porte(&self) -> &Option<String> {
-        &self.porte
+    pub fn porte(&self) -> Option<Porte> {
+        self.porte
     }
 
     pub fn ente_federativo_responsavel(&self) -> &Option<String> {
@@ -377,7 +447,7 @@ impl Empresa {
 
     pub fn socios(&self, context: &Context) -> Result<Vec<Socio>, FieldError> {
         use data_models::schema::socios;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
 
         Ok(socios::table
             .filter(socios::cnpj_basico.eq(&self.cnpj_basico))
@@ -386,7 +456,7 @@ impl Empresa {
 
     pub fn estabelecimentos(&self, context: &Context) -> Result<Vec<Estabelecimento>, FieldError> {
         use data_models::schema::estabelecimentos;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
 
         Ok(estabelecimentos::table
             .filter(estabelecimentos::cnpj_basico.eq(&self.cnpj_basico))
@@ -395,7 +465,7 @@ impl Empresa {
     
     pub fn simples(&self, context: &Context) -> Result<Simples, FieldError> {
         use data_models::schema::simples;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
 
         Ok(simples::table
             .filter(simples::cnpj_basico.eq(&self.cnpj_basico))
@@ -407,14 +477,14 @@ impl Empresa {
     }    
 }
 
-#[derive(Queryable)]
-struct Estabelecimento {
+#[derive(Clone, Queryable)]
+pub(crate) struct Estabelecimento {
     cnpj_basico: String,
     cnpj_ordem: String,
     cnpj_dv: String,
-    identificador_matriz_filial: String,
+    identificador_matriz_filial: MatrizFilial,
     nome_fantasia: Option<String>,
-    situacao_cadastral: u8,
+    situacao_cadastral: SituacaoCadastral,
     data_situacao_cadastral: Option<NaiveDate>,
     motivo_situacao_cadastral: Option<u8>,
     nome_da_cidade_no_exterior: Option<String>,
@@ -439,6 +509,45 @@ struct Estabelecimento {
     correio_eletronico: Option<String>,
     situacao_especial: Option<String>,
     data_situacao_especial: Option<NaiveDate>,
+    endereco_normalizado: Option<String>,
+    cep_valido: Option<bool>,
+    telefone1_e164: Option<String>,
+    telefone2_e164: Option<String>,
+    telefone_fax_e164: Option<String>,
+    email_normalizado: Option<String>,
+    email_valido: Option<bool>,
+}
+
+impl Estabelecimento {
+    // DDD ou número de telefone, ocultado conforme o perfil de exposição
+    fn telefone(&self, mascaramento: &Mascaramento, campo: &Option<String>) -> Option<String> {
+        let mut campo = campo.clone();
+        mascaramento.telefone(&mut campo);
+        campo
+    }
+
+    // Campos do endereço na forma original ou, com o argumento normalizado, na forma normalizada (vide
+    // data_models::enderecos)
+    fn endereco(&self, normalizado: Option<bool>) -> EnderecoNormalizado {
+        if normalizado.unwrap_or(false) {
+            return EnderecoNormalizado::new(
+                self.tipo_logradouro.as_deref(),
+                self.logradouro.as_deref(),
+                self.numero.as_deref(),
+                self.complemento.as_deref(),
+                self.bairro.as_deref(),
+                self.cep.as_deref(),
+            );
+        }
+        EnderecoNormalizado {
+            tipo_logradouro: self.tipo_logradouro.clone(),
+            logradouro: self.logradouro.clone(),
+            numero: self.numero.clone(),
+            complemento: self.complemento.clone(),
+            bairro: self.bairro.clone(),
+            cep: self.cep.clone(),
+        }
+    }
 }
 
 #[graphql_object(context = Context, description = "Um Estabelecimento pertencente a uma Empresa")]
@@ -455,12 +564,8 @@ impl Estabelecimento {
         &self.cnpj_dv
     }
 
-    pub fn identificador_matriz_filial(&self) -> &str {
-        match self.identificador_matriz_filial.as_str() {
-            "1" => "Matriz",
-            "2" => "Filial",
-            _ => "",
-        }
+    pub fn identificador_matriz_filial(&self) -> MatrizFilial {
+        self.identificador_matriz_filial
     }
 
     pub fn nome_fantasia(&self) -> &Option<String> {
@@ -468,14 +573,7 @@ impl Estabelecimento {
     }
 
     pub fn situacao_cadastral(&self) -> SituacaoCadastral {
-        match self.situacao_cadastral {
-            1 => SituacaoCadastral::NULA,
-            2 => SituacaoCadastral::ATIVA,
-            3 => SituacaoCadastral::SUSPENSA,
-            4 => SituacaoCadastral::INAPTA,
-            8 => SituacaoCadastral::BAIXADA,
-            _ => SituacaoCadastral::DESCONHECIDA,
-        }
+        self.situacao_cadastral
     }
 
     pub fn data_situacao_cadastral(&self) -> &Option<NaiveDate> {
@@ -489,7 +587,7 @@ impl Estabelecimento {
         match self.motivo_situacao_cadastral {
             Some(v) => {
                 use data_models::schema::motivos_de_situacoes_cadastrais;
-                let connection = context.pool.clone().get()?;
+                let connection = context.conexao()?;
 
                 Ok(Some(
                     motivos_de_situacoes_cadastrais::table
@@ -509,7 +607,7 @@ impl Estabelecimento {
         match self.pais {
             Some(v) => {
                 use data_models::schema::paises;
-                let connection = context.pool.clone().get()?;
+                let connection = context.conexao()?;
 
                 Ok(Some(
                     paises::table
@@ -529,7 +627,7 @@ impl Estabelecimento {
         match self.cnae_fiscal_principal {
             Some(v) => {
                 use data_models::schema::cnaes;
-                let connection = context.pool.clone().get()?;
+                let connection = context.conexao()?;
 
                 Ok(Some(
                     cnaes::table
@@ -543,12 +641,11 @@ impl Estabelecimento {
 
     pub fn cnaes_fiscais_secundarias(&self, context: &Context) -> Result<Vec<CNAE>, FieldError> {
         use data_models::schema::cnaes;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
 
         let cnaes_fiscais_secundarias: Vec<u32> = match &self.cnae_fiscal_secundaria {
             Some(v) => v
-                .to_string()
-                .split(",")
+                .split(',')
                 .map(|s| s.parse().unwrap())
                 .collect(),
             _ => vec![],
@@ -561,28 +658,47 @@ impl Estabelecimento {
         Ok(cnaes_fiscais_secundarias)
     }
 
-    pub fn tipo_logradouro(&self) -> &Option<String> {
-        &self.tipo_logradouro
+    pub fn tipo_logradouro(&self, normalizado: Option<bool>) -> Option<String> {
+        self.endereco(normalizado).tipo_logradouro
+    }
+
+    pub fn logradouro(&self, normalizado: Option<bool>) -> Option<String> {
+        self.endereco(normalizado).logradouro
+    }
+
+    #[graphql(description = "Número do endereço (com normalizado: true, as variações de \"sem número\" são retornadas como S/N)")]
+    pub fn numero(&self, normalizado: Option<bool>) -> Option<String> {
+        self.endereco(normalizado).numero
     }
 
-    pub fn logradouro(&self) -> &Option<String> {
-        &self.logradouro
+    pub fn complemento(&self, normalizado: Option<bool>) -> Option<String> {
+        self.endereco(normalizado).complemento
     }
 
-    pub fn numero(&self) -> &Option<String> {
-        &self.numero
+    pub fn bairro(&self, normalizado: Option<bool>) -> Option<String> {
+        self.endereco(normalizado).bairro
     }
 
-    pub fn complemento(&self) -> &Option<String> {
-        &self.complemento
+    #[graphql(description = "CEP (com normalizado: true, formatado como 99999-999)")]
+    pub fn cep(&self, normalizado: Option<bool>) -> Option<String> {
+        self.endereco(normalizado).cep
     }
 
-    pub fn bairro(&self) -> &Option<String> {
-        &self.bairro
+    #[graphql(description = "Endereço normalizado em uma única linha (ex: RUA DAS FLORES, 123, SALA 2 - CENTRO - CEP 01234-567 - SP)")]
+    pub fn endereco_normalizado(&self) -> Option<String> {
+        match &self.endereco_normalizado {
+            Some(endereco) => Some(endereco.clone()),
+            None => self.endereco(Some(true)).linha(self.uf.as_deref()),
+        }
     }
 
-    pub fn cep(&self) -> &Option<String> {
-        &self.cep
+    #[graphql(description = "Indica se o CEP pertence às faixas de CEP da UF")]
+    pub fn cep_valido(&self) -> Option<bool> {
+        match (self.cep_valido, &self.cep) {
+            (Some(cep_valido), _) => Some(cep_valido),
+            (None, Some(cep)) => Some(cep_valido(cep, self.uf.as_deref().unwrap_or_default())),
+            (None, None) => None,
+        }
     }
 
     pub fn uf(&self) -> &Option<String> {
@@ -593,7 +709,7 @@ impl Estabelecimento {
         match self.municipio {
             Some(v) => {
                 use data_models::schema::municipios;
-                let connection = context.pool.clone().get()?;
+                let connection = context.conexao()?;
 
                 Ok(Some(
                     municipios::table
@@ -605,32 +721,55 @@ impl Estabelecimento {
         }
     }
 
-    pub fn ddd1(&self) -> &Option<String> {
-        &self.ddd1
+    pub fn ddd1(&self, context: &Context) -> Option<String> {
+        self.telefone(&context.mascaramento, &self.ddd1)
+    }
+
+    pub fn telefone1(&self, context: &Context) -> Option<String> {
+        self.telefone(&context.mascaramento, &self.telefone1)
+    }
+
+    pub fn ddd2(&self, context: &Context) -> Option<String> {
+        self.telefone(&context.mascaramento, &self.ddd2)
     }
 
-    pub fn telefone1(&self) -> &Option<String> {
-        &self.telefone1
+    pub fn telefone2(&self, context: &Context) -> Option<String> {
+        self.telefone(&context.mascaramento, &self.telefone2)
     }
 
-    pub fn ddd2(&self) -> &Option<String> {
-        &self.ddd2
+    pub fn ddd_fax(&self, context: &Context) -> Option<String> {
+        self.telefone(&context.mascaramento, &self.ddd_fax)
     }
 
-    pub fn telefone2(&self) -> &Option<String> {
-        &self.telefone2
+    pub fn telefone_fax(&self, context: &Context) -> Option<String> {
+        self.telefone(&context.mascaramento, &self.telefone_fax)
     }
 
-    pub fn ddd_fax(&self) -> &Option<String> {
-        &self.ddd_fax
+    pub fn correio_eletronico(&self, context: &Context) -> Option<String> {
+        let mut correio_eletronico = self.correio_eletronico.clone();
+        context.mascaramento.correio_eletronico(&mut correio_eletronico);
+        correio_eletronico
     }
 
-    pub fn telefone_fax(&self) -> &Option<String> {
-        &self.telefone_fax
+    #[graphql(description = "Telefones e fax no formato E.164 (+55DDXXXXXXXX), com o tipo (fixo ou celular) e a indicação de validade")]
+    pub fn telefones(&self, context: &Context) -> Vec<Telefone> {
+        let mut telefones = [
+            ("telefone1", &self.ddd1, &self.telefone1, &self.telefone1_e164),
+            ("telefone2", &self.ddd2, &self.telefone2, &self.telefone2_e164),
+            ("fax", &self.ddd_fax, &self.telefone_fax, &self.telefone_fax_e164),
+        ]
+        .iter()
+        .filter_map(|(origem, ddd, numero, e164)| Telefone::new(origem, ddd.as_deref(), numero.as_deref(), e164.as_deref()))
+        .collect();
+        context.mascaramento.telefones(&mut telefones);
+        telefones
     }
 
-    pub fn correio_eletronico(&self) -> &Option<String> {
-        &self.correio_eletronico
+    #[graphql(description = "Correio eletrônico sem espaços e em minúsculas, com a indicação de validade")]
+    pub fn email(&self, context: &Context) -> Option<Email> {
+        let mut email = Email::new(self.correio_eletronico.as_deref(), self.email_normalizado.as_deref(), self.email_valido);
+        context.mascaramento.email(&mut email);
+        email
     }
 
     pub fn situacao_especial(&self) -> &Option<String> {
@@ -643,7 +782,7 @@ impl Estabelecimento {
 
     pub fn empresa(&self, context: &Context) -> Result<Empresa, FieldError> {
         use data_models::schema::empresas;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
 
         Ok(empresas::table
             .filter(empresas::cnpj_basico.eq(&self.cnpj_basico))
@@ -655,15 +794,87 @@ impl Estabelecimento {
     }
 }
 
-#[derive(GraphQLEnum)]
-#[graphql(description = "Situação Cadastral do Estabelecimento")]
-enum SituacaoCadastral {
-    NULA,
-    ATIVA,
-    SUSPENSA,
-    INAPTA,
-    BAIXADA,
-    DESCONHECIDA,
+struct ItemDoLoteDeEstabelecimentos {
+    cnpj: String,
+    status: StatusDoItemDoLote,
+    estabelecimento: Option<Estabelecimento>,
+}
+
+#[graphql_object(context = Context, description = "Resultado da consulta de um CNPJ na consulta de estabelecimentos em lote")]
+impl ItemDoLoteDeEstabelecimentos {
+    #[graphql(description = "CNPJ conforme informado na consulta")]
+    pub fn cnpj(&self) -> &str {
+        &self.cnpj
+    }
+
+    pub fn status(&self) -> StatusDoItemDoLote {
+        self.status
+    }
+
+    pub fn estabelecimento(&self) -> &Option<Estabelecimento> {
+        &self.estabelecimento
+    }
+}
+
+struct GrupoEconomico(grupo_economico::GrupoEconomico);
+
+#[graphql_object(description = "Grafo de participações societárias entre empresas (grupo econômico)")]
+impl GrupoEconomico {
+    pub fn cnpj_basico(&self) -> &str {
+        &self.0.cnpj_basico
+    }
+
+    pub fn profundidade(&self) -> i32 {
+        i32::from(self.0.profundidade)
+    }
+
+    pub fn nos(&self) -> &Vec<No> {
+        &self.0.nos
+    }
+
+    pub fn arestas(&self) -> &Vec<Aresta> {
+        &self.0.arestas
+    }
+
+    #[graphql(description = "Indica que o grafo foi truncado por ter atingido a quantidade máxima de empresas")]
+    pub fn truncado(&self) -> bool {
+        self.0.truncado
+    }
+
+    #[graphql(description = "Grafo exportado no formato GraphML")]
+    pub fn graphml(&self) -> String {
+        self.0.to_graphml()
+    }
+
+    #[graphql(description = "Grafo exportado no formato DOT (Graphviz)")]
+    pub fn dot(&self) -> String {
+        self.0.to_dot()
+    }
+}
+
+struct EstatisticasDoCache(data_models::cache::EstatisticasDoCache);
+
+#[graphql_object(description = "Quantidade de registros, acertos (hits) e falhas (misses) de um cache de consultas")]
+impl EstatisticasDoCache {
+    pub fn nome(&self) -> &str {
+        self.0.nome
+    }
+
+    pub fn capacidade(&self) -> f64 {
+        self.0.capacidade as f64
+    }
+
+    pub fn entradas(&self) -> f64 {
+        self.0.entradas as f64
+    }
+
+    pub fn acertos(&self) -> f64 {
+        self.0.acertos as f64
+    }
+
+    pub fn falhas(&self) -> f64 {
+        self.0.falhas as f64
+    }
 }
 
 pub struct QueryRoot;
@@ -676,119 +887,169 @@ impl QueryRoot {
 
     fn cnae(context: &Context, id: String) -> Result<CNAE, FieldError> {
         use data_models::schema::cnaes;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
+        let id = id.parse::<u32>()?;
+
+        if let Some(tabelas) = context.tabelas_auxiliares(&connection) {
+            return registro_da_tabela(&tabelas.cnaes, id, |registro| registro.id);
+        }
 
         Ok(cnaes::table
-            .filter(cnaes::id.eq(id.parse::<u32>()?))
+            .filter(cnaes::id.eq(id))
             .first::<CNAE>(&*connection)?)
     }
 
     fn cnaes(context: &Context) -> Result<Vec<CNAE>, FieldError> {
-        use data_models::schema::cnaes::dsl::*;
+        use data_models::schema::cnaes;
+        let connection = context.conexao()?;
 
-        let connection = context.pool.clone().get()?;
+        if let Some(tabelas) = context.tabelas_auxiliares(&connection) {
+            return Ok(tabelas.cnaes.iter().map(CNAE::from).collect());
+        }
 
-        Ok(cnaes.load::<CNAE>(&*connection)?)
+        Ok(cnaes::table.load::<CNAE>(&*connection)?)
     }
 
     fn natureza_juridica(context: &Context, id: String) -> Result<NaturezaJuridica, FieldError> {
         use data_models::schema::naturezas_juridicas;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
+        let id = id.parse::<u16>()?;
+
+        if let Some(tabelas) = context.tabelas_auxiliares(&connection) {
+            return registro_da_tabela(&tabelas.naturezas_juridicas, id, |registro| registro.id);
+        }
 
         Ok(naturezas_juridicas::table
-            .filter(naturezas_juridicas::id.eq(id.parse::<u16>()?))
+            .filter(naturezas_juridicas::id.eq(id))
             .first::<NaturezaJuridica>(&*connection)?)
     }
 
     fn naturezas_juridicas(context: &Context) -> Result<Vec<NaturezaJuridica>, FieldError> {
-        use data_models::schema::naturezas_juridicas::dsl::*;
-        let connection = context.pool.clone().get()?;
+        use data_models::schema::naturezas_juridicas;
+        let connection = context.conexao()?;
+
+        if let Some(tabelas) = context.tabelas_auxiliares(&connection) {
+            return Ok(tabelas.naturezas_juridicas.iter().map(NaturezaJuridica::from).collect());
+        }
 
-        Ok(naturezas_juridicas.load::<NaturezaJuridica>(&*connection)?)
+        Ok(naturezas_juridicas::table.load::<NaturezaJuridica>(&*connection)?)
     }
 
     fn municipio(context: &Context, id: String) -> Result<Municipio, FieldError> {
         use data_models::schema::municipios;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
+        let id = id.parse::<u16>()?;
+
+        if let Some(tabelas) = context.tabelas_auxiliares(&connection) {
+            return registro_da_tabela(&tabelas.municipios, id, |registro| registro.id);
+        }
 
         Ok(municipios::table
-            .filter(municipios::id.eq(id.parse::<u16>()?))
+            .filter(municipios::id.eq(id))
             .first::<Municipio>(&*connection)?)
     }
 
     fn municipios(context: &Context) -> Result<Vec<Municipio>, FieldError> {
-        use data_models::schema::municipios::dsl::*;
+        use data_models::schema::municipios;
+        let connection = context.conexao()?;
 
-        let connection = context.pool.clone().get()?;
+        if let Some(tabelas) = context.tabelas_auxiliares(&connection) {
+            return Ok(tabelas.municipios.iter().map(Municipio::from).collect());
+        }
 
-        Ok(municipios.load::<Municipio>(&*connection)?)
+        Ok(municipios::table.load::<Municipio>(&*connection)?)
     }
 
     fn pais(context: &Context, id: String) -> Result<Pais, FieldError> {
         use data_models::schema::paises;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
+        let id = id.parse::<u16>()?;
+
+        if let Some(tabelas) = context.tabelas_auxiliares(&connection) {
+            return registro_da_tabela(&tabelas.paises, id, |registro| registro.id);
+        }
 
         Ok(paises::table
-            .filter(paises::id.eq(id.parse::<u16>()?))
+            .filter(paises::id.eq(id))
             .first::<Pais>(&*connection)?)
     }
 
     fn paises(context: &Context) -> Result<Vec<Pais>, FieldError> {
-        use data_models::schema::paises::dsl::*;
+        use data_models::schema::paises;
+        let connection = context.conexao()?;
 
-        let connection = context.pool.clone().get()?;
+        if let Some(tabelas) = context.tabelas_auxiliares(&connection) {
+            return Ok(tabelas.paises.iter().map(Pais::from).collect());
+        }
 
-        Ok(paises.load::<Pais>(&*connection)?)
+        Ok(paises::table.load::<Pais>(&*connection)?)
     }
 
-    fn qualificacao_de_socio(
-        context: &Context,
-        id: String,
-    ) -> Result<QualificacaoDeSocio, FieldError> {
+    fn qualificacao_de_socio(context: &Context, id: String) -> Result<QualificacaoDeSocio, FieldError> {
         use data_models::schema::qualificacoes_de_socios;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
+        let id = id.parse::<u8>()?;
+
+        if let Some(tabelas) = context.tabelas_auxiliares(&connection) {
+            return registro_da_tabela(&tabelas.qualificacoes_de_socios, id, |registro| registro.id);
+        }
 
         Ok(qualificacoes_de_socios::table
-            .filter(qualificacoes_de_socios::id.eq(id.parse::<u8>()?))
+            .filter(qualificacoes_de_socios::id.eq(id))
             .first::<QualificacaoDeSocio>(&*connection)?)
     }
 
     fn qualificacoes_de_socios(context: &Context) -> Result<Vec<QualificacaoDeSocio>, FieldError> {
-        use data_models::schema::qualificacoes_de_socios::dsl::*;
+        use data_models::schema::qualificacoes_de_socios;
+        let connection = context.conexao()?;
 
-        let connection = context.pool.clone().get()?;
+        if let Some(tabelas) = context.tabelas_auxiliares(&connection) {
+            return Ok(tabelas.qualificacoes_de_socios.iter().map(QualificacaoDeSocio::from).collect());
+        }
 
-        Ok(qualificacoes_de_socios.load::<QualificacaoDeSocio>(&*connection)?)
+        Ok(qualificacoes_de_socios::table.load::<QualificacaoDeSocio>(&*connection)?)
     }
 
-    fn motivo_de_situacao_cadastral(
-        context: &Context,
-        id: String,
-    ) -> Result<MotivoDeSituacaoCadastral, FieldError> {
+    fn motivo_de_situacao_cadastral(context: &Context, id: String) -> Result<MotivoDeSituacaoCadastral, FieldError> {
         use data_models::schema::motivos_de_situacoes_cadastrais;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
+        let id = id.parse::<u8>()?;
+
+        if let Some(tabelas) = context.tabelas_auxiliares(&connection) {
+            return registro_da_tabela(&tabelas.motivos_de_situacoes_cadastrais, id, |registro| registro.id);
+        }
 
         Ok(motivos_de_situacoes_cadastrais::table
-            .filter(motivos_de_situacoes_cadastrais::id.eq(id.parse::<u8>()?))
+            .filter(motivos_de_situacoes_cadastrais::id.eq(id))
             .first::<MotivoDeSituacaoCadastral>(&*connection)?)
     }
 
-    fn motivos_de_situacoes_cadastrais(
-        context: &Context,
-    ) -> Result<Vec<MotivoDeSituacaoCadastral>, FieldError> {
-        use data_models::schema::motivos_de_situacoes_cadastrais::dsl::*;
-        let connection = context.pool.clone().get()?;
+    fn motivos_de_situacoes_cadastrais(context: &Context) -> Result<Vec<MotivoDeSituacaoCadastral>, FieldError> {
+        use data_models::schema::motivos_de_situacoes_cadastrais;
+        let connection = context.conexao()?;
+
+        if let Some(tabelas) = context.tabelas_auxiliares(&connection) {
+            return Ok(tabelas.motivos_de_situacoes_cadastrais.iter().map(MotivoDeSituacaoCadastral::from).collect());
+        }
 
-        Ok(motivos_de_situacoes_cadastrais.load::<MotivoDeSituacaoCadastral>(&*connection)?)
+        Ok(motivos_de_situacoes_cadastrais::table.load::<MotivoDeSituacaoCadastral>(&*connection)?)
     }
 
     fn empresa(context: &Context, cnpj_basico: String) -> Result<Empresa, FieldError> {
         use data_models::schema::empresas;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
 
-        Ok(empresas::table
-            .filter(empresas::cnpj_basico.eq(cnpj_basico))
-            .first::<Empresa>(&*connection)?)
+        context.cache.atualizar(&connection);
+        if let Some(empresa) = context.cache.empresas.obter(&cnpj_basico) {
+            return Ok(empresa);
+        }
+
+        let empresa = empresas::table
+            .filter(empresas::cnpj_basico.eq(&cnpj_basico))
+            .first::<Empresa>(&*connection)?;
+        context.cache.empresas.incluir(cnpj_basico, empresa.clone());
+
+        Ok(empresa)
     }
 
     fn estabelecimento(
@@ -796,37 +1057,215 @@ impl QueryRoot {
         cnpj_completo: String,
     ) -> Result<Estabelecimento, FieldError> {
         use data_models::schema::estabelecimentos;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
 
-        Ok(estabelecimentos::table
+        context.cache.atualizar(&connection);
+        if let Some(estabelecimento) = context.cache.estabelecimentos.obter(&cnpj_completo) {
+            return Ok(estabelecimento);
+        }
+
+        let estabelecimento = estabelecimentos::table
             .filter(estabelecimentos::cnpj_basico.eq(&cnpj_completo[..8]))
             .filter(estabelecimentos::cnpj_ordem.eq(&cnpj_completo[8..12]))
             .filter(estabelecimentos::cnpj_dv.eq(&cnpj_completo[12..]))
-            .first::<Estabelecimento>(&*connection)?)
+            .first::<Estabelecimento>(&*connection)?;
+        context.cache.estabelecimentos.incluir(cnpj_completo, estabelecimento.clone());
+
+        Ok(estabelecimento)
     }
 
-    fn socios(context: &Context, cnpj_basico: String) -> Result<Vec<Socio>, FieldError> {
+    #[graphql(description = "Consulta vários estabelecimentos de uma só vez pelo CNPJ completo (formatado ou não)")]
+    fn estabelecimentos(context: &Context, cnpjs: Vec<String>) -> Result<Vec<ItemDoLoteDeEstabelecimentos>, FieldError> {
+        use data_models::schema::estabelecimentos;
+        let connection = context.conexao()?;
+
+        if cnpjs.len() > LIMITE_DO_LOTE {
+            return Err(FieldError::from(format!("Informe no máximo {} CNPJs por lote", LIMITE_DO_LOTE)));
+        }
+
+        let (normalizados, validos) = normalizar_lote(&cnpjs);
+
+        let mut encontrados = Vec::new();
+        for bloco in validos.chunks(TAMANHO_DO_BLOCO) {
+            encontrados.extend(
+                estabelecimentos::table
+                    .filter(condicao_dos_cnpjs(bloco))
+                    .load::<Estabelecimento>(&*connection)?,
+            );
+        }
+
+        let encontrados = por_cnpj(encontrados, |e| format!("{}{}{}", e.cnpj_basico, e.cnpj_ordem, e.cnpj_dv));
+        Ok(cnpjs
+            .into_iter()
+            .zip(normalizados)
+            .map(|(cnpj, normalizado)| {
+                let normalizado = match normalizado {
+                    Some(v) => v,
+                    None => return ItemDoLoteDeEstabelecimentos { cnpj, status: StatusDoItemDoLote::Invalido, estabelecimento: None },
+                };
+                let estabelecimento = encontrados.get(&normalizado).cloned();
+                let status = match estabelecimento {
+                    Some(_) => StatusDoItemDoLote::Encontrado,
+                    None => StatusDoItemDoLote::NaoEncontrado,
+                };
+                ItemDoLoteDeEstabelecimentos { cnpj, status, estabelecimento }
+            })
+            .collect())
+    }
+
+    fn socios(
+        context: &Context,
+        cnpj_basico: Option<String>,
+        filter: Option<FiltroDeSocios>,
+        limite: Option<i32>,
+    ) -> Result<Vec<Socio>, FieldError> {
         use data_models::schema::socios;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
 
-        Ok(socios::table
-            .filter(socios::cnpj_basico.eq(cnpj_basico))
-            .load::<Socio>(&*connection)?)
+        if let Some(cnpj_basico) = cnpj_basico {
+            return Ok(socios::table
+                .filter(socios::cnpj_basico.eq(cnpj_basico))
+                .load::<Socio>(&*connection)?);
+        }
+
+        let filter = filter.unwrap_or(FiltroDeSocios { cpf: None, cnpj: None, nome: None });
+        let busca = BuscaDeSocios::new(filter.cpf.as_deref(), filter.cnpj.as_deref(), filter.nome.as_deref())
+            .map_err(|erro| match erro.parametro {
+                None => FieldError::from("Informe o cnpj_basico ou ao menos um dos campos do filtro: cpf, cnpj ou nome"),
+                Some(_) => FieldError::from(erro.mensagem),
+            })?;
+        // a busca pelos dados pessoais ocultados pelo perfil de exposição permitiria identificar as pessoas
+        if let Some(parametro) = busca.parametro_oculto(context.mascaramento.perfil()) {
+            return Err(FieldError::from(format!(
+                "O perfil de exposição {} não permite a busca de sócios pelo campo {} do filtro",
+                context.mascaramento.perfil().nome, parametro
+            )));
+        }
+        let limite = limite.map(i64::from).unwrap_or(LIMITE_PADRAO_DE_SOCIOS).clamp(1, LIMITE_MAXIMO_DE_SOCIOS);
+
+        Ok(buscar_socios(&connection, &busca, None, limite)?)
+    }
+
+    fn grupo_economico(context: &Context, cnpj_basico: String, depth: Option<i32>) -> Result<GrupoEconomico, FieldError> {
+        let connection = context.conexao()?;
+
+        let cnpj_basico = somente_digitos(&cnpj_basico);
+        if cnpj_basico.len() != 8 {
+            return Err(FieldError::from(format!("CNPJ básico inválido: {}", cnpj_basico)));
+        }
+
+        let profundidade = depth.unwrap_or(PROFUNDIDADE_PADRAO_DO_GRAFO);
+        if profundidade < 1 || profundidade > i32::from(PROFUNDIDADE_MAXIMA) {
+            return Err(FieldError::from(format!("A profundidade deve estar entre 1 e {}", PROFUNDIDADE_MAXIMA)));
+        }
+
+        let mut grafo = grupo_economico::grupo_economico(&connection, &cnpj_basico, profundidade as u8)?;
+        for no in grafo.nos.iter_mut() {
+            if let Some(razao_social) = &mut no.razao_social {
+                context.mascaramento.razao_social(razao_social);
+            }
+        }
+
+        Ok(GrupoEconomico(grafo))
+    }
+
+    #[graphql(description = "Busca as empresas pela razão social ou pelo nome fantasia (sem diferenciar acentos e aceitando o início das palavras)")]
+    fn buscar_empresas(
+        context: &Context,
+        q: String,
+        uf: Option<String>,
+        situacao_cadastral: Option<SituacaoCadastral>,
+        #[graphql(description = "Código de 7 dígitos do IBGE do município do estabelecimento")]
+        municipio_ibge: Option<i32>,
+        limite: Option<i32>,
+        pagina: Option<i32>,
+    ) -> Result<Vec<ResultadoDaBusca>, FieldError> {
+        let connection = context.conexao()?;
+
+        let expressao = expressao_de_busca(&q)
+            .ok_or_else(|| FieldError::from("Informe ao menos um termo com 3 ou mais caracteres no parâmetro q"))?;
+        let municipio_ibge = match municipio_ibge {
+            Some(codigo) if codigo < 0 || !codigo_ibge_valido(codigo as u32) => {
+                return Err(FieldError::from(format!("Código do IBGE inválido (deve conter 7 dígitos): {}", codigo)));
+            }
+            codigo => codigo.map(|codigo| codigo as u32),
+        };
+        let filtro = FiltroDaBusca {
+            uf,
+            situacao_cadastral,
+            municipio_ibge,
+        };
+        let limite = limite.map(i64::from).unwrap_or(LIMITE_PADRAO_DA_BUSCA).clamp(1, LIMITE_MAXIMO_DA_BUSCA);
+        let (_, deslocamento) = deslocamento_da_pagina(pagina.map(i64::from), limite)?;
+
+        let mut resultados = buscar_empresas(&connection, &expressao, &filtro, limite, deslocamento)?;
+        for resultado in resultados.iter_mut() {
+            context.mascaramento.razao_social(&mut resultado.razao_social);
+        }
+
+        Ok(resultados)
     }
 
     fn simples(context: &Context, cnpj_basico: String) -> Result<Simples, FieldError> {
         use data_models::schema::simples;
-        let connection = context.pool.clone().get()?;
+        let connection = context.conexao()?;
 
         Ok(simples::table
             .filter(simples::cnpj_basico.eq(cnpj_basico))
             .first::<Simples>(&*connection)?)
-    }    
-    
+    }
+
+    #[graphql(description = "Estatísticas do cache de consultas (vazio quando o cache está desabilitado)")]
+    fn estatisticas_do_cache(context: &Context) -> Vec<EstatisticasDoCache> {
+        if !context.cache.habilitado() {
+            return vec![];
+        }
+        context.cache.estatisticas().into_iter().map(EstatisticasDoCache).collect()
+    }
+}
+
+// Estado compartilhado pelas requisições, a partir do qual o contexto de cada requisição é criado
+pub struct EstadoDoServidor {
+    pub pool: r2d2::Pool<r2d2_diesel::ConnectionManager<MysqlConnection>>,
+    pub cache: Arc<CacheDeConsultas>,
+    pub perfis: PerfisDeExposicao,
 }
 
+// Contexto de uma requisição
 pub struct Context {
     pub pool: r2d2::Pool<r2d2_diesel::ConnectionManager<MysqlConnection>>,
+    pub cache: Arc<CacheDeConsultas>,
+    // ocultação dos dados pessoais conforme o perfil de exposição da requisição (vide lgpd)
+    pub mascaramento: Mascaramento,
+}
+
+// Conexão com o banco de dados, cujo tempo de uso é medido (vide monitoramento)
+pub struct Conexao {
+    conexao: r2d2::PooledConnection<r2d2_diesel::ConnectionManager<MysqlConnection>>,
+    _uso: UsoDaConexao,
+}
+
+impl Deref for Conexao {
+    type Target = MysqlConnection;
+
+    fn deref(&self) -> &MysqlConnection {
+        &self.conexao
+    }
+}
+
+impl Context {
+    fn conexao(&self) -> Result<Conexao, r2d2::Error> {
+        Ok(Conexao {
+            conexao: self.pool.get()?,
+            _uso: METRICAS.iniciar_uso_da_conexao(),
+        })
+    }
+
+    // Tabelas auxiliares em memória, quando o cache de consultas está habilitado
+    fn tabelas_auxiliares(&self, connection: &MysqlConnection) -> Option<Arc<TabelasAuxiliares>> {
+        self.cache.atualizar(connection);
+        self.cache.tabelas_auxiliares()
+    }
 }
 
 impl juniper::Context for Context {}
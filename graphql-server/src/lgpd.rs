@@ -0,0 +1,36 @@
+// Ocultação dos dados pessoais (LGPD) nas respostas do GraphQL conforme o perfil de exposição da chave de
+// API utilizada na requisição ou, sem chave, conforme o perfil padrão (vide data_models::lgpd).
+//
+// Cada requisição tem o seu próprio contexto, com o mascaramento utilizado pelos campos que contêm dados
+// pessoais. Os dados ocultados são registrados no log quando o contexto é descartado, ao final da requisição.
+
+use data_models::lgpd::Mascaramento;
+use data_models::models::ChaveDeApi;
+use rocket::outcome::try_outcome;
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Request, State};
+
+use crate::graphql_schema::{Context, EstadoDoServidor};
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Context {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        let estado = try_outcome!(request.guard::<&State<EstadoDoServidor>>().await);
+
+        // a chave é guardada no cache local da requisição pelo controle de acesso (vide acesso)
+        let chave = request.local_cache(|| None::<ChaveDeApi>);
+        let perfil = estado.perfis.perfil(chave.as_ref().map(|chave| chave.perfil.as_str()));
+        let origem = match chave {
+            Some(chave) => format!("chave {} ({}) {} {}", chave.id, chave.nome, request.method(), request.uri().path()),
+            None => format!("{} {}", request.method(), request.uri().path()),
+        };
+
+        Outcome::Success(Context {
+            pool: estado.pool.clone(),
+            cache: estado.cache.clone(),
+            mascaramento: Mascaramento::new(perfil, origem),
+        })
+    }
+}
@@ -8,54 +8,112 @@ extern crate r2d2_diesel;
 use r2d2_diesel::ConnectionManager;
 
 use std::env;
+use std::sync::Arc;
+use std::time::Instant;
 use diesel::MysqlConnection;
 use dotenv::dotenv;
 
 use rocket::{response::content, Rocket, State};
 
-use crate::graphql_schema::{Context, Schema};
+use crate::cache_de_consultas::CacheDeConsultas;
+use crate::graphql_schema::{Context, EstadoDoServidor, Schema};
+use data_models::lgpd::PerfisDeExposicao;
+use data_models::logs::iniciar_logs;
+use data_models::monitoramento::METRICAS;
 
+mod acesso;
+mod cache_de_consultas;
+pub mod cache_http;
 mod graphql_schema;
+mod lgpd;
+pub mod monitoramento;
 
 #[rocket::get("/")]
-fn graphiql() -> content::Html<String> {
+fn graphiql() -> content::RawHtml<String> {
     juniper_rocket::graphiql_source("/graphql", None)
 }
 
+// Executa a requisição, registrando a duração de cada operação pelo seu nome (vide monitoramento)
+fn executar(request: juniper_rocket::GraphQLRequest, schema: &Schema, context: &Context) -> juniper_rocket::GraphQLResponse {
+    let inicio = Instant::now();
+    let operacoes: Vec<String> = request
+        .operation_names()
+        .into_iter()
+        .map(|operacao| operacao.unwrap_or("anonima").to_string())
+        .collect();
+
+    let resposta = request.execute_sync(schema, context);
+
+    let duracao = inicio.elapsed();
+    for operacao in operacoes {
+        METRICAS.registrar_operacao_graphql(&operacao, duracao);
+    }
+    resposta
+}
+
 #[rocket::get("/graphql?<request>")]
 fn get_graphql_handler(
-    context: &State<Context>,
+    context: Context,
     request: juniper_rocket::GraphQLRequest,
     schema: &State<Schema>,
 ) -> juniper_rocket::GraphQLResponse {
-    request.execute_sync(&*schema, &*context)
+    executar(request, schema, &context)
 }
 
 #[rocket::post("/graphql", data = "<request>")]
 fn post_graphql_handler(
-    context: &State<Context>,
+    context: Context,
     request: juniper_rocket::GraphQLRequest,
     schema: &State<Schema>,
 ) -> juniper_rocket::GraphQLResponse {
-    request.execute_sync(&*schema, &*context)
+    executar(request, schema, &context)
 }
 
 #[rocket::main]
 async fn main() {
     dotenv().ok();
+    iniciar_logs("info");
 
     let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set in .env");
 
     let manager = ConnectionManager::<MysqlConnection>::new(db_url);
     let pool = r2d2::Pool::builder().max_size(15).build(manager).unwrap();
+
+    let cache = Arc::new(CacheDeConsultas::new(
+        env::var("CACHE_DE_CONSULTAS").ok().and_then(|v| v.parse().ok()),
+        env::var("CACHE_DE_CONSULTAS_TTL").ok().and_then(|v| v.parse().ok()),
+    ));
+    // carrega as tabelas auxiliares no cache logo na inicialização
+    if let Ok(connection) = pool.get() {
+        cache.atualizar(&connection);
+    }
        
-    Rocket::build()
-        .manage(Context { pool })
+    // a página do GraphiQL pode ser desabilitada (ex: em produção) com GRAPHIQL=false
+    let rotas = if env::var("GRAPHIQL").map(|v| v == "false" || v == "0").unwrap_or(false) {
+        rocket::routes![get_graphql_handler, post_graphql_handler]
+    } else {
+        rocket::routes![graphiql, get_graphql_handler, post_graphql_handler]
+    };
+
+    // o fairing das métricas é o primeiro, para que a duração medida inclua os demais fairings
+    let rocket = Rocket::build()
+        .attach(monitoramento::MetricasDasRequisicoes)
+        .manage(EstadoDoServidor { pool, cache, perfis: PerfisDeExposicao::do_ambiente() })
         .manage(graphql_schema::create_schema())
-        .mount(
-            "/",
-            rocket::routes![graphiql, get_graphql_handler, post_graphql_handler],
-        )
+        .mount("/", rotas)
+        .mount("/", monitoramento::rotas())
+        .mount("/", cache_http::rotas())
+        .register("/", acesso::catchers());
+
+    let rocket = if env::var("EXIGIR_CHAVE_DE_API").map(|v| v == "true" || v == "1").unwrap_or(false) {
+        rocket.attach(acesso::ControleDeAcessoDoGraphql::default())
+    } else {
+        rocket
+    };
+
+    // o cache HTTP identifica o perfil de exposição pela chave de API, então é anexado após o controle de acesso
+    rocket
+        .attach(cache_http::CacheHttp::new(env::var("CACHE_MAX_AGE").ok().and_then(|v| v.parse().ok())))
         .launch()
         .await
         .expect("server to launch");
@@ -0,0 +1,126 @@
+// Rotas de monitoramento do servidor (não exigem a chave de API):
+//
+//   /health  - o servidor está no ar (liveness)
+//   /ready   - o servidor pode atender às requisições: o banco de dados responde e os dados já foram importados
+//   /metrics - métricas no formato de texto do Prometheus (vide data_models::monitoramento)
+//
+// As requisições são contabilizadas pelo fairing MetricasDasRequisicoes, por rota, método e status, e as
+// operações do GraphQL pelos handlers, pelo nome da operação.
+
+use std::time::Instant;
+
+use data_models::monitoramento::{verificar_prontidao, TextoDoPrometheus, METRICAS};
+use data_models::versao::metadados_das_tabelas;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::Json;
+use rocket::{Data, Request, Response, Route, State};
+use serde::Serialize;
+
+use crate::graphql_schema::EstadoDoServidor;
+
+// rotas de monitoramento, que não exigem a chave de API (vide acesso)
+pub const ROTAS_DE_MONITORAMENTO: &[&str] = &["/health", "/ready", "/metrics"];
+
+#[derive(Serialize)]
+struct EstadoDoServico {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tabelas_importadas: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    motivo: Option<String>,
+}
+
+#[rocket::get("/health")]
+fn get_health() -> Json<EstadoDoServico> {
+    Json(EstadoDoServico {
+        status: "ok",
+        tabelas_importadas: None,
+        motivo: None,
+    })
+}
+
+#[rocket::get("/ready")]
+async fn get_ready(estado: &State<EstadoDoServidor>) -> (Status, Json<EstadoDoServico>) {
+    let pool = estado.pool.clone();
+    let prontidao = rocket::tokio::task::spawn_blocking(move || {
+        let connection = pool.get().map_err(|error| format!("Não foi possível obter uma conexão com o banco de dados: {}", error))?;
+        verificar_prontidao(&connection)
+    })
+    .await
+    .unwrap_or_else(|error| Err(format!("{:?}", error)));
+
+    match prontidao {
+        Ok(tabelas_importadas) => (
+            Status::Ok,
+            Json(EstadoDoServico {
+                status: "ok",
+                tabelas_importadas: Some(tabelas_importadas),
+                motivo: None,
+            }),
+        ),
+        Err(motivo) => (
+            Status::ServiceUnavailable,
+            Json(EstadoDoServico {
+                status: "indisponivel",
+                tabelas_importadas: None,
+                motivo: Some(motivo),
+            }),
+        ),
+    }
+}
+
+#[rocket::get("/metrics")]
+async fn get_metrics(estado: &State<EstadoDoServidor>) -> (ContentType, String) {
+    let mut texto = TextoDoPrometheus::new();
+    METRICAS.exportar(&mut texto);
+    let estado_do_pool = estado.pool.state();
+    texto.pool_de_conexoes(
+        u64::from(estado_do_pool.connections - estado_do_pool.idle_connections),
+        u64::from(estado.pool.max_size()),
+    );
+    texto.caches(&estado.cache.estatisticas());
+
+    let pool = estado.pool.clone();
+    let metadados = rocket::tokio::task::spawn_blocking(move || {
+        let connection = pool.get().map_err(|error| format!("{:?}", error))?;
+        metadados_das_tabelas(&connection).map_err(|error| format!("{:?}", error))
+    })
+    .await;
+    match metadados {
+        Ok(Ok(metadados)) => texto.idade_dos_dados(&metadados),
+        Ok(Err(error)) => tracing::error!(erro = %error, "Erro ao ler os metadados das tabelas"),
+        Err(error) => tracing::error!(erro = ?error, "Erro ao ler os metadados das tabelas"),
+    }
+
+    (ContentType::Plain, texto.texto())
+}
+
+pub fn rotas() -> Vec<Route> {
+    rocket::routes![get_health, get_ready, get_metrics]
+}
+
+// instante em que a requisição foi recebida, guardado no cache local da requisição
+struct InicioDaRequisicao(Instant);
+
+pub struct MetricasDasRequisicoes;
+
+#[rocket::async_trait]
+impl Fairing for MetricasDasRequisicoes {
+    fn info(&self) -> Info {
+        Info {
+            name: "Métricas das requisições",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        request.local_cache(|| InicioDaRequisicao(Instant::now()));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let inicio = request.local_cache(|| InicioDaRequisicao(Instant::now()));
+        let rota = request.route().map(|rota| rota.uri.path().to_string()).unwrap_or_else(|| String::from("desconhecida"));
+        METRICAS.registrar_requisicao(&rota, request.method().as_str(), response.status().code, inicio.0.elapsed());
+    }
+}
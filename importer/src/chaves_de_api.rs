@@ -0,0 +1,51 @@
+use chrono::Utc;
+use data_models::acesso::{chaves_e_uso, criar_chave, revogar_chave};
+
+use crate::cli::ComandoDasChavesDeApi;
+use crate::database::Database;
+
+// Executa os comandos de gerenciamento das chaves de API (vide data_models::acesso)
+pub fn executar(comando: &ComandoDasChavesDeApi) -> Result<(), String> {
+
+    let db_connection = Database::establish_connection();
+
+    match comando {
+        ComandoDasChavesDeApi::Criar { nome, perfil, por_minuto, por_dia } => {
+            let chave = criar_chave(&db_connection, nome, perfil, *por_minuto, *por_dia)
+                .map_err(|error| format!("Erro ao criar a chave de API: {:?}", error))?;
+            println!("Chave de API criada para {}: {}", nome, chave);
+            println!("Guarde esta chave em local seguro, pois ela não poderá ser exibida novamente.");
+        }
+        ComandoDasChavesDeApi::Listar => {
+            let hoje = Utc::now().date_naive();
+            let chaves = chaves_e_uso(&db_connection, hoje)
+                .map_err(|error| format!("Erro ao ler as chaves de API: {:?}", error))?;
+
+            println!("{:>6}  {:<40} {:<12} {:>8} {:>12} {:>12} {:>12} {:>12}", "id", "nome", "perfil", "ativa", "por minuto", "por dia", "hoje", "recusadas");
+            for (chave, uso) in chaves {
+                let limite = |v: Option<u32>| v.map(|v| v.to_string()).unwrap_or_else(|| String::from("-"));
+                println!(
+                    "{:>6}  {:<40} {:<12} {:>8} {:>12} {:>12} {:>12} {:>12}",
+                    chave.id,
+                    chave.nome,
+                    chave.perfil,
+                    if chave.ativa { "sim" } else { "não" },
+                    limite(chave.requisicoes_por_minuto),
+                    limite(chave.requisicoes_por_dia),
+                    uso.as_ref().map(|u| u.requisicoes).unwrap_or(0),
+                    uso.as_ref().map(|u| u.requisicoes_recusadas).unwrap_or(0),
+                );
+            }
+        }
+        ComandoDasChavesDeApi::Revogar { id } => {
+            let revogada = revogar_chave(&db_connection, *id)
+                .map_err(|error| format!("Erro ao revogar a chave de API: {:?}", error))?;
+            if !revogada {
+                return Err(format!("Chave de API não encontrada: {}", id));
+            }
+            println!("Chave de API {} revogada", id);
+        }
+    }
+
+    Ok(())
+}
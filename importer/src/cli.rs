@@ -1,5 +1,9 @@
 use structopt::StructOpt;
 
+use crate::deduplicacao::PoliticaDeDuplicados;
+use crate::qualidade::FormatoDaQualidade;
+use crate::relatorio::FormatoDoRelatorio;
+
 #[derive(StructOpt)]
 #[structopt(
     about = "Importador de dados do CNPJ disponibilizado pela Receita Federal Brasil para um Banco de Dados MySQL.",
@@ -50,8 +54,185 @@ pub struct Cli {
     )]
     pub force: bool,
     #[structopt(
+        long,
+        help = "Importação \"blue/green\" das tabelas grandes (empresas, estabelecimentos, socios e simples): as partes (Y1, Y2, ..., Y0) são importadas em uma tabela de carga (ex: estabelecimentos_staging), criada com a mesma estrutura da tabela original ao importar a primeira parte (Y1); e, ao finalizar a importação da última parte (Y0), a quantidade de registros da tabela de carga é validada e ela substitui a tabela original de uma só vez (RENAME TABLE). Assim, as APIs nunca consultam uma tabela importada pela metade. Pode ser utilizado junto com o flag --drop-indexes, que passa a ser aplicado à tabela de carga. Os arquivos devem ser importados em ordem das partes (Y1,Y2...Y0)."
+    )]
+    pub staging: bool,
+    #[structopt(
+        long,
+        default_value = "10",
+        help = "Redução máxima, em percentual, da quantidade de registros da tabela de carga (--staging) em relação à tabela original. Se a tabela de carga tiver menos registros, ela não substitui a tabela original e a importação termina com erro."
+    )]
+    pub staging_max_reduction: u8,
+    #[structopt(
+        long,
+        help = "Grava, nos estabelecimentos, o endereço normalizado em uma única linha (sem espaços repetidos, com o \"sem número\" padronizado como S/N e o CEP formatado) e se o CEP pertence às faixas de CEP da UF. Os campos originais do endereço não são alterados."
+    )]
+    pub normalize_addresses: bool,
+    #[structopt(
+        long,
+        help = "Grava, nos estabelecimentos, os telefones e o fax no formato E.164 (+55DDXXXXXXXX, com o nono dígito nos celulares antigos; nulos quando inválidos) e o correio eletrônico sem espaços e em minúsculas, com a indicação se ele é válido. Os campos originais não são alterados."
+    )]
+    pub normalize_contacts: bool,
+    #[structopt(
+        long,
+        default_value = "first",
+        help = "Política para os registros duplicados (com a mesma chave) nas tabelas de empresas, estabelecimentos, sócios e simples: first mantém a primeira ocorrência e ignora as seguintes; last mantém a última, substituindo as anteriores; error interrompe a importação do arquivo. A chave é verificada em todo o arquivo e entre as partes da tabela importadas na mesma execução."
+    )]
+    pub duplicates: PoliticaDeDuplicados,
+    #[structopt(
+        long,
+        help = "Gera, ao final da execução, um relatório com o resultado da importação de cada arquivo (tabela, registros lidos, inseridos, duplicados e rejeitados, duração e registros por segundo) no formato informado (json). O relatório é gravado no arquivo informado em --report-file ou, se não informado, na saída padrão."
+    )]
+    pub report: Option<FormatoDoRelatorio>,
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Caminho do arquivo onde o relatório da importação (--report) será gravado"
+    )]
+    pub report_file: Option<std::path::PathBuf>,
+    #[structopt(
+        long,
         parse(from_os_str),
-        help = "Caminho para o arquivo a ser importado ou o caminho do diretório no caso de uso do flag -f"
+        help = "Caminho de um arquivo onde os contadores da importação serão gravados no formato de texto do Prometheus, atualizados durante a importação (compatível com o textfile collector do node_exporter e com o pushgateway)."
+    )]
+    pub metrics_file: Option<std::path::PathBuf>,
+    #[structopt(
+        long,
+        help = "Não executa a verificação da integridade referencial (vide o comando verify) ao final da importação de um diretório (-f)."
+    )]
+    pub skip_verify: bool,
+    #[structopt(flatten)]
+    pub verificacao: OpcoesDaVerificacao,
+    #[structopt(
+        parse(from_os_str),
+        help = "Caminho para o arquivo a ser importado ou o caminho do diretório no caso de uso do flag -f (não deve ser informado junto com os comandos)"
+    )]
+    pub path_to_import: Option<std::path::PathBuf>,
+    #[structopt(subcommand)]
+    pub comando: Option<Comando>,
+}
+
+// Opções da verificação da integridade referencial, utilizadas pelo comando verify e pela verificação feita
+// ao final da importação de um diretório
+#[derive(StructOpt)]
+pub struct OpcoesDaVerificacao {
+    #[structopt(
+        long,
+        default_value = "0",
+        help = "Quantidade máxima de registros órfãos (sem correspondência na tabela referenciada) em cada chave estrangeira. A verificação termina com erro se a quantidade for maior."
+    )]
+    pub max_orphans: u64,
+    #[structopt(
+        long,
+        help = "Move os registros órfãos de cada chave estrangeira para uma tabela de quarentena (ex: estabelecimentos_orfaos_municipio), removendo-os da tabela."
+    )]
+    pub quarantine: bool,
+}
+
+#[derive(StructOpt)]
+pub enum Comando {
+    #[structopt(
+        name = "index",
+        about = "Gera (ou atualiza) o índice de busca textual (tantivy) a partir das tabelas empresas e estabelecimentos já importadas."
+    )]
+    Index {
+        #[structopt(
+            parse(from_os_str),
+            default_value = "indice_de_busca",
+            help = "Caminho do diretório onde o índice será gravado"
+        )]
+        diretorio: std::path::PathBuf,
+        #[structopt(
+            long,
+            help = "Gera o índice novamente desde o início, removendo todos os documentos existentes. Sem este flag, os estabelecimentos são apenas atualizados no índice e, caso o índice já tenha sido gerado a partir da mesma importação, nada é feito."
+        )]
+        recriar: bool,
+    },
+    #[structopt(
+        name = "chaves-de-api",
+        about = "Gerencia as chaves de acesso às APIs (rest-server e graphql-server) e as suas cotas de requisições."
+    )]
+    ChavesDeApi {
+        #[structopt(subcommand)]
+        comando: ComandoDasChavesDeApi,
+    },
+    #[structopt(
+        name = "quality",
+        about = "Gera o relatório de qualidade dos dados importados: nulos ou em branco por coluna, valores inválidos descartados na importação, códigos sem correspondência nas tabelas auxiliares, CNPJs com dígitos verificadores inválidos, CPFs, CEPs, UFs e e-mails mal formatados e sócios e registros do simples sem a empresa."
+    )]
+    Quality {
+        #[structopt(
+            long,
+            default_value = "json",
+            help = "Formato do relatório: html ou json"
+        )]
+        formato: FormatoDaQualidade,
+        #[structopt(
+            long,
+            parse(from_os_str),
+            help = "Caminho do arquivo onde o relatório será gravado (se não informado, o relatório é exibido na saída padrão)"
+        )]
+        arquivo: Option<std::path::PathBuf>,
+    },
+    #[structopt(
+        name = "verify",
+        about = "Verifica a integridade referencial das tabelas empresas, estabelecimentos, socios e simples: a existência das chaves primárias e estrangeiras e a quantidade de registros órfãos de cada chave estrangeira. Termina com erro quando uma chave não existe ou quando há mais registros órfãos do que o permitido."
+    )]
+    Verify {
+        #[structopt(flatten)]
+        opcoes: OpcoesDaVerificacao,
+    },
+    #[structopt(
+        name = "ibge",
+        about = "Carrega a correspondência entre os códigos de município da Receita Federal (TOM/SIAFI) e os códigos de 7 dígitos do IBGE, a partir de um CSV com as colunas codigo_rf, codigo_ibge e uf ou da Tabela de Órgãos e Municípios (TABMUN) do Tesouro Nacional, e preenche o código do IBGE e a UF dos municípios."
+    )]
+    Ibge {
+        #[structopt(
+            parse(from_os_str),
+            help = "Caminho do arquivo CSV com a correspondência dos códigos"
+        )]
+        arquivo: std::path::PathBuf,
+    },
+}
+
+#[derive(StructOpt)]
+pub enum ComandoDasChavesDeApi {
+    #[structopt(
+        name = "criar",
+        about = "Cria uma nova chave de API. A chave é exibida somente uma vez, pois apenas o seu hash é gravado no banco de dados."
+    )]
+    Criar {
+        #[structopt(help = "Nome do cliente (parceiro) que utilizará a chave")]
+        nome: String,
+        #[structopt(
+            long,
+            default_value = "publico",
+            help = "Perfil de exposição dos dados pessoais (LGPD): publico, parceiro, interno ou outro perfil configurado nos servidores"
+        )]
+        perfil: String,
+        #[structopt(
+            long,
+            help = "Quantidade máxima de requisições por minuto (sem limite, se não informada)"
+        )]
+        por_minuto: Option<u32>,
+        #[structopt(
+            long,
+            help = "Quantidade máxima de requisições por dia (sem limite, se não informada)"
+        )]
+        por_dia: Option<u32>,
+    },
+    #[structopt(
+        name = "listar",
+        about = "Lista as chaves de API, as suas cotas e a quantidade de requisições feitas no dia (UTC)."
+    )]
+    Listar,
+    #[structopt(
+        name = "revogar",
+        about = "Revoga (desativa) uma chave de API. A revogação passa a valer nos servidores em até um minuto."
     )]
-    pub path_to_import: std::path::PathBuf,
+    Revogar {
+        #[structopt(help = "Id da chave (vide o comando listar)")]
+        id: u32,
+    },
 }
@@ -29,6 +29,10 @@ impl<'a> Config<'a> {
         &self.tipo_de_arquivo
     }
 
+    pub fn file_part_number(&self) -> u8 {
+        self.file_part_number
+    }
+
     pub fn is_first_file_number(&self) -> bool {
         self.file_part_number == 1
     }
@@ -66,4 +70,26 @@ impl<'a> Config<'a> {
     pub fn force(&self) -> bool {
         self.args.force
     }
+
+    // a importação com tabela de carga só é feita nas tabelas grandes
+    pub fn staging(&self) -> bool {
+        self.args.staging && self.tipo_de_arquivo.is_large_table()
+    }
+
+    // primeira parte da importação com tabela de carga (o arquivo do simples não é dividido em partes)
+    pub fn is_first_staging_part(&self) -> bool {
+        !self.tipo_de_arquivo.has_parts() || self.is_first_file_number()
+    }
+
+    pub fn staging_max_reduction(&self) -> u8 {
+        self.args.staging_max_reduction
+    }
+
+    pub fn normalize_addresses(&self) -> bool {
+        self.args.normalize_addresses
+    }
+
+    pub fn normalize_contacts(&self) -> bool {
+        self.args.normalize_contacts
+    }
 }
@@ -1,13 +1,104 @@
 use std::env;
 
 use data_models::models::*;
-use diesel::mysql::MysqlConnection;
+use data_models::schema::socios;
+use diesel::dsl::sql;
+use diesel::expression::BoxableExpression;
+use diesel::mysql::{Mysql, MysqlConnection};
+use diesel::query_builder::{QueryFragment, QueryId};
+use diesel::sql_types::{BigInt, Bool, Text, Unsigned};
 use diesel::{prelude::*, sql_query};
 use dotenv::dotenv;
+use tracing::{error, info, warn};
 
 use crate::config::Config;
+use crate::staging::{staging_table_name, NaTabelaDeCarga};
 use crate::tipo_de_arquivo::TipoDeArquivo;
 
+#[derive(QueryableByName)]
+struct Contagem {
+    #[sql_type = "BigInt"]
+    total: i64,
+}
+
+// Alterações que envolvem as chaves estrangeiras (inclusive os índices criados para elas, FK_...). Na
+// importação com --staging, elas não são feitas na tabela de carga, pois CREATE TABLE ... LIKE não copia as
+// chaves estrangeiras e os nomes delas são únicos no banco de dados; as chaves são criadas na tabela
+// original logo após a troca das tabelas (vide swap_staging_table).
+fn is_foreign_key_modification(line: &str) -> bool {
+    line.contains("FOREIGN KEY") || line.contains(" FK_")
+}
+
+// Condição que seleciona os sócios com a mesma chave natural dos registros (a empresa, o tipo, o nome e o
+// documento do sócio, a qualificação e a data de entrada na sociedade; vide Import::import_socios)
+fn mesmos_socios(registros: &[NewSocio]) -> Box<dyn BoxableExpression<socios::table, Mysql, SqlType = Bool>> {
+    type Condicao = Box<dyn BoxableExpression<socios::table, Mysql, SqlType = Bool>>;
+
+    let mut condicao: Condicao = Box::new(sql::<Bool>("1 = 0"));
+    for registro in registros {
+        let documento: Condicao = match &registro.cnpj_ou_cpf_do_socio {
+            Some(documento) => Box::new(socios::cnpj_ou_cpf_do_socio.eq(documento.clone())),
+            None => Box::new(socios::cnpj_ou_cpf_do_socio.is_null()),
+        };
+        condicao = Box::new(
+            condicao.or(socios::cnpj_basico.eq(registro.cnpj_basico.clone())
+                .and(socios::identificador_de_socio.eq(registro.identificador_de_socio))
+                .and(socios::nome_ou_razao_social_do_socio.eq(registro.nome_ou_razao_social_do_socio.clone()))
+                .and(documento)
+                .and(socios::qualificacao_do_socio.eq(registro.qualificacao_do_socio))
+                .and(socios::data_de_entrada_na_sociedade.eq(registro.data_de_entrada_na_sociedade))),
+        );
+    }
+    condicao
+}
+
+// Índices, chaves primárias e chaves estrangeiras das tabelas grandes (recriados após a importação com
+// --drop-indexes e conferidos por importer verify)
+pub fn indexes_and_primary_keys_of(tipo_de_arquivo: &TipoDeArquivo) -> Vec<(&'static str, &'static str)> {
+
+    let table_name = tipo_de_arquivo.table_name();
+
+    match tipo_de_arquivo {
+        TipoDeArquivo::Estabelecimentos => {
+            vec![
+                (table_name,"ADD PRIMARY KEY (cnpj_basico, cnpj_ordem, cnpj_dv)"),
+                (table_name,"ADD CONSTRAINT FK_EstabEmp FOREIGN KEY (cnpj_basico) REFERENCES empresas(cnpj_basico)"),
+                (table_name,"ADD CONSTRAINT FK_EstabMotivCad FOREIGN KEY (motivo_situacao_cadastral) REFERENCES motivos_de_situacoes_cadastrais(id)"),
+                (table_name,"ADD CONSTRAINT FK_EstabPais FOREIGN KEY (pais) REFERENCES paises(id)"),
+                (table_name,"ADD CONSTRAINT FK_EstabCnaePrinc FOREIGN KEY (cnae_fiscal_principal) REFERENCES cnaes(id)"),
+                (table_name,"ADD CONSTRAINT FK_EstabMunic FOREIGN KEY (municipio) REFERENCES municipios(id)"),
+                (table_name,"ADD CONSTRAINT FK_EstabSitCad FOREIGN KEY (situacao_cadastral) REFERENCES situacoes_cadastrais(id)"),
+                (table_name,"ADD FULLTEXT INDEX estabelecimentos_busca_nome_fantasia (nome_fantasia)")
+            ]                
+        },
+        TipoDeArquivo::Empresas => {
+
+            vec![
+                (table_name,"ADD PRIMARY KEY (cnpj_basico)"),
+                (table_name,"ADD CONSTRAINT FK_EmpNatJur FOREIGN KEY (natureza_juridica) REFERENCES naturezas_juridicas(id)"),
+                (table_name,"ADD CONSTRAINT FK_EmpQualResp FOREIGN KEY (qualificacao_do_responsavel) REFERENCES qualificacoes_de_socios(id)"),
+                (TipoDeArquivo::Estabelecimentos.table_name(),"ADD CONSTRAINT FK_EstabEmp FOREIGN KEY (cnpj_basico) REFERENCES empresas(cnpj_basico)"),
+                (table_name,"ADD FULLTEXT INDEX empresas_busca_razao_social (razao_social)"),
+            ]     
+        },
+        TipoDeArquivo::Socios => {
+            vec![
+                (table_name,"ADD COLUMN id INT UNSIGNED AUTO_INCREMENT PRIMARY KEY FIRST"),
+                (table_name,"ADD INDEX socios_cnpj_basico (cnpj_basico)"),
+                (table_name,"ADD INDEX socios_nome_ou_razao_social_do_socio (nome_ou_razao_social_do_socio)"),
+                (table_name,"ADD INDEX socios_cnpj_ou_cpf_do_socio (cnpj_ou_cpf_do_socio)"),
+                (table_name,"ADD INDEX socios_cpf_do_representante_legal (cpf_do_representante_legal)")
+            ]
+        },
+        TipoDeArquivo::Simples => {
+            vec![
+                (table_name,"ADD PRIMARY KEY (cnpj_basico)")
+            ]
+        },
+        _ => Vec::new(),
+    }
+}
+
 pub struct Database<'a> {
     db_connection: MysqlConnection,
     config: &'a Config<'a>,
@@ -33,6 +124,17 @@ impl<'a> Database<'a> {
         ];
         self.sql_queries(queries, true); 
             
+        if self.config.staging() {
+            if self.config.is_first_staging_part() {
+                self.create_staging_table();
+
+                if self.config.drop_indexes() {
+                    self.drop_indexes_and_primary_keys();
+                }
+            }
+            return;
+        }
+
         if self.config.truncate_table() && self.config.is_first_file_number() {
             self.truncate_table(table_name);
         }
@@ -47,7 +149,7 @@ impl<'a> Database<'a> {
 
     }
 
-    pub fn after_table_update(&self) {
+    pub fn after_table_update(&self) -> Result<(), String> {
 
         // let table_name = self.config.tipo_de_arquivo().table_name();
         // 
@@ -61,6 +163,13 @@ impl<'a> Database<'a> {
 
         self.commit();
 
+        // na importação com --staging, a tabela de carga substitui a original ao final da última parte
+        let result = if self.config.staging() && self.config.is_last_file_number() {
+            self.validate_staging_table().and_then(|()| self.swap_staging_table())
+        } else {
+            Ok(())
+        };
+
         // Habilitando novamente algumas coisas que foram desabilitadas antes de iniciar o processamento do arquivo
         let queries = vec![
             "SET foreign_key_checks = 1", // Habilita a checagem de chaves estrangeiras
@@ -70,6 +179,124 @@ impl<'a> Database<'a> {
 
         self.sql_queries(queries, true);
 
+        result
+    }
+
+    fn staging_table_name(&self) -> String {
+        staging_table_name(self.config.tipo_de_arquivo().table_name())
+    }
+
+    // Cria a tabela de carga vazia, com a mesma estrutura (colunas e índices) da tabela original
+    fn create_staging_table(&self) {
+        let table_name = self.config.tipo_de_arquivo().table_name();
+        let staging_table_name = self.staging_table_name();
+
+        info!(tabela = table_name, tabela_de_carga = %staging_table_name, "Criando a tabela de carga");
+
+        let drop_table = format!("DROP TABLE IF EXISTS {}", staging_table_name);
+        let create_table = format!("CREATE TABLE {} LIKE {}", staging_table_name, table_name);
+        self.sql_queries(vec![drop_table.as_str(), create_table.as_str()], true);
+    }
+
+    fn count_rows(&self, table_name: &str) -> Result<i64, String> {
+        sql_query(format!("SELECT COUNT(*) AS total FROM {}", table_name))
+            .get_result::<Contagem>(&self.db_connection)
+            .map(|contagem| contagem.total)
+            .map_err(|error| format!("Erro ao contar os registros da tabela {}: {:?}", table_name, error))
+    }
+
+    // Verifica se a tabela de carga pode substituir a tabela original: ela não pode estar vazia nem ter
+    // uma redução na quantidade de registros maior do que a permitida (--staging-max-reduction)
+    fn validate_staging_table(&self) -> Result<(), String> {
+        let table_name = self.config.tipo_de_arquivo().table_name();
+        let staging_table_name = self.staging_table_name();
+
+        let staging_rows = self.count_rows(&staging_table_name)?;
+        let rows = self.count_rows(table_name)?;
+        let minimum_rows = rows * (100 - i64::from(self.config.staging_max_reduction().min(100))) / 100;
+
+        info!(
+            tabela = table_name,
+            tabela_de_carga = %staging_table_name,
+            registros = rows,
+            registros_da_tabela_de_carga = staging_rows,
+            "Validando a quantidade de registros da tabela de carga"
+        );
+
+        if staging_rows == 0 {
+            return Err(format!("A tabela de carga {} está vazia e não vai substituir a tabela {}", staging_table_name, table_name));
+        }
+
+        if staging_rows < minimum_rows {
+            return Err(format!(
+                "A tabela de carga {} tem {} registros, menos do que o mínimo de {} registros ({}% a menos que os {} registros da tabela {}), e não vai substituir a tabela. Verifique se todas as partes foram importadas ou utilize --staging-max-reduction.",
+                staging_table_name, staging_rows, minimum_rows, self.config.staging_max_reduction(), rows, table_name
+            ));
+        }
+
+        Ok(())
+    }
+
+    // Substitui a tabela original pela tabela de carga de uma só vez (RENAME TABLE é atômico) e cria as
+    // chaves estrangeiras na nova tabela. Como a checagem das chaves estrangeiras está desativada (vide
+    // before_table_update), a criação delas não percorre os registros.
+    //
+    // A tabela substituída é mantida como <tabela>_anterior (sem as chaves estrangeiras, cujos nomes são
+    // únicos no banco de dados) até a próxima importação bem-sucedida, para que possa ser restaurada.
+    fn swap_staging_table(&self) -> Result<(), String> {
+        let table_name = self.config.tipo_de_arquivo().table_name();
+        let staging_table_name = self.staging_table_name();
+        let previous_table_name = format!("{}_anterior", table_name);
+
+        let foreign_keys: Vec<(&str, &str)> = self
+            .indexes_and_primary_keys()
+            .into_iter()
+            .filter(|(_, line)| is_foreign_key_modification(line))
+            .collect();
+
+        // as chaves estrangeiras da tabela são removidas antes da troca, para que possam ser criadas na nova
+        // tabela; e as de outras tabelas que referenciam a tabela (ex: estabelecimentos -> empresas), para
+        // que sejam recriadas em seguida referenciando a nova tabela
+        let drop_foreign_keys: Vec<(&str, String)> = foreign_keys
+            .iter()
+            .filter_map(|(table, line)| line.split_whitespace().nth(2).map(|name| (*table, format!("DROP FOREIGN KEY {}", name))))
+            .collect();
+        self.alter_table_modifications(
+            drop_foreign_keys.iter().map(|(table, line)| (*table, line.as_str())).collect(),
+            false,
+        );
+
+        info!(tabela = table_name, tabela_de_carga = %staging_table_name, tabela_anterior = %previous_table_name, "Substituindo a tabela pela tabela de carga");
+
+        let drop_previous_table = format!("DROP TABLE IF EXISTS {}", previous_table_name);
+        let rename_tables = format!(
+            "RENAME TABLE {} TO {}, {} TO {}",
+            table_name, previous_table_name, staging_table_name, table_name
+        );
+        self.sql_queries(vec![drop_previous_table.as_str(), rename_tables.as_str()], true);
+
+        // sem as chaves estrangeiras, as APIs passariam a consultar uma tabela incompleta (ex: estabelecimentos
+        // sem a chave para empresas), então a importação termina com erro
+        let failed_foreign_keys: Vec<String> = foreign_keys
+            .into_iter()
+            .filter_map(|(table, line)| {
+                let query = format!("ALTER TABLE {} {}", table, line);
+                sql_query(&query).execute(&self.db_connection).err().map(|error| {
+                    error!(tabela = table, query = %query, erro = ?error, "Erro ao recriar a chave estrangeira");
+                    format!("{}: {:?}", query, error)
+                })
+            })
+            .collect();
+
+        if !failed_foreign_keys.is_empty() {
+            return Err(format!(
+                "A tabela {} foi substituída pela tabela de carga, mas não foi possível recriar as seguintes chaves estrangeiras: {}",
+                table_name,
+                failed_foreign_keys.join("; ")
+            ));
+        }
+
+        Ok(())
     }
 
     pub fn commit(&self) {
@@ -112,6 +339,7 @@ impl<'a> Database<'a> {
                     (table_name,"DROP FOREIGN KEY FK_EstabMunic"),
                     (table_name,"DROP INDEX FK_EstabMunic"),
                     (table_name,"DROP FOREIGN KEY FK_EstabEmp"),
+                    (table_name,"DROP INDEX estabelecimentos_busca_nome_fantasia"),
                     (table_name,"DROP PRIMARY KEY"),
                 ]
                 
@@ -124,6 +352,7 @@ impl<'a> Database<'a> {
                     (table_name,"DROP FOREIGN KEY FK_EmpQualResp"),
                     (table_name,"DROP INDEX FK_EmpQualResp"),
                     (TipoDeArquivo::Estabelecimentos.table_name(),"DROP FOREIGN KEY FK_EstabEmp"),
+                    (table_name,"DROP INDEX empresas_busca_razao_social"),
                     (table_name,"DROP PRIMARY KEY"),
                 ]                
         
@@ -131,7 +360,10 @@ impl<'a> Database<'a> {
             TipoDeArquivo::Socios => {
                 vec![
                     (table_name,"DROP COLUMN id"),
-                    (table_name,"DROP INDEX socios_cnpj_basico")
+                    (table_name,"DROP INDEX socios_cnpj_basico"),
+                    (table_name,"DROP INDEX socios_nome_ou_razao_social_do_socio"),
+                    (table_name,"DROP INDEX socios_cnpj_ou_cpf_do_socio"),
+                    (table_name,"DROP INDEX socios_cpf_do_representante_legal")
                 ]
             },
             TipoDeArquivo::Simples => {
@@ -140,56 +372,44 @@ impl<'a> Database<'a> {
                 ]
             },
             _ => {
-                println!("Aviso! Não há necessidade de remover os índices da tabela {} pois a quantidade de registros é muito pequena e não haveria nenhum ganho de performance.", table_name);
+                warn!(tabela = table_name, "Não há necessidade de remover os índices da tabela pois a quantidade de registros é muito pequena e não haveria nenhum ganho de performance.");
                 return ();
             }
         };
-        self.alter_table_modifications( alter_table_modifications, false)
+        self.apply_table_modifications(alter_table_modifications)
     }
 
     pub fn add_indexes_and_primary_keys(&self) {
+        let alter_table_modifications = self.indexes_and_primary_keys();
 
-        let table_name = self.config.tipo_de_arquivo().table_name();
+        if alter_table_modifications.is_empty() {
+            warn!(tabela = self.config.tipo_de_arquivo().table_name(), "Não há necessidade de recriar os índices da tabela pois a quantidade de registros é muito pequena e não haveria nenhum ganho de performance.");
+            return;
+        }
 
-        let alter_table_modifications = match self.config.tipo_de_arquivo() {
-            TipoDeArquivo::Estabelecimentos => {
-                vec![
-                    (table_name,"ADD PRIMARY KEY (cnpj_basico, cnpj_ordem, cnpj_dv)"),
-                    (table_name,"ADD CONSTRAINT FK_EstabEmp FOREIGN KEY (cnpj_basico) REFERENCES empresas(cnpj_basico)"),
-                    (table_name,"ADD CONSTRAINT FK_EstabMotivCad FOREIGN KEY (motivo_situacao_cadastral) REFERENCES motivos_de_situacoes_cadastrais(id)"),
-                    (table_name,"ADD CONSTRAINT FK_EstabPais FOREIGN KEY (pais) REFERENCES paises(id)"),
-                    (table_name,"ADD CONSTRAINT FK_EstabCnaePrinc FOREIGN KEY (cnae_fiscal_principal) REFERENCES cnaes(id)"),
-                    (table_name,"ADD CONSTRAINT FK_EstabMunic FOREIGN KEY (municipio) REFERENCES municipios(id)"),
-                    (table_name,"ADD CONSTRAINT FK_EstabSitCad FOREIGN KEY (situacao_cadastral) REFERENCES situacoes_cadastrais(id)")
-                ]                
-            },
-            TipoDeArquivo::Empresas => {
+        self.apply_table_modifications(alter_table_modifications);
+    }
 
-                vec![
-                    (table_name,"ADD PRIMARY KEY (cnpj_basico)"),
-                    (table_name,"ADD CONSTRAINT FK_EmpNatJur FOREIGN KEY (natureza_juridica) REFERENCES naturezas_juridicas(id)"),
-                    (table_name,"ADD CONSTRAINT FK_EmpQualResp FOREIGN KEY (qualificacao_do_responsavel) REFERENCES qualificacoes_de_socios(id)"),
-                    (TipoDeArquivo::Estabelecimentos.table_name(),"ADD CONSTRAINT FK_EstabEmp FOREIGN KEY (cnpj_basico) REFERENCES empresas(cnpj_basico)"),
-                ]     
-            },
-            TipoDeArquivo::Socios => {
-                vec![
-                    (table_name,"ADD COLUMN id INT UNSIGNED AUTO_INCREMENT PRIMARY KEY FIRST"),
-                    (table_name,"ADD INDEX socios_cnpj_basico (cnpj_basico)")
-                ]
-            },
-            TipoDeArquivo::Simples => {
-                vec![
-                    (table_name,"ADD PRIMARY KEY (cnpj_basico)")
-                ]
-            },
-            _ => {
-                println!("Aviso! Não há necessidade de remover os índices da tabela {} pois a quantidade de registros é muito pequena e não haveria nenhum ganho de performance.", self.config.tipo_de_arquivo().table_name());
-                return;
-            }
-        };
-        self.alter_table_modifications(alter_table_modifications, false);
-    }    
+    // Aplica as alterações na tabela ou, na importação com --staging, na tabela de carga (exceto as que
+    // envolvem as chaves estrangeiras e as das outras tabelas)
+    fn apply_table_modifications(&self, alter_table_modifications: Vec<(&str, &str)>) {
+        if !self.config.staging() {
+            return self.alter_table_modifications(alter_table_modifications, false);
+        }
+
+        let table_name = self.config.tipo_de_arquivo().table_name();
+        let staging_table_name = self.staging_table_name();
+        let staging_modifications = alter_table_modifications
+            .into_iter()
+            .filter(|(table, line)| *table == table_name && !is_foreign_key_modification(line))
+            .map(|(_, line)| (staging_table_name.as_str(), line))
+            .collect();
+        self.alter_table_modifications(staging_modifications, false);
+    }
+
+    fn indexes_and_primary_keys(&self) -> Vec<(&str, &str)> {
+        indexes_and_primary_keys_of(self.config.tipo_de_arquivo())
+    }
 
     pub fn truncate_table(&self, table_name: &str) {
         sql_query(format!("TRUNCATE TABLE {}", table_name))
@@ -204,9 +424,10 @@ impl<'a> Database<'a> {
                 .execute(&self.db_connection)
                 .unwrap_or_else(|error| {
                     if panic {
+                        error!(tabela = table_name, query = %query, erro = ?error, "Erro fatal ao alterar a tabela");
                         panic!("Erro fatal! O seguinte erro ocorreu ao executar a query {{{}}}': {:?}", query, error);
                     }
-                    println!("Aviso! O seguinte erro ocorreu ao executar a query {{{}}}': {:?}", query, error);
+                    warn!(tabela = table_name, query = %query, erro = ?error, "Erro ao alterar a tabela");
                     0
                 });
         }
@@ -219,28 +440,43 @@ impl<'a> Database<'a> {
             .execute(&self.db_connection)
             .unwrap_or_else(|error| {
                 if panic {
+                    error!(query, erro = ?error, "Erro fatal ao executar a query");
                     panic!("Erro fatal! O seguinte erro ocorreu ao executar a query {{{}}}': {:?}", query, error);
                 }
-                println!("Aviso! O seguinte erro ocorreu ao executar a query {{{}}}': {:?}", query, error);
+                warn!(query, erro = ?error, "Erro ao executar a query");
                 0
             });    
 
         }
     }
 
+    // Executa a consulta montada sobre a tabela original ou, na importação com --staging, na tabela de carga
+    fn execute<Q>(&self, consulta: Q) -> QueryResult<usize>
+    where
+        Q: RunQueryDsl<MysqlConnection> + QueryFragment<Mysql> + QueryId,
+    {
+        if self.config.staging() {
+            return NaTabelaDeCarga::new(consulta, self.config.tipo_de_arquivo().table_name())
+                .execute(&self.db_connection)
+        }
+        consulta.execute(&self.db_connection)
+    }
+
+    // A tabela foi zerada ou, na importação com --staging, a tabela de carga começou vazia: os registros são
+    // incluídos (INSERT) ao invés de substituídos (REPLACE INTO)
+    pub fn is_table_empty(&self) -> bool {
+        self.config.staging() || self.config.truncate_table() || self.config.empty()
+    }
+
     pub fn upsert_empresa(&self, new_empresa: &Vec<NewEmpresa>, force_upsert: bool) -> QueryResult<usize> {
         use data_models::schema::empresas;
 
-        if !force_upsert && (self.config.truncate_table() || self.config.empty()) {
+        if !force_upsert && self.is_table_empty() {
             // se a tabela foi zerada, então é melhor utilizar o insert ao invés do replace_into
-            return diesel::insert_into(empresas::table)
-                .values(new_empresa)
-                .execute(&self.db_connection)
+            return self.execute(diesel::insert_into(empresas::table).values(new_empresa))
         }
 
-        diesel::replace_into(empresas::table)
-            .values(new_empresa)
-            .execute(&self.db_connection)
+        self.execute(diesel::replace_into(empresas::table).values(new_empresa))
     }
 
     pub fn upsert_estabelecimento(
@@ -250,16 +486,12 @@ impl<'a> Database<'a> {
     ) -> QueryResult<usize> {
         use data_models::schema::estabelecimentos;
 
-        if !force_upsert && (self.config.truncate_table() || self.config.empty()) {
+        if !force_upsert && self.is_table_empty() {
             // se a tabela foi zerada, então é melhor utilizar o insert ao invés do replace_into
-            return diesel::insert_into(estabelecimentos::table)
-                .values(new_estabelecimento)
-                .execute(&self.db_connection)
+            return self.execute(diesel::insert_into(estabelecimentos::table).values(new_estabelecimento))
         }
 
-        diesel::replace_into(estabelecimentos::table)
-            .values(new_estabelecimento)
-            .execute(&self.db_connection)
+        self.execute(diesel::replace_into(estabelecimentos::table).values(new_estabelecimento))
     }
 
     pub fn upsert_socio(
@@ -267,18 +499,14 @@ impl<'a> Database<'a> {
         new_socio: &Vec<NewSocio>,
         force_upsert: bool
     ) -> QueryResult<usize> {
-        use data_models::schema::socios;
-
-        if !force_upsert && (self.config.truncate_table() || self.config.empty()) {
-            // se a tabela foi zerada, então é melhor utilizar o insert ao invés do replace_into
-            return diesel::insert_into(socios::table)
-                .values(new_socio)
-                .execute(&self.db_connection)
+        // a tabela de sócios não tem uma chave única (o id é gerado na inclusão), então o REPLACE INTO
+        // incluiria uma segunda cópia do sócio: os sócios substituídos são removidos pela chave natural
+        // antes de serem incluídos novamente
+        if force_upsert || !self.is_table_empty() {
+            self.execute(diesel::delete(socios::table.filter(mesmos_socios(new_socio))))?;
         }
 
-        diesel::replace_into(socios::table)
-            .values(new_socio)
-            .execute(&self.db_connection)
+        self.execute(diesel::insert_into(socios::table).values(new_socio))
     }
 
     pub fn upsert_simples(
@@ -288,16 +516,12 @@ impl<'a> Database<'a> {
     ) -> QueryResult<usize> {
         use data_models::schema::simples;
 
-        if !force_upsert && (self.config.truncate_table() || self.config.empty()) {
+        if !force_upsert && self.is_table_empty() {
             // se a tabela foi zerada, então é melhor utilizar o insert ao invés do replace_into
-            return diesel::insert_into(simples::table)
-                .values(new_simples)
-                .execute(&self.db_connection)
+            return self.execute(diesel::insert_into(simples::table).values(new_simples))
         }
 
-        diesel::replace_into(simples::table)
-            .values(new_simples)
-            .execute(&self.db_connection)
+        self.execute(diesel::replace_into(simples::table).values(new_simples))
     }
 
     pub fn upsert_cnae(&self, new_cnae: &NewCNAE) -> QueryResult<usize> {
@@ -335,6 +559,12 @@ impl<'a> Database<'a> {
             .execute(&self.db_connection)
     }
 
+    // Preenche novamente o código do IBGE e a UF dos municípios, que são apagados pelo REPLACE INTO
+    // (vide crate::ibge)
+    pub fn update_municipios_ibge(&self) -> QueryResult<usize> {
+        crate::ibge::atualizar_municipios(&self.db_connection)
+    }
+
     pub fn upsert_qualificacoes_de_socios(
         &self,
         new_qualif_socio: &NewQualificacaoDeSocio,
@@ -368,6 +598,46 @@ impl<'a> Database<'a> {
             .execute(&self.db_connection)
     }    
 
+    // Substitui os valores inválidos registrados para o arquivo (vide Import::parse_date)
+    pub fn replace_valores_invalidos(
+        &self,
+        filename: &str,
+        new_valores_invalidos: &Vec<NewValorInvalido>,
+    ) -> QueryResult<usize> {
+        use data_models::schema::valores_invalidos;
+
+        diesel::delete(valores_invalidos::table.filter(valores_invalidos::nome_do_arquivo.eq(filename)))
+            .execute(&self.db_connection)?;
+        diesel::replace_into(valores_invalidos::table)
+            .values(new_valores_invalidos)
+            .execute(&self.db_connection)
+    }
+
+    pub fn delete_valores_invalidos(&self, table_name: &str) -> QueryResult<usize> {
+        use data_models::schema::valores_invalidos;
+
+        diesel::delete(valores_invalidos::table.filter(valores_invalidos::tabela.eq(table_name)))
+            .execute(&self.db_connection)
+    }
+
+    // Soma os registros lidos do arquivo aos da importação atual da tabela (vide crate::qualidade)
+    pub fn add_registros_lidos(&self, table_name: &str, registros_lidos: u64) -> QueryResult<usize> {
+        sql_query(
+            "INSERT INTO leituras_das_tabelas (tabela, registros_lidos) VALUES (?, ?) \
+             ON DUPLICATE KEY UPDATE registros_lidos = registros_lidos + VALUES(registros_lidos)",
+        )
+        .bind::<Text, _>(table_name)
+        .bind::<Unsigned<BigInt>, _>(registros_lidos)
+        .execute(&self.db_connection)
+    }
+
+    pub fn delete_registros_lidos(&self, table_name: &str) -> QueryResult<usize> {
+        use data_models::schema::leituras_das_tabelas;
+
+        diesel::delete(leituras_das_tabelas::table.filter(leituras_das_tabelas::tabela.eq(table_name)))
+            .execute(&self.db_connection)
+    }
+
     pub fn fetch_arquivo_importado(&self,filename: &str) -> QueryResult<ArquivoImportado> {
         use data_models::schema::arquivos_importados;
 
@@ -387,7 +657,7 @@ impl<'a> Database<'a> {
             .execute(&self.db_connection)
     }   
 
-    fn establish_connection() -> MysqlConnection {
+    pub fn establish_connection() -> MysqlConnection {
         dotenv().ok();
 
         let database_url = env::var("DATABASE_URL").expect("DATABASE_URL precisa ser definida!");
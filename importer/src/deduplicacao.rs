@@ -0,0 +1,349 @@
+// Detecção dos registros duplicados (com a mesma chave) nos arquivos das tabelas grandes.
+//
+// As chaves dos registros importados são mantidas durante toda a execução do importer e entre as partes
+// (Y1, Y2, ..., Y0) da mesma tabela, de modo que um registro repetido é detectado mesmo quando as
+// ocorrências estão em partes diferentes ou não são consecutivas.
+//
+// As chaves numéricas (o CNPJ básico das empresas e do simples e o CNPJ completo dos estabelecimentos, cujas
+// partes têm tamanho fixo) são guardadas como números de 64 bits. Com cerca de 60 milhões de
+// estabelecimentos, o HashSet<u64> ocupa cerca de 600 MB (2^26 posições de 8 bytes, mais 1 byte de controle
+// cada) e, durante o seu crescimento, temporariamente o dobro. As chaves dos sócios, que incluem o nome, são guardadas completas (e não como um
+// hash, que poderia colidir e descartar um sócio diferente), ocupando cerca de 100 bytes cada.
+//
+// A política (--duplicates) indica o que é feito com as ocorrências repetidas: mantém a primeira (first),
+// ignorando as seguintes; mantém a última (last), que substitui as anteriores; ou interrompe a importação
+// do arquivo (error).
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PoliticaDeDuplicados {
+    ManterPrimeiro,
+    ManterUltimo,
+    Erro,
+}
+
+impl FromStr for PoliticaDeDuplicados {
+    type Err = String;
+
+    fn from_str(politica: &str) -> Result<Self, Self::Err> {
+        match politica {
+            "first" => Ok(PoliticaDeDuplicados::ManterPrimeiro),
+            "last" => Ok(PoliticaDeDuplicados::ManterUltimo),
+            "error" => Ok(PoliticaDeDuplicados::Erro),
+            _ => Err(format!("Política de duplicados inválida: {} (utilize first, last ou error)", politica)),
+        }
+    }
+}
+
+// Chave de um registro
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Chave {
+    // partes do CNPJ, que têm tamanho fixo, concatenadas
+    Numero(u64),
+    // demais campos, cada um precedido pelo seu tamanho para que campos diferentes não formem a mesma chave
+    Composta(Box<str>),
+}
+
+// Tamanhos das partes do CNPJ (básico, ordem e dígitos verificadores)
+const TAMANHOS_DO_CNPJ: [usize; 3] = [8, 4, 2];
+
+// Chave de um registro identificado pelo CNPJ (básico ou completo). Como as partes têm tamanho fixo, elas
+// são concatenadas em um número sem que partes diferentes formem o mesmo número; as partes fora do tamanho
+// (ou com outros caracteres além dos dígitos) formam uma chave composta.
+pub fn chave_do_cnpj(partes: &[&str]) -> Chave {
+    let tamanho_fixo = partes.len() <= TAMANHOS_DO_CNPJ.len()
+        && partes.iter().zip(TAMANHOS_DO_CNPJ.iter()).all(|(parte, tamanho)| parte.len() == *tamanho);
+    if tamanho_fixo && partes.iter().all(|parte| parte.bytes().all(|byte| byte.is_ascii_digit())) {
+        if let Ok(numero) = partes.concat().parse() {
+            return Chave::Numero(numero);
+        }
+    }
+    chave(partes)
+}
+
+// Chave de um registro a partir dos campos que o identificam
+pub fn chave(campos: &[&str]) -> Chave {
+    let tamanho: usize = campos.iter().map(|campo| campo.len()).sum();
+    let mut composta = String::with_capacity(tamanho + 3 * campos.len());
+    for campo in campos {
+        composta.push_str(&campo.len().to_string());
+        composta.push(':');
+        composta.push_str(campo);
+    }
+    Chave::Composta(composta.into_boxed_str())
+}
+
+// Registro com a mesma chave de outro já importado, com a política error
+#[derive(Debug, PartialEq)]
+pub struct RegistroDuplicado;
+
+pub struct Deduplicacao {
+    politica: PoliticaDeDuplicados,
+    tabela: String,
+    numeros: HashSet<u64>,
+    compostas: HashSet<Box<str>>,
+}
+
+impl Deduplicacao {
+    pub fn new(politica: PoliticaDeDuplicados) -> Deduplicacao {
+        Deduplicacao {
+            politica,
+            tabela: String::new(),
+            numeros: HashSet::new(),
+            compostas: HashSet::new(),
+        }
+    }
+
+    // Inicia a importação de um arquivo da tabela. As chaves são descartadas quando a tabela muda ou quando
+    // a primeira parte é importada novamente.
+    //
+    // Uma parte seguinte (Y2, ..., Y0) só pode ser importada depois das partes anteriores da tabela na mesma
+    // execução, pois os registros delas (que podem se repetir nesta parte) não seriam conhecidos: a política
+    // não seria aplicada a eles e, como os lotes são gravados com INSERT, a importação falharia no primeiro
+    // registro repetido. A exceção é a atualização de uma tabela com a política manter o último, em que todos
+    // os lotes são gravados com REPLACE INTO (substituir_registros).
+    pub fn iniciar_arquivo(&mut self, tabela: &str, primeira_parte: bool, substituir_registros: bool) -> Result<(), String> {
+        if self.tabela == tabela && !primeira_parte {
+            return Ok(());
+        }
+
+        self.tabela = tabela.to_string();
+        self.numeros = HashSet::new();
+        self.compostas = HashSet::new();
+
+        if primeira_parte || (substituir_registros && self.politica == PoliticaDeDuplicados::ManterUltimo) {
+            return Ok(());
+        }
+
+        // a tabela fica sem partes conhecidas, para que as partes seguintes também sejam recusadas
+        self.tabela = String::new();
+        Err(format!(
+            "As partes anteriores da tabela {} não foram importadas nesta execução, então os registros repetidos entre as partes não podem ser detectados (--duplicates). Importe todas as partes da tabela na mesma execução (ex: com -d, utilizando --force para importar novamente as partes já importadas).",
+            tabela
+        ))
+    }
+
+    // Registra a chave, retornando se ela ainda não havia sido importada
+    fn registrar(&mut self, chave: &Chave) -> bool {
+        match chave {
+            Chave::Numero(numero) => self.numeros.insert(*numero),
+            Chave::Composta(composta) => self.compostas.insert(composta.clone()),
+        }
+    }
+}
+
+// Lote de registros a serem gravados no banco de dados
+pub struct Lote<T> {
+    pub registros: Vec<T>,
+    // posição de cada chave em registros, para substituir o registro com a política manter o último
+    posicoes: HashMap<Chave, usize>,
+    // registros que substituem outros já gravados em lotes anteriores (política manter o último)
+    pub substituicoes: Vec<T>,
+    // posição de cada chave em substituicoes, para que cada registro seja substituído uma única vez por lote
+    posicoes_das_substituicoes: HashMap<Chave, usize>,
+}
+
+impl<T> Lote<T> {
+    pub fn new(capacidade: usize) -> Lote<T> {
+        Lote {
+            registros: Vec::with_capacity(capacidade),
+            posicoes: HashMap::with_capacity(capacidade),
+            substituicoes: Vec::new(),
+            posicoes_das_substituicoes: HashMap::new(),
+        }
+    }
+
+    // Adiciona o registro ao lote conforme a política de duplicados, retornando se a chave é duplicada
+    pub fn adicionar(&mut self, chave: Chave, registro: T, deduplicacao: &mut Deduplicacao) -> Result<bool, RegistroDuplicado> {
+        if deduplicacao.registrar(&chave) {
+            self.posicoes.insert(chave, self.registros.len());
+            self.registros.push(registro);
+            return Ok(false);
+        }
+
+        match deduplicacao.politica {
+            PoliticaDeDuplicados::ManterPrimeiro => {}
+            PoliticaDeDuplicados::ManterUltimo => {
+                if let Some(posicao) = self.posicoes.get(&chave) {
+                    self.registros[*posicao] = registro;
+                } else if let Some(posicao) = self.posicoes_das_substituicoes.get(&chave) {
+                    self.substituicoes[*posicao] = registro;
+                } else {
+                    self.posicoes_das_substituicoes.insert(chave, self.substituicoes.len());
+                    self.substituicoes.push(registro);
+                }
+            }
+            PoliticaDeDuplicados::Erro => return Err(RegistroDuplicado),
+        }
+        Ok(true)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.registros.is_empty() && self.substituicoes.is_empty()
+    }
+
+    pub fn limpar(&mut self) {
+        self.registros.clear();
+        self.posicoes.clear();
+        self.substituicoes.clear();
+        self.posicoes_das_substituicoes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // registros e substituições de um lote gravado
+    type LoteGravado = (Vec<String>, Vec<String>);
+
+    // Adiciona os registros (chave, valor) em lotes do tamanho informado, como na importação, retornando os
+    // lotes gravados e a quantidade de duplicados
+    fn importar(politica: PoliticaDeDuplicados, registros: &[(&str, &str)], tamanho_do_lote: usize) -> Result<(Vec<LoteGravado>, usize), RegistroDuplicado> {
+        let mut deduplicacao = Deduplicacao::new(politica);
+        deduplicacao.iniciar_arquivo("empresas", true, false).unwrap();
+
+        let mut lote = Lote::new(tamanho_do_lote);
+        let mut gravados = Vec::new();
+        let mut duplicados = 0;
+        for (chave_do_registro, valor) in registros {
+            if lote.adicionar(chave(&[chave_do_registro]), valor.to_string(), &mut deduplicacao)? {
+                duplicados += 1;
+            }
+            if lote.registros.len() == tamanho_do_lote {
+                gravados.push((lote.registros.clone(), lote.substituicoes.clone()));
+                lote.limpar();
+            }
+        }
+        if !lote.is_empty() {
+            gravados.push((lote.registros.clone(), lote.substituicoes.clone()));
+        }
+        Ok((gravados, duplicados))
+    }
+
+    fn textos(valores: &[&str]) -> Vec<String> {
+        valores.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn manter_o_primeiro() {
+        // no mesmo lote
+        let (gravados, duplicados) = importar(PoliticaDeDuplicados::ManterPrimeiro, &[("1", "a"), ("2", "b"), ("1", "c")], 10).unwrap();
+        assert_eq!(gravados, vec![(textos(&["a", "b"]), vec![])]);
+        assert_eq!(duplicados, 1);
+
+        // em lotes diferentes
+        let (gravados, duplicados) = importar(PoliticaDeDuplicados::ManterPrimeiro, &[("1", "a"), ("2", "b"), ("1", "c"), ("3", "d")], 2).unwrap();
+        assert_eq!(gravados, vec![(textos(&["a", "b"]), vec![]), (textos(&["d"]), vec![])]);
+        assert_eq!(duplicados, 1);
+    }
+
+    #[test]
+    fn manter_o_ultimo() {
+        // no mesmo lote o registro é substituído na sua posição
+        let (gravados, duplicados) = importar(PoliticaDeDuplicados::ManterUltimo, &[("1", "a"), ("2", "b"), ("1", "c")], 10).unwrap();
+        assert_eq!(gravados, vec![(textos(&["c", "b"]), vec![])]);
+        assert_eq!(duplicados, 1);
+
+        // em lotes diferentes o registro é gravado como substituição do que já foi gravado, uma única vez por lote
+        let (gravados, duplicados) = importar(PoliticaDeDuplicados::ManterUltimo, &[("1", "a"), ("2", "b"), ("1", "c"), ("3", "d"), ("1", "e")], 2).unwrap();
+        assert_eq!(gravados, vec![(textos(&["a", "b"]), vec![]), (textos(&["d"]), textos(&["e"]))]);
+        assert_eq!(duplicados, 2);
+    }
+
+    #[test]
+    fn erro_nos_duplicados() {
+        assert_eq!(importar(PoliticaDeDuplicados::Erro, &[("1", "a"), ("2", "b"), ("1", "c")], 10).unwrap_err(), RegistroDuplicado);
+        assert_eq!(importar(PoliticaDeDuplicados::Erro, &[("1", "a"), ("2", "b"), ("1", "c")], 2).unwrap_err(), RegistroDuplicado);
+
+        let (gravados, duplicados) = importar(PoliticaDeDuplicados::Erro, &[("1", "a"), ("2", "b")], 10).unwrap();
+        assert_eq!(gravados, vec![(textos(&["a", "b"]), vec![])]);
+        assert_eq!(duplicados, 0);
+    }
+
+    #[test]
+    fn chaves_entre_as_partes_da_tabela() {
+        let mut deduplicacao = Deduplicacao::new(PoliticaDeDuplicados::ManterPrimeiro);
+        assert!(deduplicacao.iniciar_arquivo("empresas", true, false).is_ok());
+        assert!(deduplicacao.registrar(&chave(&["12345678"])));
+
+        // a parte seguinte da mesma tabela mantém as chaves
+        assert!(deduplicacao.iniciar_arquivo("empresas", false, false).is_ok());
+        assert!(!deduplicacao.registrar(&chave(&["12345678"])));
+
+        // a parte importada sem as anteriores é recusada, assim como as seguintes
+        assert!(deduplicacao.iniciar_arquivo("simples", false, false).is_err());
+        assert!(deduplicacao.iniciar_arquivo("simples", false, false).is_err());
+
+        // a primeira parte inicia a tabela novamente, sem as chaves da importação anterior
+        assert!(deduplicacao.iniciar_arquivo("empresas", true, false).is_ok());
+        assert!(deduplicacao.registrar(&chave(&["12345678"])));
+    }
+
+    #[test]
+    fn partes_sem_as_anteriores() {
+        // com a política manter o último, a atualização da tabela (REPLACE INTO em todos os lotes) é permitida
+        let mut deduplicacao = Deduplicacao::new(PoliticaDeDuplicados::ManterUltimo);
+        assert!(deduplicacao.iniciar_arquivo("empresas", false, true).is_ok());
+        assert!(deduplicacao.iniciar_arquivo("empresas", false, true).is_ok());
+
+        // mas não a importação em uma tabela zerada ou de carga (INSERT)
+        let mut deduplicacao = Deduplicacao::new(PoliticaDeDuplicados::ManterUltimo);
+        assert!(deduplicacao.iniciar_arquivo("empresas", false, false).is_err());
+
+        // com as demais políticas, os registros das partes anteriores precisam ser conhecidos
+        for politica in [PoliticaDeDuplicados::ManterPrimeiro, PoliticaDeDuplicados::Erro] {
+            let mut deduplicacao = Deduplicacao::new(politica);
+            assert!(deduplicacao.iniciar_arquivo("empresas", false, true).is_err());
+        }
+    }
+
+    #[test]
+    fn duplicados_em_lotes_diferentes_nao_sao_gravados_novamente() {
+        // o registro repetido em um lote seguinte nunca é incluído novamente (o lote é gravado com INSERT):
+        // ele é ignorado, substitui o registro já gravado (REPLACE INTO) ou interrompe a importação
+        let registros = [("1", "a"), ("2", "b"), ("3", "c"), ("4", "d"), ("2", "e"), ("5", "f")];
+
+        let (gravados, duplicados) = importar(PoliticaDeDuplicados::ManterPrimeiro, &registros, 2).unwrap();
+        assert_eq!(
+            gravados,
+            vec![(textos(&["a", "b"]), vec![]), (textos(&["c", "d"]), vec![]), (textos(&["f"]), vec![])]
+        );
+        assert_eq!(duplicados, 1);
+
+        let (gravados, duplicados) = importar(PoliticaDeDuplicados::ManterUltimo, &registros, 2).unwrap();
+        assert_eq!(
+            gravados,
+            vec![(textos(&["a", "b"]), vec![]), (textos(&["c", "d"]), vec![]), (textos(&["f"]), textos(&["e"]))]
+        );
+        assert_eq!(duplicados, 1);
+
+        assert_eq!(importar(PoliticaDeDuplicados::Erro, &registros, 2).unwrap_err(), RegistroDuplicado);
+    }
+
+    #[test]
+    fn chaves_numericas_e_compostas() {
+        assert_eq!(chave_do_cnpj(&["12345678", "0001", "95"]), Chave::Numero(12345678000195));
+        assert_eq!(chave_do_cnpj(&["01234567"]), Chave::Numero(1234567));
+
+        // as chaves compostas guardam todos os campos, sem colisões
+        assert_eq!(chave(&["12345678", "FULANO"]), Chave::Composta("8:123456786:FULANO".into()));
+        assert_ne!(chave(&["AB", "C"]), chave(&["A", "BC"]));
+        assert_ne!(chave(&["1:A", ""]), chave(&["1", "A"]));
+    }
+
+    #[test]
+    fn campos_numericos_de_tamanho_variavel_nao_colidem() {
+        // sócios sem documento e sem nome: empresa, identificador, qualificação e data de entrada
+        assert_ne!(
+            chave(&["01234567", "2", "", "", "25", "20200101"]),
+            chave(&["12345672", "2", "", "", "5", "20200101"])
+        );
+
+        // partes do CNPJ fora do tamanho não são concatenadas em um número
+        assert_ne!(chave_do_cnpj(&["1234567", "80001", "95"]), chave_do_cnpj(&["12345678", "0001", "95"]));
+        assert_eq!(chave_do_cnpj(&["1234567"]), chave(&["1234567"]));
+    }
+}
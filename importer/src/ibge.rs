@@ -0,0 +1,307 @@
+// Correspondência (crosswalk) entre os códigos de município da Receita Federal (TOM/SIAFI), utilizados em
+// estabelecimentos.municipio, e os códigos de 7 dígitos do IBGE, carregada por importer ibge <ARQUIVO>.
+//
+// O arquivo é um CSV com cabeçalho, separado por ";" ou ",", em UTF-8 ou ISO-8859-15, com as colunas:
+//
+//   - código do município na RF: codigo_rf ou uma coluna de código com TOM ou SIAFI no nome;
+//   - código do município no IBGE: codigo_ibge ou uma coluna de código com IBGE no nome;
+//   - UF: uf.
+//
+// Assim, além do formato simples (codigo_rf;codigo_ibge;uf), é aceita a Tabela de Órgãos e Municípios
+// (TABMUN) publicada pelo Tesouro Nacional (colunas "CODIGO DO MUNICIPIO - TOM", "CODIGO DO MUNICIPIO -
+// IBGE", ..., "UF"). A tabela municipios_ibge é substituída pelo conteúdo do arquivo e as colunas codigo_ibge
+// e uf dos municípios são atualizadas a partir dela, o que também é feito a cada importação do arquivo de
+// municípios da RF (que substitui os registros da tabela municipios).
+//
+// O arquivo não acompanha o projeto: a TABMUN é obtida junto ao Tesouro Nacional (ou a correspondência junto
+// ao IBGE). Como a tabela municipios_ibge referencia a tabela municipios, os municípios da RF devem ser
+// importados antes; as linhas de municípios que não constam dela são ignoradas, assim como as linhas cujo
+// código do IBGE já foi atribuído a outro município no arquivo.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use chrono::Utc;
+use data_models::models::{NewMetadadosDasTabelas, NewMunicipioIbge};
+use data_models::schema::{metadados_das_tabelas, municipios, municipios_ibge};
+use diesel::mysql::MysqlConnection;
+use diesel::{prelude::*, sql_query};
+use encoding::all::ISO_8859_15;
+use encoding::{DecoderTrap, Encoding};
+use tracing::{info, warn};
+
+use crate::database::Database;
+
+// quantidade de registros gravados por INSERT
+const REGISTROS_POR_INSERT: usize = 1000;
+
+// código de cada UF no IBGE, que corresponde aos 2 primeiros dígitos do código dos seus municípios
+const CODIGOS_DAS_UFS: [(u32, &str); 27] = [
+    (11, "RO"), (12, "AC"), (13, "AM"), (14, "RR"), (15, "PA"), (16, "AP"), (17, "TO"),
+    (21, "MA"), (22, "PI"), (23, "CE"), (24, "RN"), (25, "PB"), (26, "PE"), (27, "AL"), (28, "SE"), (29, "BA"),
+    (31, "MG"), (32, "ES"), (33, "RJ"), (35, "SP"),
+    (41, "PR"), (42, "SC"), (43, "RS"),
+    (50, "MS"), (51, "MT"), (52, "GO"), (53, "DF"),
+];
+
+struct Colunas {
+    codigo_rf: usize,
+    codigo_ibge: usize,
+    uf: usize,
+}
+
+impl Colunas {
+    // Identifica as colunas pelos nomes do cabeçalho (em maiúsculas e sem os acentos de "código" e "município")
+    fn do_cabecalho(cabecalho: &csv::StringRecord) -> Result<Colunas, String> {
+        let nomes: Vec<String> = cabecalho
+            .iter()
+            .map(|nome| nome.trim().to_uppercase().replace('Ó', "O").replace('Í', "I"))
+            .collect();
+        let coluna = |descricao: &str, corresponde: &dyn Fn(&str) -> bool| {
+            nomes
+                .iter()
+                .position(|nome| corresponde(nome))
+                .ok_or_else(|| format!("O arquivo não possui a coluna {} (cabeçalho: {})", descricao, nomes.join(", ")))
+        };
+
+        Ok(Colunas {
+            codigo_rf: coluna("do código da RF", &|nome| {
+                nome == "CODIGO_RF" || (nome.starts_with("COD") && (nome.contains("TOM") || nome.contains("SIAFI")))
+            })?,
+            codigo_ibge: coluna("do código do IBGE", &|nome| {
+                nome == "CODIGO_IBGE" || (nome.starts_with("COD") && nome.contains("IBGE"))
+            })?,
+            uf: coluna("da UF", &|nome| nome == "UF")?,
+        })
+    }
+}
+
+// Correspondências lidas do arquivo, por código da RF, sem repetir o código do IBGE
+#[derive(Default)]
+struct Correspondencias {
+    por_codigo_rf: BTreeMap<u16, NewMunicipioIbge>,
+    // código da RF ao qual cada código do IBGE foi atribuído
+    por_codigo_ibge: HashMap<u32, u16>,
+}
+
+impl Correspondencias {
+    // Adiciona a correspondência. Quando o código da RF se repete a última linha é utilizada, retornando o
+    // código do IBGE substituído; quando o código do IBGE já foi atribuído a outro município, a linha é recusada.
+    fn adicionar(&mut self, municipio: NewMunicipioIbge) -> Result<Option<u32>, String> {
+        match self.por_codigo_ibge.get(&municipio.codigo_ibge) {
+            Some(codigo_rf) if *codigo_rf != municipio.municipio => {
+                return Err(format!(
+                    "O código do IBGE {} já foi atribuído ao código da RF {}",
+                    municipio.codigo_ibge, codigo_rf
+                ))
+            }
+            _ => {}
+        }
+
+        self.por_codigo_ibge.insert(municipio.codigo_ibge, municipio.municipio);
+        let codigo_ibge = municipio.codigo_ibge;
+        match self.por_codigo_rf.insert(municipio.municipio, municipio) {
+            Some(anterior) => {
+                // o código do IBGE substituído fica livre para os outros municípios
+                if anterior.codigo_ibge != codigo_ibge {
+                    self.por_codigo_ibge.remove(&anterior.codigo_ibge);
+                }
+                Ok(Some(anterior.codigo_ibge))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+// Carrega a correspondência entre os códigos de município da RF e do IBGE a partir do arquivo
+pub fn importar(arquivo: &Path) -> Result<(), String> {
+
+    let bytes = fs::read(arquivo).map_err(|error| format!("Erro ao ler o arquivo {}: {}", arquivo.display(), error))?;
+    let texto = match String::from_utf8(bytes) {
+        Ok(texto) => texto,
+        Err(error) => ISO_8859_15
+            .decode(error.as_bytes(), DecoderTrap::Strict)
+            .map_err(|error| format!("Erro ao decodificar o arquivo {}: {}", arquivo.display(), error))?,
+    };
+    let texto = texto.trim_start_matches('\u{feff}');
+
+    let delimitador = match texto.lines().next() {
+        Some(linha) if linha.contains(';') => b';',
+        _ => b',',
+    };
+    let mut leitor = csv::ReaderBuilder::new()
+        .delimiter(delimitador)
+        .flexible(true)
+        .from_reader(texto.as_bytes());
+
+    let colunas = Colunas::do_cabecalho(
+        leitor
+            .headers()
+            .map_err(|error| format!("Erro ao ler o cabeçalho do arquivo {}: {}", arquivo.display(), error))?,
+    )?;
+
+    let mut correspondencias = Correspondencias::default();
+    let mut invalidos = 0;
+    for (posicao, registro) in leitor.records().enumerate() {
+        // a linha 1 é o cabeçalho
+        let linha = posicao + 2;
+        let registro = registro.map_err(|error| format!("Erro ao ler a linha {} do arquivo {}: {}", linha, arquivo.display(), error))?;
+        let campo = |coluna: usize| registro.get(coluna).unwrap_or_default().trim();
+
+        let codigo_rf = campo(colunas.codigo_rf);
+        match correspondencia(codigo_rf, campo(colunas.codigo_ibge), campo(colunas.uf)).and_then(|municipio| correspondencias.adicionar(municipio)) {
+            Ok(Some(_)) => warn!(linha, codigo_rf, "Código da RF repetido no arquivo; a última linha é utilizada"),
+            Ok(None) => {}
+            Err(erro) => {
+                invalidos += 1;
+                warn!(linha, erro = %erro, "Linha inválida ignorada");
+            }
+        }
+    }
+
+    if correspondencias.por_codigo_rf.is_empty() {
+        return Err(format!("O arquivo {} não possui nenhuma correspondência válida", arquivo.display()));
+    }
+
+    // a tabela municipios_ibge referencia a tabela municipios, então os municípios que não constam dela são
+    // ignorados
+    let db_connection = Database::establish_connection();
+    let municipios_da_rf: HashSet<u16> = municipios::table
+        .select(municipios::id)
+        .load::<u16>(&db_connection)
+        .map_err(|error| format!("Erro ao ler os municípios da RF: {:?}", error))?
+        .into_iter()
+        .collect();
+    if municipios_da_rf.is_empty() {
+        return Err(String::from("Nenhum município da RF foi importado; importe o arquivo de municípios antes da correspondência"));
+    }
+    let (registros, fora_da_rf): (Vec<NewMunicipioIbge>, Vec<NewMunicipioIbge>) = correspondencias
+        .por_codigo_rf
+        .into_values()
+        .partition(|municipio| municipios_da_rf.contains(&municipio.municipio));
+    for municipio in &fora_da_rf {
+        warn!(codigo_rf = municipio.municipio, codigo_ibge = municipio.codigo_ibge, "Município que não consta dos municípios da RF ignorado");
+    }
+
+    let municipios = db_connection
+        .transaction::<_, diesel::result::Error, _>(|| {
+            diesel::delete(municipios_ibge::table).execute(&db_connection)?;
+            for bloco in registros.chunks(REGISTROS_POR_INSERT) {
+                diesel::insert_into(municipios_ibge::table)
+                    .values(bloco)
+                    .execute(&db_connection)?;
+            }
+            let municipios = atualizar_municipios(&db_connection)?;
+
+            // a data/hora de importação indica aos servidores que os municípios foram alterados
+            let agora = Utc::now().naive_utc();
+            diesel::replace_into(metadados_das_tabelas::table)
+                .values(&NewMetadadosDasTabelas {
+                    tabela: "municipios",
+                    data_hora_de_atualizacao: agora,
+                    data_hora_de_importacao: agora,
+                })
+                .execute(&db_connection)?;
+
+            Ok(municipios)
+        })
+        .map_err(|error| format!("Erro ao gravar a correspondência dos códigos de município: {:?}", error))?;
+
+    let sem_correspondencia = contar_sem_correspondencia(&db_connection)
+        .map_err(|error| format!("Erro ao contar os municípios sem o código do IBGE: {:?}", error))?;
+    if sem_correspondencia > 0 {
+        warn!(municipios = sem_correspondencia, "Municípios da RF sem o código do IBGE no arquivo");
+    }
+    info!(
+        arquivo = %arquivo.display(),
+        linhas_invalidas = invalidos,
+        municipios_fora_da_rf = fora_da_rf.len(),
+        municipios_atualizados = municipios,
+        "Correspondência dos códigos de município da RF e do IBGE carregada"
+    );
+    Ok(())
+}
+
+// Atualiza as colunas codigo_ibge e uf dos municípios a partir da tabela municipios_ibge
+pub fn atualizar_municipios(db_connection: &MysqlConnection) -> QueryResult<usize> {
+    sql_query(
+        "UPDATE municipios m LEFT JOIN municipios_ibge c ON c.municipio = m.id \
+         SET m.codigo_ibge = c.codigo_ibge, m.uf = c.uf",
+    )
+    .execute(db_connection)
+}
+
+fn contar_sem_correspondencia(db_connection: &MysqlConnection) -> QueryResult<i64> {
+    municipios::table
+        .filter(municipios::codigo_ibge.is_null())
+        .count()
+        .get_result(db_connection)
+}
+
+// Valida uma linha do arquivo: o código da RF é numérico, o do IBGE tem 7 dígitos e os 2 primeiros
+// correspondem ao código da UF
+fn correspondencia(codigo_rf: &str, codigo_ibge: &str, uf: &str) -> Result<NewMunicipioIbge, String> {
+    let municipio: u16 = codigo_rf
+        .parse()
+        .map_err(|_| format!("Código da RF inválido: {:?}", codigo_rf))?;
+    let codigo: u32 = match codigo_ibge.parse() {
+        Ok(codigo) if codigo_ibge.len() == 7 => codigo,
+        _ => return Err(format!("Código do IBGE inválido (deve conter 7 dígitos): {:?}", codigo_ibge)),
+    };
+    let uf = uf.to_uppercase();
+    match CODIGOS_DAS_UFS.iter().find(|(codigo_da_uf, _)| *codigo_da_uf == codigo / 100_000) {
+        Some((_, uf_do_codigo)) if *uf_do_codigo == uf => {}
+        Some((_, uf_do_codigo)) => return Err(format!("O código do IBGE {} é da UF {} e não de {:?}", codigo, uf_do_codigo, uf)),
+        None => return Err(format!("O código do IBGE {} não corresponde a nenhuma UF", codigo)),
+    }
+
+    Ok(NewMunicipioIbge {
+        municipio,
+        codigo_ibge: codigo,
+        uf,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn municipio(codigo_rf: &str, codigo_ibge: &str, uf: &str) -> NewMunicipioIbge {
+        correspondencia(codigo_rf, codigo_ibge, uf).unwrap()
+    }
+
+    #[test]
+    fn linhas_validas_e_invalidas() {
+        let sao_paulo = municipio("7107", "3550308", "sp");
+        assert_eq!((sao_paulo.municipio, sao_paulo.codigo_ibge, sao_paulo.uf.as_str()), (7107, 3550308, "SP"));
+
+        assert!(correspondencia("ABC", "3550308", "SP").is_err());
+        assert!(correspondencia("7107", "355030", "SP").is_err());
+        assert!(correspondencia("7107", "3550308", "RJ").is_err());
+        assert!(correspondencia("7107", "9950308", "SP").is_err());
+    }
+
+    #[test]
+    fn codigo_do_ibge_repetido_e_recusado() {
+        let mut correspondencias = Correspondencias::default();
+        assert_eq!(correspondencias.adicionar(municipio("7107", "3550308", "SP")), Ok(None));
+
+        // outro código da RF com o mesmo código do IBGE: a linha é recusada e a correspondência anterior mantida
+        assert!(correspondencias.adicionar(municipio("7108", "3550308", "SP")).is_err());
+        assert_eq!(correspondencias.por_codigo_rf.len(), 1);
+        assert_eq!(correspondencias.por_codigo_rf[&7107].codigo_ibge, 3550308);
+    }
+
+    #[test]
+    fn codigo_da_rf_repetido_utiliza_a_ultima_linha() {
+        let mut correspondencias = Correspondencias::default();
+        assert_eq!(correspondencias.adicionar(municipio("7107", "3550308", "SP")), Ok(None));
+        assert_eq!(correspondencias.adicionar(municipio("7107", "3509502", "SP")), Ok(Some(3550308)));
+        assert_eq!(correspondencias.por_codigo_rf[&7107].codigo_ibge, 3509502);
+
+        // o código do IBGE substituído pode ser atribuído a outro município
+        assert_eq!(correspondencias.adicionar(municipio("7108", "3550308", "SP")), Ok(None));
+        assert!(correspondencias.adicionar(municipio("7109", "3509502", "SP")).is_err());
+    }
+}
@@ -1,20 +1,28 @@
+use std::collections::BTreeMap;
 use std::error::Error;
-use std::io::{self, Write};
+use std::io;
 use std::str::FromStr;
 use std::time::Instant;
 
 use csv::Reader;
+use diesel::QueryResult;
 
 use bigdecimal::BigDecimal;
 use chrono::{FixedOffset, NaiveDate, TimeZone, Utc};
 use encoding::all::ISO_8859_15;
 use encoding::{DecoderTrap, Encoding};
 use serde_derive::Deserialize;
+use tracing::{debug, error, info, info_span, Span};
 use zip::read::ZipFile;
 
 use crate::config::Config;
 use crate::database::Database;
+use crate::deduplicacao::{chave, chave_do_cnpj, Chave, Deduplicacao, Lote, RegistroDuplicado};
+use crate::relatorio::{Relatorio, RelatorioDoArquivo, SituacaoDoArquivo};
 use crate::tipo_de_arquivo::TipoDeArquivo;
+use data_models::dominios::{FaixaEtaria, IdentificadorDeSocio, MatrizFilial, OpcaoSimples, Porte, SituacaoCadastral};
+use data_models::contatos::{email_valido, normalizar_email, telefone_e164};
+use data_models::enderecos::{cep_valido, EnderecoNormalizado};
 use data_models::models::*;
 
 // nos campos razao_social e ente_federativo_responsavel foi necessario o uso do serde_bytes
@@ -114,47 +122,108 @@ struct SimplesCsvRecord {
     data_de_exclusao_do_mei: Option<String>,
 }
 
-fn naive_date_from_str(date_option: Option<String>) -> Option<NaiveDate> {
-    let date_str = match date_option {
-        Some(v) => v,
-        None => return None,
-    };
+fn naive_date_from_str(date_option: Option<&str>) -> Option<NaiveDate> {
+    let date_str = date_option?;
 
-    let parsed_date = NaiveDate::parse_from_str(&date_str, "%Y%m%d");
-
-    match parsed_date {
-        Ok(v) => Some(v),
-        Err(_e) => None,
-    }
+    NaiveDate::parse_from_str(date_str, "%Y%m%d").ok()
 }
 pub struct Import<'a> {
     config: &'a Config<'a>,
     db: Database<'a>,
+    relatorio: &'a mut Relatorio,
+    deduplicacao: &'a mut Deduplicacao,
+    filename: String,
     start_time: Instant,
+    // registros incluídos/atualizados na tabela
     num_records: usize,
+    num_records_read: usize,
+    num_duplicated_records: usize,
+    num_rejected_records: usize,
+    num_batches: usize,
+    // valores inválidos por coluna: quantidade, o primeiro valor encontrado e se o registro foi rejeitado (ou,
+    // senão, se o valor foi gravado como nulo)
+    invalid_values: BTreeMap<&'static str, (u32, String, bool)>,
+    skipped: bool,
 }
 
 impl<'a> Import<'a> {
-    pub fn new(config: &'a Config<'a>) -> Import<'a> {
+    pub fn new(config: &'a Config<'a>, relatorio: &'a mut Relatorio, deduplicacao: &'a mut Deduplicacao) -> Import<'a> {
         let db = Database::new(&config);
         let start_time = Instant::now();
 
         Import {
             config,
             db,
+            relatorio,
+            deduplicacao,
+            filename: String::new(),
             start_time,
             num_records: 0,
+            num_records_read: 0,
+            num_duplicated_records: 0,
+            num_rejected_records: 0,
+            num_batches: 0,
+            invalid_values: BTreeMap::new(),
+            skipped: false,
         }
     }
 
-    pub fn run(&mut self, mut file: ZipFile) -> Result<(), String> {
-        let filename = &*file.name().to_owned();
+    pub fn run(&mut self, file: ZipFile) -> Result<(), String> {
+        self.filename = file.name().to_owned();
+
+        // os eventos registrados durante a importação contêm o arquivo, a tabela e a parte (Y1, Y2, ..., Y0)
+        let _arquivo = info_span!(
+            "arquivo",
+            arquivo = %self.filename,
+            tabela = self.config.tipo_de_arquivo().table_name(),
+            parte = self.config.file_part_number()
+        )
+        .entered();
+
+        let result = self.import_file(file);
+        if let Err(erro) = &result {
+            error!(erro = %erro, "Erro ao importar o arquivo");
+        }
+
+        let situacao = match (&result, self.skipped) {
+            (Err(_), _) => SituacaoDoArquivo::Erro,
+            (Ok(()), true) => SituacaoDoArquivo::Ignorado,
+            (Ok(()), false) => SituacaoDoArquivo::Importado,
+        };
+        let relatorio_do_arquivo = self.relatorio_do_arquivo(situacao, result.as_ref().err().cloned());
+        self.relatorio.registrar(relatorio_do_arquivo);
+
+        result
+    }
+
+    fn import_file(&mut self, mut file: ZipFile) -> Result<(), String> {
+        let filename = &*self.filename.clone();
 
         if self.file_already_imported(filename) {
-            println!("Como o arquivo já foi importando anteriormente, vamos pular ele. Utilize --force para forçar a importação novamente.");
+            info!("Como o arquivo já foi importando anteriormente, vamos pular ele. Utilize --force para forçar a importação novamente.");
+            self.skipped = true;
             return Ok(()); // se for importação de um diretório, vai para o próximo aquivo; senão encerra.
         }
 
+        if self.config.tipo_de_arquivo().is_large_table() {
+            self.deduplicacao.iniciar_arquivo(
+                self.config.tipo_de_arquivo().table_name(),
+                self.config.is_first_staging_part(),
+                !self.db.is_table_empty(),
+            )?;
+        }
+
+        // os valores inválidos registrados nas importações anteriores da tabela são descartados ao importar a
+        // primeira parte, para que o relatório de qualidade considere somente os arquivos da importação atual
+        if self.config.is_first_staging_part() {
+            self.db
+                .delete_valores_invalidos(self.config.tipo_de_arquivo().table_name())
+                .expect("Erro ao remover os registros da tabela de valores inválidos!");
+            self.db
+                .delete_registros_lidos(self.config.tipo_de_arquivo().table_name())
+                .expect("Erro ao remover os registros da tabela de leituras das tabelas!");
+        }
+
         let rdr = csv::ReaderBuilder::new()
             .delimiter(b';')
             .has_headers(false)
@@ -179,7 +248,12 @@ impl<'a> Import<'a> {
 
         match import_table(rdr) {
             Ok(()) => {
-                self.db.after_table_update();
+                self.db.after_table_update()?;
+
+                self.save_invalid_values(filename);
+                self.db
+                    .add_registros_lidos(self.config.tipo_de_arquivo().table_name(), self.num_records_read as u64)
+                    .expect("Erro ao inserir registros na tabela de leituras das tabelas!");
 
                 let duration_in_seconds = self.duration_in_seconds();
 
@@ -199,7 +273,9 @@ impl<'a> Import<'a> {
                     self.db.commit();
                 }
 
-                {
+                // na importação com --staging, os metadados (que indicam aos servidores que os dados foram
+                // atualizados) só são gravados quando a tabela de carga substitui a tabela original
+                if !self.config.staging() || self.config.is_last_file_number() {
                     // pega a data e hora de modificação do arquivo que se encontra dentro do arquivo ZIP
                     // e converte para horário UTC (considerando que esta data/hora do arquivo é GMT-3)
                     let lm = &file.last_modified();
@@ -223,21 +299,15 @@ impl<'a> Import<'a> {
                     self.db.commit();
                 }
 
-                if duration_in_seconds == 0 {
-                    println!(
-                        "{} registros importados em {} milissegundos: {} registros/segundo",
-                        self.num_records,
-                        self.duration_in_millis(),
-                        self.records_per_seconds()
-                    );
-                } else {
-                    println!(
-                        "{} registros importados em {} segundos: {} registros/segundo",
-                        self.num_records,
-                        duration_in_seconds,
-                        self.records_per_seconds()
-                    );
-                }
+                info!(
+                    registros_lidos = self.num_records_read,
+                    registros_inseridos = self.num_records,
+                    registros_duplicados = self.num_duplicated_records,
+                    registros_rejeitados = self.num_rejected_records,
+                    duracao_em_milissegundos = self.duration_in_millis() as u64,
+                    registros_por_segundo = self.records_per_seconds(),
+                    "Arquivo importado"
+                );
             }
             Err(err) => return Err(format!("Erro ao executar: {}", err)),
         }
@@ -249,7 +319,7 @@ impl<'a> Import<'a> {
         match self.db.fetch_arquivo_importado(&filename) {
             Ok(_arquivo) => match self.config.force() {
                 true => {
-                    println!("O arquivo já foi importado anteriormente; mas, como o flag --force foi informado, vamos importá-lo novamente.");
+                    info!("O arquivo já foi importado anteriormente; mas, como o flag --force foi informado, vamos importá-lo novamente.");
                     false
                 }
                 _ => true,
@@ -263,59 +333,60 @@ impl<'a> Import<'a> {
         R: io::Read,
     {
         let mut raw_record = csv::ByteRecord::new();
-        let mut records: Vec<NewEmpresa> = Vec::with_capacity(self.config.rows_per_insert());
-        let mut previous_cnpj_basico: Option<String> = None;
+        let mut batch: Lote<NewEmpresa> = Lote::new(self.config.rows_per_insert());
 
         while rdr.read_byte_record(&mut raw_record)? {
             let record: EmpresaCsvRecord = raw_record.deserialize(None).expect(&format!(
                 "Erro ao deserializar o seguinte registro: {:?}",
                 raw_record
             ));
+            self.num_records_read += 1;
             let razao_social = ISO_8859_15.decode(record.razao_social, DecoderTrap::Strict)?;
             let ente_federativo_responsavel =
                 ISO_8859_15.decode(record.ente_federativo_responsavel, DecoderTrap::Strict)?;
-            let porte_da_empresa = match std::str::from_utf8(record.porte_da_empresa) {
-                Ok(v) => v.to_string(),
-                Err(_e) => "ER".to_string(),
+            // códigos de porte inválidos (ou que não puderam ser decodificados) são gravados como nulos
+            let porte = String::from_utf8_lossy(record.porte_da_empresa);
+            let porte_da_empresa = match Porte::from_str(&porte) {
+                Ok(porte) => Some(porte),
+                Err(_) => {
+                    if !porte.trim().is_empty() {
+                        self.register_invalid_value("porte", &porte);
+                    }
+                    None
+                }
             };
 
-            if !razao_social.is_empty()
-                && previous_cnpj_basico.as_deref() != Some(&record.cnpj_basico)
-            {
+            if razao_social.is_empty() {
                 // se a Razão Social estiver em branco o registro é inválido
-                // ou se o mesmo CNPJ Básico já foi processado, deve ser ignorado senão vai dar chave
-                // duplicada no banco de dados.
-
-                previous_cnpj_basico = Some(record.cnpj_basico.clone());
-
-                records.push(NewEmpresa {
-                    cnpj_basico: record.cnpj_basico,
-                    razao_social,
-                    natureza_juridica: Some(record.natureza_juridica),
-                    qualificacao_do_responsavel: Some(record.qualificacao_do_responsavel),
-                    capital_social: Some(
-                        BigDecimal::from_str(
-                            &record.capital_social_da_empresa.replacen(",", ".", 1),
-                        )
-                        .unwrap(),
-                    ), // arrumar a conversão aqui
-                    porte: Some(porte_da_empresa),
-                    ente_federativo_responsavel: Some(ente_federativo_responsavel),
-                });
-                self.num_records += 1;
+                self.num_rejected_records += 1;
+                continue;
             }
 
-            if records.len() == self.config.rows_per_insert() {
-                self.db
-                    .upsert_empresa(&records, self.num_records < 5) // força um upsert nos primeiros 5 registros, pois os registros dos arquivos a serem importados podem conter linhas repetidas no início do arquivo com relação ao arquivo anterior. Normalmente a primeira linha, mas, por segurança, aqui optei pelas 5 primeiras linhas.
-                    .expect(&format!("Erro ao inserir registros na tabela de empresas!"));
-                records.clear();
+            let key = chave_do_cnpj(&[&record.cnpj_basico]);
+            let new_empresa = NewEmpresa {
+                cnpj_basico: record.cnpj_basico,
+                razao_social,
+                natureza_juridica: Some(record.natureza_juridica),
+                qualificacao_do_responsavel: Some(record.qualificacao_do_responsavel),
+                capital_social: Some(
+                    BigDecimal::from_str(
+                        &record.capital_social_da_empresa.replacen(",", ".", 1),
+                    )
+                    .unwrap(),
+                ), // arrumar a conversão aqui
+                porte: porte_da_empresa,
+                ente_federativo_responsavel: Some(ente_federativo_responsavel),
+            };
+
+            self.add_to_batch(&mut batch, key, new_empresa, rdr.position().line())?;
+
+            if batch.registros.len() == self.config.rows_per_insert() {
+                self.write_batch(&mut batch, Database::upsert_empresa, "Erro ao inserir registros na tabela de empresas!");
                 self.show_progress();
             }
         }
-        self.db
-            .upsert_empresa(&records, false)
-            .expect(&format!("Erro ao inserir registros na tabela de empresas!"));
+
+        self.write_batch(&mut batch, Database::upsert_empresa, "Erro ao inserir registros na tabela de empresas!");
 
         Ok(())
     }
@@ -326,18 +397,14 @@ impl<'a> Import<'a> {
     {
         let mut raw_record = csv::ByteRecord::new();
 
-        let mut records: Vec<NewEstabelecimento> =
-            Vec::with_capacity(self.config.rows_per_insert());
-
-        let mut previous_cnpj_basico: Option<String> = None;
-        let mut previous_cnpj_ordem: Option<String> = None;
-        let mut previous_cnpj_dv: Option<String> = None;
+        let mut batch: Lote<NewEstabelecimento> = Lote::new(self.config.rows_per_insert());
 
         while rdr.read_byte_record(&mut raw_record)? {
             let record: EstabelecimentoCsvRecord = raw_record.deserialize(None).expect(&format!(
                 "Erro ao deserializar o seguinte registro: {:?}",
                 raw_record
             ));
+            self.num_records_read += 1;
             let nome_fantasia = Some(
                 ISO_8859_15
                     .decode(record.nome_fantasia, DecoderTrap::Strict)
@@ -379,71 +446,102 @@ impl<'a> Import<'a> {
                     .unwrap(),
             );
 
-            if previous_cnpj_basico.as_deref() != Some(&record.cnpj_basico)
-                && previous_cnpj_ordem.as_deref() != Some(&record.cnpj_ordem)
-                && previous_cnpj_dv.as_deref() != Some(&record.cnpj_dv)
-            {
-                // se o mesmo CNPJ já foi processado, deve ser ignorado, pois senão vai dar chave
-                // duplicada no banco de dados.
-
-                previous_cnpj_basico = Some(record.cnpj_basico.clone());
-                previous_cnpj_ordem = Some(record.cnpj_ordem.clone());
-                previous_cnpj_dv = Some(record.cnpj_dv.clone());
-
-                records.push(NewEstabelecimento {
-                    cnpj_basico: record.cnpj_basico,
-                    cnpj_ordem: record.cnpj_ordem,
-                    cnpj_dv: record.cnpj_dv,
-                    identificador_matriz_filial: record.identificador_matriz_filial,
-                    nome_fantasia,
-                    situacao_cadastral: record.situacao_cadastral,
-                    data_situacao_cadastral: naive_date_from_str(record.data_situacao_cadastral),
-                    motivo_situacao_cadastral: record.motivo_situacao_cadastral,
-                    nome_da_cidade_no_exterior,
-                    pais: record.pais,
-                    data_de_inicio_da_atividade: naive_date_from_str(
-                        record.data_de_inicio_da_atividade,
-                    ),
-                    cnae_fiscal_principal: record.cnae_fiscal_principal,
-                    cnae_fiscal_secundaria: record.cnae_fiscal_secundaria,
-                    tipo_logradouro,
-                    logradouro,
-                    numero,
-                    complemento,
-                    bairro,
-                    cep: record.cep,
-                    uf: record.uf,
-                    municipio: record.municipio,
-                    ddd1: record.ddd1,
-                    telefone1: record.telefone1,
-                    ddd2: record.ddd2,
-                    telefone2: record.telefone2,
-                    ddd_fax: record.ddd_fax,
-                    telefone_fax: record.telefone_fax,
-                    correio_eletronico,
-                    situacao_especial: record.situacao_especial,
-                    data_situacao_especial: naive_date_from_str(record.data_situacao_especial),
-                });
-
-                self.num_records += 1;
-
-                if records.len() == self.config.rows_per_insert() {
-                    self.db
-                        .upsert_estabelecimento(&records, self.num_records < 5) // força um upsert nos primeiros 5 registros, pois os registros dos arquivos a serem importados podem conter linhas repetidas no início do arquivo com relação ao arquivo anterior. Normalmente a primeira linha, mas, por segurança, aqui optei pelas 5 primeiras linhas.
-                        .expect(&format!(
-                            "Erro ao inserir registros na tabela de estabelecimentos!"
-                        ));
-                    records.clear();
-                    self.show_progress();
+            let key = chave_do_cnpj(&[&record.cnpj_basico, &record.cnpj_ordem, &record.cnpj_dv]);
+
+            let (endereco_normalizado, cep_valido) = if self.config.normalize_addresses() {
+                let endereco = EnderecoNormalizado::new(
+                    tipo_logradouro.as_deref(),
+                    logradouro.as_deref(),
+                    numero.as_deref(),
+                    complemento.as_deref(),
+                    bairro.as_deref(),
+                    record.cep.as_deref(),
+                );
+                (
+                    endereco.linha(record.uf.as_deref()),
+                    record.cep.as_deref().map(|cep| cep_valido(cep, record.uf.as_deref().unwrap_or_default())),
+                )
+            } else {
+                (None, None)
+            };
+
+            let (telefone1_e164, telefone2_e164, telefone_fax_e164, email_normalizado) = if self.config.normalize_contacts() {
+                (
+                    telefone_e164(record.ddd1.as_deref(), record.telefone1.as_deref()),
+                    telefone_e164(record.ddd2.as_deref(), record.telefone2.as_deref()),
+                    telefone_e164(record.ddd_fax.as_deref(), record.telefone_fax.as_deref()),
+                    correio_eletronico.as_deref().and_then(normalizar_email),
+                )
+            } else {
+                (None, None, None, None)
+            };
+            let email_valido = email_normalizado.as_deref().map(email_valido);
+
+            // os registros com códigos fora do domínio são rejeitados
+            let identificador_matriz_filial = match MatrizFilial::from_str(&record.identificador_matriz_filial) {
+                Ok(v) => v,
+                Err(_) => {
+                    self.reject_invalid_value("identificador_matriz_filial", &record.identificador_matriz_filial);
+                    continue;
                 }
+            };
+            let situacao_cadastral = match SituacaoCadastral::from_codigo(record.situacao_cadastral) {
+                Some(v) => v,
+                None => {
+                    self.reject_invalid_value("situacao_cadastral", &record.situacao_cadastral.to_string());
+                    continue;
+                }
+            };
+
+            let new_estabelecimento = NewEstabelecimento {
+                cnpj_basico: record.cnpj_basico,
+                cnpj_ordem: record.cnpj_ordem,
+                cnpj_dv: record.cnpj_dv,
+                identificador_matriz_filial,
+                nome_fantasia,
+                situacao_cadastral,
+                data_situacao_cadastral: self.parse_date("data_situacao_cadastral", record.data_situacao_cadastral),
+                motivo_situacao_cadastral: record.motivo_situacao_cadastral,
+                nome_da_cidade_no_exterior,
+                pais: record.pais,
+                data_de_inicio_da_atividade: self.parse_date("data_de_inicio_da_atividade", record.data_de_inicio_da_atividade),
+                cnae_fiscal_principal: record.cnae_fiscal_principal,
+                cnae_fiscal_secundaria: record.cnae_fiscal_secundaria,
+                tipo_logradouro,
+                logradouro,
+                numero,
+                complemento,
+                bairro,
+                cep: record.cep,
+                uf: record.uf,
+                municipio: record.municipio,
+                ddd1: record.ddd1,
+                telefone1: record.telefone1,
+                ddd2: record.ddd2,
+                telefone2: record.telefone2,
+                ddd_fax: record.ddd_fax,
+                telefone_fax: record.telefone_fax,
+                correio_eletronico,
+                situacao_especial: record.situacao_especial,
+                data_situacao_especial: self.parse_date("data_situacao_especial", record.data_situacao_especial),
+                endereco_normalizado,
+                cep_valido,
+                telefone1_e164,
+                telefone2_e164,
+                telefone_fax_e164,
+                email_normalizado,
+                email_valido,
+            };
+
+            self.add_to_batch(&mut batch, key, new_estabelecimento, rdr.position().line())?;
+
+            if batch.registros.len() == self.config.rows_per_insert() {
+                self.write_batch(&mut batch, Database::upsert_estabelecimento, "Erro ao inserir registros na tabela de estabelecimentos!");
+                self.show_progress();
             }
         }
 
-        self.db
-            .upsert_estabelecimento(&records, false)
-            .expect(&format!(
-                "Erro ao inserir registros na tabela de estabelecimentos!"
-            ));
+        self.write_batch(&mut batch, Database::upsert_estabelecimento, "Erro ao inserir registros na tabela de estabelecimentos!");
 
         Ok(())
     }
@@ -454,50 +552,79 @@ impl<'a> Import<'a> {
     {
         let mut raw_record = csv::ByteRecord::new();
 
-        let mut records: Vec<NewSocio> = Vec::with_capacity(self.config.rows_per_insert());
+        let mut batch: Lote<NewSocio> = Lote::new(self.config.rows_per_insert());
 
         while rdr.read_byte_record(&mut raw_record)? {
             let record: SocioCsvRecord = raw_record.deserialize(None).expect(&format!(
                 "Erro ao deserializar o seguinte registro: {:?}",
                 raw_record
             ));
+            self.num_records_read += 1;
+
+            // os registros com códigos fora do domínio são rejeitados
+            let identificador_de_socio = match IdentificadorDeSocio::from_codigo(record.identificador_de_socio) {
+                Some(v) => v,
+                None => {
+                    self.reject_invalid_value("identificador_de_socio", &record.identificador_de_socio.to_string());
+                    continue;
+                }
+            };
+            let faixa_etaria_do_socio = match FaixaEtaria::from_codigo(record.faixa_etaria_do_socio) {
+                Some(v) => v,
+                None => {
+                    self.reject_invalid_value("faixa_etaria_do_socio", &record.faixa_etaria_do_socio.to_string());
+                    continue;
+                }
+            };
+            // a data de entrada na sociedade é obrigatória e faz parte da chave do sócio
+            let data_de_entrada_na_sociedade = match naive_date_from_str(Some(&record.data_de_entrada_na_sociedade)) {
+                Some(v) => v,
+                None => {
+                    self.reject_invalid_value("data_de_entrada_na_sociedade", &record.data_de_entrada_na_sociedade);
+                    continue;
+                }
+            };
+
+            let nome_ou_razao_social_do_socio = ISO_8859_15
+                .decode(record.nome_ou_razao_social_do_socio, DecoderTrap::Strict)
+                .unwrap();
 
-            records.push(NewSocio {
+            // a tabela de sócios não tem uma chave nos arquivos, então o sócio é identificado pela empresa,
+            // pelo tipo, documento e nome do sócio, pela qualificação e pela data de entrada na sociedade
+            let key = chave(&[
+                &record.cnpj_basico,
+                &record.identificador_de_socio.to_string(),
+                record.cnpj_ou_cpf_do_socio.as_deref().unwrap_or_default(),
+                &nome_ou_razao_social_do_socio,
+                &record.qualificacao_do_socio.to_string(),
+                &record.data_de_entrada_na_sociedade,
+            ]);
+
+            let new_socio = NewSocio {
                 cnpj_basico: record.cnpj_basico,
-                identificador_de_socio: record.identificador_de_socio,
-                nome_ou_razao_social_do_socio: ISO_8859_15
-                    .decode(record.nome_ou_razao_social_do_socio, DecoderTrap::Strict)
-                    .unwrap(),
+                identificador_de_socio,
+                nome_ou_razao_social_do_socio,
                 cnpj_ou_cpf_do_socio: record.cnpj_ou_cpf_do_socio,
                 qualificacao_do_socio: record.qualificacao_do_socio,
-                data_de_entrada_na_sociedade: NaiveDate::parse_from_str(
-                    &record.data_de_entrada_na_sociedade,
-                    "%Y%m%d",
-                )
-                .unwrap(),
+                data_de_entrada_na_sociedade,
                 pais_do_socio: record.pais_do_socio,
                 cpf_do_representante_legal: record.cpf_do_representante_legal,
                 nome_do_representante_legal: ISO_8859_15
                     .decode(record.nome_do_representante_legal, DecoderTrap::Strict)
                     .unwrap(),
                 qualificacao_do_representante_legal: record.qualificacao_do_representante_legal,
-                faixa_etaria_do_socio: record.faixa_etaria_do_socio,
-            });
+                faixa_etaria_do_socio,
+            };
 
-            self.num_records += 1;
+            self.add_to_batch(&mut batch, key, new_socio, rdr.position().line())?;
 
-            if records.len() == self.config.rows_per_insert() {
-                self.db
-                    .upsert_socio(&records, self.num_records < 5) // força um upsert nos primeiros 5 registros, pois os registros dos arquivos a serem importados podem conter linhas repetidas no início do arquivo com relação ao arquivo anterior. Normalmente a primeira linha, mas, por segurança, aqui optei pelas 5 primeiras linhas.
-                    .expect(&format!("Erro ao inserir registros na tabela de socios!"));
-                records.clear();
+            if batch.registros.len() == self.config.rows_per_insert() {
+                self.write_batch(&mut batch, Database::upsert_socio, "Erro ao inserir registros na tabela de socios!");
                 self.show_progress();
             }
         }
 
-        self.db
-            .upsert_socio(&records, false)
-            .expect(&format!("Erro ao inserir registros na tabela de socios!"));
+        self.write_batch(&mut batch, Database::upsert_socio, "Erro ao inserir registros na tabela de socios!");
 
         Ok(())
     }
@@ -508,40 +635,51 @@ impl<'a> Import<'a> {
     {
         let mut raw_record = csv::ByteRecord::new();
 
-        let mut records: Vec<NewSimples> = Vec::with_capacity(self.config.rows_per_insert());
+        let mut batch: Lote<NewSimples> = Lote::new(self.config.rows_per_insert());
 
         while rdr.read_byte_record(&mut raw_record)? {
             let record: SimplesCsvRecord = raw_record.deserialize(None).expect(&format!(
                 "Erro ao deserializar o seguinte registro: {:?}",
                 raw_record
             ));
+            self.num_records_read += 1;
+
+            // os registros com códigos fora do domínio são rejeitados
+            let opcao_pelo_simples = match OpcaoSimples::from_str(&record.opcao_pelo_simples) {
+                Ok(v) => v,
+                Err(_) => {
+                    self.reject_invalid_value("opcao_pelo_simples", &record.opcao_pelo_simples);
+                    continue;
+                }
+            };
+            let opcao_pelo_mei = match OpcaoSimples::from_str(&record.opcao_pelo_mei) {
+                Ok(v) => v,
+                Err(_) => {
+                    self.reject_invalid_value("opcao_pelo_mei", &record.opcao_pelo_mei);
+                    continue;
+                }
+            };
 
-            records.push(NewSimples {
+            let key = chave_do_cnpj(&[&record.cnpj_basico]);
+            let new_simples = NewSimples {
                 cnpj_basico: record.cnpj_basico,
-                opcao_pelo_simples: record.opcao_pelo_simples,
-                data_de_opcao_pelo_simples: naive_date_from_str(record.data_de_opcao_pelo_simples),
-                data_de_exclusao_do_simples: naive_date_from_str(
-                    record.data_de_exclusao_do_simples,
-                ),
-                opcao_pelo_mei: record.opcao_pelo_mei,
-                data_de_opcao_pelo_mei: naive_date_from_str(record.data_de_opcao_pelo_mei),
-                data_de_exclusao_do_mei: naive_date_from_str(record.data_de_exclusao_do_mei),
-            });
+                opcao_pelo_simples,
+                data_de_opcao_pelo_simples: self.parse_date("data_de_opcao_pelo_simples", record.data_de_opcao_pelo_simples),
+                data_de_exclusao_do_simples: self.parse_date("data_de_exclusao_do_simples", record.data_de_exclusao_do_simples),
+                opcao_pelo_mei,
+                data_de_opcao_pelo_mei: self.parse_date("data_de_opcao_pelo_mei", record.data_de_opcao_pelo_mei),
+                data_de_exclusao_do_mei: self.parse_date("data_de_exclusao_do_mei", record.data_de_exclusao_do_mei),
+            };
 
-            self.num_records += 1;
+            self.add_to_batch(&mut batch, key, new_simples, rdr.position().line())?;
 
-            if records.len() == self.config.rows_per_insert() {
-                self.db
-                    .upsert_simples(&records, self.num_records < 5) // força um upsert nos primeiros 5 registros, pois os registros dos arquivos a serem importados podem conter linhas repetidas no início do arquivo com relação ao arquivo anterior. Normalmente a primeira linha, mas, por segurança, aqui optei pelas 5 primeiras linhas.
-                    .expect(&format!("Erro ao inserir registros na tabela do simples!"));
-                records.clear();
+            if batch.registros.len() == self.config.rows_per_insert() {
+                self.write_batch(&mut batch, Database::upsert_simples, "Erro ao inserir registros na tabela do simples!");
                 self.show_progress();
             }
         }
 
-        self.db
-            .upsert_simples(&records, false)
-            .expect(&format!("Erro ao inserir registros na tabela do simples!"));
+        self.write_batch(&mut batch, Database::upsert_simples, "Erro ao inserir registros na tabela do simples!");
 
         Ok(())
     }
@@ -553,6 +691,7 @@ impl<'a> Import<'a> {
         let mut raw_record = csv::ByteRecord::new();
 
         while rdr.read_byte_record(&mut raw_record)? {
+            self.num_records_read += 1;
             let id: u32 = std::str::from_utf8(&raw_record[0])
                 .unwrap()
                 .parse()
@@ -578,6 +717,7 @@ impl<'a> Import<'a> {
         let mut raw_record = csv::ByteRecord::new();
 
         while rdr.read_byte_record(&mut raw_record)? {
+            self.num_records_read += 1;
             let id: u16 = std::str::from_utf8(&raw_record[0])
                 .unwrap()
                 .parse()
@@ -606,6 +746,7 @@ impl<'a> Import<'a> {
         let mut raw_record = csv::ByteRecord::new();
 
         while rdr.read_byte_record(&mut raw_record)? {
+            self.num_records_read += 1;
             let id: u8 = std::str::from_utf8(&raw_record[0])
                 .unwrap()
                 .parse()
@@ -633,6 +774,7 @@ impl<'a> Import<'a> {
         let mut raw_record = csv::ByteRecord::new();
 
         while rdr.read_byte_record(&mut raw_record)? {
+            self.num_records_read += 1;
             let id: u16 = std::str::from_utf8(&raw_record[0])
                 .unwrap()
                 .parse()
@@ -658,6 +800,7 @@ impl<'a> Import<'a> {
         let mut raw_record = csv::ByteRecord::new();
 
         while rdr.read_byte_record(&mut raw_record)? {
+            self.num_records_read += 1;
             let id: u16 = std::str::from_utf8(&raw_record[0])
                 .unwrap()
                 .parse()
@@ -673,6 +816,8 @@ impl<'a> Import<'a> {
             self.num_records += 1;
             self.show_progress();
         }
+
+        self.db.update_municipios_ibge()?;
         Ok(())
     }
 
@@ -686,6 +831,7 @@ impl<'a> Import<'a> {
         let mut raw_record = csv::ByteRecord::new();
 
         while rdr.read_byte_record(&mut raw_record)? {
+            self.num_records_read += 1;
             let id: u8 = std::str::from_utf8(&raw_record[0])
                 .unwrap()
                 .parse()
@@ -706,11 +852,122 @@ impl<'a> Import<'a> {
         Ok(())
     }
 
-    fn show_progress(&self) {
-        if self.config.verbose() {
-            // os espaços no final servem para sobrepor na reimpressão do texto, já que o tamanho é variável
-            print!("{} registros importados até agora. {} registros/segundo.                                                           \r", self.num_records, self.records_per_seconds());
-            io::stdout().flush().unwrap();
+    fn show_progress(&mut self) {
+        // exibido com o flag --verbose (ou com RUST_LOG=importer=debug)
+        debug!(
+            registros_inseridos = self.num_records,
+            registros_por_segundo = self.records_per_seconds(),
+            "Registros importados até agora"
+        );
+
+        let relatorio_do_arquivo = self.relatorio_do_arquivo(SituacaoDoArquivo::EmAndamento, None);
+        self.relatorio.atualizar(&relatorio_do_arquivo);
+    }
+
+    // Converte a data (AAAAMMDD) do arquivo. As datas inválidas são gravadas como nulas e registradas na tabela
+    // valores_invalidos; as datas em branco ou zeradas (0 ou 00000000) indicam que a data não foi informada.
+    fn parse_date(&mut self, column: &'static str, date_option: Option<String>) -> Option<NaiveDate> {
+        let date = naive_date_from_str(date_option.as_deref());
+        if let (None, Some(date_str)) = (date, &date_option) {
+            if !date_str.trim().trim_matches('0').is_empty() {
+                self.register_invalid_value(column, date_str);
+            }
+        }
+        date
+    }
+
+    // Registra o valor inválido de uma coluna, que é gravado como nulo
+    fn register_invalid_value(&mut self, column: &'static str, value: &str) {
+        self.add_invalid_value(column, value, false);
+    }
+
+    // Registra o valor inválido de uma coluna obrigatória, cujo registro é rejeitado
+    fn reject_invalid_value(&mut self, column: &'static str, value: &str) {
+        self.add_invalid_value(column, value, true);
+        self.num_rejected_records += 1;
+    }
+
+    fn add_invalid_value(&mut self, column: &'static str, value: &str, rejected: bool) {
+        let (count, _, _) = self.invalid_values.entry(column).or_insert_with(|| (0, value.to_string(), rejected));
+        *count += 1;
+    }
+
+    fn save_invalid_values(&self, filename: &str) {
+        let table_name = self.config.tipo_de_arquivo().table_name();
+        let records: Vec<NewValorInvalido> = self
+            .invalid_values
+            .iter()
+            .map(|(column, (count, example, rejected))| NewValorInvalido {
+                nome_do_arquivo: filename,
+                coluna: column,
+                tabela: table_name,
+                quantidade: *count,
+                exemplo: example,
+                registro_rejeitado: *rejected,
+            })
+            .collect();
+
+        self.db
+            .replace_valores_invalidos(filename, &records)
+            .expect("Erro ao inserir registros na tabela de valores inválidos!");
+    }
+
+    // Adiciona o registro ao lote conforme a política de duplicados (--duplicates), contando os duplicados
+    fn add_to_batch<T>(&mut self, batch: &mut Lote<T>, key: Chave, record: T, line: u64) -> Result<(), String> {
+        match batch.adicionar(key, record, self.deduplicacao) {
+            Ok(true) => self.num_duplicated_records += 1,
+            Ok(false) => self.num_records += 1,
+            Err(RegistroDuplicado) => {
+                return Err(format!("Registro duplicado na linha {} do arquivo (--duplicates error)", line));
+            }
+        }
+        Ok(())
+    }
+
+    // Grava o lote de registros e, com a política de duplicados manter o último (--duplicates last), os
+    // registros que substituem outros gravados em lotes anteriores
+    fn write_batch<T>(
+        &mut self,
+        batch: &mut Lote<T>,
+        upsert: fn(&Database<'a>, &Vec<T>, bool) -> QueryResult<usize>,
+        error_message: &str,
+    ) {
+        if batch.is_empty() {
+            return;
+        }
+
+        let _lote = self.batch_span(batch.registros.len()).entered();
+
+        upsert(&self.db, &batch.registros, false).expect(error_message);
+        if !batch.substituicoes.is_empty() {
+            upsert(&self.db, &batch.substituicoes, true).expect(error_message);
+        }
+
+        batch.limpar();
+    }
+
+    // Span do lote de registros que será gravado no banco de dados; os eventos registrados durante a
+    // gravação contêm o índice do lote no arquivo
+    fn batch_span(&mut self, num_records: usize) -> Span {
+        self.num_batches += 1;
+        info_span!("lote", indice = self.num_batches, registros = num_records)
+    }
+
+    fn relatorio_do_arquivo(&self, situacao: SituacaoDoArquivo, erro: Option<String>) -> RelatorioDoArquivo {
+        let duracao_em_segundos = Instant::now().duration_since(self.start_time).as_secs_f64();
+
+        RelatorioDoArquivo {
+            arquivo: self.filename.clone(),
+            tabela: self.config.tipo_de_arquivo().table_name().to_string(),
+            parte: self.config.file_part_number(),
+            situacao,
+            registros_lidos: self.num_records_read as u64,
+            registros_inseridos: self.num_records as u64,
+            registros_duplicados: self.num_duplicated_records as u64,
+            registros_rejeitados: self.num_rejected_records as u64,
+            duracao_em_segundos,
+            registros_por_segundo: if duracao_em_segundos > 0.0 { self.num_records as f64 / duracao_em_segundos } else { 0.0 },
+            erro,
         }
     }
 
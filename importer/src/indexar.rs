@@ -0,0 +1,134 @@
+use std::path::Path;
+
+use data_models::indice::{DocumentoDoIndice, IndiceDeBusca};
+use data_models::models::MetadadosDasTabelas;
+use data_models::schema::metadados_das_tabelas;
+use diesel::mysql::MysqlConnection;
+use diesel::sql_types::{BigInt, Text};
+use diesel::{prelude::*, sql_query};
+use tracing::info;
+
+use crate::database::Database;
+
+// quantidade de estabelecimentos lidos do banco de dados de cada vez
+const REGISTROS_POR_LEITURA: i64 = 50_000;
+// quantidade de estabelecimentos gravados no índice entre cada confirmação (commit)
+const REGISTROS_POR_CONFIRMACAO: usize = 1_000_000;
+
+// Gera ou atualiza o índice de busca textual a partir das tabelas empresas e estabelecimentos.
+// A versão do índice é a data/hora de atualização na RF e a data/hora de importação das tabelas (vide
+// metadados_das_tabelas), de modo que o índice só é atualizado quando uma nova importação foi feita,
+// inclusive quando a mesma versão da RF é importada novamente.
+//
+// A atualização não é incremental quanto aos registros: como as tabelas não indicam quais estabelecimentos
+// mudaram, todos os documentos são gravados novamente a cada atualização (substituindo os existentes, sem
+// recriar o índice) e os dos estabelecimentos excluídos são removidos ao final.
+pub fn indexar(diretorio: &Path, recriar: bool) -> Result<(), String> {
+
+    let db_connection = Database::establish_connection();
+
+    let versao = versao_dos_dados(&db_connection)
+        .map_err(|error| format!("Erro ao ler os metadados das tabelas: {:?}", error))?;
+
+    let indice = IndiceDeBusca::criar_ou_abrir(diretorio)
+        .map_err(|error| format!("Erro ao abrir o índice no diretório {}: {:?}", diretorio.display(), error))?;
+
+    let versao_do_indice = indice.versao()
+        .map_err(|error| format!("Erro ao ler a versão do índice: {:?}", error))?;
+
+    if !recriar && versao_do_indice.as_deref() == Some(versao.as_str()) {
+        info!(versao = %versao, "O índice já está atualizado com os dados importados. Utilize o flag --recriar para gerá-lo novamente.");
+        return Ok(());
+    }
+
+    let mut escritor = indice.escritor()
+        .map_err(|error| format!("Erro ao abrir o índice para gravação: {:?}", error))?;
+
+    if recriar {
+        escritor.remover_todos()
+            .map_err(|error| format!("Erro ao remover os documentos do índice: {:?}", error))?;
+    }
+
+    info!(diretorio = %diretorio.display(), "Gerando o índice de busca");
+
+    let mut ultimo_cnpj = (String::new(), String::new(), String::new());
+    let mut total = 0;
+    let mut nao_confirmados = 0;
+
+    loop {
+        let estabelecimentos = ler_estabelecimentos(&db_connection, &ultimo_cnpj)
+            .map_err(|error| format!("Erro ao ler os estabelecimentos: {:?}", error))?;
+
+        let ultimo = match estabelecimentos.last() {
+            Some(e) => (e.cnpj_basico.clone(), e.cnpj_ordem.clone(), e.cnpj_dv.clone()),
+            None => break,
+        };
+
+        for estabelecimento in &estabelecimentos {
+            escritor.gravar(estabelecimento);
+        }
+        total += estabelecimentos.len();
+        nao_confirmados += estabelecimentos.len();
+
+        if nao_confirmados >= REGISTROS_POR_CONFIRMACAO {
+            escritor.confirmar(versao_do_indice.as_deref().unwrap_or_default())
+                .map_err(|error| format!("Erro ao gravar o índice: {:?}", error))?;
+            nao_confirmados = 0;
+            info!(estabelecimentos = total, "Estabelecimentos indexados até agora");
+        }
+
+        ultimo_cnpj = ultimo;
+    }
+
+    // os estabelecimentos excluídos do banco de dados desde a geração anterior são removidos do índice
+    if !recriar {
+        escritor.confirmar(versao_do_indice.as_deref().unwrap_or_default())
+            .map_err(|error| format!("Erro ao gravar o índice: {:?}", error))?;
+        let removidos = escritor.remover_ausentes()
+            .map_err(|error| format!("Erro ao remover os estabelecimentos excluídos do índice: {:?}", error))?;
+        info!(estabelecimentos = removidos, "Estabelecimentos excluídos removidos do índice");
+    }
+
+    // somente a última confirmação registra a nova versão, assim uma indexação interrompida é refeita
+    escritor.confirmar(&versao)
+        .map_err(|error| format!("Erro ao gravar o índice: {:?}", error))?;
+
+    info!(estabelecimentos = total, "Índice gerado com sucesso!");
+
+    Ok(())
+}
+
+fn versao_dos_dados(db_connection: &MysqlConnection) -> QueryResult<String> {
+    let tabelas = ["empresas", "estabelecimentos"];
+
+    let metadados = metadados_das_tabelas::table
+        .filter(metadados_das_tabelas::tabela.eq_any(&tabelas))
+        .order(metadados_das_tabelas::tabela)
+        .load::<MetadadosDasTabelas>(db_connection)?;
+
+    Ok(metadados
+        .iter()
+        .map(|m| format!("{}={}/{}", m.tabela, m.data_hora_de_atualizacao, m.data_hora_de_importacao))
+        .collect::<Vec<String>>()
+        .join(";"))
+}
+
+fn ler_estabelecimentos(db_connection: &MysqlConnection, apos: &(String, String, String)) -> QueryResult<Vec<DocumentoDoIndice>> {
+    sql_query(
+        "SELECT es.cnpj_basico, es.cnpj_ordem, es.cnpj_dv, e.razao_social, es.nome_fantasia, \
+                es.tipo_logradouro, es.logradouro, es.bairro, m.nome AS municipio, c.nome AS cnae, \
+                es.uf, es.situacao_cadastral \
+         FROM estabelecimentos es \
+         INNER JOIN empresas e ON e.cnpj_basico = es.cnpj_basico \
+         LEFT JOIN municipios m ON m.id = es.municipio \
+         LEFT JOIN cnaes c ON c.id = es.cnae_fiscal_principal \
+         WHERE (es.cnpj_basico, es.cnpj_ordem, es.cnpj_dv) > (?, ?, ?) \
+         ORDER BY es.cnpj_basico, es.cnpj_ordem, es.cnpj_dv \
+         LIMIT ?",
+    )
+    .bind::<Text, _>(&apos.0)
+    .bind::<Text, _>(&apos.1)
+    .bind::<Text, _>(&apos.2)
+    .bind::<BigInt, _>(REGISTROS_POR_LEITURA)
+    .load::<DocumentoDoIndice>(db_connection)
+}
@@ -0,0 +1,313 @@
+// Verificação da integridade referencial das tabelas grandes (importer verify), executada também ao final da
+// importação de um diretório (-f). Na importação, a checagem das chaves estrangeiras fica desativada e, com
+// --drop-indexes, as chaves são recriadas sem percorrer os registros (vide Database::add_indexes_and_primary_keys),
+// então registros órfãos e chaves não recriadas passam despercebidos. A verificação confere:
+//
+//   - se as chaves primárias e estrangeiras de indexes_and_primary_keys_of existem;
+//   - a quantidade de registros órfãos de cada chave estrangeira (ex: estabelecimentos.municipio sem o
+//     município correspondente), que podem ser movidos para tabelas de quarentena (--quarantine);
+//   - nas chaves primárias não recriadas, a quantidade de chaves duplicadas, que impedem a sua criação.
+//
+// A verificação termina com erro quando uma chave não existe ou quando a quantidade de órfãos de uma chave
+// estrangeira é maior do que a permitida (--max-orphans).
+
+use diesel::mysql::MysqlConnection;
+use diesel::sql_types::{BigInt, Text};
+use diesel::{prelude::*, sql_query};
+use tracing::{info, warn};
+
+use crate::cli::OpcoesDaVerificacao;
+use crate::database::{indexes_and_primary_keys_of, Database};
+use crate::tipo_de_arquivo::TipoDeArquivo;
+
+// as empresas são verificadas antes dos estabelecimentos, para que, com --quarantine, os estabelecimentos das
+// empresas movidas para a quarentena também sejam movidos
+const TABELAS: [TipoDeArquivo; 4] = [
+    TipoDeArquivo::Empresas,
+    TipoDeArquivo::Estabelecimentos,
+    TipoDeArquivo::Socios,
+    TipoDeArquivo::Simples,
+];
+
+#[derive(QueryableByName)]
+struct Contagem {
+    #[sql_type = "BigInt"]
+    total: i64,
+}
+
+struct ChavePrimaria {
+    tabela: &'static str,
+    // colunas da chave ou None quando a chave é uma coluna auto incremento (ex: socios.id)
+    colunas: Option<&'static str>,
+}
+
+struct ChaveEstrangeira {
+    tabela: &'static str,
+    nome: &'static str,
+    coluna: &'static str,
+    tabela_referenciada: &'static str,
+    coluna_referenciada: &'static str,
+}
+
+impl ChaveEstrangeira {
+    // Lê a chave estrangeira de uma alteração no formato
+    // ADD CONSTRAINT <nome> FOREIGN KEY (<coluna>) REFERENCES <tabela>(<coluna>)
+    fn from_line(tabela: &'static str, line: &'static str) -> Option<ChaveEstrangeira> {
+        let partes: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .filter(|parte| !parte.is_empty())
+            .collect();
+
+        match partes[..] {
+            ["ADD", "CONSTRAINT", nome, "FOREIGN", "KEY", coluna, "REFERENCES", tabela_referenciada, coluna_referenciada] => {
+                Some(ChaveEstrangeira {
+                    tabela,
+                    nome,
+                    coluna,
+                    tabela_referenciada,
+                    coluna_referenciada,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    // Junção e condição que selecionam os registros órfãos da tabela (t)
+    fn orfaos(&self) -> String {
+        format!(
+            "{0} t LEFT JOIN {2} r ON r.{3} = t.{1} WHERE t.{1} IS NOT NULL AND r.{3} IS NULL",
+            self.tabela, self.coluna, self.tabela_referenciada, self.coluna_referenciada
+        )
+    }
+
+    fn tabela_de_quarentena(&self) -> String {
+        format!("{}_orfaos_{}", self.tabela, self.coluna)
+    }
+}
+
+// Chaves primárias e estrangeiras das alterações (tabela e linha) de indexes_and_primary_keys_of
+fn chaves_das_alteracoes(alteracoes: impl IntoIterator<Item = (&'static str, &'static str)>) -> (Vec<ChavePrimaria>, Vec<ChaveEstrangeira>) {
+    let mut chaves_primarias = Vec::new();
+    let mut chaves_estrangeiras: Vec<ChaveEstrangeira> = Vec::new();
+    for (tabela, line) in alteracoes {
+        if let Some(chave) = ChaveEstrangeira::from_line(tabela, line) {
+            // a mesma chave pode ser recriada na importação de mais de uma tabela (ex: FK_EstabEmp)
+            if !chaves_estrangeiras.iter().any(|outra| outra.nome == chave.nome) {
+                chaves_estrangeiras.push(chave);
+            }
+        } else if line.starts_with("ADD PRIMARY KEY") {
+            let colunas = line.trim_start_matches("ADD PRIMARY KEY").trim().trim_start_matches('(').trim_end_matches(')');
+            chaves_primarias.push(ChavePrimaria { tabela, colunas: Some(colunas) });
+        } else if line.contains("PRIMARY KEY") {
+            chaves_primarias.push(ChavePrimaria { tabela, colunas: None });
+        }
+    }
+    (chaves_primarias, chaves_estrangeiras)
+}
+
+// Problema a ser relatado quando a quantidade de registros órfãos da chave é maior do que a permitida
+fn problema_dos_orfaos(chave: &ChaveEstrangeira, orfaos: i64, max_orphans: u64) -> Option<String> {
+    if orfaos as u64 <= max_orphans {
+        return None;
+    }
+    Some(format!(
+        "{} registros da tabela {} sem correspondência em {}.{} (chave estrangeira {}, máximo de {})",
+        orfaos, chave.tabela, chave.tabela_referenciada, chave.coluna_referenciada, chave.nome, max_orphans
+    ))
+}
+
+// Verifica a integridade referencial das tabelas grandes
+pub fn verificar(opcoes: &OpcoesDaVerificacao) -> Result<(), String> {
+
+    let db_connection = Database::establish_connection();
+
+    let (chaves_primarias, chaves_estrangeiras) = chaves_das_alteracoes(TABELAS.iter().flat_map(indexes_and_primary_keys_of));
+
+    let mut problemas = Vec::new();
+
+    for chave in &chaves_primarias {
+        let existe = restricao_existe(&db_connection, chave.tabela, "PRIMARY", "PRIMARY KEY")
+            .map_err(|error| format!("Erro ao verificar a chave primária da tabela {}: {:?}", chave.tabela, error))?;
+        if existe {
+            info!(tabela = chave.tabela, "A chave primária existe");
+            continue;
+        }
+
+        let duplicadas = match chave.colunas {
+            Some(colunas) => contar(
+                &db_connection,
+                &format!("(SELECT 1 FROM {0} GROUP BY {1} HAVING COUNT(*) > 1) duplicadas", chave.tabela, colunas),
+            )
+            .map_err(|error| format!("Erro ao contar as chaves duplicadas da tabela {}: {:?}", chave.tabela, error))?,
+            None => 0,
+        };
+        warn!(tabela = chave.tabela, chaves_duplicadas = duplicadas, "A chave primária não foi recriada");
+        problemas.push(format!("a chave primária da tabela {} não existe ({} chaves duplicadas)", chave.tabela, duplicadas));
+    }
+
+    for chave in &chaves_estrangeiras {
+        let existe = restricao_existe(&db_connection, chave.tabela, chave.nome, "FOREIGN KEY")
+            .map_err(|error| format!("Erro ao verificar a chave estrangeira {}: {:?}", chave.nome, error))?;
+        if !existe {
+            warn!(tabela = chave.tabela, chave_estrangeira = chave.nome, "A chave estrangeira não foi recriada");
+            problemas.push(format!("a chave estrangeira {} da tabela {} não existe", chave.nome, chave.tabela));
+        }
+
+        let orfaos = contar(&db_connection, &chave.orfaos())
+            .map_err(|error| format!("Erro ao contar os registros órfãos da chave estrangeira {}: {:?}", chave.nome, error))?;
+        info!(
+            tabela = chave.tabela,
+            chave_estrangeira = chave.nome,
+            coluna = chave.coluna,
+            tabela_referenciada = chave.tabela_referenciada,
+            registros_orfaos = orfaos,
+            "Registros órfãos da chave estrangeira"
+        );
+
+        if orfaos > 0 && opcoes.quarantine {
+            mover_para_a_quarentena(&db_connection, chave)
+                .map_err(|error| format!("Erro ao mover os registros órfãos da chave estrangeira {} para a quarentena: {:?}", chave.nome, error))?;
+        }
+
+        if let Some(problema) = problema_dos_orfaos(chave, orfaos, opcoes.max_orphans) {
+            warn!(chave_estrangeira = chave.nome, registros_orfaos = orfaos, maximo = opcoes.max_orphans, "A quantidade de registros órfãos é maior do que a permitida");
+            problemas.push(problema);
+        }
+    }
+
+    if !problemas.is_empty() {
+        return Err(format!("A verificação da integridade referencial encontrou problemas: {}", problemas.join("; ")));
+    }
+
+    info!("A verificação da integridade referencial não encontrou problemas");
+    Ok(())
+}
+
+// Move os registros órfãos da chave estrangeira para a tabela de quarentena (ex: estabelecimentos_orfaos_municipio),
+// criada novamente a cada verificação
+fn mover_para_a_quarentena(db_connection: &MysqlConnection, chave: &ChaveEstrangeira) -> QueryResult<()> {
+    let tabela_de_quarentena = chave.tabela_de_quarentena();
+
+    sql_query(format!("DROP TABLE IF EXISTS {}", tabela_de_quarentena)).execute(db_connection)?;
+    sql_query(format!("CREATE TABLE {} AS SELECT t.* FROM {}", tabela_de_quarentena, chave.orfaos())).execute(db_connection)?;
+    let removidos = sql_query(format!("DELETE t FROM {}", chave.orfaos())).execute(db_connection)?;
+
+    warn!(
+        tabela = chave.tabela,
+        chave_estrangeira = chave.nome,
+        tabela_de_quarentena = %tabela_de_quarentena,
+        registros = removidos,
+        "Registros órfãos movidos para a quarentena"
+    );
+    Ok(())
+}
+
+fn restricao_existe(db_connection: &MysqlConnection, tabela: &str, nome: &str, tipo: &str) -> QueryResult<bool> {
+    sql_query(
+        "SELECT COUNT(*) AS total FROM information_schema.TABLE_CONSTRAINTS \
+         WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND CONSTRAINT_NAME = ? AND CONSTRAINT_TYPE = ?",
+    )
+    .bind::<Text, _>(tabela)
+    .bind::<Text, _>(nome)
+    .bind::<Text, _>(tipo)
+    .get_result::<Contagem>(db_connection)
+    .map(|contagem| contagem.total > 0)
+}
+
+fn consulta_da_contagem(consulta: &str) -> String {
+    format!("SELECT COUNT(*) AS total FROM {}", consulta)
+}
+
+fn contar(db_connection: &MysqlConnection, consulta: &str) -> QueryResult<i64> {
+    sql_query(consulta_da_contagem(consulta))
+        .get_result::<Contagem>(db_connection)
+        .map(|contagem| contagem.total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estrangeiras(chaves: &[ChaveEstrangeira]) -> Vec<(&str, &str, &str, &str, &str)> {
+        chaves.iter().map(|c| (c.tabela, c.nome, c.coluna, c.tabela_referenciada, c.coluna_referenciada)).collect()
+    }
+
+    fn primarias(chaves: &[ChavePrimaria]) -> Vec<(&str, Option<&str>)> {
+        chaves.iter().map(|c| (c.tabela, c.colunas)).collect()
+    }
+
+    #[test]
+    fn chaves_das_alteracoes_das_tabelas() {
+        let (chaves_primarias, chaves_estrangeiras) = chaves_das_alteracoes(vec![
+            ("empresas", "ADD PRIMARY KEY (cnpj_basico)"),
+            ("empresas", "ADD CONSTRAINT FK_EmpNatJur FOREIGN KEY (natureza_juridica) REFERENCES naturezas_juridicas(id)"),
+            ("estabelecimentos", "ADD CONSTRAINT FK_EstabEmp FOREIGN KEY (cnpj_basico) REFERENCES empresas(cnpj_basico)"),
+            ("empresas", "ADD FULLTEXT INDEX empresas_busca_razao_social (razao_social)"),
+            ("estabelecimentos", "ADD PRIMARY KEY (cnpj_basico, cnpj_ordem, cnpj_dv)"),
+            // a mesma chave recriada na importação de outra tabela
+            ("estabelecimentos", "ADD CONSTRAINT FK_EstabEmp FOREIGN KEY (cnpj_basico) REFERENCES empresas(cnpj_basico)"),
+            ("socios", "ADD COLUMN id INT UNSIGNED AUTO_INCREMENT PRIMARY KEY FIRST"),
+            ("socios", "ADD INDEX socios_cnpj_basico (cnpj_basico)"),
+        ]);
+
+        assert_eq!(
+            primarias(&chaves_primarias),
+            vec![
+                ("empresas", Some("cnpj_basico")),
+                ("estabelecimentos", Some("cnpj_basico, cnpj_ordem, cnpj_dv")),
+                ("socios", None),
+            ]
+        );
+        assert_eq!(
+            estrangeiras(&chaves_estrangeiras),
+            vec![
+                ("empresas", "FK_EmpNatJur", "natureza_juridica", "naturezas_juridicas", "id"),
+                ("estabelecimentos", "FK_EstabEmp", "cnpj_basico", "empresas", "cnpj_basico"),
+            ]
+        );
+    }
+
+    #[test]
+    fn chaves_das_tabelas_grandes() {
+        let (chaves_primarias, chaves_estrangeiras) = chaves_das_alteracoes(TABELAS.iter().flat_map(indexes_and_primary_keys_of));
+
+        assert_eq!(chaves_primarias.len(), 4);
+        let nomes: Vec<&str> = chaves_estrangeiras.iter().map(|chave| chave.nome).collect();
+        assert_eq!(nomes.iter().filter(|nome| **nome == "FK_EstabEmp").count(), 1);
+        assert!(nomes.contains(&"FK_EstabMunic"));
+        assert!(nomes.contains(&"FK_EmpQualResp"));
+    }
+
+    #[test]
+    fn linhas_que_nao_sao_chaves_estrangeiras() {
+        assert!(ChaveEstrangeira::from_line("socios", "ADD INDEX socios_cnpj_basico (cnpj_basico)").is_none());
+        assert!(ChaveEstrangeira::from_line("empresas", "ADD PRIMARY KEY (cnpj_basico)").is_none());
+        // chave estrangeira composta
+        assert!(ChaveEstrangeira::from_line("t", "ADD CONSTRAINT FK_T FOREIGN KEY (a, b) REFERENCES r(a, b)").is_none());
+    }
+
+    #[test]
+    fn contagem_dos_registros_orfaos() {
+        let chave = ChaveEstrangeira::from_line(
+            "estabelecimentos",
+            "ADD CONSTRAINT FK_EstabMunic FOREIGN KEY (municipio) REFERENCES municipios(id)",
+        )
+        .unwrap();
+
+        assert_eq!(
+            consulta_da_contagem(&chave.orfaos()),
+            "SELECT COUNT(*) AS total FROM estabelecimentos t LEFT JOIN municipios r ON r.id = t.municipio \
+             WHERE t.municipio IS NOT NULL AND r.id IS NULL"
+        );
+        assert_eq!(chave.tabela_de_quarentena(), "estabelecimentos_orfaos_municipio");
+
+        assert_eq!(problema_dos_orfaos(&chave, 0, 0), None);
+        assert_eq!(problema_dos_orfaos(&chave, 10, 10), None);
+        assert_eq!(
+            problema_dos_orfaos(&chave, 11, 10),
+            Some(String::from(
+                "11 registros da tabela estabelecimentos sem correspondência em municipios.id (chave estrangeira FK_EstabMunic, máximo de 10)"
+            ))
+        );
+    }
+}
@@ -1,5 +1,17 @@
+#[macro_use]
+extern crate diesel;
+
 pub mod config;
 pub mod tipo_de_arquivo;
 pub mod import;
 pub mod cli;
-pub mod database;
\ No newline at end of file
+pub mod database;
+pub mod chaves_de_api;
+pub mod relatorio;
+pub mod deduplicacao;
+pub mod qualidade;
+pub mod integridade;
+pub mod ibge;
+pub mod staging;
+#[cfg(feature = "indice")]
+pub mod indexar;
@@ -1,18 +1,25 @@
 use std::path::PathBuf;
 use std::{fs, io};
 
-use importer::cli::Cli;
+use importer::cli::{Cli, Comando};
 use importer::config::Config;
+use importer::deduplicacao::Deduplicacao;
 use importer::import::Import;
+use importer::relatorio::Relatorio;
+use data_models::logs::iniciar_logs;
 use structopt::StructOpt;
+use tracing::{error, info};
 
 fn main() {
     let args = Cli::from_args();
 
+    // com o flag --verbose, o progresso da importação também é exibido (vide RUST_LOG em data_models::logs)
+    iniciar_logs(if args.verbose { "info,importer=debug" } else { "info" });
+
     std::process::exit(match real_main(args) {
         Ok(_) => 0,
         Err(err) => {
-            eprintln!("Erro: {:?}", err);
+            error!(erro = %err, "A execução do importer terminou com erro");
             1
         }
     })
@@ -20,17 +27,61 @@ fn main() {
 
 fn real_main(args: Cli) -> Result<(), String> {
 
-    if args.folder {
-        return import_from_dir(&args)
+    if let Some(comando) = &args.comando {
+        return run_command(comando);
+    }
+
+    let path_to_import = match &args.path_to_import {
+        Some(path) => path,
+        None => return Err(String::from("Você deve informar o caminho do arquivo ou do diretório a ser importado")),
+    };
+
+    let mut relatorio = Relatorio::new(&args);
+    let mut deduplicacao = Deduplicacao::new(args.duplicates);
+
+    let result = if args.folder {
+        import_from_dir(&args, path_to_import, &mut relatorio, &mut deduplicacao)
+    } else {
+        import_from_file(&args, path_to_import, &mut relatorio, &mut deduplicacao)
+    };
+
+    // ao final da importação de um diretório (carga completa), a integridade referencial é verificada
+    let result = result.and_then(|()| {
+        if args.folder && !args.skip_verify && relatorio.importou_arquivos() {
+            importer::integridade::verificar(&args.verificacao)
+        } else {
+            Ok(())
+        }
+    });
+
+    relatorio.finalizar(&result)?;
+
+    result
+}
+
+fn run_command(comando: &Comando) -> Result<(), String> {
+    match comando {
+        Comando::Index { diretorio, recriar } => index(diretorio, *recriar),
+        Comando::ChavesDeApi { comando } => importer::chaves_de_api::executar(comando),
+        Comando::Verify { opcoes } => importer::integridade::verificar(opcoes),
+        Comando::Quality { formato, arquivo } => importer::qualidade::gerar_relatorio(*formato, arquivo.as_deref()),
+        Comando::Ibge { arquivo } => importer::ibge::importar(arquivo),
     }
+}
+
+#[cfg(feature = "indice")]
+fn index(diretorio: &PathBuf, recriar: bool) -> Result<(), String> {
+    importer::indexar::indexar(diretorio, recriar)
+}
 
-    return import_from_file(&args, &args.path_to_import);
+#[cfg(not(feature = "indice"))]
+fn index(_diretorio: &PathBuf, _recriar: bool) -> Result<(), String> {
+    Err(String::from("O importer foi compilado sem o suporte ao índice de busca (feature \"indice\")"))
 }
 
-fn import_from_dir(args: &Cli) -> Result<(), String> {
+fn import_from_dir(args: &Cli, path_to_import: &PathBuf, relatorio: &mut Relatorio, deduplicacao: &mut Deduplicacao) -> Result<(), String> {
     
-    let path_to_import = &args.path_to_import;
-    println!("Importando arquivos a partir do diretório: {}", path_to_import.display());
+    info!(diretorio = %path_to_import.display(), "Importando arquivos a partir do diretório");
 
     if fs::Metadata::is_file(&path_to_import.metadata().unwrap()) {
         return Err(String::from("Você informou um arquivo como argumento! Com o flag -d como argumento você deve informar um caminho de diretório onde estão os arquivos para serem importados"));
@@ -49,14 +100,11 @@ fn import_from_dir(args: &Cli) -> Result<(), String> {
 
     for entry in entries {
         if let Some(ext) = entry.extension() {
-            if ext == "zip" {
-                    if fs::Metadata::is_file(&entry.metadata().unwrap()) {
-                        println!("Importando o arquivo {} ...", entry.display());
-                        if let Err(error) = import_from_file(&args, &entry) {
-                            return Err(format!("Ocorreu um erro ao processar o arquivo {}: {:?}", entry.display(), error));
-                        }
-                        println!("=========");
-                    }
+            if ext == "zip" && fs::Metadata::is_file(&entry.metadata().unwrap()) {
+                info!(arquivo_zip = %entry.display(), "Importando o arquivo");
+                if let Err(error) = import_from_file(&args, &entry, relatorio, deduplicacao) {
+                    return Err(format!("Ocorreu um erro ao processar o arquivo {}: {:?}", entry.display(), error));
+                }
             }
         }
     }
@@ -64,7 +112,7 @@ fn import_from_dir(args: &Cli) -> Result<(), String> {
     Ok(())      
 }
 
-fn import_from_file(args: &Cli, file_to_import: &PathBuf) -> Result<(), String> {
+fn import_from_file(args: &Cli, file_to_import: &PathBuf, relatorio: &mut Relatorio, deduplicacao: &mut Deduplicacao) -> Result<(), String> {
 
     if fs::Metadata::is_dir(&file_to_import.metadata().unwrap()) {
          return Err(String::from("Você informou um diretório como argumento! Você deve informar um arquivo para ser importado."));
@@ -85,7 +133,7 @@ fn import_from_file(args: &Cli, file_to_import: &PathBuf) -> Result<(), String>
         } else {
             match Config::new(&*file.name(), &args) {
                 Ok(config) => {
-                    let mut import = Import::new(&config);
+                    let mut import = Import::new(&config, relatorio, deduplicacao);
         
                     import.run(file)?;
                 },
@@ -0,0 +1,540 @@
+// Relatório de qualidade dos dados importados (importer quality), com a contagem, em cada tabela grande, de:
+//
+//   - nulos ou em branco em cada coluna;
+//   - valores inválidos encontrados na importação, registrados pelo importer na tabela valores_invalidos: os
+//     gravados como nulos (datas inválidas e códigos de porte inexistentes), em relação aos registros da
+//     tabela, e os das colunas obrigatórias (ex: códigos de situação cadastral inexistentes e datas de
+//     entrada do sócio inválidas), cujos registros são rejeitados, em relação aos registros lidos dos
+//     arquivos (tabela leituras_das_tabelas);
+//   - códigos fora do domínio ou sem correspondência nas tabelas auxiliares (ex: municípios e CNAEs);
+//   - CNPJs com dígitos verificadores inválidos e CPFs fora do formato mascarado da RF (***999999**), pois,
+//     como os CPFs são mascarados nos arquivos, os seus dígitos verificadores não podem ser conferidos;
+//   - CEPs, UFs e e-mails mal formatados;
+//   - sócios e registros do simples sem a empresa correspondente.
+//
+// Cada verificação percorre a tabela inteira, então a geração do relatório pode demorar nas tabelas grandes.
+// A verificação dos CNAEs secundários utiliza JSON_TABLE e requer o MySQL 8.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use data_models::enderecos::UNIDADES_FEDERATIVAS;
+use diesel::mysql::MysqlConnection;
+use diesel::sql_types::{BigInt, Bool, Text};
+use diesel::{prelude::*, sql_query};
+use serde::Serialize;
+use tracing::info;
+
+use crate::database::Database;
+use crate::relatorio::gravar_arquivo;
+
+const TABELAS: [&str; 4] = ["empresas", "estabelecimentos", "socios", "simples"];
+
+// pesos do segundo dígito verificador do CNPJ; o primeiro utiliza os mesmos pesos, a partir do segundo
+const PESOS_DO_CNPJ: [u32; 13] = [6, 5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2];
+
+#[derive(Clone, Copy)]
+pub enum FormatoDaQualidade {
+    Html,
+    Json,
+}
+
+impl FromStr for FormatoDaQualidade {
+    type Err = String;
+
+    fn from_str(formato: &str) -> Result<Self, Self::Err> {
+        match formato {
+            "html" => Ok(FormatoDaQualidade::Html),
+            "json" => Ok(FormatoDaQualidade::Json),
+            _ => Err(format!("Formato de relatório inválido: {} (utilize html ou json)", formato)),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RelatorioDeQualidade {
+    gerado_em: DateTime<Utc>,
+    tabelas: Vec<QualidadeDaTabela>,
+}
+
+#[derive(Serialize)]
+struct QualidadeDaTabela {
+    tabela: String,
+    registros: i64,
+    // registros lidos dos arquivos da importação atual, inclusive os rejeitados e os duplicados
+    #[serde(skip_serializing_if = "Option::is_none")]
+    registros_lidos: Option<i64>,
+    colunas: Vec<PreenchimentoDaColuna>,
+    problemas: Vec<Problema>,
+}
+
+#[derive(Serialize)]
+struct PreenchimentoDaColuna {
+    coluna: String,
+    nulos_ou_em_branco: i64,
+    percentual: f64,
+}
+
+#[derive(Serialize)]
+struct Problema {
+    verificacao: String,
+    descricao: String,
+    quantidade: i64,
+    percentual: f64,
+    // primeiro valor inválido encontrado na importação (somente nos valores inválidos registrados pelo importer)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exemplo: Option<String>,
+}
+
+// Verificação feita com SELECT COUNT(*) AS total FROM <consulta>
+struct Verificacao {
+    tabela: &'static str,
+    nome: &'static str,
+    descricao: &'static str,
+    consulta: String,
+}
+
+#[derive(QueryableByName)]
+struct Contagem {
+    #[sql_type = "BigInt"]
+    total: i64,
+}
+
+#[derive(QueryableByName)]
+struct Coluna {
+    #[sql_type = "Text"]
+    coluna: String,
+    #[sql_type = "Text"]
+    tipo: String,
+}
+
+#[derive(QueryableByName)]
+struct Contagens {
+    #[sql_type = "Text"]
+    valores: String,
+}
+
+#[derive(QueryableByName)]
+struct ValorInvalido {
+    #[sql_type = "Text"]
+    tabela: String,
+    #[sql_type = "Text"]
+    coluna: String,
+    #[sql_type = "BigInt"]
+    total: i64,
+    #[sql_type = "Text"]
+    exemplo: String,
+    #[sql_type = "Bool"]
+    registro_rejeitado: bool,
+}
+
+// Gera o relatório de qualidade no formato informado, gravando-o no arquivo ou, se não informado, na saída padrão
+pub fn gerar_relatorio(formato: FormatoDaQualidade, arquivo: Option<&Path>) -> Result<(), String> {
+
+    let db_connection = Database::establish_connection();
+
+    let mut tabelas = Vec::new();
+    for tabela in TABELAS.iter() {
+        info!(tabela, "Verificando a qualidade dos dados da tabela");
+        tabelas.push(
+            qualidade_da_tabela(&db_connection, tabela)
+                .map_err(|error| format!("Erro ao verificar a qualidade da tabela {}: {:?}", tabela, error))?,
+        );
+    }
+
+    let valores_invalidos = valores_invalidos(&db_connection)
+        .map_err(|error| format!("Erro ao ler os valores inválidos da importação: {:?}", error))?;
+    for valor in valores_invalidos {
+        if let Some(tabela) = tabelas.iter_mut().find(|tabela| tabela.tabela == valor.tabela) {
+            let problema = problema_do_valor_invalido(valor, tabela.registros, tabela.registros_lidos);
+            tabela.problemas.push(problema);
+        }
+    }
+
+    let relatorio = RelatorioDeQualidade {
+        gerado_em: Utc::now(),
+        tabelas,
+    };
+    let texto = match formato {
+        FormatoDaQualidade::Json => serde_json::to_string_pretty(&relatorio)
+            .map_err(|error| format!("Erro ao gerar o relatório de qualidade: {}", error))?,
+        FormatoDaQualidade::Html => html(&relatorio),
+    };
+
+    match arquivo {
+        Some(caminho) => gravar_arquivo(caminho, &texto)
+            .map_err(|error| format!("Erro ao gravar o relatório de qualidade em {}: {}", caminho.display(), error)),
+        None => {
+            println!("{}", texto);
+            Ok(())
+        }
+    }
+}
+
+fn qualidade_da_tabela(db_connection: &MysqlConnection, tabela: &str) -> QueryResult<QualidadeDaTabela> {
+    let colunas = sql_query(
+        "SELECT COLUMN_NAME AS coluna, DATA_TYPE AS tipo FROM information_schema.COLUMNS \
+         WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? ORDER BY ORDINAL_POSITION",
+    )
+    .bind::<Text, _>(tabela)
+    .load::<Coluna>(db_connection)?;
+
+    // as contagens de todas as colunas são feitas em uma única consulta (e uma única leitura da tabela),
+    // retornadas como uma lista separada por vírgulas: a quantidade de registros e os nulos de cada coluna
+    let contagens: Vec<String> = colunas
+        .iter()
+        .map(|coluna| match coluna.tipo.as_str() {
+            "char" | "varchar" | "text" => format!("COALESCE(SUM({0} IS NULL OR TRIM({0}) = ''), 0)", coluna.coluna),
+            _ => format!("COALESCE(SUM({} IS NULL), 0)", coluna.coluna),
+        })
+        .collect();
+    let valores = sql_query(format!("SELECT CONCAT_WS(',', COUNT(*), {}) AS valores FROM {}", contagens.join(", "), tabela))
+        .get_result::<Contagens>(db_connection)?
+        .valores;
+    let valores: Vec<i64> = valores.split(',').map(|valor| valor.parse().unwrap_or(0)).collect();
+
+    let registros = valores[0];
+    let colunas = colunas
+        .into_iter()
+        .zip(&valores[1..])
+        .map(|(coluna, nulos)| PreenchimentoDaColuna {
+            coluna: coluna.coluna,
+            nulos_ou_em_branco: *nulos,
+            percentual: percentual(*nulos, registros),
+        })
+        .collect();
+
+    let mut problemas = Vec::new();
+    for verificacao in verificacoes().into_iter().filter(|verificacao| verificacao.tabela == tabela) {
+        info!(tabela, verificacao = verificacao.nome, "Executando a verificação");
+        let quantidade = sql_query(format!("SELECT COUNT(*) AS total FROM {}", verificacao.consulta))
+            .get_result::<Contagem>(db_connection)?
+            .total;
+        problemas.push(Problema {
+            verificacao: verificacao.nome.to_string(),
+            descricao: verificacao.descricao.to_string(),
+            quantidade,
+            percentual: percentual(quantidade, registros),
+            exemplo: None,
+        });
+    }
+
+    Ok(QualidadeDaTabela {
+        tabela: tabela.to_string(),
+        registros,
+        registros_lidos: registros_lidos(db_connection, tabela)?,
+        colunas,
+        problemas,
+    })
+}
+
+fn valores_invalidos(db_connection: &MysqlConnection) -> QueryResult<Vec<ValorInvalido>> {
+    sql_query(
+        "SELECT tabela, coluna, CAST(SUM(quantidade) AS SIGNED) AS total, MIN(exemplo) AS exemplo, \
+         registro_rejeitado FROM valores_invalidos \
+         GROUP BY tabela, coluna, registro_rejeitado ORDER BY tabela, coluna",
+    )
+    .load::<ValorInvalido>(db_connection)
+}
+
+// Registros lidos dos arquivos da importação atual da tabela (vide Import::import_file); não existem nas
+// tabelas importadas antes da criação da tabela leituras_das_tabelas
+fn registros_lidos(db_connection: &MysqlConnection, tabela: &str) -> QueryResult<Option<i64>> {
+    sql_query("SELECT CAST(registros_lidos AS SIGNED) AS total FROM leituras_das_tabelas WHERE tabela = ?")
+        .bind::<Text, _>(tabela)
+        .get_result::<Contagem>(db_connection)
+        .optional()
+        .map(|contagem| contagem.map(|contagem| contagem.total))
+}
+
+// Problema dos valores inválidos de uma coluna. Os valores gravados como nulos são comparados aos registros
+// da tabela; os registros rejeitados, que não constam da tabela, aos registros lidos dos arquivos (ou, se
+// não forem conhecidos, aos registros da tabela mais os rejeitados).
+fn problema_do_valor_invalido(valor: ValorInvalido, registros: i64, registros_lidos: Option<i64>) -> Problema {
+    let (descricao, total) = if valor.registro_rejeitado {
+        (
+            format!("Registros rejeitados na importação por valores inválidos em {}", valor.coluna),
+            registros_lidos.unwrap_or(registros + valor.total),
+        )
+    } else {
+        (format!("Valores inválidos em {} gravados como nulos na importação", valor.coluna), registros)
+    };
+
+    Problema {
+        verificacao: format!("{}_invalido", valor.coluna),
+        descricao,
+        quantidade: valor.total,
+        percentual: percentual(valor.total, total),
+        exemplo: Some(valor.exemplo),
+    }
+}
+
+fn verificacoes() -> Vec<Verificacao> {
+    let verificacao = |tabela, nome, descricao, consulta: String| Verificacao { tabela, nome, descricao, consulta };
+    let sem_correspondencia = |tabela: &str, coluna: &str, tabela_auxiliar: &str| {
+        format!(
+            "{0} t LEFT JOIN {2} a ON a.id = t.{1} WHERE t.{1} IS NOT NULL AND a.id IS NULL",
+            tabela, coluna, tabela_auxiliar
+        )
+    };
+    let cpf_mascarado = "'^[*]{3}[0-9]{6}[*]{2}$'";
+
+    vec![
+        verificacao(
+            "empresas",
+            "natureza_juridica_inexistente",
+            "Natureza jurídica sem correspondência na tabela naturezas_juridicas",
+            sem_correspondencia("empresas", "natureza_juridica", "naturezas_juridicas"),
+        ),
+        verificacao(
+            "empresas",
+            "qualificacao_do_responsavel_inexistente",
+            "Qualificação do responsável sem correspondência na tabela qualificacoes_de_socios",
+            sem_correspondencia("empresas", "qualificacao_do_responsavel", "qualificacoes_de_socios"),
+        ),
+        verificacao(
+            "estabelecimentos",
+            "cnpj_invalido",
+            "CNPJ com dígitos verificadores inválidos",
+            format!("estabelecimentos WHERE {}", cnpj_invalido("CONCAT(cnpj_basico, cnpj_ordem, cnpj_dv)")),
+        ),
+        verificacao(
+            "estabelecimentos",
+            "cnae_principal_inexistente",
+            "CNAE fiscal principal sem correspondência na tabela cnaes",
+            sem_correspondencia("estabelecimentos", "cnae_fiscal_principal", "cnaes"),
+        ),
+        verificacao(
+            "estabelecimentos",
+            "cnae_secundario_inexistente",
+            "Estabelecimentos com algum CNAE fiscal secundário sem correspondência na tabela cnaes",
+            // JSON_TABLE, que separa a lista de CNAEs secundários, só existe a partir do MySQL 8
+            String::from(
+                "estabelecimentos e WHERE e.cnae_fiscal_secundaria IS NOT NULL AND e.cnae_fiscal_secundaria <> '' \
+                 AND EXISTS (SELECT 1 FROM JSON_TABLE(CONCAT('[\"', REPLACE(e.cnae_fiscal_secundaria, ',', '\",\"'), '\"]'), \
+                 '$[*]' COLUMNS (cnae VARCHAR(7) PATH '$')) s LEFT JOIN cnaes c ON c.id = s.cnae WHERE c.id IS NULL)",
+            ),
+        ),
+        verificacao(
+            "estabelecimentos",
+            "municipio_inexistente",
+            "Município sem correspondência na tabela municipios",
+            sem_correspondencia("estabelecimentos", "municipio", "municipios"),
+        ),
+        verificacao(
+            "estabelecimentos",
+            "pais_inexistente",
+            "País sem correspondência na tabela paises",
+            sem_correspondencia("estabelecimentos", "pais", "paises"),
+        ),
+        verificacao(
+            "estabelecimentos",
+            "motivo_situacao_cadastral_inexistente",
+            "Motivo da situação cadastral sem correspondência na tabela motivos_de_situacoes_cadastrais",
+            sem_correspondencia("estabelecimentos", "motivo_situacao_cadastral", "motivos_de_situacoes_cadastrais"),
+        ),
+        verificacao(
+            "estabelecimentos",
+            "cep_mal_formatado",
+            "CEP preenchido que não possui 8 dígitos",
+            String::from("estabelecimentos WHERE cep IS NOT NULL AND cep <> '' AND cep NOT REGEXP '^[0-9]{8}$'"),
+        ),
+        verificacao(
+            "estabelecimentos",
+            "uf_invalida",
+            "UF preenchida que não é uma unidade federativa (ou EX, no exterior)",
+            format!(
+                "estabelecimentos WHERE uf IS NOT NULL AND uf <> '' AND uf NOT IN ({})",
+                UNIDADES_FEDERATIVAS.iter().map(|uf| format!("'{}'", uf)).collect::<Vec<String>>().join(", ")
+            ),
+        ),
+        verificacao(
+            "estabelecimentos",
+            "cep_fora_da_uf",
+            "CEP que não pertence às faixas de CEP da UF (somente nos estabelecimentos importados com --normalize-addresses)",
+            String::from("estabelecimentos WHERE cep_valido = FALSE"),
+        ),
+        verificacao(
+            "estabelecimentos",
+            "email_mal_formatado",
+            "E-mail preenchido fora do formato usuario@dominio.tld",
+            String::from(
+                "estabelecimentos WHERE correio_eletronico IS NOT NULL AND correio_eletronico <> '' \
+                 AND correio_eletronico NOT REGEXP '^[^@ ]+@[^@ ]+[.][^@ ]+$'",
+            ),
+        ),
+        verificacao(
+            "socios",
+            "cnpj_do_socio_invalido",
+            "Sócio pessoa jurídica com CNPJ com dígitos verificadores inválidos",
+            format!("socios WHERE identificador_de_socio = 1 AND {}", cnpj_invalido("COALESCE(cnpj_ou_cpf_do_socio, '')")),
+        ),
+        verificacao(
+            "socios",
+            "cpf_do_socio_mal_formatado",
+            "Sócio pessoa física com CPF fora do formato mascarado da RF (***999999**)",
+            format!(
+                "socios WHERE identificador_de_socio = 2 AND COALESCE(cnpj_ou_cpf_do_socio, '') NOT REGEXP {}",
+                cpf_mascarado
+            ),
+        ),
+        verificacao(
+            "socios",
+            "cpf_do_representante_legal_mal_formatado",
+            "CPF do representante legal preenchido fora do formato mascarado da RF (***999999**)",
+            format!(
+                "socios WHERE cpf_do_representante_legal <> '' AND cpf_do_representante_legal NOT REGEXP {}",
+                cpf_mascarado
+            ),
+        ),
+        verificacao(
+            "socios",
+            "qualificacao_do_socio_inexistente",
+            "Qualificação do sócio sem correspondência na tabela qualificacoes_de_socios",
+            sem_correspondencia("socios", "qualificacao_do_socio", "qualificacoes_de_socios"),
+        ),
+        verificacao(
+            "socios",
+            "pais_do_socio_inexistente",
+            "País do sócio sem correspondência na tabela paises",
+            sem_correspondencia("socios", "pais_do_socio", "paises"),
+        ),
+        verificacao(
+            "socios",
+            "socio_sem_empresa",
+            "Sócio sem a empresa correspondente na tabela empresas",
+            String::from("socios s LEFT JOIN empresas e ON e.cnpj_basico = s.cnpj_basico WHERE e.cnpj_basico IS NULL"),
+        ),
+        verificacao(
+            "simples",
+            "simples_sem_empresa",
+            "Opção pelo simples sem a empresa correspondente na tabela empresas",
+            String::from("simples s LEFT JOIN empresas e ON e.cnpj_basico = s.cnpj_basico WHERE e.cnpj_basico IS NULL"),
+        ),
+    ]
+}
+
+// Condição SQL verdadeira quando a expressão não é um CNPJ de 14 dígitos com os dígitos verificadores corretos
+// (vide data_models::documentos::cnpj_valido)
+fn cnpj_invalido(cnpj: &str) -> String {
+    format!(
+        "({0} NOT REGEXP '^[0-9]{{14}}$' OR SUBSTRING({0}, 13, 1) <> {1} OR SUBSTRING({0}, 14, 1) <> {2})",
+        cnpj,
+        digito_verificador(cnpj, &PESOS_DO_CNPJ[1..]),
+        digito_verificador(cnpj, &PESOS_DO_CNPJ)
+    )
+}
+
+// Expressão SQL do dígito verificador (módulo 11) calculado a partir dos primeiros dígitos da expressão
+fn digito_verificador(cnpj: &str, pesos: &[u32]) -> String {
+    let soma: Vec<String> = pesos
+        .iter()
+        .enumerate()
+        .map(|(posicao, peso)| format!("SUBSTRING({}, {}, 1) * {}", cnpj, posicao + 1, peso))
+        .collect();
+    format!("IF(MOD({0}, 11) < 2, 0, 11 - MOD({0}, 11))", soma.join(" + "))
+}
+
+fn percentual(quantidade: i64, registros: i64) -> f64 {
+    if registros == 0 {
+        return 0.0;
+    }
+    quantidade as f64 * 100.0 / registros as f64
+}
+
+fn html(relatorio: &RelatorioDeQualidade) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html lang=\"pt-BR\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Qualidade dos dados do CNPJ</title>\n<style>\n\
+         body { font-family: sans-serif; margin: 2em; }\n\
+         table { border-collapse: collapse; margin-bottom: 1.5em; }\n\
+         th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\n\
+         td.numero { text-align: right; }\n\
+         tr.problema td { background: #fde8e8; }\n\
+         </style>\n</head>\n<body>\n<h1>Qualidade dos dados do CNPJ</h1>\n",
+    );
+    html.push_str(&format!("<p>Gerado em {}</p>\n", relatorio.gerado_em.format("%d/%m/%Y %H:%M:%S UTC")));
+
+    for tabela in &relatorio.tabelas {
+        match tabela.registros_lidos {
+            Some(registros_lidos) => html.push_str(&format!(
+                "<h2>{} ({} registros; {} lidos na importação)</h2>\n",
+                tabela.tabela, tabela.registros, registros_lidos
+            )),
+            None => html.push_str(&format!("<h2>{} ({} registros)</h2>\n", tabela.tabela, tabela.registros)),
+        }
+
+        html.push_str("<h3>Problemas</h3>\n<table>\n<tr><th>Verificação</th><th>Descrição</th><th>Quantidade</th><th>%</th><th>Exemplo</th></tr>\n");
+        for problema in &tabela.problemas {
+            html.push_str(&format!(
+                "<tr{}><td>{}</td><td>{}</td><td class=\"numero\">{}</td><td class=\"numero\">{:.2}</td><td>{}</td></tr>\n",
+                if problema.quantidade > 0 { " class=\"problema\"" } else { "" },
+                problema.verificacao,
+                escapar(&problema.descricao),
+                problema.quantidade,
+                problema.percentual,
+                escapar(problema.exemplo.as_deref().unwrap_or_default())
+            ));
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h3>Colunas nulas ou em branco</h3>\n<table>\n<tr><th>Coluna</th><th>Nulos ou em branco</th><th>%</th></tr>\n");
+        for coluna in &tabela.colunas {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td class=\"numero\">{}</td><td class=\"numero\">{:.2}</td></tr>\n",
+                coluna.coluna, coluna.nulos_ou_em_branco, coluna.percentual
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn escapar(texto: &str) -> String {
+    texto
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valor_invalido(coluna: &str, total: i64, registro_rejeitado: bool) -> ValorInvalido {
+        ValorInvalido {
+            tabela: String::from("socios"),
+            coluna: coluna.to_string(),
+            total,
+            exemplo: String::from("20231350"),
+            registro_rejeitado,
+        }
+    }
+
+    #[test]
+    fn valores_gravados_como_nulos_em_relacao_aos_registros_da_tabela() {
+        let problema = problema_do_valor_invalido(valor_invalido("porte", 25, false), 1000, Some(1100));
+
+        assert_eq!(problema.verificacao, "porte_invalido");
+        assert_eq!(problema.descricao, "Valores inválidos em porte gravados como nulos na importação");
+        assert_eq!(problema.quantidade, 25);
+        assert_eq!(problema.percentual, 2.5);
+        assert_eq!(problema.exemplo.as_deref(), Some("20231350"));
+    }
+
+    #[test]
+    fn registros_rejeitados_em_relacao_aos_registros_lidos() {
+        let problema = problema_do_valor_invalido(valor_invalido("data_de_entrada_na_sociedade", 100, true), 900, Some(1000));
+
+        assert_eq!(problema.descricao, "Registros rejeitados na importação por valores inválidos em data_de_entrada_na_sociedade");
+        assert_eq!(problema.percentual, 10.0);
+
+        // sem os registros lidos, os rejeitados são somados aos registros da tabela
+        let problema = problema_do_valor_invalido(valor_invalido("data_de_entrada_na_sociedade", 100, true), 900, None);
+        assert_eq!(problema.percentual, 10.0);
+    }
+}
@@ -0,0 +1,381 @@
+// Relatório da execução do importer, para ser lido por outros programas (ex: o agendador das importações):
+//
+//   --report json          grava, ao final da execução, o relatório de cada arquivo importado em JSON (em
+//                          --report-file ou, se não informado, na saída padrão)
+//   --metrics-file <PATH>  grava os contadores da importação no formato de texto do Prometheus, atualizados
+//                          durante a importação (compatível com o textfile collector do node_exporter e com o
+//                          pushgateway, ex: curl --data-binary @<PATH> http://pushgateway:9091/metrics/job/importer)
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use data_models::monitoramento::TextoDoPrometheus;
+use serde::Serialize;
+use tracing::error;
+
+use crate::cli::Cli;
+
+// intervalo mínimo entre as gravações do arquivo de métricas durante a importação
+const INTERVALO_DAS_METRICAS: Duration = Duration::from_secs(1);
+
+// valor de uma métrica extraído do relatório de um arquivo
+type ValorDaMetrica = fn(&RelatorioDoArquivo) -> f64;
+
+#[derive(Clone, Copy)]
+pub enum FormatoDoRelatorio {
+    Json,
+}
+
+impl FromStr for FormatoDoRelatorio {
+    type Err = String;
+
+    fn from_str(formato: &str) -> Result<Self, Self::Err> {
+        match formato {
+            "json" => Ok(FormatoDoRelatorio::Json),
+            _ => Err(format!("Formato de relatório inválido: {} (utilize json)", formato)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SituacaoDoArquivo {
+    EmAndamento,
+    Importado,
+    // o arquivo já havia sido importado e não foi utilizado o flag --force
+    Ignorado,
+    Erro,
+}
+
+impl SituacaoDoArquivo {
+    fn nome(&self) -> &'static str {
+        match self {
+            SituacaoDoArquivo::EmAndamento => "em_andamento",
+            SituacaoDoArquivo::Importado => "importado",
+            SituacaoDoArquivo::Ignorado => "ignorado",
+            SituacaoDoArquivo::Erro => "erro",
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct RelatorioDoArquivo {
+    pub arquivo: String,
+    pub tabela: String,
+    // número da parte (Y1, Y2, ..., Y0) nas tabelas grandes; 0 nas demais
+    pub parte: u8,
+    pub situacao: SituacaoDoArquivo,
+    pub registros_lidos: u64,
+    // registros incluídos ou atualizados (REPLACE INTO) na tabela
+    pub registros_inseridos: u64,
+    // registros que repetem a chave de outro registro já importado da tabela (vide --duplicates)
+    pub registros_duplicados: u64,
+    // registros inválidos, que não foram gravados (ex: empresa sem razão social)
+    pub registros_rejeitados: u64,
+    pub duracao_em_segundos: f64,
+    pub registros_por_segundo: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub erro: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RelatorioDaExecucao<'a> {
+    inicio: DateTime<Utc>,
+    fim: DateTime<Utc>,
+    duracao_em_segundos: f64,
+    sucesso: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    erro: Option<&'a str>,
+    arquivos: &'a [RelatorioDoArquivo],
+}
+
+pub struct Relatorio {
+    formato: Option<FormatoDoRelatorio>,
+    arquivo_do_relatorio: Option<PathBuf>,
+    arquivo_de_metricas: Option<PathBuf>,
+    inicio: DateTime<Utc>,
+    instante_do_inicio: Instant,
+    ultima_gravacao_das_metricas: Option<Instant>,
+    arquivos: Vec<RelatorioDoArquivo>,
+}
+
+impl Relatorio {
+    pub fn new(args: &Cli) -> Relatorio {
+        Relatorio {
+            formato: args.report,
+            arquivo_do_relatorio: args.report_file.clone(),
+            arquivo_de_metricas: args.metrics_file.clone(),
+            inicio: Utc::now(),
+            instante_do_inicio: Instant::now(),
+            ultima_gravacao_das_metricas: None,
+            arquivos: Vec::new(),
+        }
+    }
+
+    // Atualiza as métricas com os contadores do arquivo que está sendo importado
+    pub fn atualizar(&mut self, atual: &RelatorioDoArquivo) {
+        let gravar = match self.ultima_gravacao_das_metricas {
+            Some(instante) => instante.elapsed() >= INTERVALO_DAS_METRICAS,
+            None => true,
+        };
+        if gravar {
+            self.gravar_metricas(Some(atual), true);
+        }
+    }
+
+    // Registra o relatório de um arquivo que terminou de ser processado
+    pub fn registrar(&mut self, arquivo: RelatorioDoArquivo) {
+        self.arquivos.push(arquivo);
+        self.gravar_metricas(None, true);
+    }
+
+    // Indica se algum arquivo foi importado (e não apenas ignorado) na execução
+    pub fn importou_arquivos(&self) -> bool {
+        self.arquivos.iter().any(|arquivo| arquivo.situacao == SituacaoDoArquivo::Importado)
+    }
+
+    // Grava o relatório e as métricas finais da execução
+    pub fn finalizar(&mut self, resultado: &Result<(), String>) -> Result<(), String> {
+        self.gravar_metricas(None, false);
+
+        let formato = match self.formato {
+            Some(formato) => formato,
+            None => return Ok(()),
+        };
+
+        let relatorio = RelatorioDaExecucao {
+            inicio: self.inicio,
+            fim: Utc::now(),
+            duracao_em_segundos: self.instante_do_inicio.elapsed().as_secs_f64(),
+            sucesso: resultado.is_ok(),
+            erro: resultado.as_ref().err().map(|erro| erro.as_str()),
+            arquivos: &self.arquivos,
+        };
+        let texto = match formato {
+            FormatoDoRelatorio::Json => serde_json::to_string_pretty(&relatorio)
+                .map_err(|error| format!("Erro ao gerar o relatório da importação: {}", error))?,
+        };
+
+        match &self.arquivo_do_relatorio {
+            Some(caminho) => gravar_arquivo(caminho, &texto)
+                .map_err(|error| format!("Erro ao gravar o relatório da importação em {}: {}", caminho.display(), error)),
+            None => {
+                println!("{}", texto);
+                Ok(())
+            }
+        }
+    }
+
+    fn gravar_metricas(&mut self, atual: Option<&RelatorioDoArquivo>, em_andamento: bool) {
+        let caminho = match &self.arquivo_de_metricas {
+            Some(caminho) => caminho,
+            None => return,
+        };
+
+        let mut texto = TextoDoPrometheus::new();
+        let arquivos: Vec<&RelatorioDoArquivo> = self.arquivos.iter().chain(atual).collect();
+
+        texto.metrica("cnpj_importacao_em_andamento", "gauge", "Indica se a importação está em andamento (1) ou terminou (0)");
+        texto.valor("cnpj_importacao_em_andamento", &[], if em_andamento { 1.0 } else { 0.0 });
+        texto.metrica("cnpj_importacao_inicio_timestamp_segundos", "gauge", "Data e hora do início da importação (Unix)");
+        texto.valor("cnpj_importacao_inicio_timestamp_segundos", &[], self.inicio.timestamp() as f64);
+        texto.metrica("cnpj_importacao_atualizacao_timestamp_segundos", "gauge", "Data e hora da última atualização destas métricas (Unix)");
+        texto.valor("cnpj_importacao_atualizacao_timestamp_segundos", &[], Utc::now().timestamp() as f64);
+
+        texto.metrica("cnpj_importacao_arquivos", "gauge", "Arquivos processados por situação");
+        for situacao in &[SituacaoDoArquivo::EmAndamento, SituacaoDoArquivo::Importado, SituacaoDoArquivo::Ignorado, SituacaoDoArquivo::Erro] {
+            let quantidade = arquivos.iter().filter(|arquivo| arquivo.situacao == *situacao).count();
+            texto.valor("cnpj_importacao_arquivos", &[("situacao", situacao.nome())], quantidade as f64);
+        }
+
+        let contadores: [(&str, &str, ValorDaMetrica); 5] = [
+            ("cnpj_importacao_registros_lidos_total", "Registros lidos do arquivo", |arquivo| arquivo.registros_lidos as f64),
+            ("cnpj_importacao_registros_inseridos_total", "Registros incluídos ou atualizados na tabela", |arquivo| arquivo.registros_inseridos as f64),
+            ("cnpj_importacao_registros_duplicados_total", "Registros que repetem a chave de outro registro já importado da tabela", |arquivo| arquivo.registros_duplicados as f64),
+            ("cnpj_importacao_registros_rejeitados_total", "Registros inválidos que não foram gravados", |arquivo| arquivo.registros_rejeitados as f64),
+            ("cnpj_importacao_duracao_segundos", "Duração da importação do arquivo", |arquivo| arquivo.duracao_em_segundos),
+        ];
+        for (nome, ajuda, valor) in contadores.iter() {
+            let tipo = if nome.ends_with("_total") { "counter" } else { "gauge" };
+            texto.metrica(nome, tipo, ajuda);
+            for arquivo in &arquivos {
+                texto.valor(nome, &[("arquivo", &arquivo.arquivo), ("tabela", &arquivo.tabela)], valor(arquivo));
+            }
+        }
+
+        if let Err(error) = gravar_arquivo(caminho, &texto.texto()) {
+            error!(arquivo_de_metricas = %caminho.display(), erro = %error, "Erro ao gravar as métricas da importação");
+        }
+        self.ultima_gravacao_das_metricas = Some(Instant::now());
+    }
+}
+
+// Grava o arquivo por inteiro (em um arquivo temporário renomeado em seguida), para que quem o lê durante a
+// importação nunca encontre um arquivo gravado pela metade
+pub fn gravar_arquivo(caminho: &Path, texto: &str) -> std::io::Result<()> {
+    let mut temporario = caminho.as_os_str().to_owned();
+    temporario.push(".tmp");
+    fs::write(&temporario, texto)?;
+    fs::rename(&temporario, caminho)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    // Arquivo temporário, removido ao final do teste
+    struct ArquivoTemporario(PathBuf);
+
+    impl ArquivoTemporario {
+        fn new(nome: &str) -> ArquivoTemporario {
+            ArquivoTemporario(std::env::temp_dir().join(format!("importer_{}_{}", std::process::id(), nome)))
+        }
+
+        fn ler(&self) -> String {
+            fs::read_to_string(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for ArquivoTemporario {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn relatorio(arquivo_do_relatorio: Option<&ArquivoTemporario>, arquivo_de_metricas: Option<&ArquivoTemporario>) -> Relatorio {
+        Relatorio {
+            formato: Some(FormatoDoRelatorio::Json),
+            arquivo_do_relatorio: arquivo_do_relatorio.map(|arquivo| arquivo.0.clone()),
+            arquivo_de_metricas: arquivo_de_metricas.map(|arquivo| arquivo.0.clone()),
+            inicio: Utc::now(),
+            instante_do_inicio: Instant::now(),
+            ultima_gravacao_das_metricas: None,
+            arquivos: Vec::new(),
+        }
+    }
+
+    fn arquivo(arquivo: &str, situacao: SituacaoDoArquivo, registros: [u64; 4], erro: Option<&str>) -> RelatorioDoArquivo {
+        RelatorioDoArquivo {
+            arquivo: arquivo.to_string(),
+            tabela: String::from("empresas"),
+            parte: 1,
+            situacao,
+            registros_lidos: registros[0],
+            registros_inseridos: registros[1],
+            registros_duplicados: registros[2],
+            registros_rejeitados: registros[3],
+            duracao_em_segundos: 2.5,
+            registros_por_segundo: registros[1] as f64 / 2.5,
+            erro: erro.map(String::from),
+        }
+    }
+
+    #[test]
+    fn relatorio_em_json() {
+        let arquivo_do_relatorio = ArquivoTemporario::new("relatorio_em_json.json");
+        let mut relatorio = relatorio(Some(&arquivo_do_relatorio), None);
+        relatorio.registrar(arquivo("K3241.K03200Y1.D10911.EMPRECSV.zip", SituacaoDoArquivo::Importado, [1000, 990, 7, 3], None));
+        relatorio.registrar(arquivo("K3241.K03200Y2.D10911.EMPRECSV.zip", SituacaoDoArquivo::Erro, [10, 0, 0, 0], Some("Arquivo corrompido")));
+
+        relatorio.finalizar(&Err(String::from("Erro ao importar o diretório"))).unwrap();
+
+        let json: Value = serde_json::from_str(&arquivo_do_relatorio.ler()).unwrap();
+        assert_eq!(json["sucesso"], false);
+        assert_eq!(json["erro"], "Erro ao importar o diretório");
+        assert!(json["duracao_em_segundos"].as_f64().unwrap() >= 0.0);
+        assert!(json["inicio"].as_str().unwrap() <= json["fim"].as_str().unwrap());
+
+        let arquivos = json["arquivos"].as_array().unwrap();
+        assert_eq!(arquivos.len(), 2);
+        assert_eq!(arquivos[0]["arquivo"], "K3241.K03200Y1.D10911.EMPRECSV.zip");
+        assert_eq!(arquivos[0]["tabela"], "empresas");
+        assert_eq!(arquivos[0]["parte"], 1);
+        assert_eq!(arquivos[0]["situacao"], "importado");
+        assert_eq!(arquivos[0]["registros_lidos"], 1000);
+        assert_eq!(arquivos[0]["registros_inseridos"], 990);
+        assert_eq!(arquivos[0]["registros_duplicados"], 7);
+        assert_eq!(arquivos[0]["registros_rejeitados"], 3);
+        assert_eq!(arquivos[0]["duracao_em_segundos"], 2.5);
+        assert_eq!(arquivos[0]["registros_por_segundo"], 396.0);
+        assert!(arquivos[0].get("erro").is_none());
+        assert_eq!(arquivos[1]["situacao"], "erro");
+        assert_eq!(arquivos[1]["erro"], "Arquivo corrompido");
+    }
+
+    #[test]
+    fn relatorio_da_execucao_sem_erro() {
+        let arquivo_do_relatorio = ArquivoTemporario::new("relatorio_sem_erro.json");
+        let mut relatorio = relatorio(Some(&arquivo_do_relatorio), None);
+        relatorio.registrar(arquivo("K3241.K03200Y0.D10911.EMPRECSV.zip", SituacaoDoArquivo::Ignorado, [0, 0, 0, 0], None));
+
+        relatorio.finalizar(&Ok(())).unwrap();
+
+        let json: Value = serde_json::from_str(&arquivo_do_relatorio.ler()).unwrap();
+        assert_eq!(json["sucesso"], true);
+        assert!(json.get("erro").is_none());
+        assert_eq!(json["arquivos"][0]["situacao"], "ignorado");
+    }
+
+    #[test]
+    fn arquivos_importados() {
+        let mut relatorio = relatorio(None, None);
+        relatorio.formato = None;
+        assert!(!relatorio.importou_arquivos());
+
+        relatorio.registrar(arquivo("a.zip", SituacaoDoArquivo::Ignorado, [0, 0, 0, 0], None));
+        relatorio.registrar(arquivo("b.zip", SituacaoDoArquivo::Erro, [1, 0, 0, 0], Some("erro")));
+        assert!(!relatorio.importou_arquivos());
+
+        relatorio.registrar(arquivo("c.zip", SituacaoDoArquivo::Importado, [1, 1, 0, 0], None));
+        assert!(relatorio.importou_arquivos());
+
+        // sem o --report, nada é gravado
+        assert_eq!(relatorio.finalizar(&Ok(())), Ok(()));
+    }
+
+    #[test]
+    fn metricas_da_importacao() {
+        let arquivo_de_metricas = ArquivoTemporario::new("metricas.prom");
+        let mut relatorio = relatorio(None, Some(&arquivo_de_metricas));
+        relatorio.formato = None;
+
+        relatorio.registrar(arquivo("a.zip", SituacaoDoArquivo::Importado, [100, 95, 2, 3], None));
+        // as métricas do arquivo em andamento são gravadas no máximo uma vez a cada INTERVALO_DAS_METRICAS
+        relatorio.atualizar(&arquivo("b.zip", SituacaoDoArquivo::EmAndamento, [10, 8, 0, 0], None));
+        assert!(!arquivo_de_metricas.ler().contains("b.zip"));
+        relatorio.ultima_gravacao_das_metricas = Some(Instant::now() - INTERVALO_DAS_METRICAS);
+        relatorio.atualizar(&arquivo("b.zip", SituacaoDoArquivo::EmAndamento, [50, 40, 0, 1], None));
+
+        let metricas = arquivo_de_metricas.ler();
+        assert!(metricas.contains("cnpj_importacao_em_andamento 1\n"));
+        assert!(metricas.contains("cnpj_importacao_arquivos{situacao=\"importado\"} 1\n"));
+        assert!(metricas.contains("cnpj_importacao_arquivos{situacao=\"em_andamento\"} 1\n"));
+        assert!(metricas.contains("# TYPE cnpj_importacao_registros_lidos_total counter\n"));
+        assert!(metricas.contains("cnpj_importacao_registros_lidos_total{arquivo=\"a.zip\",tabela=\"empresas\"} 100\n"));
+        assert!(metricas.contains("cnpj_importacao_registros_lidos_total{arquivo=\"b.zip\",tabela=\"empresas\"} 50\n"));
+        assert!(metricas.contains("cnpj_importacao_registros_duplicados_total{arquivo=\"a.zip\",tabela=\"empresas\"} 2\n"));
+        assert!(metricas.contains("cnpj_importacao_registros_rejeitados_total{arquivo=\"a.zip\",tabela=\"empresas\"} 3\n"));
+        assert!(metricas.contains("# TYPE cnpj_importacao_duracao_segundos gauge\n"));
+        assert!(metricas.contains("cnpj_importacao_duracao_segundos{arquivo=\"a.zip\",tabela=\"empresas\"} 2.5\n"));
+
+        // ao final, o arquivo em andamento deixa de constar das métricas
+        relatorio.finalizar(&Ok(())).unwrap();
+        let metricas = arquivo_de_metricas.ler();
+        assert!(metricas.contains("cnpj_importacao_em_andamento 0\n"));
+        assert!(metricas.contains("cnpj_importacao_arquivos{situacao=\"em_andamento\"} 0\n"));
+        assert!(!metricas.contains("b.zip"));
+    }
+
+    #[test]
+    fn formato_do_relatorio() {
+        assert!(matches!("json".parse::<FormatoDoRelatorio>(), Ok(FormatoDoRelatorio::Json)));
+        assert_eq!(
+            "xml".parse::<FormatoDoRelatorio>().err(),
+            Some(String::from("Formato de relatório inválido: xml (utilize json)"))
+        );
+    }
+}
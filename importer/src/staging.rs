@@ -0,0 +1,105 @@
+// Tabelas de carga (staging) das tabelas grandes, utilizadas na importação com o flag --staging: as partes
+// (Y1, Y2, ..., Y0) são importadas na tabela <tabela>_staging, criada com CREATE TABLE ... LIKE a partir da
+// tabela original (vide Database::create_staging_table), que é trocada pela tabela de carga (RENAME TABLE)
+// ao final da importação da última parte.
+//
+// Como as duas tabelas têm as mesmas colunas, as consultas são montadas sobre a tabela original (com o
+// mapeamento dos registros de data_models::models) e apenas o nome da tabela é trocado no SQL gerado.
+
+use diesel::mysql::{Mysql, MysqlQueryBuilder};
+use diesel::query_builder::{AstPass, QueryBuilder, QueryFragment, QueryId};
+use diesel::{QueryResult, RunQueryDsl};
+
+pub fn staging_table_name(table_name: &str) -> String {
+    format!("{}_staging", table_name)
+}
+
+// Consulta (insert, replace ou delete) da tabela original executada na tabela de carga
+pub struct NaTabelaDeCarga<Q> {
+    consulta: Q,
+    tabela: String,
+    tabela_de_carga: String,
+}
+
+impl<Q> NaTabelaDeCarga<Q> {
+    pub fn new(consulta: Q, table_name: &str) -> NaTabelaDeCarga<Q> {
+        NaTabelaDeCarga {
+            consulta,
+            tabela: identificador(table_name),
+            tabela_de_carga: identificador(&staging_table_name(table_name)),
+        }
+    }
+}
+
+// Nome da tabela como ele aparece no SQL gerado pelo diesel (ex: `empresas`)
+fn identificador(table_name: &str) -> String {
+    let mut query_builder = MysqlQueryBuilder::new();
+    // o push_identifier do MySQL não falha
+    let _ = query_builder.push_identifier(table_name);
+    query_builder.finish()
+}
+
+impl<Q: QueryFragment<Mysql>> QueryFragment<Mysql> for NaTabelaDeCarga<Q> {
+    // os parâmetros (bind) da consulta são os mesmos, só o SQL muda (vide to_sql)
+    fn walk_ast(&self, out: AstPass<Mysql>) -> QueryResult<()> {
+        self.consulta.walk_ast(out)
+    }
+
+    // o nome da tabela aparece no SQL como identificador (`empresas`), inclusive ao qualificar as colunas
+    // (`empresas`.`cnpj_basico`), e as colunas nunca têm o mesmo nome da tabela
+    fn to_sql(&self, out: &mut MysqlQueryBuilder) -> QueryResult<()> {
+        let mut query_builder = MysqlQueryBuilder::new();
+        self.consulta.to_sql(&mut query_builder)?;
+        out.push_sql(&query_builder.finish().replace(&self.tabela, &self.tabela_de_carga));
+        Ok(())
+    }
+}
+
+// Sem identificador estático, o cache de prepared statements do diesel utiliza o próprio SQL (já com o nome
+// da tabela de carga) como chave
+impl<Q> QueryId for NaTabelaDeCarga<Q> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<Q, Conn> RunQueryDsl<Conn> for NaTabelaDeCarga<Q> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_models::schema::{empresas, socios};
+    use diesel::debug_query;
+    use diesel::prelude::*;
+
+    fn sql<Q: QueryFragment<Mysql>>(consulta: &Q) -> String {
+        let mut query_builder = MysqlQueryBuilder::new();
+        consulta.to_sql(&mut query_builder).unwrap();
+        query_builder.finish()
+    }
+
+    #[test]
+    fn insert_na_tabela_de_carga() {
+        let consulta = diesel::insert_into(empresas::table).values((
+            empresas::cnpj_basico.eq("12345678"),
+            empresas::razao_social.eq("EMPRESA"),
+        ));
+        let original = debug_query::<Mysql, _>(&consulta).to_string();
+
+        let na_tabela_de_carga = sql(&NaTabelaDeCarga::new(consulta, "empresas"));
+
+        assert!(original.starts_with("INSERT INTO `empresas` (`cnpj_basico`, `razao_social`)"));
+        assert!(na_tabela_de_carga.starts_with("INSERT INTO `empresas_staging` (`cnpj_basico`, `razao_social`)"));
+        assert!(!na_tabela_de_carga.contains("`empresas`"));
+    }
+
+    #[test]
+    fn delete_com_colunas_qualificadas_na_tabela_de_carga() {
+        let consulta = diesel::delete(socios::table.filter(socios::cnpj_basico.eq("12345678")));
+
+        assert_eq!(
+            sql(&NaTabelaDeCarga::new(consulta, "socios")),
+            "DELETE FROM `socios_staging` WHERE `socios_staging`.`cnpj_basico` = ?"
+        );
+    }
+}
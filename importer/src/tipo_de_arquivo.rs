@@ -47,7 +47,7 @@ impl FromStr for TipoDeArquivo {
 }
 
 impl TipoDeArquivo {
-    pub fn table_name(&self) -> &str {
+    pub fn table_name(&self) -> &'static str {
         match &self {
             TipoDeArquivo::CNAES => "cnaes",
             TipoDeArquivo::Empresas => "empresas",
@@ -61,4 +61,17 @@ impl TipoDeArquivo {
             TipoDeArquivo::Socios => "socios",            
         }
     }
-}
\ No newline at end of file
+
+    // tabelas grandes, cujos registros são gravados em lotes (vide --rows-per-insert)
+    pub fn is_large_table(&self) -> bool {
+        matches!(
+            self,
+            TipoDeArquivo::Empresas | TipoDeArquivo::Estabelecimentos | TipoDeArquivo::Socios | TipoDeArquivo::Simples
+        )
+    }
+
+    // tabelas cujos arquivos são divididos em partes (Y1, Y2, ..., Y0)
+    pub fn has_parts(&self) -> bool {
+        matches!(self, TipoDeArquivo::Empresas | TipoDeArquivo::Estabelecimentos | TipoDeArquivo::Socios)
+    }
+}
@@ -0,0 +1,110 @@
+// Exige uma chave de API (cabeçalho X-API-Key ou Authorization: Bearer) nas requisições às rotas da API
+// e controla as cotas de requisições de cada chave (vide data_models::acesso). É habilitado pela variável
+// de ambiente EXIGIR_CHAVE_DE_API.
+//
+// As chaves são carregadas na inicialização (o servidor não é iniciado se não for possível carregá-las) e
+// sincronizadas com o banco de dados por uma tarefa em segundo plano; se uma sincronização falhar, as chaves
+// carregadas anteriormente continuam valendo.
+//
+// O Rocket não permite que um fairing responda à requisição, então as requisições recusadas são desviadas
+// para uma rota inexistente e o catcher responde com 401 (chave ausente ou inválida) ou 429 (cota excedida,
+// com o cabeçalho Retry-After), conforme o motivo guardado no cache local da requisição.
+
+use std::sync::Arc;
+
+use data_models::acesso::{chave_da_requisicao, ControleDeAcesso, INTERVALO_DE_SINCRONIZACAO};
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use diesel::Connection;
+use rocket::{Build, Data, Request, Rocket};
+use rocket_sync_db_pools::Config;
+
+use crate::DBPool;
+
+// rotas que não exigem a chave de API
+const ROTAS_PUBLICAS: &[&str] = &["/api/openapi.json", "/api/docs", "/api/docs/swagger-ui.css", "/api/docs/swagger-ui-bundle.js"];
+
+// caminho (sem rota) para o qual as requisições recusadas são desviadas
+const ROTA_DA_RECUSA: &str = "/api/acesso_recusado";
+
+#[derive(Default)]
+pub struct ControleDeAcessoDaApi {
+    controle: Arc<ControleDeAcesso>,
+}
+
+#[rocket::async_trait]
+impl Fairing for ControleDeAcessoDaApi {
+    fn info(&self) -> Info {
+        Info {
+            name: "Controle de acesso por chave de API",
+            kind: Kind::Ignite | Kind::Request,
+        }
+    }
+
+    // Carrega as chaves de API antes de iniciar o servidor (o fairing deve ser anexado após o do DBPool) e
+    // inicia a sincronização periódica das chaves e dos contadores de uso com o banco de dados
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let conn = match DBPool::get_one(&rocket).await {
+            Some(conn) => conn,
+            None => {
+                tracing::error!("Não foi possível obter uma conexão para carregar as chaves de API");
+                return Err(rocket);
+            }
+        };
+
+        let controle = self.controle.clone();
+        if let Err(error) = conn.consultar(move |c| controle.sincronizar(c)).await {
+            tracing::error!(erro = ?error, "Erro ao carregar as chaves de API");
+            return Err(rocket);
+        }
+
+        // a tarefa utiliza uma conexão própria, para não ocupar uma conexão do pool entre as sincronizações
+        let url = match Config::from("cnpj_db", &rocket) {
+            Ok(config) => config.url,
+            Err(error) => {
+                tracing::error!(erro = %error, "Erro ao ler a configuração do banco de dados");
+                return Err(rocket);
+            }
+        };
+        let controle = self.controle.clone();
+        rocket::tokio::spawn(async move {
+            loop {
+                rocket::tokio::time::sleep(INTERVALO_DE_SINCRONIZACAO).await;
+
+                let url = url.clone();
+                let controle = controle.clone();
+                let resultado = rocket::tokio::task::spawn_blocking(move || {
+                    let connection = diesel::MysqlConnection::establish(&url).map_err(|error| format!("{:?}", error))?;
+                    controle.sincronizar(&connection).map_err(|error| format!("{:?}", error))
+                })
+                .await;
+                match resultado {
+                    Ok(Ok(())) => (),
+                    Ok(Err(error)) => tracing::error!(erro = %error, "Erro ao sincronizar as chaves de API"),
+                    Err(error) => tracing::error!(erro = ?error, "Erro ao sincronizar as chaves de API"),
+                }
+            }
+        });
+
+        Ok(rocket)
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let caminho = request.uri().path();
+        if !caminho.starts_with("/api/") || ROTAS_PUBLICAS.contains(&caminho.as_str()) {
+            return;
+        }
+
+        let headers = request.headers();
+        match self.controle.verificar(chave_da_requisicao(headers.get_one("X-API-Key"), headers.get_one("Authorization"))) {
+            Ok(chave) => {
+                request.local_cache(|| Some(chave));
+            }
+            Err(recusa) => {
+                request.local_cache(|| Some(recusa));
+                request.set_uri(Origin::parse(ROTA_DA_RECUSA).unwrap());
+            }
+        }
+    }
+}
+
@@ -0,0 +1,195 @@
+// Cache HTTP das respostas da API. Os dados só mudam a cada importação, então as respostas de sucesso
+// das requisições GET/HEAD recebem os cabeçalhos ETag e Last-Modified (derivados da versão das tabelas
+// consultadas, vide data_models::versao) e Cache-Control, e as requisições condicionais (If-None-Match
+// ou If-Modified-Since) cuja representação não mudou são respondidas com 304 (Not Modified).
+//
+// A validação é feita antes da rota, para que as requisições não modificadas não consultem o banco de
+// dados. Como o Rocket não permite que um fairing responda à requisição, elas são desviadas para uma rota
+// interna que responde 304. O ETag só é conhecido pelo cliente após uma resposta de sucesso, mas um
+// If-None-Match: * ou um If-Modified-Since recebem 304 mesmo em URIs que responderiam com erro.
+//
+// A representação depende do perfil de exposição dos dados pessoais (vide lgpd), então o nome do perfil
+// faz parte do ETag. Com a chave de API, o perfil é o da chave e as respostas não podem ser compartilhadas
+// pelos caches intermediários (Cache-Control: private); sem ela, todos recebem o perfil padrão.
+
+use data_models::acesso::RecusaDeAcesso;
+use data_models::lgpd::PerfisDeExposicao;
+use data_models::models::{ChaveDeApi, MetadadosDasTabelas};
+use data_models::versao::{metadados_das_tabelas, MetadadosRecentes, VersaoDosDados};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::{Header, Method, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Data, Request, Response, Route};
+
+use crate::DBPool;
+
+// tempo (em segundos) que as respostas podem ser mantidas em cache pelos clientes e CDNs, quando não
+// informado na variável de ambiente CACHE_MAX_AGE
+const MAX_AGE_PADRAO: u64 = 3600;
+
+// rota interna para a qual as requisições não modificadas são desviadas
+pub const ROTA_DO_NAO_MODIFICADO: &str = "/api/nao_modificado";
+
+// Validadores da representação solicitada, guardados no cache local da requisição
+struct Validadores {
+    etag: String,
+    last_modified: String,
+    // a requisição é feita com a chave de API
+    com_chave: bool,
+    nao_modificado: bool,
+}
+
+pub struct CacheHttp {
+    max_age: u64,
+    metadados: MetadadosRecentes,
+}
+
+impl CacheHttp {
+    pub fn new(max_age: Option<u64>) -> CacheHttp {
+        CacheHttp {
+            max_age: max_age.unwrap_or(MAX_AGE_PADRAO),
+            metadados: MetadadosRecentes::default(),
+        }
+    }
+
+    async fn metadados(&self, request: &Request<'_>) -> Option<Vec<MetadadosDasTabelas>> {
+        if let Some(metadados) = self.metadados.obter() {
+            return Some(metadados);
+        }
+
+        let conn = DBPool::get_one(request.rocket()).await?;
+        match conn.consultar(|c| metadados_das_tabelas(c)).await {
+            Ok(metadados) => {
+                self.metadados.atualizar(metadados.clone());
+                Some(metadados)
+            }
+            Err(error) => {
+                tracing::error!(erro = ?error, "Erro ao ler os metadados das tabelas");
+                None
+            }
+        }
+    }
+}
+
+// Tabelas cujos dados são retornados pela rota (lista vazia = todas as tabelas). As rotas que não
+// dependem dos dados importados (ex: documentação) não são tratadas pelo cache.
+fn tabelas_da_rota(caminho: &str) -> Option<&'static [&'static str]> {
+    let caminho = caminho.strip_prefix("/api/v1").or_else(|| caminho.strip_prefix("/api"))?;
+
+    match caminho.trim_start_matches('/').split('/').next()? {
+        "openapi.json" | "docs" | "cache" => None,
+        "cnaes" => Some(&["cnaes"]),
+        "naturezas_juridicas" => Some(&["naturezas_juridicas"]),
+        "qualificacoes_de_socios" => Some(&["qualificacoes_de_socios"]),
+        "paises" => Some(&["paises"]),
+        "municipios" => Some(&["municipios"]),
+        "motivos_de_situacoes_cadastrais" => Some(&["motivos_de_situacoes_cadastrais"]),
+        _ => Some(&[]),
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for CacheHttp {
+    fn info(&self) -> Info {
+        Info {
+            name: "Cache HTTP (ETag/Last-Modified)",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    // Calcula os validadores da representação e desvia as requisições não modificadas (o fairing deve ser
+    // anexado após o do controle de acesso, que identifica a chave de API)
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        if !matches!(request.method(), Method::Get | Method::Head) || request.local_cache(|| None::<RecusaDeAcesso>).is_some() {
+            return;
+        }
+
+        let tabelas = match tabelas_da_rota(request.uri().path().as_str()) {
+            Some(tabelas) => tabelas,
+            None => return,
+        };
+
+        let versao = match self.metadados(request).await.and_then(|m| VersaoDosDados::de(&m, tabelas)) {
+            Some(versao) => versao,
+            None => return,
+        };
+
+        // a chave é guardada no cache local da requisição pelo controle de acesso (vide acesso)
+        let chave = request.local_cache(|| None::<ChaveDeApi>);
+        let perfil = match request.rocket().state::<PerfisDeExposicao>() {
+            Some(perfis) => perfis.perfil(chave.as_ref().map(|chave| chave.perfil.as_str())).nome,
+            None => return,
+        };
+
+        // a mesma URI pode ter representações diferentes conforme o formato solicitado (JSON, NDJSON ou CSV)
+        // e o perfil de exposição
+        let accept = request.headers().get_one("Accept").unwrap_or_default();
+        let etag = versao.etag(&format!("{} {} {}", request.uri(), accept, perfil));
+
+        let nao_modificado = versao.nao_modificado(
+            &etag,
+            request.headers().get_one("If-None-Match"),
+            request.headers().get_one("If-Modified-Since"),
+        );
+
+        let com_chave = chave.is_some();
+        request.local_cache(|| {
+            Some(Validadores {
+                etag,
+                last_modified: versao.last_modified(),
+                com_chave,
+                nao_modificado,
+            })
+        });
+        if nao_modificado {
+            request.set_uri(Origin::parse(ROTA_DO_NAO_MODIFICADO).unwrap());
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let validadores = match request.local_cache(|| None::<Validadores>) {
+            Some(validadores) => validadores,
+            None => return,
+        };
+
+        let status = if validadores.nao_modificado { Status::NotModified } else { Status::Ok };
+        if response.status() != status {
+            return;
+        }
+
+        response.set_header(Header::new("ETag", validadores.etag.clone()));
+        response.set_header(Header::new("Last-Modified", validadores.last_modified.clone()));
+        if validadores.com_chave {
+            response.set_header(Header::new("Cache-Control", format!("private, max-age={}", self.max_age)));
+            response.set_header(Header::new("Vary", "Accept, Authorization, X-API-Key"));
+        } else {
+            response.set_header(Header::new("Cache-Control", format!("public, max-age={}", self.max_age)));
+            response.set_header(Header::new("Vary", "Accept"));
+        }
+    }
+}
+
+// Guarda de requisição que só permite a rota interna às requisições desviadas pelo fairing
+struct NaoModificado;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for NaoModificado {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        match request.local_cache(|| None::<Validadores>) {
+            Some(validadores) if validadores.nao_modificado => Outcome::Success(NaoModificado),
+            _ => Outcome::Forward(()),
+        }
+    }
+}
+
+#[get("/nao_modificado")]
+fn get_nao_modificado(_nao_modificado: NaoModificado) -> (Status, ()) {
+    (Status::NotModified, ())
+}
+
+pub fn rotas() -> Vec<Route> {
+    routes![get_nao_modificado]
+}
@@ -0,0 +1,292 @@
+// Formatos de resposta das rotas que retornam listas de registros. Além do JSON (padrão), é possível
+// solicitar, por meio do cabeçalho Accept, as respostas em NDJSON (application/x-ndjson) ou CSV (text/csv),
+// ou, por meio do parâmetro formato (json, ndjson ou csv), que tem precedência sobre o cabeçalho.
+// Nestes dois formatos a resposta é enviada em streaming, à medida que os registros são lidos do banco
+// de dados em blocos, de modo que o consumo de memória não depende da quantidade de registros retornados.
+
+use std::io::Cursor;
+
+use rocket::futures::stream::{BoxStream, StreamExt};
+use rocket::http::{ContentType, MediaType, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::stream::ReaderStream;
+use rocket::response::{Responder, Response, Result};
+use rocket::serde::json::{self, Json};
+use rocket::Request;
+use data_models::busca::ResultadoDaBusca;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Formato {
+    Json,
+    Ndjson,
+    Csv,
+}
+
+impl Formato {
+    pub fn content_type(&self) -> ContentType {
+        match self {
+            Formato::Json => ContentType::JSON,
+            Formato::Ndjson => ContentType::new("application", "x-ndjson"),
+            Formato::Csv => ContentType::new("text", "csv").with_params(("charset", "utf-8")),
+        }
+    }
+
+    pub fn do_nome(nome: &str) -> Option<Formato> {
+        match nome {
+            "json" => Some(Formato::Json),
+            "ndjson" => Some(Formato::Ndjson),
+            "csv" => Some(Formato::Csv),
+            _ => None,
+        }
+    }
+
+    // Indica se a resposta deve ser enviada em streaming
+    pub fn streaming(&self) -> bool {
+        *self != Formato::Json
+    }
+
+    // Converte um bloco de registros nas linhas correspondentes do formato. No CSV, o cabeçalho é
+    // incluído apenas quando solicitado (no primeiro bloco).
+    pub fn linhas<T: Serialize + LinhaCsv>(&self, registros: &[T], com_cabecalho: bool) -> Vec<u8> {
+        match self {
+            Formato::Csv => {
+                let mut writer = csv::Writer::from_writer(vec![]);
+                if com_cabecalho {
+                    writer.write_record(T::cabecalho()).expect("Erro ao gerar o cabeçalho do CSV");
+                }
+                for registro in registros {
+                    writer.write_record(registro.campos()).expect("Erro ao gerar a linha do CSV");
+                }
+                writer.into_inner().expect("Erro ao gerar o CSV")
+            }
+            _ => {
+                let mut linhas = Vec::new();
+                for registro in registros {
+                    linhas.extend(json::to_string(registro).expect("Erro ao serializar o registro").into_bytes());
+                    linhas.push(b'\n');
+                }
+                linhas
+            }
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Formato {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if let Some(nome) = request.query_value::<&str>("formato") {
+            return match nome.ok().and_then(Formato::do_nome) {
+                Some(formato) => Outcome::Success(formato),
+                None => Outcome::Failure((Status::BadRequest, ())),
+            };
+        }
+
+        let ndjson = MediaType::new("application", "x-ndjson");
+
+        let formato = request
+            .accept()
+            .and_then(|accept| {
+                accept.media_types().find_map(|media_type| {
+                    if *media_type == ndjson {
+                        Some(Formato::Ndjson)
+                    } else if *media_type == MediaType::CSV {
+                        Some(Formato::Csv)
+                    } else if *media_type == MediaType::JSON {
+                        Some(Formato::Json)
+                    } else {
+                        None
+                    }
+                })
+            })
+            .unwrap_or(Formato::Json);
+
+        Outcome::Success(formato)
+    }
+}
+
+// Registro que pode ser exportado como uma linha de um arquivo CSV
+pub trait LinhaCsv {
+    fn cabecalho() -> Vec<&'static str>;
+    fn campos(&self) -> Vec<String>;
+}
+
+// Converte um campo opcional em texto para o CSV
+pub fn campo_csv<T: ToString>(valor: &Option<T>) -> String {
+    valor.as_ref().map(|v| v.to_string()).unwrap_or_default()
+}
+
+pub struct RespostaEmStreaming {
+    pub formato: Formato,
+    pub linhas: BoxStream<'static, Vec<u8>>,
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for RespostaEmStreaming {
+    fn respond_to(self, _: &'r Request<'_>) -> Result<'o> {
+        Response::build()
+            .header(self.formato.content_type())
+            .streamed_body(ReaderStream::from(self.linhas.map(Cursor::new)))
+            .ok()
+    }
+}
+
+// Resposta das rotas que retornam listas de registros: JSON (completo) ou streaming (NDJSON/CSV)
+#[derive(rocket::Responder)]
+pub enum Resposta<T> {
+    Json(Json<T>),
+    Streaming(RespostaEmStreaming),
+}
+
+impl LinhaCsv for ResultadoDaBusca {
+    fn cabecalho() -> Vec<&'static str> {
+        vec!["cnpj", "razao_social", "nome_fantasia", "uf", "situacao_cadastral", "relevancia"]
+    }
+
+    fn campos(&self) -> Vec<String> {
+        vec![
+            format!("{}{}{}", self.cnpj_basico, self.cnpj_ordem, self.cnpj_dv),
+            self.razao_social.clone(),
+            campo_csv(&self.nome_fantasia),
+            campo_csv(&self.uf),
+            self.situacao_cadastral.descricao().to_string(),
+            self.relevancia.to_string(),
+        ]
+    }
+}
+
+#[cfg(test)]
+#[allow(unused_imports)] // as rotas de teste geram macros uri! que não são utilizadas
+mod tests {
+    use super::*;
+    use rocket::futures::stream;
+    use rocket::http::{Accept, Header};
+    use rocket::local::blocking::Client;
+
+    #[derive(Serialize)]
+    struct Registro {
+        cnpj: &'static str,
+        nome: &'static str,
+        observacao: Option<&'static str>,
+    }
+
+    impl LinhaCsv for Registro {
+        fn cabecalho() -> Vec<&'static str> {
+            vec!["cnpj", "nome", "observacao"]
+        }
+
+        fn campos(&self) -> Vec<String> {
+            vec![self.cnpj.to_string(), self.nome.to_string(), campo_csv(&self.observacao)]
+        }
+    }
+
+    fn registros() -> Vec<Registro> {
+        vec![
+            Registro { cnpj: "11222333000181", nome: "EMPRESA; FILIAL", observacao: None },
+            Registro { cnpj: "11222333000262", nome: "EMPRESA \"A\", LTDA", observacao: Some("linha 1\nlinha 2") },
+        ]
+    }
+
+    #[test]
+    fn linhas_do_csv() {
+        let csv = String::from_utf8(Formato::Csv.linhas(&registros(), true)).unwrap();
+
+        // o cabeçalho segue a ordem dos campos; os campos com aspas, vírgulas ou quebras de linha são
+        // delimitados por aspas (com as aspas duplicadas) e o ponto e vírgula não separa os campos
+        assert_eq!(
+            csv,
+            "cnpj,nome,observacao\n\
+             11222333000181,EMPRESA; FILIAL,\n\
+             11222333000262,\"EMPRESA \"\"A\"\", LTDA\",\"linha 1\nlinha 2\"\n"
+        );
+
+        let mut leitor = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(leitor.headers().unwrap(), vec!["cnpj", "nome", "observacao"]);
+        let linhas: Vec<csv::StringRecord> = leitor.records().map(|linha| linha.unwrap()).collect();
+        assert_eq!(linhas[0], vec!["11222333000181", "EMPRESA; FILIAL", ""]);
+        assert_eq!(linhas[1], vec!["11222333000262", "EMPRESA \"A\", LTDA", "linha 1\nlinha 2"]);
+
+        // nos blocos seguintes ao primeiro o cabeçalho não é repetido
+        let bloco = String::from_utf8(Formato::Csv.linhas(&registros()[..1], false)).unwrap();
+        assert_eq!(bloco, "11222333000181,EMPRESA; FILIAL,\n");
+    }
+
+    #[test]
+    fn linhas_do_ndjson() {
+        let ndjson = String::from_utf8(Formato::Ndjson.linhas(&registros(), true)).unwrap();
+
+        // um objeto por linha, sem cabeçalho, com as quebras de linha dos campos escapadas
+        assert_eq!(
+            ndjson,
+            "{\"cnpj\":\"11222333000181\",\"nome\":\"EMPRESA; FILIAL\",\"observacao\":null}\n\
+             {\"cnpj\":\"11222333000262\",\"nome\":\"EMPRESA \\\"A\\\", LTDA\",\"observacao\":\"linha 1\\nlinha 2\"}\n"
+        );
+        assert!(Formato::Ndjson.linhas::<Registro>(&[], true).is_empty());
+    }
+
+    #[rocket::get("/formato")]
+    fn formato(formato: Formato) -> String {
+        format!("{:?}", formato)
+    }
+
+    #[rocket::get("/registros")]
+    fn em_streaming(formato: Formato) -> RespostaEmStreaming {
+        let blocos = vec![registros(), vec![Registro { cnpj: "11222333000343", nome: "FILIAL 2", observacao: None }]];
+        let linhas = stream::iter(blocos.into_iter().enumerate())
+            .map(move |(i, bloco)| formato.linhas(&bloco, i == 0))
+            .boxed();
+        RespostaEmStreaming { formato, linhas }
+    }
+
+    fn cliente() -> Client {
+        Client::tracked(rocket::build().mount("/", rocket::routes![formato, em_streaming])).expect("Erro ao criar o cliente de testes")
+    }
+
+    fn formato_negociado(cliente: &Client, uri: &str, accept: Option<&str>) -> (Status, String) {
+        let mut request = cliente.get(uri.to_string());
+        if let Some(accept) = accept {
+            request = request.header(Header::new("Accept", accept.to_string()));
+        }
+        let response = request.dispatch();
+        (response.status(), response.into_string().unwrap_or_default())
+    }
+
+    #[test]
+    fn negociacao_do_formato() {
+        let cliente = cliente();
+        let negociar = |uri: &str, accept: Option<&str>| formato_negociado(&cliente, uri, accept);
+
+        assert_eq!(negociar("/formato", None), (Status::Ok, String::from("Json")));
+        assert_eq!(negociar("/formato", Some("*/*")), (Status::Ok, String::from("Json")));
+        assert_eq!(negociar("/formato", Some("text/csv")), (Status::Ok, String::from("Csv")));
+        assert_eq!(negociar("/formato", Some("application/x-ndjson")), (Status::Ok, String::from("Ndjson")));
+        // o primeiro tipo conhecido do Accept
+        assert_eq!(negociar("/formato", Some("text/html, text/csv, application/json")), (Status::Ok, String::from("Csv")));
+
+        // o parâmetro formato tem precedência sobre o Accept
+        assert_eq!(negociar("/formato?formato=csv", Some("application/json")), (Status::Ok, String::from("Csv")));
+        assert_eq!(negociar("/formato?formato=ndjson", None), (Status::Ok, String::from("Ndjson")));
+        assert_eq!(negociar("/formato?formato=json", Some("text/csv")), (Status::Ok, String::from("Json")));
+        assert_eq!(negociar("/formato?formato=xml", Some("text/csv")).0, Status::BadRequest);
+    }
+
+    #[test]
+    fn resposta_em_streaming() {
+        let cliente = cliente();
+
+        let response = cliente.get("/registros?formato=csv").dispatch();
+        assert_eq!(response.content_type(), Some(Formato::Csv.content_type()));
+        assert_eq!(
+            response.into_string().unwrap(),
+            "cnpj,nome,observacao\n\
+             11222333000181,EMPRESA; FILIAL,\n\
+             11222333000262,\"EMPRESA \"\"A\"\", LTDA\",\"linha 1\nlinha 2\"\n\
+             11222333000343,FILIAL 2,\n"
+        );
+
+        let response = cliente.get("/registros").header(Accept::new([MediaType::new("application", "x-ndjson").into()])).dispatch();
+        assert_eq!(response.content_type(), Some(Formato::Ndjson.content_type()));
+        assert_eq!(response.into_string().unwrap().lines().count(), 3);
+    }
+}
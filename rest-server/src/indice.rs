@@ -0,0 +1,82 @@
+// Busca e autocompletar a partir do índice de busca textual (tantivy) gerado pelo comando `importer index`,
+// sem acessar o banco de dados. O caminho do índice é informado na variável de ambiente INDICE_DE_BUSCA
+// e o índice é aberto somente para leitura.
+
+use std::env;
+use std::path::Path;
+
+use data_models::indice::{IndiceDeBusca, ResultadoDoIndice};
+use data_models::lgpd::Mascaramento;
+use rocket::serde::json::Json;
+use rocket::{Build, Rocket, State};
+
+use rest_server::CustomError;
+
+use crate::lgpd::{Exposicao, Mascarar};
+
+// quantidade de resultados retornados quando o limite não é informado
+const LIMITE_PADRAO: usize = 10;
+// quantidade máxima de resultados retornados em uma única consulta
+const LIMITE_MAXIMO: usize = 100;
+
+struct Indice(Option<IndiceDeBusca>);
+
+impl Indice {
+    fn abrir() -> Indice {
+        match env::var("INDICE_DE_BUSCA") {
+            Ok(diretorio) => match IndiceDeBusca::abrir_somente_leitura(Path::new(&diretorio)) {
+                Ok(indice) => Indice(Some(indice)),
+                Err(error) => {
+                    tracing::warn!(diretorio = %diretorio, erro = ?error, "Não foi possível abrir o índice de busca");
+                    Indice(None)
+                }
+            },
+            Err(_) => Indice(None),
+        }
+    }
+
+    fn indice(&self) -> Result<&IndiceDeBusca, CustomError> {
+        self.0
+            .as_ref()
+            .ok_or_else(|| CustomError::ServiceUnavailableErr(String::from("O índice de busca não está disponível")))
+    }
+}
+
+impl Mascarar for ResultadoDoIndice {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        if let Some(razao_social) = &mut self.razao_social {
+            mascaramento.razao_social(razao_social);
+        }
+    }
+}
+
+fn limite(limite: Option<usize>) -> usize {
+    limite.unwrap_or(LIMITE_PADRAO).clamp(1, LIMITE_MAXIMO)
+}
+
+#[get("/indice/busca?<q>&<limite>", format = "json")]
+fn get_busca_no_indice(indice: &State<Indice>, exposicao: Exposicao, q: String, limite: Option<usize>) -> Result<Json<Vec<ResultadoDoIndice>>, CustomError> {
+    let resultados = indice
+        .indice()?
+        .buscar(&q, self::limite(limite))
+        .map_err(|error| CustomError::BadRequestErr(format!("Erro na busca: {}", error)))?;
+
+    Ok(Json(exposicao.aplicar(resultados)))
+}
+
+#[get("/autocompletar?<q>&<limite>", format = "json")]
+fn get_autocompletar(indice: &State<Indice>, exposicao: Exposicao, q: String, limite: Option<usize>) -> Result<Json<Vec<ResultadoDoIndice>>, CustomError> {
+    let resultados = indice
+        .indice()?
+        .autocompletar(&q, self::limite(limite))
+        .map_err(|error| CustomError::BadRequestErr(format!("Erro na busca: {}", error)))?;
+
+    Ok(Json(exposicao.aplicar(resultados)))
+}
+
+pub fn montar(rocket: Rocket<Build>) -> Rocket<Build> {
+    rocket
+        .manage(Indice::abrir())
+        .mount("/api", routes![get_busca_no_indice, get_autocompletar])
+        .mount("/api/v1", routes![get_busca_no_indice, get_autocompletar])
+}
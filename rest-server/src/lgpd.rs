@@ -0,0 +1,118 @@
+// Ocultação dos dados pessoais (LGPD) nas respostas da API conforme o perfil de exposição da chave de API
+// utilizada na requisição ou, sem chave, conforme o perfil padrão (vide data_models::lgpd).
+//
+// Os resultados são mascarados depois de lidos do cache de consultas, que guarda os registros completos.
+
+use std::ops::Deref;
+use std::sync::Arc;
+
+use data_models::busca::ResultadoDaBusca;
+use data_models::grupo_economico::GrupoEconomico;
+use data_models::lgpd::{Mascaramento, PerfisDeExposicao};
+use data_models::models::{ChaveDeApi, Empresa, Estabelecimento, Socio};
+use rocket::outcome::try_outcome;
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Request, State};
+
+use crate::tabelas::Pagina;
+
+// Resultados que contêm dados pessoais
+pub trait Mascarar {
+    fn mascarar(&mut self, mascaramento: &Mascaramento);
+}
+
+impl<T: Mascarar> Mascarar for Vec<T> {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        for item in self.iter_mut() {
+            item.mascarar(mascaramento);
+        }
+    }
+}
+
+impl<T: Mascarar> Mascarar for Option<T> {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        if let Some(item) = self {
+            item.mascarar(mascaramento);
+        }
+    }
+}
+
+impl<T: Mascarar> Mascarar for Pagina<T> {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        self.registros.mascarar(mascaramento);
+    }
+}
+
+impl Mascarar for Empresa {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        mascaramento.empresa(self);
+    }
+}
+
+impl Mascarar for Estabelecimento {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        mascaramento.estabelecimento(self);
+    }
+}
+
+impl Mascarar for Socio {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        mascaramento.socio(self);
+    }
+}
+
+impl Mascarar for ResultadoDaBusca {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        mascaramento.razao_social(&mut self.razao_social);
+    }
+}
+
+impl Mascarar for GrupoEconomico {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        for no in self.nos.iter_mut() {
+            if let Some(razao_social) = &mut no.razao_social {
+                mascaramento.razao_social(razao_social);
+            }
+        }
+    }
+}
+
+// Guarda de requisição com o mascaramento dos dados pessoais da requisição. Pode ser clonado e movido
+// para as respostas em streaming; a auditoria é registrada quando o último clone é descartado.
+#[derive(Clone)]
+pub struct Exposicao(Arc<Mascaramento>);
+
+impl Exposicao {
+    pub fn aplicar<T: Mascarar>(&self, mut resultado: T) -> T {
+        resultado.mascarar(&self.0);
+        resultado
+    }
+}
+
+impl Deref for Exposicao {
+    type Target = Mascaramento;
+
+    fn deref(&self) -> &Mascaramento {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Exposicao {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        let perfis = try_outcome!(request.guard::<&State<PerfisDeExposicao>>().await);
+
+        // a chave é guardada no cache local da requisição pelo controle de acesso (vide acesso)
+        let chave = request.local_cache(|| None::<ChaveDeApi>);
+        let perfil = perfis.perfil(chave.as_ref().map(|chave| chave.perfil.as_str()));
+        // somente o caminho é registrado, pois os parâmetros podem conter dados pessoais (ex: o CPF buscado)
+        let origem = match chave {
+            Some(chave) => format!("chave {} ({}) {} {}", chave.id, chave.nome, request.method(), request.uri().path()),
+            None => format!("{} {}", request.method(), request.uri().path()),
+        };
+
+        Outcome::Success(Exposicao(Arc::new(Mascaramento::new(perfil, origem))))
+    }
+}
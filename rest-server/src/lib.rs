@@ -1,13 +1,36 @@
+use data_models::acesso::RecusaDeAcesso;
 use failure::Fail;
-use rocket::http::{ContentType, Status};
+use rocket::http::{ContentType, Header, Status};
 use rocket::response::{Responder, Response, Result};
-use rocket::Request;
+use rocket::serde::json;
+use rocket::{Catcher, Request};
+use serde::Serialize;
 use std::io::Cursor;
 
+pub mod formato;
+
 #[derive(Debug, Fail)]
 pub enum CustomError {
     #[fail(display = "Database Error: {}", 0)]
     DatabaseErr(diesel::result::Error),
+    #[fail(display = "Bad Request: {}", 0)]
+    BadRequestErr(String),
+    // parâmetro inválido: nome do parâmetro e a mensagem
+    #[fail(display = "Invalid Parameter {}: {}", 0, 1)]
+    InvalidParamErr(String, String),
+    // parâmetro que não pode ser utilizado com o perfil de exposição da chave de API
+    #[fail(display = "Forbidden: {}", 0)]
+    ForbiddenErr(String),
+    #[fail(display = "Not Found: {}", 0)]
+    NotFoundErr(String),
+    #[fail(display = "Service Unavailable: {}", 0)]
+    ServiceUnavailableErr(String),
+}
+
+impl CustomError {
+    pub fn invalid_param(param: &str, message: String) -> CustomError {
+        CustomError::InvalidParamErr(param.to_string(), message)
+    }
 }
 
 impl From<diesel::result::Error> for CustomError {
@@ -16,14 +39,339 @@ impl From<diesel::result::Error> for CustomError {
     }
 }
 
+// Detalhe de um erro referente a um parâmetro específico da requisição
+#[derive(Debug, Serialize)]
+pub struct ErrorDetail {
+    pub param: String,
+    pub message: String,
+}
+
+// Corpo (JSON) de todas as respostas de erro da API
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: String,
+    pub details: Vec<ErrorDetail>,
+}
+
+impl ErrorBody {
+    pub fn new(status: Status, message: String) -> ErrorBody {
+        ErrorBody {
+            code: error_code(status),
+            message,
+            details: vec![],
+        }
+    }
+
+    fn respond(self, status: Status) -> Result<'static> {
+        let body = json::to_string(&self).expect("Erro ao serializar a resposta de erro");
+        Response::build()
+            .status(status)
+            .header(ContentType::JSON)
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+
+    // Resposta às requisições recusadas pelo controle de acesso (vide acesso.rs)
+    fn respond_to_refusal(recusa: &RecusaDeAcesso) -> Result<'static> {
+        match recusa {
+            RecusaDeAcesso::CotaExcedida { segundos_para_renovacao, .. } => {
+                let mut response = ErrorBody::new(Status::TooManyRequests, recusa.mensagem()).respond(Status::TooManyRequests)?;
+                response.set_header(Header::new("Retry-After", segundos_para_renovacao.to_string()));
+                Ok(response)
+            }
+            _ => ErrorBody::new(Status::Unauthorized, recusa.mensagem()).respond(Status::Unauthorized),
+        }
+    }
+}
+
+fn error_code(status: Status) -> &'static str {
+    match status.code {
+        400 => "BAD_REQUEST",
+        401 => "UNAUTHORIZED",
+        403 => "FORBIDDEN",
+        404 => "NOT_FOUND",
+        422 => "UNPROCESSABLE_ENTITY",
+        429 => "TOO_MANY_REQUESTS",
+        503 => "SERVICE_UNAVAILABLE",
+        _ => "INTERNAL_ERROR",
+    }
+}
+
 impl<'r> Responder<'r, 'static> for CustomError {
     fn respond_to(self, _: &'r Request<'_>) -> Result<'static> {
-        let body = format!("Diesel error: {}", self);
-        let res = Response::build()
-            .status(Status::InternalServerError)
-            .header(ContentType::Plain)
-            .sized_body(body.len(), Cursor::new(body))
-            .finalize();
-        Ok(res)
+        let (status, body) = match self {
+            CustomError::DatabaseErr(diesel::result::Error::NotFound) => {
+                (Status::NotFound, ErrorBody::new(Status::NotFound, String::from("Registro não encontrado")))
+            },
+            CustomError::DatabaseErr(error) => {
+                // o erro é apenas registrado no log, para não expor detalhes do banco de dados (ex: SQL) ao cliente
+                tracing::error!(erro = ?error, "Erro no banco de dados");
+                (Status::InternalServerError, ErrorBody::new(Status::InternalServerError, String::from("Erro interno ao consultar o banco de dados")))
+            },
+            CustomError::BadRequestErr(message) => (Status::BadRequest, ErrorBody::new(Status::BadRequest, message)),
+            CustomError::InvalidParamErr(param, message) => {
+                let mut body = ErrorBody::new(Status::BadRequest, format!("Parâmetro inválido: {}", param));
+                body.details.push(ErrorDetail { param, message });
+                (Status::BadRequest, body)
+            },
+            CustomError::ForbiddenErr(message) => (Status::Forbidden, ErrorBody::new(Status::Forbidden, message)),
+            CustomError::NotFoundErr(message) => (Status::NotFound, ErrorBody::new(Status::NotFound, message)),
+            CustomError::ServiceUnavailableErr(message) => (Status::ServiceUnavailable, ErrorBody::new(Status::ServiceUnavailable, message)),
+        };
+        body.respond(status)
+    }
+}
+
+// Resposta de erro gerada pelos catchers
+pub struct ErrorResponse(Status, ErrorBody);
+
+impl<'r> Responder<'r, 'static> for ErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> Result<'static> {
+        // as requisições recusadas pelo controle de acesso são desviadas para uma rota inexistente
+        if let Some(recusa) = request.local_cache(|| None::<RecusaDeAcesso>) {
+            return ErrorBody::respond_to_refusal(recusa);
+        }
+        self.1.respond(self.0)
+    }
+}
+
+// Responde aos erros gerados pelo próprio Rocket (rota inexistente, parâmetro com tipo inválido,
+// pool de conexões com o banco de dados indisponível, etc.) no mesmo formato JSON dos erros da API
+#[rocket::catch(default)]
+fn default_catcher(status: Status, _: &Request<'_>) -> ErrorResponse {
+    let message = match status.code {
+        400 => "Requisição inválida",
+        401 => "Chave de API ausente ou inválida",
+        403 => "Acesso proibido",
+        404 => "Recurso não encontrado",
+        422 => "Não foi possível processar os parâmetros ou o corpo da requisição",
+        429 => "Cota de requisições excedida",
+        503 => "Serviço temporariamente indisponível",
+        _ => "Erro interno",
+    };
+    ErrorResponse(status, ErrorBody::new(status, String::from(message)))
+}
+
+pub fn catchers() -> Vec<Catcher> {
+    rocket::catchers![default_catcher]
+}
+
+#[cfg(test)]
+#[allow(unused_imports)] // as rotas de teste geram macros uri! que não são utilizadas
+mod tests {
+    use super::*;
+    use rocket::fairing::AdHoc;
+    use rocket::http::uri::Origin;
+    use rocket::local::blocking::Client;
+    use rocket::serde::json::{Json, Value};
+    use rocket::serde::Deserialize;
+
+    #[derive(Deserialize)]
+    #[serde(crate = "rocket::serde")]
+    struct Corpo {
+        quantidade: u32,
+    }
+
+    #[rocket::get("/requisicao_invalida")]
+    fn requisicao_invalida() -> std::result::Result<(), CustomError> {
+        Err(CustomError::BadRequestErr(String::from("Informe ao menos um parâmetro")))
+    }
+
+    #[rocket::get("/parametro_invalido")]
+    fn parametro_invalido() -> std::result::Result<(), CustomError> {
+        Err(CustomError::invalid_param("cnpj", String::from("CNPJ inválido: 123")))
+    }
+
+    #[rocket::get("/proibido")]
+    fn proibido() -> std::result::Result<(), CustomError> {
+        Err(CustomError::ForbiddenErr(String::from("O perfil de exposição publico não permite a busca")))
+    }
+
+    #[rocket::get("/nao_encontrado")]
+    fn nao_encontrado() -> std::result::Result<(), CustomError> {
+        Err(CustomError::NotFoundErr(String::from("Empresa não encontrada")))
+    }
+
+    #[rocket::get("/registro_nao_encontrado")]
+    fn registro_nao_encontrado() -> std::result::Result<(), CustomError> {
+        Err(CustomError::DatabaseErr(diesel::result::Error::NotFound))
     }
-}
\ No newline at end of file
+
+    #[rocket::get("/erro_no_banco")]
+    fn erro_no_banco() -> std::result::Result<(), CustomError> {
+        Err(CustomError::DatabaseErr(diesel::result::Error::QueryBuilderError("SELECT * FROM empresas".into())))
+    }
+
+    #[rocket::get("/indisponivel")]
+    fn indisponivel() -> std::result::Result<(), CustomError> {
+        Err(CustomError::ServiceUnavailableErr(String::from("Índice não carregado")))
+    }
+
+    #[rocket::get("/erro_interno")]
+    fn erro_interno() -> Status {
+        Status::InternalServerError
+    }
+
+    #[rocket::get("/numero/<numero>")]
+    fn numero(numero: u32) -> String {
+        numero.to_string()
+    }
+
+    #[rocket::get("/proibido_pelo_rocket")]
+    fn proibido_pelo_rocket() -> Status {
+        Status::Forbidden
+    }
+
+    #[rocket::post("/corpo", data = "<corpo>")]
+    fn corpo(corpo: Json<Corpo>) -> String {
+        corpo.quantidade.to_string()
+    }
+
+    fn cliente() -> Client {
+        let rocket = rocket::build()
+            .mount(
+                "/",
+                rocket::routes![
+                    requisicao_invalida,
+                    parametro_invalido,
+                    proibido,
+                    nao_encontrado,
+                    registro_nao_encontrado,
+                    erro_no_banco,
+                    indisponivel,
+                    erro_interno,
+                    proibido_pelo_rocket,
+                    numero,
+                    corpo
+                ],
+            )
+            .register("/", catchers())
+            // recusa as requisições como o controle de acesso (vide acesso.rs do servidor), conforme o caminho
+            .attach(AdHoc::on_request("Recusa de acesso", |request, _| {
+                Box::pin(async move {
+                    let recusa = match request.uri().path().as_str() {
+                        "/sem_chave" => RecusaDeAcesso::ChaveAusente,
+                        "/cota_excedida" => RecusaDeAcesso::CotaExcedida { periodo: "minuto", segundos_para_renovacao: 42 },
+                        _ => return,
+                    };
+                    request.local_cache(|| Some(recusa));
+                    request.set_uri(Origin::parse("/acesso_recusado").unwrap());
+                })
+            }));
+        Client::tracked(rocket).expect("Erro ao criar o cliente de testes")
+    }
+
+    // Status e corpo (JSON) da resposta à requisição GET
+    fn get(cliente: &Client, uri: &str) -> (Status, Value) {
+        let response = cliente.get(uri.to_string()).dispatch();
+        assert_eq!(response.content_type(), Some(ContentType::JSON), "{}", uri);
+        (response.status(), response.into_json::<Value>().expect("Corpo da resposta não é JSON"))
+    }
+
+    #[test]
+    fn erros_da_api() {
+        let cliente = cliente();
+
+        let (status, corpo) = get(&cliente, "/requisicao_invalida");
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(corpo["code"], "BAD_REQUEST");
+        assert_eq!(corpo["message"], "Informe ao menos um parâmetro");
+        assert_eq!(corpo["details"], Value::Array(vec![]));
+
+        let (status, corpo) = get(&cliente, "/parametro_invalido");
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(corpo["code"], "BAD_REQUEST");
+        assert_eq!(corpo["message"], "Parâmetro inválido: cnpj");
+        assert_eq!(corpo["details"][0]["param"], "cnpj");
+        assert_eq!(corpo["details"][0]["message"], "CNPJ inválido: 123");
+
+        let (status, corpo) = get(&cliente, "/proibido");
+        assert_eq!(status, Status::Forbidden);
+        assert_eq!(corpo["code"], "FORBIDDEN");
+
+        let (status, corpo) = get(&cliente, "/nao_encontrado");
+        assert_eq!(status, Status::NotFound);
+        assert_eq!(corpo["code"], "NOT_FOUND");
+        assert_eq!(corpo["message"], "Empresa não encontrada");
+
+        let (status, corpo) = get(&cliente, "/registro_nao_encontrado");
+        assert_eq!(status, Status::NotFound);
+        assert_eq!(corpo["message"], "Registro não encontrado");
+
+        let (status, corpo) = get(&cliente, "/indisponivel");
+        assert_eq!(status, Status::ServiceUnavailable);
+        assert_eq!(corpo["code"], "SERVICE_UNAVAILABLE");
+    }
+
+    #[test]
+    fn erro_no_banco_nao_expoe_o_sql() {
+        let (status, corpo) = get(&cliente(), "/erro_no_banco");
+        assert_eq!(status, Status::InternalServerError);
+        assert_eq!(corpo["code"], "INTERNAL_ERROR");
+        assert_eq!(corpo["message"], "Erro interno ao consultar o banco de dados");
+        assert!(!corpo.to_string().contains("SELECT"));
+    }
+
+    #[test]
+    fn erros_do_rocket() {
+        let cliente = cliente();
+
+        // rota inexistente
+        let (status, corpo) = get(&cliente, "/inexistente");
+        assert_eq!(status, Status::NotFound);
+        assert_eq!(corpo["code"], "NOT_FOUND");
+        assert_eq!(corpo["message"], "Recurso não encontrado");
+        assert_eq!(corpo["details"], Value::Array(vec![]));
+
+        // parâmetro do caminho com tipo inválido (a rota não corresponde à requisição)
+        let (status, corpo) = get(&cliente, "/numero/abc");
+        assert_eq!(status, Status::NotFound);
+        assert_eq!(corpo["code"], "NOT_FOUND");
+
+        let (status, corpo) = get(&cliente, "/erro_interno");
+        assert_eq!(status, Status::InternalServerError);
+        assert_eq!(corpo["code"], "INTERNAL_ERROR");
+        assert_eq!(corpo["message"], "Erro interno");
+    }
+
+    #[test]
+    fn requisicoes_recusadas_pelo_controle_de_acesso() {
+        let cliente = cliente();
+
+        let (status, corpo) = get(&cliente, "/sem_chave");
+        assert_eq!(status, Status::Unauthorized);
+        assert_eq!(corpo["code"], "UNAUTHORIZED");
+        assert_eq!(corpo["message"], RecusaDeAcesso::ChaveAusente.mensagem());
+
+        let response = cliente.get("/cota_excedida").dispatch();
+        assert_eq!(response.status(), Status::TooManyRequests);
+        assert_eq!(response.headers().get_one("Retry-After"), Some("42"));
+        assert_eq!(response.into_json::<Value>().unwrap()["code"], "TOO_MANY_REQUESTS");
+
+        // o 403 gerado pelo próprio Rocket também é respondido no formato dos erros da API
+        let (status, corpo) = get(&cliente, "/proibido_pelo_rocket");
+        assert_eq!(status, Status::Forbidden);
+        assert_eq!(corpo["code"], "FORBIDDEN");
+        assert_eq!(corpo["message"], "Acesso proibido");
+    }
+
+    #[test]
+    fn corpo_da_requisicao_invalido() {
+        let cliente = cliente();
+
+        // JSON malformado
+        let response = cliente.post("/corpo").header(ContentType::JSON).body("{").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        let corpo = response.into_json::<Value>().unwrap();
+        assert_eq!(corpo["code"], "BAD_REQUEST");
+        assert_eq!(corpo["message"], "Requisição inválida");
+
+        // JSON válido, mas com um campo de tipo inválido
+        let response = cliente.post("/corpo").header(ContentType::JSON).body(r#"{"quantidade": "dez"}"#).dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let corpo = response.into_json::<Value>().unwrap();
+        assert_eq!(corpo["code"], "UNPROCESSABLE_ENTITY");
+        assert_eq!(corpo["message"], "Não foi possível processar os parâmetros ou o corpo da requisição");
+    }
+}
@@ -1,21 +1,75 @@
 use std::env;
+use std::sync::Arc;
 use dotenv::dotenv;
 
 #[macro_use] 
 extern crate rocket;
 
-use data_models::{ models::{CNAE, Empresa, Estabelecimento, MotivoDeSituacaoCadastral, Municipio, NaturezaJuridica, Pais, QualificacaoDeSocio}};
-use data_models::schema::{empresas, estabelecimentos, cnaes, paises, municipios, naturezas_juridicas, motivos_de_situacoes_cadastrais, qualificacoes_de_socios};
+mod acesso;
+pub mod cache_de_consultas;
+pub mod cache_http;
+#[cfg(feature = "indice")]
+mod indice;
+pub mod lgpd;
+pub mod monitoramento;
+pub mod openapi;
+pub mod tabelas;
+pub mod v1;
+
+use data_models::{ models::{CNAE, Empresa, Estabelecimento, MotivoDeSituacaoCadastral, Municipio, NaturezaJuridica, Pais, QualificacaoDeSocio, Simples, Socio}};
+use data_models::schema::{empresas, estabelecimentos, cnaes, paises, municipios, naturezas_juridicas, motivos_de_situacoes_cadastrais, qualificacoes_de_socios, simples, socios};
+use data_models::documentos::{normalizar_cnpj, somente_digitos};
+use data_models::grupo_economico::{grupo_economico, PROFUNDIDADE_MAXIMA};
+use data_models::socios::{buscar_socios, BuscaDeSocios};
+use data_models::busca::{buscar_empresas, buscar_empresas_em_blocos, codigo_ibge_valido, expressao_de_busca, FiltroDaBusca, ResultadoDaBusca, LIMITE_MAXIMO_DA_BUSCA, LIMITE_PADRAO_DA_BUSCA};
+use data_models::dominios::SituacaoCadastral;
+use data_models::lote::{condicao_dos_cnpjs, normalizar_lote, por_cnpj, StatusDoItemDoLote, LIMITE_DO_LOTE, TAMANHO_DO_BLOCO};
+use data_models::paginacao::deslocamento_da_pagina;
 use diesel::prelude::*;
-use rocket::{figment::{map, value::{Map, Value}}, serde::{Serialize, json::Json}};
+use diesel::r2d2::{ConnectionManager, Pool};
+use rocket::futures::{stream, StreamExt};
+use rocket::{figment::{map, value::{Map, Value}}, http::ContentType, serde::{Deserialize, Serialize, json::{self, Json}}};
 
+use rocket::{fairing::AdHoc, State};
 use rocket_sync_db_pools::database;
 
-use rest_server::CustomError;
+use rest_server::{catchers, CustomError};
+use rest_server::formato::{campo_csv, Formato, LinhaCsv, Resposta, RespostaEmStreaming};
+use cache_de_consultas::CacheDeConsultas;
+use data_models::lgpd::{Mascaramento, PerfisDeExposicao};
+use data_models::logs::iniciar_logs;
+use data_models::monitoramento::METRICAS;
+use lgpd::{Exposicao, Mascarar};
+use tabelas::{paginacao, Pagina, Paginacao};
+
+// quantidade máxima de conexões do pool com o banco de dados
+const TAMANHO_DO_POOL: u32 = 10;
 
 #[database("cnpj_db")]
 struct DBPool(diesel::MysqlConnection);
 
+impl DBPool {
+    // Executa a consulta no banco de dados, medindo o tempo de uso da conexão (vide monitoramento)
+    async fn consultar<T, F>(&self, consulta: F) -> T
+    where
+        F: FnOnce(&mut diesel::MysqlConnection) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let _uso = METRICAS.iniciar_uso_da_conexao();
+        self.run(consulta).await
+    }
+}
+
+// Conexão das rotas que validam os parâmetros do caminho (ex: o CNPJ): o Rocket avalia os guards antes do
+// corpo da rota, e com o guard DBPool uma requisição com parâmetros inválidos seria respondida com 503 (e não
+// 400) quando não houvesse conexão disponível. Por ser um alias, o DBPool não é consultado como sentinela
+// (a rota responde com 503 se o pool não existir), o que permite testar as rotas sem o banco de dados.
+type ConexaoAposValidacao = Result<DBPool, ()>;
+
+fn conexao(conn: ConexaoAposValidacao) -> Result<DBPool, CustomError> {
+    conn.map_err(|_| CustomError::ServiceUnavailableErr(String::from("Não foi possível obter uma conexão com o banco de dados")))
+}
+
 #[derive(Serialize)]
 struct CnaeResult {
     cnae: CNAE,
@@ -25,7 +79,7 @@ struct CnaeResult {
 async fn get_cnaes(conn: DBPool, cnae_num: u32) -> Result<Json<CnaeResult>, CustomError> {
 
     let cnae = conn
-        .run(move |c| {
+        .consultar(move |c| {
             cnaes::table
                 .filter(cnaes::id.eq(cnae_num))
                 .first::<CNAE>(c)
@@ -41,18 +95,53 @@ async fn get_cnaes(conn: DBPool, cnae_num: u32) -> Result<Json<CnaeResult>, Cust
     )
 }
 
-#[derive(Serialize)]
+// Valida o CNPJ básico (8 primeiros dígitos do CNPJ) informado na rota, retornando somente os dígitos
+fn validar_cnpj_basico(cnpjbas: &str) -> Result<String, CustomError> {
+    let cnpjbas = somente_digitos(cnpjbas);
+    if cnpjbas.len() != 8 {
+        return Err(CustomError::invalid_param("cnpjbas", String::from("O CNPJ básico deve conter 8 dígitos")));
+    }
+    Ok(cnpjbas)
+}
+
+// Verifica se a empresa existe, para que as rotas de sub-recursos da empresa (sócios, simples, etc.)
+// diferenciem uma empresa inexistente (404) de uma empresa sem registros (lista vazia)
+fn verificar_empresa(c: &mut diesel::MysqlConnection, cnpjbas: &str) -> Result<(), CustomError> {
+    let existe = diesel::select(diesel::dsl::exists(empresas::table.filter(empresas::cnpj_basico.eq(cnpjbas))))
+        .get_result::<bool>(c)?;
+    if !existe {
+        return Err(CustomError::NotFoundErr(format!("Empresa não encontrada: {}", cnpjbas)));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Serialize)]
 struct EmpresaResult {
     empresa: Empresa,
     natureza_juridica: Option<NaturezaJuridica>,
     qualificacao_do_responsavel: Option<QualificacaoDeSocio>,
 }
 
+impl Mascarar for EmpresaResult {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        self.empresa.mascarar(mascaramento);
+    }
+}
+
 #[get("/empresas/<cnpjbas>", format = "json")] // cnpjbas = CNPJ Básico, ou seja, os 8 primeiros digitos do CNPJ
-async fn get_empresas(conn: DBPool, cnpjbas: String) -> Result<Json<EmpresaResult>, CustomError> {
+async fn get_empresas(conn: ConexaoAposValidacao, cache: &State<CacheDeConsultas>, exposicao: Exposicao, cnpjbas: String) -> Result<Json<EmpresaResult>, CustomError> {
+
+    let cnpjbas = validar_cnpj_basico(&cnpjbas)?;
+    let conn = conexao(conn)?;
+
+    cache.atualizar(&conn).await;
+    if let Some(resultado) = cache.empresas.obter(&cnpjbas) {
+        return Ok(Json(exposicao.aplicar(resultado)));
+    }
+    let chave = cnpjbas.clone();
 
     let query_result = conn
-        .run(move |c| {
+        .consultar(move |c| {
             empresas::table
                 .filter(empresas::cnpj_basico.eq(cnpjbas))
                 .left_join(naturezas_juridicas::table)
@@ -67,18 +156,121 @@ async fn get_empresas(conn: DBPool, cnpjbas: String) -> Result<Json<EmpresaResul
         qualificacao_do_responsavel
     ) = query_result;
 
-    Ok(
-        Json(
-            EmpresaResult {
-                empresa,
-                natureza_juridica,
-                qualificacao_do_responsavel,
-            }
-        )
-    )
+    let resultado = EmpresaResult {
+        empresa,
+        natureza_juridica,
+        qualificacao_do_responsavel,
+    };
+    cache.empresas.incluir(chave, resultado.clone());
+
+    Ok(Json(exposicao.aplicar(resultado)))
 }
 
 #[derive(Serialize)]
+struct SocioDaEmpresaResult {
+    socio: Socio,
+    qualificacao_do_socio: Option<QualificacaoDeSocio>,
+    qualificacao_do_representante_legal: Option<QualificacaoDeSocio>,
+    pais: Option<Pais>,
+}
+
+impl Mascarar for SocioDaEmpresaResult {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        self.socio.mascarar(mascaramento);
+    }
+}
+
+// Retorna o quadro de sócios da empresa
+#[get("/empresas/<cnpjbas>/socios")]
+async fn get_socios_da_empresa(conn: ConexaoAposValidacao, exposicao: Exposicao, cnpjbas: String) -> Result<Json<Vec<SocioDaEmpresaResult>>, CustomError> {
+
+    let cnpjbas = validar_cnpj_basico(&cnpjbas)?;
+    let conn = conexao(conn)?;
+
+    let query_result = conn
+        .consultar(move |c| -> Result<Vec<SocioDaEmpresaResult>, CustomError> {
+            verificar_empresa(c, &cnpjbas)?;
+
+            let socios_da_empresa = socios::table
+                .filter(socios::cnpj_basico.eq(&cnpjbas))
+                .order(socios::id)
+                .load::<Socio>(c)?;
+            let qualificacoes = qualificacoes_de_socios::table
+                .load::<QualificacaoDeSocio>(c)?;
+            let ids_dos_paises: Vec<u16> = socios_da_empresa.iter().filter_map(|s| s.pais_do_socio).collect();
+            let paises_dos_socios = paises::table
+                .filter(paises::id.eq_any(ids_dos_paises))
+                .load::<Pais>(c)?;
+
+            let qualificacao = |id: u8| qualificacoes.iter().find(|q| q.id == id).cloned();
+
+            Ok(socios_da_empresa
+                .into_iter()
+                .map(|socio| SocioDaEmpresaResult {
+                    qualificacao_do_socio: qualificacao(socio.qualificacao_do_socio),
+                    qualificacao_do_representante_legal: qualificacao(socio.qualificacao_do_representante_legal),
+                    pais: socio.pais_do_socio.and_then(|id| paises_dos_socios.iter().find(|p| p.id == id).cloned()),
+                    socio,
+                })
+                .collect())
+        })
+        .await?;
+
+    Ok(Json(exposicao.aplicar(query_result)))
+}
+
+// Retorna a situação da empresa quanto ao Simples Nacional e ao MEI
+#[get("/empresas/<cnpjbas>/simples")]
+async fn get_simples_da_empresa(conn: ConexaoAposValidacao, cnpjbas: String) -> Result<Json<Simples>, CustomError> {
+
+    let cnpjbas = validar_cnpj_basico(&cnpjbas)?;
+    let conn = conexao(conn)?;
+
+    let query_result = conn
+        .consultar(move |c| -> Result<Simples, CustomError> {
+            verificar_empresa(c, &cnpjbas)?;
+
+            simples::table
+                .filter(simples::cnpj_basico.eq(&cnpjbas))
+                .first::<Simples>(c)
+                .optional()?
+                .ok_or_else(|| CustomError::NotFoundErr(format!("A empresa {} não possui registro no Simples Nacional", cnpjbas)))
+        })
+        .await?;
+
+    Ok(Json(query_result))
+}
+
+// Retorna os estabelecimentos (matriz e filiais) da empresa, paginados
+#[get("/empresas/<cnpjbas>/estabelecimentos?<pagina>&<limite>")]
+async fn get_estabelecimentos_da_empresa(conn: ConexaoAposValidacao, exposicao: Exposicao, cnpjbas: String, pagina: Option<i64>, limite: Option<i64>) -> Result<Json<Pagina<Estabelecimento>>, CustomError> {
+
+    let cnpjbas = validar_cnpj_basico(&cnpjbas)?;
+    let Paginacao { pagina, limite, deslocamento } = paginacao(pagina, limite)?;
+    let conn = conexao(conn)?;
+
+    let (total, registros) = conn
+        .consultar(move |c| -> Result<(i64, Vec<Estabelecimento>), CustomError> {
+            verificar_empresa(c, &cnpjbas)?;
+
+            let total = estabelecimentos::table
+                .filter(estabelecimentos::cnpj_basico.eq(&cnpjbas))
+                .count()
+                .get_result::<i64>(c)?;
+            let registros = estabelecimentos::table
+                .filter(estabelecimentos::cnpj_basico.eq(&cnpjbas))
+                .order((estabelecimentos::cnpj_ordem, estabelecimentos::cnpj_dv))
+                .offset(deslocamento)
+                .limit(limite)
+                .load::<Estabelecimento>(c)?;
+            Ok((total, registros))
+        })
+        .await?;
+
+    Ok(Json(exposicao.aplicar(Pagina { pagina, limite, total, registros })))
+}
+
+#[derive(Clone, Serialize)]
 struct EstabelecimentoResult {
     estabelecimento: Estabelecimento,
     motivo_situacao_cadastral: Option<MotivoDeSituacaoCadastral>,
@@ -91,11 +283,28 @@ struct EstabelecimentoResult {
     qualificacao_do_responsavel: Option<QualificacaoDeSocio>,
 }
 
+impl Mascarar for EstabelecimentoResult {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        self.estabelecimento.mascarar(mascaramento);
+        self.empresa.mascarar(mascaramento);
+    }
+}
+
 #[get("/estabelecimentos/<com_cnaes_secundarias>/<cnpj_completo>", format = "json")]
-async fn get_estabelecimentos(conn: DBPool, cnpj_completo: String, com_cnaes_secundarias: bool) -> Result<Json<EstabelecimentoResult>, CustomError> {
+async fn get_estabelecimentos(conn: ConexaoAposValidacao, cache: &State<CacheDeConsultas>, exposicao: Exposicao, cnpj_completo: String, com_cnaes_secundarias: bool) -> Result<Json<EstabelecimentoResult>, CustomError> {
+
+    let cnpj_completo = normalizar_cnpj(&cnpj_completo)
+        .ok_or_else(|| CustomError::invalid_param("cnpj_completo", format!("CNPJ inválido (deve conter 14 dígitos e os dígitos verificadores corretos): {}", cnpj_completo)))?;
+    let conn = conexao(conn)?;
+
+    cache.atualizar(&conn).await;
+    let chave = format!("{}/{}", cnpj_completo, com_cnaes_secundarias);
+    if let Some(resultado) = cache.estabelecimentos.obter(&chave) {
+        return Ok(Json(exposicao.aplicar(resultado)));
+    }
 
     let query_result = conn
-        .run(move |c| {
+        .consultar(move |c| {
             estabelecimentos::table
                 .filter(estabelecimentos::cnpj_basico.eq(&cnpj_completo[..8]))
                 .filter(estabelecimentos::cnpj_ordem.eq(&cnpj_completo[8..12]))
@@ -131,66 +340,658 @@ async fn get_estabelecimentos(conn: DBPool, cnpj_completo: String, com_cnaes_sec
         qualificacao_do_responsavel,
     ) = query_result;
 
-    if com_cnaes_secundarias {
-            let cnaes_fiscais_secundarias: Vec<u32> = match &estabelecimento.cnae_fiscal_secundaria {
-                Some(v) => v.to_string().split(",").map(|s| s.parse().unwrap()).collect(),
-                _ => vec![]
+    let cnaes_fiscais_secundarias = if com_cnaes_secundarias {
+        let cnaes_fiscais_secundarias: Vec<u32> = match &estabelecimento.cnae_fiscal_secundaria {
+            Some(v) => v.split(',').map(|s| s.parse().unwrap()).collect(),
+            _ => vec![]
+        };
+
+        let cnaes_fiscais_secundarias = conn
+            .consultar(|c| {
+                cnaes::table
+                    .filter(cnaes::id.eq_any(cnaes_fiscais_secundarias))
+                    .load::<CNAE>(c)
+            })
+            .await?;
+
+        Some(cnaes_fiscais_secundarias)
+    } else {
+        None
+    };
+
+    let resultado = EstabelecimentoResult{
+        estabelecimento,
+        motivo_situacao_cadastral,
+        pais,
+        municipio,
+        cnae_fiscal_principal,
+        cnaes_fiscais_secundarias,
+        empresa,
+        natureza_juridica,
+        qualificacao_do_responsavel,
+    };
+    cache.estabelecimentos.incluir(chave, resultado.clone());
+
+    Ok(Json(exposicao.aplicar(resultado)))
+}
+
+#[derive(Deserialize)]
+struct LoteDeCnpjs {
+    cnpjs: Vec<String>,
+    com_cnaes_secundarias: Option<bool>,
+}
+
+#[derive(Serialize)]
+struct ItemDoLoteResult {
+    cnpj: String,
+    status: StatusDoItemDoLote,
+    estabelecimento: Option<EstabelecimentoResult>,
+}
+
+impl Mascarar for ItemDoLoteResult {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        self.estabelecimento.mascarar(mascaramento);
+    }
+}
+
+impl LinhaCsv for ItemDoLoteResult {
+    fn cabecalho() -> Vec<&'static str> {
+        vec![
+            "cnpj",
+            "status",
+            "razao_social",
+            "nome_fantasia",
+            "situacao_cadastral",
+            "cnae_fiscal_principal",
+            "municipio",
+            "uf",
+        ]
+    }
+
+    fn campos(&self) -> Vec<String> {
+        let e = self.estabelecimento.as_ref();
+        vec![
+            self.cnpj.clone(),
+            json::to_string(&self.status).unwrap_or_default().trim_matches('"').to_string(),
+            campo_csv(&e.and_then(|e| e.empresa.as_ref()).map(|empresa| &empresa.razao_social)),
+            campo_csv(&e.and_then(|e| e.estabelecimento.nome_fantasia.as_ref())),
+            campo_csv(&e.map(|e| e.estabelecimento.situacao_cadastral.descricao())),
+            campo_csv(&e.and_then(|e| e.cnae_fiscal_principal.as_ref()).map(|cnae| &cnae.nome)),
+            campo_csv(&e.and_then(|e| e.municipio.as_ref()).map(|municipio| &municipio.nome)),
+            campo_csv(&e.and_then(|e| e.estabelecimento.uf.as_ref())),
+        ]
+    }
+}
+
+// Consulta vários estabelecimentos de uma só vez. Os CNPJs podem ser informados formatados ou não e o
+// resultado é retornado na mesma ordem em que os CNPJs foram informados, com a situação de cada um.
+#[post("/estabelecimentos/lote", format = "json", data = "<lote>")]
+async fn post_estabelecimentos_lote(conn: DBPool, formato: Formato, exposicao: Exposicao, lote: Json<LoteDeCnpjs>) -> Result<Resposta<Vec<ItemDoLoteResult>>, CustomError> {
+
+    let lote = lote.into_inner();
+    if lote.cnpjs.len() > LIMITE_DO_LOTE {
+        return Err(CustomError::invalid_param("cnpjs", format!("Informe no máximo {} CNPJs por lote", LIMITE_DO_LOTE)));
+    }
+    let com_cnaes_secundarias = lote.com_cnaes_secundarias.unwrap_or(false);
+
+    let (normalizados, validos) = normalizar_lote(&lote.cnpjs);
+
+    let encontrados = conn
+        .consultar(move |c| -> QueryResult<Vec<EstabelecimentoResult>> {
+            let mut encontrados = Vec::new();
+
+            for bloco in validos.chunks(TAMANHO_DO_BLOCO) {
+                let query_result = estabelecimentos::table
+                    .left_join(motivos_de_situacoes_cadastrais::table)
+                    .left_join(paises::table)
+                    .left_join(municipios::table)
+                    .left_join(cnaes::table)
+                    .left_join(empresas::table)
+                    .left_join(naturezas_juridicas::table.on(empresas::natureza_juridica.eq(naturezas_juridicas::id.nullable())))
+                    .left_join(qualificacoes_de_socios::table.on(empresas::qualificacao_do_responsavel.eq(qualificacoes_de_socios::id.nullable())))
+                    .filter(condicao_dos_cnpjs(bloco))
+                    .load::<(
+                        Estabelecimento,
+                        Option<MotivoDeSituacaoCadastral>,
+                        Option<Pais>,
+                        Option<Municipio>,
+                        Option<CNAE>,
+                        Option<Empresa>,
+                        Option<NaturezaJuridica>,
+                        Option<QualificacaoDeSocio>,
+                    )>(c)?;
+
+                for (
+                    estabelecimento,
+                    motivo_situacao_cadastral,
+                    pais,
+                    municipio,
+                    cnae_fiscal_principal,
+                    empresa,
+                    natureza_juridica,
+                    qualificacao_do_responsavel,
+                ) in query_result {
+                    encontrados.push(EstabelecimentoResult {
+                        estabelecimento,
+                        motivo_situacao_cadastral,
+                        pais,
+                        municipio,
+                        cnae_fiscal_principal,
+                        cnaes_fiscais_secundarias: None,
+                        empresa,
+                        natureza_juridica,
+                        qualificacao_do_responsavel,
+                    });
+                }
+            }
+
+            if com_cnaes_secundarias {
+                let mut ids: Vec<u32> = encontrados
+                    .iter()
+                    .filter_map(|e| e.estabelecimento.cnae_fiscal_secundaria.as_ref())
+                    .flat_map(|v| v.split(',').filter_map(|s| s.trim().parse().ok()).collect::<Vec<u32>>())
+                    .collect();
+                ids.sort_unstable();
+                ids.dedup();
+
+                let cnaes_encontrados = cnaes::table
+                    .filter(cnaes::id.eq_any(ids))
+                    .load::<CNAE>(c)?;
+
+                for e in encontrados.iter_mut() {
+                    let ids: Vec<u32> = match &e.estabelecimento.cnae_fiscal_secundaria {
+                        Some(v) => v.split(',').filter_map(|s| s.trim().parse().ok()).collect(),
+                        None => vec![],
+                    };
+                    e.cnaes_fiscais_secundarias = Some(
+                        cnaes_encontrados.iter().filter(|cnae| ids.contains(&cnae.id)).cloned().collect()
+                    );
+                }
+            }
+
+            Ok(encontrados)
+        })
+        .await?;
+
+    let encontrados = por_cnpj(encontrados, |e| {
+        format!("{}{}{}", e.estabelecimento.cnpj_basico, e.estabelecimento.cnpj_ordem, e.estabelecimento.cnpj_dv)
+    });
+    let itens: Vec<ItemDoLoteResult> = lote.cnpjs
+        .into_iter()
+        .zip(normalizados)
+        .map(|(cnpj, normalizado)| {
+            let normalizado = match normalizado {
+                Some(v) => v,
+                None => return ItemDoLoteResult { cnpj, status: StatusDoItemDoLote::Invalido, estabelecimento: None },
             };
-           
-            let cnaes_fiscais_secundarias = conn
-                .run(|c| {
-                    cnaes::table
-                        .filter(cnaes::id.eq_any(cnaes_fiscais_secundarias))
-                        .load::<CNAE>(c)
-                })
-                .await?;
-        
-            Ok(Json(EstabelecimentoResult{
-                estabelecimento,
-                motivo_situacao_cadastral,
-                pais,
-                municipio,
-                cnae_fiscal_principal,
-                cnaes_fiscais_secundarias: Some(cnaes_fiscais_secundarias),
-                empresa,
-                natureza_juridica,
-                qualificacao_do_responsavel,
-            }))            
+            let estabelecimento = encontrados.get(&normalizado).cloned();
+            let status = match estabelecimento {
+                Some(_) => StatusDoItemDoLote::Encontrado,
+                None => StatusDoItemDoLote::NaoEncontrado,
+            };
+            ItemDoLoteResult { cnpj, status, estabelecimento }
+        })
+        .collect();
+    let itens = exposicao.aplicar(itens);
 
-        } else {
-            Ok(Json(EstabelecimentoResult{
-                estabelecimento,
-                motivo_situacao_cadastral,
-                pais,
-                municipio,
-                cnae_fiscal_principal,
-                cnaes_fiscais_secundarias: None,
-                empresa,
-                natureza_juridica,
-                qualificacao_do_responsavel,
-            }))
+    if formato.streaming() {
+        // o lote já está limitado a LIMITE_DO_LOTE CNPJs, então as linhas são geradas de uma só vez
+        return Ok(Resposta::Streaming(RespostaEmStreaming {
+            formato,
+            linhas: stream::once(async move { formato.linhas(&itens, true) }).boxed(),
+        }));
+    }
+
+    Ok(Resposta::Json(Json(itens)))
+}
+
+// quantidade máxima de sócios retornados em uma única consulta
+const LIMITE_PADRAO_DE_SOCIOS: i64 = 100;
+const LIMITE_MAXIMO_DE_SOCIOS: i64 = 1000;
+// nas respostas em streaming (NDJSON/CSV) os registros são lidos em blocos, por isso o limite pode ser maior
+const LIMITE_MAXIMO_EM_STREAMING: i64 = 1_000_000;
+// quantidade de registros lidos do banco de dados em cada bloco das respostas em streaming
+const REGISTROS_POR_BLOCO: i64 = 1000;
+// quantidade máxima de conexões do pool das respostas em streaming
+const TAMANHO_DO_POOL_DO_STREAMING: u32 = 4;
+
+// Pool de conexões das respostas em streaming, separado do pool das rotas (DBPool). Cada bloco é lido com
+// uma conexão obtida do pool e devolvida em seguida, então uma resposta longa não ocupa uma conexão durante
+// toda a transmissão (que depende da velocidade do cliente) nem esgota as conexões das demais rotas.
+pub struct PoolDoStreaming(Pool<ConnectionManager<diesel::MysqlConnection>>);
+
+impl PoolDoStreaming {
+    // As conexões são estabelecidas somente quando utilizadas
+    fn new(url: &str) -> PoolDoStreaming {
+        PoolDoStreaming(
+            Pool::builder()
+                .max_size(TAMANHO_DO_POOL_DO_STREAMING)
+                .min_idle(Some(0))
+                .build_unchecked(ConnectionManager::new(url)),
+        )
+    }
+}
+
+// Gera a resposta em streaming (NDJSON ou CSV), lendo os registros do banco de dados em blocos por
+// meio da função consultar_bloco, que recebe a continuação (a chave do último registro do bloco anterior)
+// e a quantidade de registros do bloco, e retorna os registros e a chave do último deles. A continuação
+// pela chave, em vez do OFFSET, faz com que cada bloco seja lido diretamente pelo índice.
+fn em_streaming<T, K, F>(pool: &PoolDoStreaming, formato: Formato, limite: i64, consultar_bloco: F) -> RespostaEmStreaming
+where
+    T: Serialize + LinhaCsv + Send + 'static,
+    K: Send + 'static,
+    F: Fn(&mut diesel::MysqlConnection, Option<K>, i64) -> QueryResult<(Vec<T>, Option<K>)> + Send + Sync + 'static,
+{
+    let consultar_bloco = Arc::new(consultar_bloco);
+    let pool = pool.0.clone();
+
+    let linhas = stream::unfold(Some((None, 0)), move |estado| {
+        let consultar_bloco = consultar_bloco.clone();
+        let pool = pool.clone();
+        async move {
+            let (apos, lidos) = estado?;
+            let quantidade = REGISTROS_POR_BLOCO.min(limite - lidos);
+            if quantidade <= 0 {
+                return None;
+            }
+
+            let bloco = rocket::tokio::task::spawn_blocking(move || {
+                let mut conn = pool.get().map_err(|error| format!("{:?}", error))?;
+                consultar_bloco(&mut conn, apos, quantidade).map_err(|error| format!("{:?}", error))
+            })
+            .await
+            .map_err(|error| format!("{:?}", error))
+            .and_then(|bloco| bloco);
+
+            match bloco {
+                Ok((registros, _)) if registros.is_empty() && lidos > 0 => None,
+                Ok((registros, ultimo)) => {
+                    let linhas = formato.linhas(&registros, lidos == 0);
+                    let proximo_estado = match ultimo {
+                        Some(ultimo) if registros.len() as i64 == quantidade => Some((Some(ultimo), lidos + quantidade)),
+                        _ => None,
+                    };
+                    Some((linhas, proximo_estado))
+                }
+                Err(error) => {
+                    // o status da resposta já foi enviado, então o erro é apenas registrado e a resposta encerrada
+                    tracing::error!(erro = %error, "Erro ao ler os registros da resposta em streaming");
+                    None
+                }
+            }
         }
-    
+    });
+
+    RespostaEmStreaming {
+        formato,
+        linhas: linhas.boxed(),
+    }
+}
+
+#[derive(Serialize)]
+struct SocioResult {
+    socio: Socio,
+    qualificacao_do_socio: Option<QualificacaoDeSocio>,
+    empresa: Option<Empresa>,
+}
+
+impl Mascarar for SocioResult {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        self.socio.mascarar(mascaramento);
+        self.empresa.mascarar(mascaramento);
+    }
+}
+
+impl LinhaCsv for SocioResult {
+    fn cabecalho() -> Vec<&'static str> {
+        vec![
+            "cnpj_basico",
+            "razao_social",
+            "identificador_de_socio",
+            "nome_ou_razao_social_do_socio",
+            "cnpj_ou_cpf_do_socio",
+            "qualificacao_do_socio",
+            "data_de_entrada_na_sociedade",
+            "cpf_do_representante_legal",
+            "nome_do_representante_legal",
+            "faixa_etaria_do_socio",
+        ]
+    }
+
+    fn campos(&self) -> Vec<String> {
+        vec![
+            self.socio.cnpj_basico.clone(),
+            campo_csv(&self.empresa.as_ref().map(|e| &e.razao_social)),
+            self.socio.identificador_de_socio.descricao().to_string(),
+            self.socio.nome_ou_razao_social_do_socio.clone(),
+            campo_csv(&self.socio.cnpj_ou_cpf_do_socio),
+            campo_csv(&self.qualificacao_do_socio.as_ref().map(|q| &q.nome)),
+            self.socio.data_de_entrada_na_sociedade.to_string(),
+            self.socio.cpf_do_representante_legal.clone(),
+            self.socio.nome_do_representante_legal.clone(),
+            self.socio.faixa_etaria_do_socio.descricao().to_string(),
+        ]
+    }
+}
+
+// Consulta um bloco de sócios onde a pessoa informada é sócia ou representante legal, já com a
+// empresa e a qualificação do sócio de cada registro
+fn consultar_socios(c: &mut diesel::MysqlConnection, busca: &BuscaDeSocios, apos: Option<u32>, quantidade: i64) -> QueryResult<Vec<SocioResult>> {
+    let socios_encontrados = buscar_socios::<Socio>(c, busca, apos, quantidade)?;
+
+    let cnpjs_basicos: Vec<&String> = socios_encontrados.iter().map(|s| &s.cnpj_basico).collect();
+    let empresas_encontradas = empresas::table
+        .filter(empresas::cnpj_basico.eq_any(cnpjs_basicos))
+        .load::<Empresa>(c)?;
+    let qualificacoes = qualificacoes_de_socios::table
+        .load::<QualificacaoDeSocio>(c)?;
+
+    Ok(socios_encontrados
+        .into_iter()
+        .map(|socio| {
+            let qualificacao_do_socio = qualificacoes.iter()
+                .find(|q| q.id == socio.qualificacao_do_socio)
+                .cloned();
+            let empresa = empresas_encontradas.iter()
+                .find(|e| e.cnpj_basico == socio.cnpj_basico)
+                .cloned();
+            SocioResult {
+                socio,
+                qualificacao_do_socio,
+                empresa,
+            }
+        })
+        .collect())
+}
+
+// Valida os parâmetros da busca de sócios, recusando (403) a busca pelos dados pessoais que o perfil de
+// exposição oculta
+fn parametros_da_busca_de_socios(exposicao: &Mascaramento, cpf: Option<String>, cnpj: Option<String>, nome: Option<String>) -> Result<BuscaDeSocios, CustomError> {
+    let busca = BuscaDeSocios::new(cpf.as_deref(), cnpj.as_deref(), nome.as_deref()).map_err(|erro| match erro.parametro {
+        Some(parametro) => CustomError::invalid_param(parametro, erro.mensagem),
+        None => CustomError::BadRequestErr(erro.mensagem),
+    })?;
+
+    if let Some(parametro) = busca.parametro_oculto(exposicao.perfil()) {
+        return Err(CustomError::ForbiddenErr(format!(
+            "O perfil de exposição {} não permite a busca de sócios pelo parâmetro {}",
+            exposicao.perfil().nome, parametro
+        )));
+    }
+
+    Ok(busca)
+}
+
+// Busca todas as empresas onde a pessoa (física ou jurídica) informada é sócia ou representante legal.
+// Pode-se buscar pelo CNPJ do sócio (pessoa jurídica), pelo CPF (completo ou mascarado como nos arquivos
+// da RF) e/ou pelo início do nome do sócio.
+#[get("/socios?<cpf>&<cnpj>&<nome>&<limite>")]
+#[allow(clippy::too_many_arguments)] // os parâmetros da rota são os parâmetros da consulta
+async fn get_socios(conn: DBPool, pool: &State<PoolDoStreaming>, formato: Formato, exposicao: Exposicao, cpf: Option<String>, cnpj: Option<String>, nome: Option<String>, limite: Option<i64>) -> Result<Resposta<Vec<SocioResult>>, CustomError> {
+
+    let busca = parametros_da_busca_de_socios(&exposicao, cpf, cnpj, nome)?;
+
+    if formato.streaming() {
+        let limite = limite.unwrap_or(LIMITE_MAXIMO_EM_STREAMING).clamp(1, LIMITE_MAXIMO_EM_STREAMING);
+        return Ok(Resposta::Streaming(em_streaming(pool, formato, limite, move |c, apos, quantidade| {
+            let socios = consultar_socios(c, &busca, apos, quantidade)?;
+            let ultimo = socios.last().map(|s| s.socio.id);
+            Ok((exposicao.aplicar(socios), ultimo))
+        })));
+    }
+
+    let limite = limite.unwrap_or(LIMITE_PADRAO_DE_SOCIOS).clamp(1, LIMITE_MAXIMO_DE_SOCIOS);
+    let query_result = conn
+        .consultar(move |c| consultar_socios(c, &busca, None, limite))
+        .await?;
+
+    Ok(Resposta::Json(Json(exposicao.aplicar(query_result))))
+}
+
+// profundidade padrão na navegação do grafo de participações societárias
+const PROFUNDIDADE_PADRAO_DO_GRAFO: u8 = 3;
+
+// Retorna o grafo de participações societárias (grupo econômico) da empresa, navegando tanto pelas
+// empresas sócias quanto pelas empresas das quais ela é sócia. O parâmetro formato aceita json (padrão),
+// graphml ou dot (Graphviz).
+#[get("/empresas/<cnpjbas>/grafo?<depth>&<formato>")]
+async fn get_grafo_da_empresa(conn: ConexaoAposValidacao, exposicao: Exposicao, cnpjbas: String, depth: Option<u8>, formato: Option<String>) -> Result<(ContentType, String), CustomError> {
+
+    let cnpjbas = validar_cnpj_basico(&cnpjbas)?;
+
+    let profundidade = depth.unwrap_or(PROFUNDIDADE_PADRAO_DO_GRAFO);
+    if profundidade == 0 || profundidade > PROFUNDIDADE_MAXIMA {
+        return Err(CustomError::invalid_param("depth", format!("A profundidade deve estar entre 1 e {}", PROFUNDIDADE_MAXIMA)));
+    }
+    let conn = conexao(conn)?;
+
+    let grafo = conn
+        .consultar(move |c| grupo_economico(c, &cnpjbas, profundidade))
+        .await?;
+    let grafo = exposicao.aplicar(grafo);
+
+    match formato.as_deref().unwrap_or("json") {
+        "json" => Ok((ContentType::JSON, json::to_string(&grafo).expect("Erro ao serializar o grafo"))),
+        "graphml" => Ok((ContentType::new("application", "graphml+xml"), grafo.to_graphml())),
+        "dot" => Ok((ContentType::new("text", "vnd.graphviz"), grafo.to_dot())),
+        v => Err(CustomError::invalid_param("formato", format!("Formato inválido: {} (utilize json, graphml ou dot)", v))),
+    }
+}
+
+// Busca as empresas (e seus estabelecimentos) pela razão social ou pelo nome fantasia. Os termos
+// informados em q podem ser apenas o início das palavras e não diferenciam acentos. Os resultados
+// podem ser filtrados pela UF, pelo código da situação cadastral e pelo código do IBGE do município do
+// estabelecimento.
+#[get("/busca?<q>&<uf>&<situacao>&<municipio_ibge>&<limite>&<pagina>")]
+#[allow(clippy::too_many_arguments)] // os parâmetros da rota são os parâmetros da consulta
+async fn get_busca(conn: DBPool, pool: &State<PoolDoStreaming>, formato: Formato, exposicao: Exposicao, q: String, uf: Option<String>, situacao: Option<u8>, municipio_ibge: Option<u32>, limite: Option<i64>, pagina: Option<i64>) -> Result<Resposta<Vec<ResultadoDaBusca>>, CustomError> {
+
+    let expressao = expressao_de_busca(&q)
+        .ok_or_else(|| CustomError::invalid_param("q", String::from("Informe ao menos um termo com 3 ou mais caracteres")))?;
+
+    let situacao_cadastral = match situacao {
+        Some(v) => Some(SituacaoCadastral::from_codigo(v).ok_or_else(|| CustomError::invalid_param("situacao", format!("Situação cadastral inválida: {}", v)))?),
+        None => None,
+    };
+    if let Some(codigo) = municipio_ibge.filter(|codigo| !codigo_ibge_valido(*codigo)) {
+        return Err(CustomError::invalid_param("municipio_ibge", format!("Código do IBGE inválido (deve conter 7 dígitos): {}", codigo)));
+    }
+    let filtro = FiltroDaBusca {
+        uf,
+        situacao_cadastral,
+        municipio_ibge,
+    };
+
+    // em streaming os resultados são ordenados pelo CNPJ, para que sejam lidos em blocos pela continuação,
+    // e a página corresponde aos blocos de limite resultados
+    if formato.streaming() {
+        let limite = limite.unwrap_or(LIMITE_MAXIMO_EM_STREAMING).clamp(1, LIMITE_MAXIMO_EM_STREAMING);
+        let (_, deslocamento) = deslocamento_da_pagina(pagina, limite)
+            .map_err(|mensagem| CustomError::invalid_param("pagina", mensagem))?;
+        return Ok(Resposta::Streaming(em_streaming(pool, formato, limite, move |c, apos, quantidade| {
+            let resultados = buscar_empresas_em_blocos(c, &expressao, &filtro, apos.as_ref(), quantidade, deslocamento)?;
+            let ultimo = resultados.last().map(ResultadoDaBusca::continuacao);
+            Ok((exposicao.aplicar(resultados), ultimo))
+        })));
+    }
+
+    let limite = limite.unwrap_or(LIMITE_PADRAO_DA_BUSCA).clamp(1, LIMITE_MAXIMO_DA_BUSCA);
+    let (_, deslocamento) = deslocamento_da_pagina(pagina, limite)
+        .map_err(|mensagem| CustomError::invalid_param("pagina", mensagem))?;
+
+    let resultados = conn
+        .consultar(move |c| buscar_empresas(c, &expressao, &filtro, limite, deslocamento))
+        .await?;
+
+    Ok(Resposta::Json(Json(exposicao.aplicar(resultados))))
 }
 
 #[launch]
 fn rocket() -> _ {
 
     dotenv().ok();
+    iniciar_logs("info");
 
     let db_url = env::var("DATABASE_URL").unwrap();
     let db: Map<_, Value> = map! {
-        "url" => db_url.into(),
-        "pool_size" => 10.into()
+        "url" => db_url.clone().into(),
+        "pool_size" => TAMANHO_DO_POOL.into()
     };
 
     let figment = rocket::Config::figment().merge(("databases", map!["cnpj_db" => db]));
 
-    rocket::custom(figment)
+    // o fairing das métricas é o primeiro, para que a duração medida inclua os demais fairings
+    let rocket = rocket::custom(figment).attach(monitoramento::MetricasDasRequisicoes);
+
+    #[cfg(feature = "indice")]
+    let rocket = indice::montar(rocket);
+
+    let rocket = rocket
         .mount("/api", routes![
             get_cnaes,
             get_empresas,
-            get_estabelecimentos
+            get_socios_da_empresa,
+            get_simples_da_empresa,
+            get_estabelecimentos_da_empresa,
+            get_estabelecimentos,
+            post_estabelecimentos_lote,
+            get_socios,
+            get_grafo_da_empresa,
+            get_busca
         ])
-        .attach(DBPool::fairing())
-}
\ No newline at end of file
+        .mount("/api", tabelas::rotas())
+        .mount("/api/v1", v1::rotas())
+        .mount("/api/v1", routes![get_grafo_da_empresa, get_busca])
+        .mount("/api/v1", tabelas::rotas())
+        .mount("/api/v1", cache_de_consultas::rotas())
+        .mount("/api", openapi::rotas())
+        .mount("/api", cache_http::rotas())
+        .mount("/", monitoramento::rotas())
+        .manage(CacheDeConsultas::new(
+            env::var("CACHE_DE_CONSULTAS").ok().and_then(|v| v.parse().ok()),
+            env::var("CACHE_DE_CONSULTAS_TTL").ok().and_then(|v| v.parse().ok()),
+        ))
+        .manage(PerfisDeExposicao::do_ambiente())
+        .manage(PoolDoStreaming::new(&db_url))
+        .register("/", catchers())
+        .attach(DBPool::fairing());
+
+    // o controle de acesso carrega as chaves de API na inicialização, após a criação do pool de conexões
+    let rocket = if env::var("EXIGIR_CHAVE_DE_API").map(|v| v == "true" || v == "1").unwrap_or(false) {
+        rocket.attach(acesso::ControleDeAcessoDaApi::default())
+    } else {
+        rocket
+    };
+
+    rocket
+        // carrega as tabelas auxiliares no cache logo na inicialização
+        .attach(AdHoc::on_liftoff("Carga do cache de consultas", |rocket| Box::pin(async move {
+            if let (Some(cache), Some(conn)) = (rocket.state::<CacheDeConsultas>(), DBPool::get_one(rocket).await) {
+                cache.atualizar(&conn).await;
+            }
+        })))
+        .attach(openapi::VerificacaoDaEspecificacao)
+        .attach(v1::AvisoDeDescontinuacao)
+        .attach(cache_http::CacheHttp::new(env::var("CACHE_MAX_AGE").ok().and_then(|v| v.parse().ok())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::fairing::{Fairing, Info, Kind};
+    use rocket::http::{Accept, Header, Status};
+    use rocket::local::blocking::Client;
+    use rocket::serde::json::Value;
+    use rocket::{Data, Request};
+
+    // Controle de acesso sem a carga das chaves na inicialização (que exige o banco de dados): nenhuma
+    // chave está ativa
+    struct ControleDeAcessoSemChaves(acesso::ControleDeAcessoDaApi);
+
+    #[rocket::async_trait]
+    impl Fairing for ControleDeAcessoSemChaves {
+        fn info(&self) -> Info {
+            Info { name: "Controle de acesso sem chaves", kind: Kind::Request }
+        }
+
+        async fn on_request(&self, request: &mut Request<'_>, data: &mut Data<'_>) {
+            self.0.on_request(request, data).await
+        }
+    }
+
+    // Servidor com as rotas da API, mas sem o pool de conexões com o banco de dados: as rotas que obtêm
+    // uma conexão respondem com 503
+    fn cliente(exigir_chave_de_api: bool) -> Client {
+        let rocket = rocket::build()
+            .mount("/api", routes![get_empresas, get_simples_da_empresa, get_estabelecimentos, get_grafo_da_empresa])
+            .manage(CacheDeConsultas::new(None, None))
+            .manage(PerfisDeExposicao::do_ambiente())
+            .register("/", catchers());
+        let rocket = if exigir_chave_de_api {
+            rocket.attach(ControleDeAcessoSemChaves(acesso::ControleDeAcessoDaApi::default()))
+        } else {
+            rocket
+        };
+        Client::tracked(rocket).expect("Erro ao criar o cliente de testes")
+    }
+
+    // Status e corpo (JSON) da resposta à requisição GET
+    fn get(cliente: &Client, uri: &str, chave_de_api: Option<&str>) -> (Status, Value) {
+        let mut request = cliente.get(uri.to_string()).header(Accept::JSON);
+        if let Some(chave) = chave_de_api {
+            request = request.header(Header::new("X-API-Key", chave.to_string()));
+        }
+        let response = request.dispatch();
+        assert_eq!(response.content_type(), Some(ContentType::JSON), "{}", uri);
+        (response.status(), response.into_json::<Value>().expect("Corpo da resposta não é JSON"))
+    }
+
+    #[test]
+    fn cnpj_invalido_na_rota_dos_estabelecimentos() {
+        let cliente = cliente(false);
+
+        for cnpj in ["1122233300018", "11222333000182", "abcdefghijklmn"] {
+            let (status, corpo) = get(&cliente, &format!("/api/estabelecimentos/false/{}", cnpj), None);
+            assert_eq!(status, Status::BadRequest, "{}", cnpj);
+            assert_eq!(corpo["code"], "BAD_REQUEST");
+            assert_eq!(corpo["message"], "Parâmetro inválido: cnpj_completo");
+            assert_eq!(corpo["details"][0]["param"], "cnpj_completo");
+        }
+
+        // o CNPJ válido passa pela validação e a rota tenta obter uma conexão
+        let (status, corpo) = get(&cliente, "/api/estabelecimentos/false/11222333000181", None);
+        assert_eq!(status, Status::ServiceUnavailable);
+        assert_eq!(corpo["code"], "SERVICE_UNAVAILABLE");
+    }
+
+    #[test]
+    fn cnpj_basico_invalido_nas_rotas_da_empresa() {
+        let cliente = cliente(false);
+
+        for uri in ["/api/empresas/1234567", "/api/empresas/1234567/simples", "/api/empresas/123456789/grafo"] {
+            let (status, corpo) = get(&cliente, uri, None);
+            assert_eq!(status, Status::BadRequest, "{}", uri);
+            assert_eq!(corpo["details"][0]["param"], "cnpjbas");
+        }
+    }
+
+    #[test]
+    fn chave_de_api_desconhecida_nas_rotas_da_api() {
+        let cliente = cliente(true);
+
+        for uri in ["/api/estabelecimentos/false/11222333000181", "/api/empresas/12345678"] {
+            let (status, corpo) = get(&cliente, uri, Some("cnpj_desconhecida"));
+            assert_eq!(status, Status::Unauthorized, "{}", uri);
+            assert_eq!(corpo["code"], "UNAUTHORIZED");
+            assert_eq!(corpo["message"], "Chave de API inválida ou revogada");
+
+            let (status, corpo) = get(&cliente, uri, None);
+            assert_eq!(status, Status::Unauthorized, "{}", uri);
+            assert_eq!(corpo["message"], "Informe a chave de API no cabeçalho X-API-Key");
+        }
+
+        // a chave é verificada antes dos parâmetros da rota
+        let (status, _) = get(&cliente, "/api/estabelecimentos/false/123", Some("cnpj_desconhecida"));
+        assert_eq!(status, Status::Unauthorized);
+    }
+}
@@ -0,0 +1,116 @@
+// Rotas de monitoramento do servidor, fora de /api (não exigem a chave de API):
+//
+//   /health  - o servidor está no ar (liveness)
+//   /ready   - o servidor pode atender às requisições: o banco de dados responde e os dados já foram importados
+//   /metrics - métricas no formato de texto do Prometheus (vide data_models::monitoramento)
+//
+// As requisições são contabilizadas pelo fairing MetricasDasRequisicoes, por rota, método e status.
+
+use std::time::Instant;
+
+use data_models::monitoramento::{verificar_prontidao, TextoDoPrometheus, METRICAS};
+use data_models::versao::metadados_das_tabelas;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::Json;
+use rocket::{Data, Request, Response, Route, State};
+use serde::Serialize;
+
+use crate::cache_de_consultas::CacheDeConsultas;
+use crate::{DBPool, TAMANHO_DO_POOL};
+
+#[derive(Serialize)]
+struct EstadoDoServico {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tabelas_importadas: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    motivo: Option<String>,
+}
+
+fn indisponivel(motivo: String) -> (Status, Json<EstadoDoServico>) {
+    (
+        Status::ServiceUnavailable,
+        Json(EstadoDoServico {
+            status: "indisponivel",
+            tabelas_importadas: None,
+            motivo: Some(motivo),
+        }),
+    )
+}
+
+#[get("/health")]
+fn get_health() -> Json<EstadoDoServico> {
+    Json(EstadoDoServico {
+        status: "ok",
+        tabelas_importadas: None,
+        motivo: None,
+    })
+}
+
+#[get("/ready")]
+async fn get_ready(conn: Option<DBPool>) -> (Status, Json<EstadoDoServico>) {
+    let conn = match conn {
+        Some(conn) => conn,
+        None => return indisponivel(String::from("Não foi possível obter uma conexão com o banco de dados")),
+    };
+
+    match conn.consultar(|c| verificar_prontidao(c)).await {
+        Ok(tabelas_importadas) => (
+            Status::Ok,
+            Json(EstadoDoServico {
+                status: "ok",
+                tabelas_importadas: Some(tabelas_importadas),
+                motivo: None,
+            }),
+        ),
+        Err(motivo) => indisponivel(motivo),
+    }
+}
+
+#[get("/metrics")]
+async fn get_metrics(conn: Option<DBPool>, cache: &State<CacheDeConsultas>) -> (ContentType, String) {
+    let mut texto = TextoDoPrometheus::new();
+    METRICAS.exportar(&mut texto);
+    texto.pool_de_conexoes(METRICAS.conexoes_em_uso(), u64::from(TAMANHO_DO_POOL));
+    texto.caches(&cache.estatisticas());
+
+    if let Some(conn) = conn {
+        match conn.consultar(|c| metadados_das_tabelas(c)).await {
+            Ok(metadados) => texto.idade_dos_dados(&metadados),
+            Err(error) => tracing::error!(erro = ?error, "Erro ao ler os metadados das tabelas"),
+        }
+    }
+
+    (ContentType::Plain, texto.texto())
+}
+
+pub fn rotas() -> Vec<Route> {
+    routes![get_health, get_ready, get_metrics]
+}
+
+// instante em que a requisição foi recebida, guardado no cache local da requisição
+struct InicioDaRequisicao(Instant);
+
+pub struct MetricasDasRequisicoes;
+
+#[rocket::async_trait]
+impl Fairing for MetricasDasRequisicoes {
+    fn info(&self) -> Info {
+        Info {
+            name: "Métricas das requisições",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        request.local_cache(|| InicioDaRequisicao(Instant::now()));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let inicio = request.local_cache(|| InicioDaRequisicao(Instant::now()));
+        // o caminho da rota (ex: /api/v1/empresas/<cnpjbas>) e não o da requisição, para limitar a quantidade de séries
+        let rota = request.route().map(|rota| rota.uri.path().to_string()).unwrap_or_else(|| String::from("desconhecida"));
+        METRICAS.registrar_requisicao(&rota, request.method().as_str(), response.status().code, inicio.0.elapsed());
+    }
+}
@@ -0,0 +1,160 @@
+// Especificação OpenAPI 3 da API REST (rest-server/openapi.json) e a página do Swagger UI.
+//
+// Os arquivos do Swagger UI são servidos pelo próprio servidor, a partir do diretório SWAGGER_UI_DIR
+// (rest-server/swagger-ui, por padrão), e não de uma CDN. Eles são baixados, em uma versão fixa, pelo script
+// rest-server/swagger-ui/baixar.sh.
+//
+// A especificação é mantida manualmente junto com as rotas. Para que ela não fique desatualizada, ao
+// iniciar o servidor todas as rotas montadas são conferidas com a especificação e a inicialização é
+// abortada caso alguma rota não esteja documentada.
+
+use std::env;
+use std::path::PathBuf;
+
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::fs::NamedFile;
+use rocket::http::ContentType;
+use rocket::response::content::RawHtml;
+use rocket::serde::json::{self, Value};
+use rocket::{Build, Rocket, Route};
+
+use crate::cache_http::ROTA_DO_NAO_MODIFICADO;
+
+const ESPECIFICACAO: &str = include_str!("../openapi.json");
+
+// rotas internas, para as quais as requisições são desviadas pelos fairings, que não são documentadas
+const ROTAS_INTERNAS: &[&str] = &[ROTA_DO_NAO_MODIFICADO];
+
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="pt-BR">
+<head>
+    <meta charset="utf-8">
+    <title>Dados Públicos CNPJ - API REST</title>
+    <link rel="stylesheet" href="/api/docs/swagger-ui.css">
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="/api/docs/swagger-ui-bundle.js"></script>
+    <script>
+        window.onload = () => {
+            window.ui = SwaggerUIBundle({ url: "/api/openapi.json", dom_id: "#swagger-ui" });
+        };
+    </script>
+</body>
+</html>
+"##;
+
+#[get("/openapi.json")]
+fn get_openapi() -> (ContentType, &'static str) {
+    (ContentType::JSON, ESPECIFICACAO)
+}
+
+#[get("/docs")]
+fn get_docs() -> RawHtml<&'static str> {
+    RawHtml(SWAGGER_UI)
+}
+
+// arquivos do Swagger UI utilizados pela página (vide swagger-ui/baixar.sh)
+const ARQUIVOS_DO_SWAGGER_UI: &[&str] = &["swagger-ui.css", "swagger-ui-bundle.js"];
+
+#[get("/docs/<arquivo>")]
+async fn get_arquivo_do_swagger_ui(arquivo: &str) -> Option<NamedFile> {
+    if !ARQUIVOS_DO_SWAGGER_UI.contains(&arquivo) {
+        return None;
+    }
+
+    let diretorio = env::var("SWAGGER_UI_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/swagger-ui")));
+    NamedFile::open(diretorio.join(arquivo)).await.ok()
+}
+
+pub fn rotas() -> Vec<Route> {
+    routes![get_openapi, get_docs, get_arquivo_do_swagger_ui]
+}
+
+// Converte o caminho de uma rota do Rocket (ex: /api/empresas/<cnpjbas>) no formato da
+// especificação OpenAPI (ex: /api/empresas/{cnpjbas})
+fn caminho_da_especificacao(caminho: &str) -> String {
+    caminho
+        .split('/')
+        .map(|segmento| {
+            if segmento.starts_with('<') && segmento.ends_with('>') {
+                format!("{{{}}}", segmento.trim_matches(|c| c == '<' || c == '>' || c == '.'))
+            } else {
+                segmento.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+// Retorna as rotas (método e caminho) que não constam da especificação
+pub fn rotas_sem_especificacao<'a>(rotas: impl Iterator<Item = &'a Route>) -> Result<Vec<String>, String> {
+    let especificacao: Value = json::from_str(ESPECIFICACAO)
+        .map_err(|error| format!("Especificação OpenAPI inválida: {}", error))?;
+
+    Ok(rotas
+        .filter(|rota| !ROTAS_INTERNAS.contains(&rota.uri.path()))
+        .filter_map(|rota| {
+            let caminho = caminho_da_especificacao(rota.uri.path());
+            let metodo = rota.method.as_str().to_lowercase();
+            match especificacao["paths"][&caminho][&metodo] {
+                Value::Object(_) => None,
+                _ => Some(format!("{} {}", rota.method, caminho)),
+            }
+        })
+        .collect())
+}
+
+// Fairing que impede a inicialização do servidor quando há rotas montadas sem especificação
+pub struct VerificacaoDaEspecificacao;
+
+#[rocket::async_trait]
+impl Fairing for VerificacaoDaEspecificacao {
+    fn info(&self) -> Info {
+        Info {
+            name: "Verificação da especificação OpenAPI",
+            kind: Kind::Ignite,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        match rotas_sem_especificacao(rocket.routes()) {
+            Ok(rotas) if rotas.is_empty() => Ok(rocket),
+            Ok(rotas) => {
+                tracing::error!(
+                    rotas = %rotas.join(", "),
+                    "As seguintes rotas não constam da especificação OpenAPI (rest-server/openapi.json)"
+                );
+                Err(rocket)
+            }
+            Err(error) => {
+                tracing::error!("{}", error);
+                Err(rocket)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn todas_as_rotas_montadas_constam_da_especificacao() {
+        // a construção do servidor não se conecta ao banco de dados
+        env::set_var("DATABASE_URL", "mysql://teste@localhost/teste");
+        let rocket = crate::rocket();
+
+        assert!(rocket.routes().count() > 0);
+        assert_eq!(rotas_sem_especificacao(rocket.routes()), Ok(vec![]));
+    }
+
+    #[test]
+    fn caminhos_das_rotas_no_formato_da_especificacao() {
+        assert_eq!(caminho_da_especificacao("/api/empresas/<cnpjbas>"), "/api/empresas/{cnpjbas}");
+        assert_eq!(caminho_da_especificacao("/api/docs/<arquivo..>"), "/api/docs/{arquivo}");
+        assert_eq!(caminho_da_especificacao("/api/openapi.json"), "/api/openapi.json");
+    }
+}
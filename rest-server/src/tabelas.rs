@@ -0,0 +1,175 @@
+// Rotas de listagem (paginada) e de consulta por id das tabelas auxiliares (domínios) da RF. Quando o
+// cache de consultas está habilitado, as tabelas são servidas da memória (vide cache_de_consultas).
+
+use std::convert::TryFrom;
+
+use data_models::models::{CNAE, FaixaEtaria, MetadadosDasTabelas, MotivoDeSituacaoCadastral, Municipio, NaturezaJuridica, NomeDaSituacaoCadastral, Pais, QualificacaoDeSocio};
+use data_models::schema::{cnaes, faixas_etarias, metadados_das_tabelas, motivos_de_situacoes_cadastrais, municipios, naturezas_juridicas, paises, qualificacoes_de_socios, situacoes_cadastrais};
+use data_models::paginacao::deslocamento_da_pagina;
+use diesel::prelude::*;
+use rocket::serde::{Serialize, json::Json};
+use rocket::{Route, State};
+
+use rest_server::CustomError;
+
+use crate::cache_de_consultas::CacheDeConsultas;
+use crate::DBPool;
+
+// quantidade de registros por página quando o limite não é informado
+const LIMITE_PADRAO_POR_PAGINA: i64 = 100;
+const LIMITE_MAXIMO_POR_PAGINA: i64 = 1000;
+
+#[derive(Serialize)]
+pub struct Pagina<T> {
+    pub pagina: i64,
+    pub limite: i64,
+    pub total: i64,
+    pub registros: Vec<T>,
+}
+
+// Página (a partir de 1), limite de registros por página e deslocamento do primeiro registro da página
+#[derive(Clone, Copy)]
+pub struct Paginacao {
+    pub pagina: i64,
+    pub limite: i64,
+    pub deslocamento: i64,
+}
+
+// Retorna a paginação a partir dos parâmetros informados (400 quando a página é grande demais)
+pub fn paginacao(pagina: Option<i64>, limite: Option<i64>) -> Result<Paginacao, CustomError> {
+    let limite = limite.unwrap_or(LIMITE_PADRAO_POR_PAGINA).clamp(1, LIMITE_MAXIMO_POR_PAGINA);
+    let (pagina, deslocamento) = deslocamento_da_pagina(pagina, limite)
+        .map_err(|mensagem| CustomError::invalid_param("pagina", mensagem))?;
+
+    Ok(Paginacao { pagina, limite, deslocamento })
+}
+
+// Página de uma tabela carregada em memória
+fn pagina_da_tabela<T: Clone>(registros: &[T], paginacao: Paginacao) -> Pagina<T> {
+    let Paginacao { pagina, limite, deslocamento } = paginacao;
+    let inicio = usize::try_from(deslocamento).unwrap_or(usize::MAX).min(registros.len());
+    let fim = inicio.saturating_add(limite as usize).min(registros.len());
+
+    Pagina { pagina, limite, total: registros.len() as i64, registros: registros[inicio..fim].to_vec() }
+}
+
+// Gera as rotas de listagem paginada e de consulta por id de uma tabela auxiliar
+macro_rules! rotas_de_tabela {
+    ($listar:ident, $rota_da_lista:tt, $consultar:ident, $rota_do_registro:tt, $tabela:ident, $modelo:ident, $tipo_do_id:ty) => {
+        #[get($rota_da_lista)]
+        async fn $listar(conn: DBPool, cache: &State<CacheDeConsultas>, pagina: Option<i64>, limite: Option<i64>) -> Result<Json<Pagina<$modelo>>, CustomError> {
+            let paginacao = paginacao(pagina, limite)?;
+            let Paginacao { pagina, limite, deslocamento } = paginacao;
+
+            cache.atualizar(&conn).await;
+            if let Some(tabelas) = cache.tabelas_auxiliares() {
+                return Ok(Json(pagina_da_tabela(&tabelas.$tabela, paginacao)));
+            }
+
+            let (total, registros) = conn
+                .consultar(move |c| -> QueryResult<(i64, Vec<$modelo>)> {
+                    let total = $tabela::table.count().get_result::<i64>(c)?;
+                    let registros = $tabela::table
+                        .order($tabela::id)
+                        .offset(deslocamento)
+                        .limit(limite)
+                        .load::<$modelo>(c)?;
+                    Ok((total, registros))
+                })
+                .await?;
+
+            Ok(Json(Pagina { pagina, limite, total, registros }))
+        }
+
+        #[get($rota_do_registro)]
+        async fn $consultar(conn: DBPool, cache: &State<CacheDeConsultas>, id: $tipo_do_id) -> Result<Json<$modelo>, CustomError> {
+            cache.atualizar(&conn).await;
+            if let Some(tabelas) = cache.tabelas_auxiliares() {
+                // as tabelas em memória estão ordenadas pelo id
+                return match tabelas.$tabela.binary_search_by_key(&id, |registro| registro.id) {
+                    Ok(posicao) => Ok(Json(tabelas.$tabela[posicao].clone())),
+                    Err(_) => Err(CustomError::DatabaseErr(diesel::result::Error::NotFound)),
+                };
+            }
+
+            let registro = conn
+                .consultar(move |c| {
+                    $tabela::table
+                        .filter($tabela::id.eq(id))
+                        .first::<$modelo>(c)
+                })
+                .await?;
+
+            Ok(Json(registro))
+        }
+    };
+}
+
+rotas_de_tabela!(get_naturezas_juridicas, "/naturezas_juridicas?<pagina>&<limite>", get_natureza_juridica, "/naturezas_juridicas/<id>", naturezas_juridicas, NaturezaJuridica, u16);
+rotas_de_tabela!(get_qualificacoes_de_socios, "/qualificacoes_de_socios?<pagina>&<limite>", get_qualificacao_de_socio, "/qualificacoes_de_socios/<id>", qualificacoes_de_socios, QualificacaoDeSocio, u8);
+rotas_de_tabela!(get_paises, "/paises?<pagina>&<limite>", get_pais, "/paises/<id>", paises, Pais, u16);
+rotas_de_tabela!(get_municipios, "/municipios?<pagina>&<limite>", get_municipio, "/municipios/<id>", municipios, Municipio, u16);
+rotas_de_tabela!(get_motivos_de_situacoes_cadastrais, "/motivos_de_situacoes_cadastrais?<pagina>&<limite>", get_motivo_de_situacao_cadastral, "/motivos_de_situacoes_cadastrais/<id>", motivos_de_situacoes_cadastrais, MotivoDeSituacaoCadastral, u8);
+rotas_de_tabela!(get_faixas_etarias, "/faixas_etarias?<pagina>&<limite>", get_faixa_etaria, "/faixas_etarias/<id>", faixas_etarias, FaixaEtaria, u8);
+rotas_de_tabela!(get_situacoes_cadastrais, "/situacoes_cadastrais?<pagina>&<limite>", get_situacao_cadastral, "/situacoes_cadastrais/<id>", situacoes_cadastrais, NomeDaSituacaoCadastral, u8);
+
+// a consulta de um CNAE pelo id já existe em /cnaes/<cnae_num>, portanto aqui só é gerada a listagem
+#[get("/cnaes?<pagina>&<limite>")]
+async fn get_lista_de_cnaes(conn: DBPool, cache: &State<CacheDeConsultas>, pagina: Option<i64>, limite: Option<i64>) -> Result<Json<Pagina<CNAE>>, CustomError> {
+    let paginacao = paginacao(pagina, limite)?;
+    let Paginacao { pagina, limite, deslocamento } = paginacao;
+
+    cache.atualizar(&conn).await;
+    if let Some(tabelas) = cache.tabelas_auxiliares() {
+        return Ok(Json(pagina_da_tabela(&tabelas.cnaes, paginacao)));
+    }
+
+    let (total, registros) = conn
+        .consultar(move |c| -> QueryResult<(i64, Vec<CNAE>)> {
+            let total = cnaes::table.count().get_result::<i64>(c)?;
+            let registros = cnaes::table
+                .order(cnaes::id)
+                .offset(deslocamento)
+                .limit(limite)
+                .load::<CNAE>(c)?;
+            Ok((total, registros))
+        })
+        .await?;
+
+    Ok(Json(Pagina { pagina, limite, total, registros }))
+}
+
+// data/hora da última atualização (na RF) e da importação de cada tabela
+#[get("/metadados_das_tabelas")]
+async fn get_metadados_das_tabelas(conn: DBPool) -> Result<Json<Vec<MetadadosDasTabelas>>, CustomError> {
+    let metadados = conn
+        .consultar(|c| {
+            metadados_das_tabelas::table
+                .order(metadados_das_tabelas::tabela)
+                .load::<MetadadosDasTabelas>(c)
+        })
+        .await?;
+
+    Ok(Json(metadados))
+}
+
+pub fn rotas() -> Vec<Route> {
+    routes![
+        get_naturezas_juridicas,
+        get_natureza_juridica,
+        get_qualificacoes_de_socios,
+        get_qualificacao_de_socio,
+        get_paises,
+        get_pais,
+        get_municipios,
+        get_municipio,
+        get_motivos_de_situacoes_cadastrais,
+        get_motivo_de_situacao_cadastral,
+        get_faixas_etarias,
+        get_faixa_etaria,
+        get_situacoes_cadastrais,
+        get_situacao_cadastral,
+        get_lista_de_cnaes,
+        get_metadados_das_tabelas
+    ]
+}
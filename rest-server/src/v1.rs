@@ -0,0 +1,777 @@
+// Versão 1 da API REST, montada em /api/v1.
+//
+// Os recursos (empresa, estabelecimento e sócio) têm sempre o mesmo formato, seja qual for a rota que os
+// retorna: os campos mantêm os nomes das colunas do banco de dados e os códigos das tabelas auxiliares são
+// substituídos pelos respectivos registros ({id, nome}). Os dados relacionados são incluídos apenas quando
+// solicitados no parâmetro expand (ex: ?expand=empresa,cnaes_secundarios,socios,simples). Os campos do
+// endereço dos estabelecimentos são retornados como constam nos arquivos da Receita Federal ou, com
+// ?endereco=normalizado, na forma normalizada (vide data_models::enderecos).
+//
+// As rotas anteriores (/api/...) continuam disponíveis, mas são obsoletas: suas respostas incluem os
+// cabeçalhos Deprecation e Sunset (vide AvisoDeDescontinuacao).
+
+use std::collections::HashMap;
+
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use data_models::contatos::{Email, Telefone};
+use data_models::documentos::normalizar_cnpj;
+use data_models::enderecos::{cep_valido, EnderecoNormalizado};
+use data_models::lgpd::Mascaramento;
+use data_models::dominios::{FaixaEtaria, IdentificadorDeSocio, MatrizFilial, Porte, SituacaoCadastral};
+use data_models::lote::{condicao_dos_cnpjs, normalizar_lote, por_cnpj, StatusDoItemDoLote, LIMITE_DO_LOTE, TAMANHO_DO_BLOCO};
+use data_models::socios::{buscar_socios, BuscaDeSocios};
+use data_models::models::{CNAE, Empresa, Estabelecimento, MotivoDeSituacaoCadastral, Municipio, NaturezaJuridica, Pais, QualificacaoDeSocio, Simples, Socio};
+use data_models::schema::{cnaes, empresas, estabelecimentos, motivos_de_situacoes_cadastrais, municipios, naturezas_juridicas, paises, qualificacoes_de_socios, simples, socios};
+use diesel::mysql::MysqlConnection;
+use diesel::prelude::*;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::futures::{stream, StreamExt};
+use rocket::http::Header;
+use rocket::serde::json::{self, Json};
+use rocket::{Request, Response, Route, State};
+use serde::{Deserialize, Serialize};
+
+use rest_server::formato::{campo_csv, Formato, LinhaCsv, Resposta, RespostaEmStreaming};
+use rest_server::CustomError;
+
+use crate::cache_de_consultas::CacheDeConsultas;
+use crate::cache_http::ROTA_DO_NAO_MODIFICADO;
+use crate::lgpd::{Exposicao, Mascarar};
+use crate::tabelas::{paginacao, Pagina, Paginacao};
+use crate::{em_streaming, parametros_da_busca_de_socios, validar_cnpj_basico, verificar_empresa, DBPool, PoolDoStreaming};
+use crate::{LIMITE_MAXIMO_DE_SOCIOS, LIMITE_MAXIMO_EM_STREAMING, LIMITE_PADRAO_DE_SOCIOS};
+
+// data a partir da qual as rotas obsoletas (fora de /api/v1) poderão ser removidas (cabeçalho Sunset)
+const DATA_DE_DESATIVACAO: &str = "Mon, 01 Nov 2027 00:00:00 GMT";
+
+const EXPANSOES_DA_EMPRESA: &[&str] = &["socios", "simples"];
+const EXPANSOES_DO_ESTABELECIMENTO: &[&str] = &["empresa", "cnaes_secundarios", "socios", "simples"];
+const EXPANSOES_DO_SOCIO: &[&str] = &["empresa"];
+
+// Dados relacionados solicitados no parâmetro expand
+#[derive(Debug, Clone, Copy, Default)]
+struct Expansao {
+    empresa: bool,
+    cnaes_secundarios: bool,
+    socios: bool,
+    simples: bool,
+}
+
+impl Expansao {
+    // Interpreta o parâmetro expand (valores separados por vírgula), aceitando apenas as expansões permitidas na rota
+    fn de(expand: Option<String>, permitidas: &[&str]) -> Result<Expansao, CustomError> {
+        let mut expansao = Expansao::default();
+
+        for item in expand.iter().flat_map(|v| v.split(',')).map(str::trim).filter(|v| !v.is_empty()) {
+            if !permitidas.contains(&item) {
+                return Err(CustomError::invalid_param("expand", format!("Expansão inválida: {} (utilize {})", item, permitidas.join(", "))));
+            }
+            match item {
+                "empresa" => expansao.empresa = true,
+                "cnaes_secundarios" => expansao.cnaes_secundarios = true,
+                "socios" => expansao.socios = true,
+                _ => expansao.simples = true,
+            }
+        }
+
+        Ok(expansao)
+    }
+}
+
+// Forma dos campos do endereço solicitada no parâmetro endereco
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FormaDoEndereco {
+    Bruto,
+    Normalizado,
+}
+
+impl FormaDoEndereco {
+    fn de(endereco: Option<String>) -> Result<FormaDoEndereco, CustomError> {
+        match endereco.as_deref().map(str::trim) {
+            None | Some("") | Some("bruto") => Ok(FormaDoEndereco::Bruto),
+            Some("normalizado") => Ok(FormaDoEndereco::Normalizado),
+            Some(outra) => Err(CustomError::invalid_param("endereco", format!("Forma do endereço inválida: {} (utilize bruto ou normalizado)", outra))),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct EmpresaV1 {
+    cnpj_basico: String,
+    razao_social: String,
+    natureza_juridica: Option<NaturezaJuridica>,
+    qualificacao_do_responsavel: Option<QualificacaoDeSocio>,
+    capital_social: Option<BigDecimal>,
+    porte: Option<Porte>,
+    ente_federativo_responsavel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    socios: Option<Vec<SocioV1>>,
+    // quando expandido e a empresa não possui registro no Simples Nacional, o campo é retornado como null
+    #[serde(skip_serializing_if = "Option::is_none")]
+    simples: Option<Option<Simples>>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SocioV1 {
+    cnpj_basico: String,
+    identificador_de_socio: IdentificadorDeSocio,
+    nome_ou_razao_social_do_socio: String,
+    cnpj_ou_cpf_do_socio: Option<String>,
+    qualificacao_do_socio: Option<QualificacaoDeSocio>,
+    data_de_entrada_na_sociedade: NaiveDate,
+    pais_do_socio: Option<Pais>,
+    cpf_do_representante_legal: String,
+    nome_do_representante_legal: String,
+    qualificacao_do_representante_legal: Option<QualificacaoDeSocio>,
+    faixa_etaria_do_socio: FaixaEtaria,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    empresa: Option<EmpresaV1>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct EstabelecimentoV1 {
+    cnpj: String,
+    cnpj_basico: String,
+    cnpj_ordem: String,
+    cnpj_dv: String,
+    identificador_matriz_filial: MatrizFilial,
+    nome_fantasia: Option<String>,
+    situacao_cadastral: SituacaoCadastral,
+    data_situacao_cadastral: Option<NaiveDate>,
+    motivo_situacao_cadastral: Option<MotivoDeSituacaoCadastral>,
+    nome_da_cidade_no_exterior: Option<String>,
+    pais: Option<Pais>,
+    data_de_inicio_da_atividade: Option<NaiveDate>,
+    cnae_fiscal_principal: Option<CNAE>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cnaes_secundarios: Option<Vec<CNAE>>,
+    tipo_logradouro: Option<String>,
+    logradouro: Option<String>,
+    numero: Option<String>,
+    complemento: Option<String>,
+    bairro: Option<String>,
+    cep: Option<String>,
+    uf: Option<String>,
+    municipio: Option<Municipio>,
+    ddd1: Option<String>,
+    telefone1: Option<String>,
+    ddd2: Option<String>,
+    telefone2: Option<String>,
+    ddd_fax: Option<String>,
+    telefone_fax: Option<String>,
+    correio_eletronico: Option<String>,
+    situacao_especial: Option<String>,
+    data_situacao_especial: Option<NaiveDate>,
+    // endereço normalizado em uma única linha (gravado pelo importer com --normalize-addresses ou, se não
+    // gravado, calculado na consulta)
+    endereco_normalizado: Option<String>,
+    // indica se o CEP pertence às faixas de CEP da UF
+    cep_valido: Option<bool>,
+    // telefones e fax no formato E.164, com o tipo e a indicação de validade (gravados pelo importer com
+    // --normalize-contacts ou, se não gravados, calculados na consulta)
+    telefones: Vec<Telefone>,
+    email: Option<Email>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    empresa: Option<EmpresaV1>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    socios: Option<Vec<SocioV1>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    simples: Option<Option<Simples>>,
+}
+
+impl Mascarar for EmpresaV1 {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        mascaramento.razao_social(&mut self.razao_social);
+        self.socios.mascarar(mascaramento);
+    }
+}
+
+impl Mascarar for SocioV1 {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        mascaramento.nome_do_socio(self.identificador_de_socio, &mut self.nome_ou_razao_social_do_socio);
+        mascaramento.documento_do_socio(self.identificador_de_socio, &mut self.cnpj_ou_cpf_do_socio);
+        mascaramento.representante_legal(&mut self.cpf_do_representante_legal, &mut self.nome_do_representante_legal);
+        self.empresa.mascarar(mascaramento);
+    }
+}
+
+impl Mascarar for EstabelecimentoV1 {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        for telefone in [&mut self.ddd1, &mut self.telefone1, &mut self.ddd2, &mut self.telefone2, &mut self.ddd_fax, &mut self.telefone_fax] {
+            mascaramento.telefone(telefone);
+        }
+        mascaramento.telefones(&mut self.telefones);
+        mascaramento.correio_eletronico(&mut self.correio_eletronico);
+        mascaramento.email(&mut self.email);
+        self.empresa.mascarar(mascaramento);
+        self.socios.mascarar(mascaramento);
+    }
+}
+
+impl LinhaCsv for SocioV1 {
+    fn cabecalho() -> Vec<&'static str> {
+        vec![
+            "cnpj_basico",
+            "razao_social",
+            "identificador_de_socio",
+            "nome_ou_razao_social_do_socio",
+            "cnpj_ou_cpf_do_socio",
+            "qualificacao_do_socio",
+            "data_de_entrada_na_sociedade",
+            "pais_do_socio",
+            "cpf_do_representante_legal",
+            "nome_do_representante_legal",
+            "qualificacao_do_representante_legal",
+            "faixa_etaria_do_socio",
+        ]
+    }
+
+    fn campos(&self) -> Vec<String> {
+        vec![
+            self.cnpj_basico.clone(),
+            campo_csv(&self.empresa.as_ref().map(|e| &e.razao_social)),
+            self.identificador_de_socio.descricao().to_string(),
+            self.nome_ou_razao_social_do_socio.clone(),
+            campo_csv(&self.cnpj_ou_cpf_do_socio),
+            campo_csv(&self.qualificacao_do_socio.as_ref().map(|q| &q.nome)),
+            self.data_de_entrada_na_sociedade.to_string(),
+            campo_csv(&self.pais_do_socio.as_ref().map(|p| &p.nome)),
+            self.cpf_do_representante_legal.clone(),
+            self.nome_do_representante_legal.clone(),
+            campo_csv(&self.qualificacao_do_representante_legal.as_ref().map(|q| &q.nome)),
+            self.faixa_etaria_do_socio.descricao().to_string(),
+        ]
+    }
+}
+
+fn ids_dos_cnaes_secundarios(estabelecimento: &Estabelecimento) -> Vec<u32> {
+    match &estabelecimento.cnae_fiscal_secundaria {
+        Some(v) => v.split(',').filter_map(|s| s.trim().parse().ok()).collect(),
+        None => vec![],
+    }
+}
+
+// Converte os sócios no formato da API, resolvendo as qualificações e os países
+fn socios_v1(c: &MysqlConnection, socios_encontrados: Vec<Socio>) -> QueryResult<Vec<SocioV1>> {
+    let qualificacoes = qualificacoes_de_socios::table
+        .load::<QualificacaoDeSocio>(c)?;
+    let ids_dos_paises: Vec<u16> = socios_encontrados.iter().filter_map(|s| s.pais_do_socio).collect();
+    let paises_dos_socios = paises::table
+        .filter(paises::id.eq_any(ids_dos_paises))
+        .load::<Pais>(c)?;
+
+    let qualificacao = |id: u8| qualificacoes.iter().find(|q| q.id == id).cloned();
+
+    Ok(socios_encontrados
+        .into_iter()
+        .map(|socio| SocioV1 {
+            qualificacao_do_socio: qualificacao(socio.qualificacao_do_socio),
+            pais_do_socio: socio.pais_do_socio.and_then(|id| paises_dos_socios.iter().find(|p| p.id == id).cloned()),
+            qualificacao_do_representante_legal: qualificacao(socio.qualificacao_do_representante_legal),
+            cnpj_basico: socio.cnpj_basico,
+            identificador_de_socio: socio.identificador_de_socio,
+            nome_ou_razao_social_do_socio: socio.nome_ou_razao_social_do_socio,
+            cnpj_ou_cpf_do_socio: socio.cnpj_ou_cpf_do_socio,
+            data_de_entrada_na_sociedade: socio.data_de_entrada_na_sociedade,
+            cpf_do_representante_legal: socio.cpf_do_representante_legal,
+            nome_do_representante_legal: socio.nome_do_representante_legal,
+            faixa_etaria_do_socio: socio.faixa_etaria_do_socio,
+            empresa: None,
+        })
+        .collect())
+}
+
+// Sócios das empresas informadas, agrupados pelo CNPJ básico
+fn socios_das_empresas(c: &MysqlConnection, cnpjs_basicos: &[String]) -> QueryResult<HashMap<String, Vec<SocioV1>>> {
+    let socios_encontrados = socios::table
+        .filter(socios::cnpj_basico.eq_any(cnpjs_basicos))
+        .order(socios::id)
+        .load::<Socio>(c)?;
+
+    let mut por_empresa: HashMap<String, Vec<SocioV1>> = HashMap::new();
+    for socio in socios_v1(c, socios_encontrados)? {
+        por_empresa.entry(socio.cnpj_basico.clone()).or_default().push(socio);
+    }
+    Ok(por_empresa)
+}
+
+// Registros do Simples Nacional das empresas informadas, pelo CNPJ básico
+fn simples_das_empresas(c: &MysqlConnection, cnpjs_basicos: &[String]) -> QueryResult<HashMap<String, Simples>> {
+    Ok(simples::table
+        .filter(simples::cnpj_basico.eq_any(cnpjs_basicos))
+        .load::<Simples>(c)?
+        .into_iter()
+        .map(|s| (s.cnpj_basico.clone(), s))
+        .collect())
+}
+
+// Consulta as empresas informadas no formato da API, com os dados relacionados solicitados
+fn empresas_v1(c: &MysqlConnection, cnpjs_basicos: &[String], expansao: Expansao) -> QueryResult<Vec<EmpresaV1>> {
+    let encontradas = empresas::table
+        .filter(empresas::cnpj_basico.eq_any(cnpjs_basicos))
+        .left_join(naturezas_juridicas::table)
+        .left_join(qualificacoes_de_socios::table)
+        .order(empresas::cnpj_basico)
+        .load::<(Empresa, Option<NaturezaJuridica>, Option<QualificacaoDeSocio>)>(c)?;
+
+    let mut socios_por_empresa = if expansao.socios { socios_das_empresas(c, cnpjs_basicos)? } else { HashMap::new() };
+    let mut simples_por_empresa = if expansao.simples { simples_das_empresas(c, cnpjs_basicos)? } else { HashMap::new() };
+
+    Ok(encontradas
+        .into_iter()
+        .map(|(empresa, natureza_juridica, qualificacao_do_responsavel)| EmpresaV1 {
+            socios: if expansao.socios { Some(socios_por_empresa.remove(&empresa.cnpj_basico).unwrap_or_default()) } else { None },
+            simples: if expansao.simples { Some(simples_por_empresa.remove(&empresa.cnpj_basico)) } else { None },
+            cnpj_basico: empresa.cnpj_basico,
+            razao_social: empresa.razao_social,
+            natureza_juridica,
+            qualificacao_do_responsavel,
+            capital_social: empresa.capital_social,
+            porte: empresa.porte,
+            ente_federativo_responsavel: empresa.ente_federativo_responsavel,
+        })
+        .collect())
+}
+
+// Converte os estabelecimentos no formato da API, resolvendo as tabelas auxiliares e incluindo os dados
+// relacionados solicitados
+fn estabelecimentos_v1(c: &MysqlConnection, encontrados: Vec<Estabelecimento>, expansao: Expansao, forma_do_endereco: FormaDoEndereco) -> QueryResult<Vec<EstabelecimentoV1>> {
+    let mut ids_dos_cnaes: Vec<u32> = encontrados.iter().filter_map(|e| e.cnae_fiscal_principal).collect();
+    if expansao.cnaes_secundarios {
+        ids_dos_cnaes.extend(encontrados.iter().flat_map(ids_dos_cnaes_secundarios));
+    }
+    ids_dos_cnaes.sort_unstable();
+    ids_dos_cnaes.dedup();
+
+    let cnaes_encontrados = cnaes::table
+        .filter(cnaes::id.eq_any(ids_dos_cnaes))
+        .load::<CNAE>(c)?;
+    let motivos = motivos_de_situacoes_cadastrais::table
+        .filter(motivos_de_situacoes_cadastrais::id.eq_any(encontrados.iter().filter_map(|e| e.motivo_situacao_cadastral).collect::<Vec<u8>>()))
+        .load::<MotivoDeSituacaoCadastral>(c)?;
+    let paises_encontrados = paises::table
+        .filter(paises::id.eq_any(encontrados.iter().filter_map(|e| e.pais).collect::<Vec<u16>>()))
+        .load::<Pais>(c)?;
+    let municipios_encontrados = municipios::table
+        .filter(municipios::id.eq_any(encontrados.iter().filter_map(|e| e.municipio).collect::<Vec<u16>>()))
+        .load::<Municipio>(c)?;
+
+    let mut cnpjs_basicos: Vec<String> = encontrados.iter().map(|e| e.cnpj_basico.clone()).collect();
+    cnpjs_basicos.sort_unstable();
+    cnpjs_basicos.dedup();
+
+    let empresas_encontradas: HashMap<String, EmpresaV1> = if expansao.empresa {
+        empresas_v1(c, &cnpjs_basicos, Expansao::default())?
+            .into_iter()
+            .map(|e| (e.cnpj_basico.clone(), e))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+    let socios_por_empresa = if expansao.socios { socios_das_empresas(c, &cnpjs_basicos)? } else { HashMap::new() };
+    let simples_por_empresa = if expansao.simples { simples_das_empresas(c, &cnpjs_basicos)? } else { HashMap::new() };
+
+    let cnae = |id: u32| cnaes_encontrados.iter().find(|cnae| cnae.id == id).cloned();
+
+    Ok(encontrados
+        .into_iter()
+        .map(|e| {
+            let normalizado = EnderecoNormalizado::new(
+                e.tipo_logradouro.as_deref(),
+                e.logradouro.as_deref(),
+                e.numero.as_deref(),
+                e.complemento.as_deref(),
+                e.bairro.as_deref(),
+                e.cep.as_deref(),
+            );
+            let endereco_normalizado = e.endereco_normalizado.clone().or_else(|| normalizado.linha(e.uf.as_deref()));
+            let cep_valido = e.cep_valido.or_else(|| e.cep.as_deref().map(|cep| cep_valido(cep, e.uf.as_deref().unwrap_or_default())));
+            let telefones = [
+                ("telefone1", &e.ddd1, &e.telefone1, &e.telefone1_e164),
+                ("telefone2", &e.ddd2, &e.telefone2, &e.telefone2_e164),
+                ("fax", &e.ddd_fax, &e.telefone_fax, &e.telefone_fax_e164),
+            ]
+            .iter()
+            .filter_map(|(origem, ddd, numero, e164)| Telefone::new(origem, ddd.as_deref(), numero.as_deref(), e164.as_deref()))
+            .collect();
+            let email = Email::new(e.correio_eletronico.as_deref(), e.email_normalizado.as_deref(), e.email_valido);
+            let endereco = match forma_do_endereco {
+                FormaDoEndereco::Normalizado => normalizado,
+                FormaDoEndereco::Bruto => EnderecoNormalizado {
+                    tipo_logradouro: e.tipo_logradouro.clone(),
+                    logradouro: e.logradouro.clone(),
+                    numero: e.numero.clone(),
+                    complemento: e.complemento.clone(),
+                    bairro: e.bairro.clone(),
+                    cep: e.cep.clone(),
+                },
+            };
+
+            EstabelecimentoV1 {
+                cnpj: format!("{}{}{}", e.cnpj_basico, e.cnpj_ordem, e.cnpj_dv),
+                motivo_situacao_cadastral: e.motivo_situacao_cadastral.and_then(|id| motivos.iter().find(|m| m.id == id).cloned()),
+                pais: e.pais.and_then(|id| paises_encontrados.iter().find(|p| p.id == id).cloned()),
+                cnae_fiscal_principal: e.cnae_fiscal_principal.and_then(cnae),
+                cnaes_secundarios: if expansao.cnaes_secundarios { Some(ids_dos_cnaes_secundarios(&e).into_iter().filter_map(cnae).collect()) } else { None },
+                municipio: e.municipio.and_then(|id| municipios_encontrados.iter().find(|m| m.id == id).cloned()),
+                empresa: if expansao.empresa { empresas_encontradas.get(&e.cnpj_basico).cloned() } else { None },
+                socios: if expansao.socios { Some(socios_por_empresa.get(&e.cnpj_basico).cloned().unwrap_or_default()) } else { None },
+                simples: if expansao.simples { Some(simples_por_empresa.get(&e.cnpj_basico).cloned()) } else { None },
+                cnpj_basico: e.cnpj_basico,
+                cnpj_ordem: e.cnpj_ordem,
+                cnpj_dv: e.cnpj_dv,
+                identificador_matriz_filial: e.identificador_matriz_filial,
+                nome_fantasia: e.nome_fantasia,
+                situacao_cadastral: e.situacao_cadastral,
+                data_situacao_cadastral: e.data_situacao_cadastral,
+                nome_da_cidade_no_exterior: e.nome_da_cidade_no_exterior,
+                data_de_inicio_da_atividade: e.data_de_inicio_da_atividade,
+                tipo_logradouro: endereco.tipo_logradouro,
+                logradouro: endereco.logradouro,
+                numero: endereco.numero,
+                complemento: endereco.complemento,
+                bairro: endereco.bairro,
+                cep: endereco.cep,
+                uf: e.uf,
+                ddd1: e.ddd1,
+                telefone1: e.telefone1,
+                ddd2: e.ddd2,
+                telefone2: e.telefone2,
+                ddd_fax: e.ddd_fax,
+                telefone_fax: e.telefone_fax,
+                correio_eletronico: e.correio_eletronico,
+                situacao_especial: e.situacao_especial,
+                data_situacao_especial: e.data_situacao_especial,
+                endereco_normalizado,
+                cep_valido,
+                telefones,
+                email,
+            }
+        })
+        .collect())
+}
+
+// Consulta um CNAE pelo código (na versão anterior o CNAE era retornado dentro do campo cnae)
+#[get("/cnaes/<id>")]
+async fn get_cnae(conn: DBPool, id: u32) -> Result<Json<CNAE>, CustomError> {
+
+    let cnae = conn
+        .consultar(move |c| {
+            cnaes::table
+                .filter(cnaes::id.eq(id))
+                .first::<CNAE>(c)
+        })
+        .await?;
+
+    Ok(Json(cnae))
+}
+
+#[get("/empresas/<cnpjbas>?<expand>")] // cnpjbas = CNPJ Básico, ou seja, os 8 primeiros digitos do CNPJ
+async fn get_empresa(conn: DBPool, cache: &State<CacheDeConsultas>, exposicao: Exposicao, cnpjbas: String, expand: Option<String>) -> Result<Json<EmpresaV1>, CustomError> {
+
+    let cnpjbas = validar_cnpj_basico(&cnpjbas)?;
+    let expansao = Expansao::de(expand, EXPANSOES_DA_EMPRESA)?;
+
+    cache.atualizar(&conn).await;
+    let chave = format!("{}{:?}", cnpjbas, expansao);
+    if let Some(empresa) = cache.empresas_v1.obter(&chave) {
+        return Ok(Json(exposicao.aplicar(empresa)));
+    }
+
+    let empresa = conn
+        .consultar(move |c| -> Result<EmpresaV1, CustomError> {
+            empresas_v1(c, std::slice::from_ref(&cnpjbas), expansao)?
+                .pop()
+                .ok_or_else(|| CustomError::NotFoundErr(format!("Empresa não encontrada: {}", cnpjbas)))
+        })
+        .await?;
+    cache.empresas_v1.incluir(chave, empresa.clone());
+
+    Ok(Json(exposicao.aplicar(empresa)))
+}
+
+// Retorna os estabelecimentos (matriz e filiais) da empresa, paginados
+#[get("/empresas/<cnpjbas>/estabelecimentos?<pagina>&<limite>&<expand>&<endereco>")]
+async fn get_estabelecimentos_da_empresa(conn: DBPool, exposicao: Exposicao, cnpjbas: String, pagina: Option<i64>, limite: Option<i64>, expand: Option<String>, endereco: Option<String>) -> Result<Json<Pagina<EstabelecimentoV1>>, CustomError> {
+
+    let cnpjbas = validar_cnpj_basico(&cnpjbas)?;
+    let expansao = Expansao::de(expand, EXPANSOES_DO_ESTABELECIMENTO)?;
+    let forma_do_endereco = FormaDoEndereco::de(endereco)?;
+    let Paginacao { pagina, limite, deslocamento } = paginacao(pagina, limite)?;
+
+    let (total, registros) = conn
+        .consultar(move |c| -> Result<(i64, Vec<EstabelecimentoV1>), CustomError> {
+            verificar_empresa(c, &cnpjbas)?;
+
+            let total = estabelecimentos::table
+                .filter(estabelecimentos::cnpj_basico.eq(&cnpjbas))
+                .count()
+                .get_result::<i64>(c)?;
+            let encontrados = estabelecimentos::table
+                .filter(estabelecimentos::cnpj_basico.eq(&cnpjbas))
+                .order((estabelecimentos::cnpj_ordem, estabelecimentos::cnpj_dv))
+                .offset(deslocamento)
+                .limit(limite)
+                .load::<Estabelecimento>(c)?;
+            Ok((total, estabelecimentos_v1(c, encontrados, expansao, forma_do_endereco)?))
+        })
+        .await?;
+
+    Ok(Json(exposicao.aplicar(Pagina { pagina, limite, total, registros })))
+}
+
+// Retorna o quadro de sócios da empresa
+#[get("/empresas/<cnpjbas>/socios")]
+async fn get_socios_da_empresa(conn: DBPool, exposicao: Exposicao, cnpjbas: String) -> Result<Json<Vec<SocioV1>>, CustomError> {
+
+    let cnpjbas = validar_cnpj_basico(&cnpjbas)?;
+
+    let socios_da_empresa = conn
+        .consultar(move |c| -> Result<Vec<SocioV1>, CustomError> {
+            verificar_empresa(c, &cnpjbas)?;
+            Ok(socios_das_empresas(c, std::slice::from_ref(&cnpjbas))?.remove(&cnpjbas).unwrap_or_default())
+        })
+        .await?;
+
+    Ok(Json(exposicao.aplicar(socios_da_empresa)))
+}
+
+// Retorna a situação da empresa quanto ao Simples Nacional e ao MEI
+#[get("/empresas/<cnpjbas>/simples")]
+async fn get_simples_da_empresa(conn: DBPool, cnpjbas: String) -> Result<Json<Simples>, CustomError> {
+
+    let cnpjbas = validar_cnpj_basico(&cnpjbas)?;
+
+    let simples_da_empresa = conn
+        .consultar(move |c| -> Result<Simples, CustomError> {
+            verificar_empresa(c, &cnpjbas)?;
+            simples_das_empresas(c, std::slice::from_ref(&cnpjbas))?
+                .remove(&cnpjbas)
+                .ok_or_else(|| CustomError::NotFoundErr(format!("A empresa {} não possui registro no Simples Nacional", cnpjbas)))
+        })
+        .await?;
+
+    Ok(Json(simples_da_empresa))
+}
+
+#[get("/estabelecimentos/<cnpj>?<expand>&<endereco>")]
+async fn get_estabelecimento(conn: DBPool, cache: &State<CacheDeConsultas>, exposicao: Exposicao, cnpj: String, expand: Option<String>, endereco: Option<String>) -> Result<Json<EstabelecimentoV1>, CustomError> {
+
+    let cnpj = normalizar_cnpj(&cnpj)
+        .ok_or_else(|| CustomError::invalid_param("cnpj", format!("CNPJ inválido (deve conter 14 dígitos e os dígitos verificadores corretos): {}", cnpj)))?;
+    let expansao = Expansao::de(expand, EXPANSOES_DO_ESTABELECIMENTO)?;
+    let forma_do_endereco = FormaDoEndereco::de(endereco)?;
+
+    cache.atualizar(&conn).await;
+    let chave = format!("{}{:?}{:?}", cnpj, expansao, forma_do_endereco);
+    if let Some(estabelecimento) = cache.estabelecimentos_v1.obter(&chave) {
+        return Ok(Json(exposicao.aplicar(estabelecimento)));
+    }
+
+    let estabelecimento = conn
+        .consultar(move |c| -> QueryResult<EstabelecimentoV1> {
+            let encontrado = estabelecimentos::table
+                .filter(estabelecimentos::cnpj_basico.eq(&cnpj[..8]))
+                .filter(estabelecimentos::cnpj_ordem.eq(&cnpj[8..12]))
+                .filter(estabelecimentos::cnpj_dv.eq(&cnpj[12..]))
+                .first::<Estabelecimento>(c)?;
+            estabelecimentos_v1(c, vec![encontrado], expansao, forma_do_endereco)?
+                .pop()
+                .ok_or(diesel::result::Error::NotFound)
+        })
+        .await?;
+    cache.estabelecimentos_v1.incluir(chave, estabelecimento.clone());
+
+    Ok(Json(exposicao.aplicar(estabelecimento)))
+}
+
+#[derive(Deserialize)]
+struct LoteDeCnpjs {
+    cnpjs: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ItemDoLoteV1 {
+    cnpj: String,
+    status: StatusDoItemDoLote,
+    estabelecimento: Option<EstabelecimentoV1>,
+}
+
+impl Mascarar for ItemDoLoteV1 {
+    fn mascarar(&mut self, mascaramento: &Mascaramento) {
+        self.estabelecimento.mascarar(mascaramento);
+    }
+}
+
+impl LinhaCsv for ItemDoLoteV1 {
+    fn cabecalho() -> Vec<&'static str> {
+        vec![
+            "cnpj",
+            "status",
+            "razao_social",
+            "nome_fantasia",
+            "situacao_cadastral",
+            "cnae_fiscal_principal",
+            "municipio",
+            "uf",
+        ]
+    }
+
+    fn campos(&self) -> Vec<String> {
+        let e = self.estabelecimento.as_ref();
+        vec![
+            self.cnpj.clone(),
+            json::to_string(&self.status).unwrap_or_default().trim_matches('"').to_string(),
+            campo_csv(&e.and_then(|e| e.empresa.as_ref()).map(|empresa| &empresa.razao_social)),
+            campo_csv(&e.and_then(|e| e.nome_fantasia.as_ref())),
+            campo_csv(&e.map(|e| e.situacao_cadastral.descricao())),
+            campo_csv(&e.and_then(|e| e.cnae_fiscal_principal.as_ref()).map(|cnae| &cnae.nome)),
+            campo_csv(&e.and_then(|e| e.municipio.as_ref()).map(|municipio| &municipio.nome)),
+            campo_csv(&e.and_then(|e| e.uf.as_ref())),
+        ]
+    }
+}
+
+// Consulta vários estabelecimentos de uma só vez. O resultado é retornado na mesma ordem em que os CNPJs
+// foram informados, com a situação de cada um.
+#[post("/estabelecimentos/lote?<expand>&<endereco>", format = "json", data = "<lote>")]
+async fn post_estabelecimentos_lote(conn: DBPool, formato: Formato, exposicao: Exposicao, lote: Json<LoteDeCnpjs>, expand: Option<String>, endereco: Option<String>) -> Result<Resposta<Vec<ItemDoLoteV1>>, CustomError> {
+
+    let lote = lote.into_inner();
+    if lote.cnpjs.len() > LIMITE_DO_LOTE {
+        return Err(CustomError::invalid_param("cnpjs", format!("Informe no máximo {} CNPJs por lote", LIMITE_DO_LOTE)));
+    }
+    let expansao = Expansao::de(expand, EXPANSOES_DO_ESTABELECIMENTO)?;
+    let forma_do_endereco = FormaDoEndereco::de(endereco)?;
+
+    let (normalizados, validos) = normalizar_lote(&lote.cnpjs);
+
+    let encontrados = conn
+        .consultar(move |c| -> QueryResult<Vec<EstabelecimentoV1>> {
+            let mut encontrados = Vec::new();
+            for bloco in validos.chunks(TAMANHO_DO_BLOCO) {
+                let estabelecimentos_do_bloco = estabelecimentos::table
+                    .filter(condicao_dos_cnpjs(bloco))
+                    .load::<Estabelecimento>(c)?;
+                encontrados.extend(estabelecimentos_v1(c, estabelecimentos_do_bloco, expansao, forma_do_endereco)?);
+            }
+            Ok(encontrados)
+        })
+        .await?;
+
+    let encontrados = por_cnpj(encontrados, |e| e.cnpj.clone());
+    let itens: Vec<ItemDoLoteV1> = lote.cnpjs
+        .into_iter()
+        .zip(normalizados)
+        .map(|(cnpj, normalizado)| {
+            let normalizado = match normalizado {
+                Some(v) => v,
+                None => return ItemDoLoteV1 { cnpj, status: StatusDoItemDoLote::Invalido, estabelecimento: None },
+            };
+            let estabelecimento = encontrados.get(&normalizado).cloned();
+            let status = match estabelecimento {
+                Some(_) => StatusDoItemDoLote::Encontrado,
+                None => StatusDoItemDoLote::NaoEncontrado,
+            };
+            ItemDoLoteV1 { cnpj, status, estabelecimento }
+        })
+        .collect();
+    let itens = exposicao.aplicar(itens);
+
+    if formato.streaming() {
+        return Ok(Resposta::Streaming(RespostaEmStreaming {
+            formato,
+            linhas: stream::once(async move { formato.linhas(&itens, true) }).boxed(),
+        }));
+    }
+
+    Ok(Resposta::Json(Json(itens)))
+}
+
+// Consulta um bloco de sócios no formato da API, incluindo a empresa quando solicitado, e retorna também o
+// id do último sócio do bloco (a continuação da leitura em blocos)
+fn consultar_socios(c: &mut MysqlConnection, busca: &BuscaDeSocios, expansao: Expansao, apos: Option<u32>, quantidade: i64) -> QueryResult<(Vec<SocioV1>, Option<u32>)> {
+    let socios_encontrados = buscar_socios::<Socio>(c, busca, apos, quantidade)?;
+    let ultimo = socios_encontrados.last().map(|s| s.id);
+    let mut socios_encontrados = socios_v1(c, socios_encontrados)?;
+
+    if expansao.empresa {
+        let cnpjs_basicos: Vec<String> = socios_encontrados.iter().map(|s| s.cnpj_basico.clone()).collect();
+        let empresas_encontradas = empresas_v1(c, &cnpjs_basicos, Expansao::default())?;
+        for socio in socios_encontrados.iter_mut() {
+            socio.empresa = empresas_encontradas.iter().find(|e| e.cnpj_basico == socio.cnpj_basico).cloned();
+        }
+    }
+
+    Ok((socios_encontrados, ultimo))
+}
+
+// Busca os sócios pelo CPF (completo ou mascarado como nos arquivos da RF), pelo CNPJ do sócio pessoa
+// jurídica e/ou pelo início do nome
+#[get("/socios?<cpf>&<cnpj>&<nome>&<limite>&<expand>")]
+#[allow(clippy::too_many_arguments)] // os parâmetros da rota são os parâmetros da consulta
+async fn get_socios(conn: DBPool, pool: &State<PoolDoStreaming>, formato: Formato, exposicao: Exposicao, cpf: Option<String>, cnpj: Option<String>, nome: Option<String>, limite: Option<i64>, expand: Option<String>) -> Result<Resposta<Vec<SocioV1>>, CustomError> {
+
+    let busca = parametros_da_busca_de_socios(&exposicao, cpf, cnpj, nome)?;
+    let expansao = Expansao::de(expand, EXPANSOES_DO_SOCIO)?;
+
+    if formato.streaming() {
+        let limite = limite.unwrap_or(LIMITE_MAXIMO_EM_STREAMING).clamp(1, LIMITE_MAXIMO_EM_STREAMING);
+        return Ok(Resposta::Streaming(em_streaming(pool, formato, limite, move |c, apos, quantidade| {
+            consultar_socios(c, &busca, expansao, apos, quantidade).map(|(socios, ultimo)| (exposicao.aplicar(socios), ultimo))
+        })));
+    }
+
+    let limite = limite.unwrap_or(LIMITE_PADRAO_DE_SOCIOS).clamp(1, LIMITE_MAXIMO_DE_SOCIOS);
+    let socios_encontrados = conn
+        .consultar(move |c| consultar_socios(c, &busca, expansao, None, limite))
+        .await?
+        .0;
+
+    Ok(Resposta::Json(Json(exposicao.aplicar(socios_encontrados))))
+}
+
+pub fn rotas() -> Vec<Route> {
+    routes![
+        get_cnae,
+        get_empresa,
+        get_estabelecimentos_da_empresa,
+        get_socios_da_empresa,
+        get_simples_da_empresa,
+        get_estabelecimento,
+        post_estabelecimentos_lote,
+        get_socios
+    ]
+}
+
+// Fairing que inclui nas respostas das rotas obsoletas (fora de /api/v1) os cabeçalhos Deprecation e
+// Sunset, além de um link para a documentação da API
+pub struct AvisoDeDescontinuacao;
+
+#[rocket::async_trait]
+impl Fairing for AvisoDeDescontinuacao {
+    fn info(&self) -> Info {
+        Info {
+            name: "Aviso de descontinuação das rotas obsoletas",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let obsoleta = request.route().is_some_and(|rota| {
+            let caminho = rota.uri.path();
+            caminho.starts_with("/api/")
+                && !caminho.starts_with("/api/v1/")
+                && caminho != "/api/openapi.json"
+                && !caminho.starts_with("/api/docs")
+                && caminho != ROTA_DO_NAO_MODIFICADO
+        });
+
+        if obsoleta {
+            response.set_header(Header::new("Deprecation", "true"));
+            response.set_header(Header::new("Sunset", DATA_DE_DESATIVACAO));
+            response.set_header(Header::new("Link", "</api/docs>; rel=\"deprecation\""));
+        }
+    }
+}
@@ -0,0 +1,14 @@
+#!/bin/sh
+# Baixa os arquivos do Swagger UI servidos pelo rest-server em /api/docs, para que a página de documentação
+# não dependa de uma CDN. A versão é fixa e o npm confere a integridade do pacote baixado (sha512 publicado
+# no registro). Ao atualizar a versão, confira a página /api/docs.
+set -eu
+
+VERSAO=4.15.5
+
+cd "$(dirname "$0")"
+pacote=$(npm pack --silent "swagger-ui-dist@$VERSAO")
+tar -xzf "$pacote" package/swagger-ui.css package/swagger-ui-bundle.js package/LICENSE
+mv package/swagger-ui.css package/swagger-ui-bundle.js .
+mv package/LICENSE LICENSE-swagger-ui
+rm -r package "$pacote"